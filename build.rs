@@ -0,0 +1,48 @@
+//! Dumps the syntax definitions bundled by the `minimal-syntaxes` feature.
+
+/// Syntaxes kept from syntect's defaults, by name. Plain text is always
+/// added.
+#[cfg(all(feature = "syntect", not(feature = "highlighting")))]
+const MINIMAL_SYNTAXES: &[&str] = &[
+    "Bourne Again Shell (bash)",
+    "C",
+    "C++",
+    "CSS",
+    "Diff",
+    "Go",
+    "HTML",
+    "Java",
+    "JavaScript",
+    "JSON",
+    "Makefile",
+    "Python",
+    "Rust",
+    "SQL",
+    "YAML",
+];
+
+#[cfg(all(feature = "syntect", not(feature = "highlighting")))]
+fn main() {
+    use std::path::Path;
+
+    use syntect::parsing::{SyntaxSet, SyntaxSetBuilder};
+
+    let defaults = SyntaxSet::load_defaults_newlines().into_builder();
+    let mut builder = SyntaxSetBuilder::new();
+    builder.add_plain_text_syntax();
+    for syntax in defaults.syntaxes() {
+        if MINIMAL_SYNTAXES.contains(&syntax.name.as_str()) {
+            builder.add(syntax.clone());
+        }
+    }
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    let path = Path::new(&out_dir).join("minimal_syntaxes.packdump");
+    syntect::dumps::dump_to_uncompressed_file(&builder.build(), &path)
+        .expect("write the syntax dump");
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+#[cfg(not(all(feature = "syntect", not(feature = "highlighting"))))]
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+}
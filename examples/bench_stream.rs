@@ -0,0 +1,45 @@
+//! Replays a recorded LLM response at a realistic token rate and reports
+//! end-to-end render latency percentiles.
+//!
+//! Usage: `cargo run --release --example bench_stream [tokens/s] [jitter] [runs]`
+
+use std::io;
+
+use forge_markdown_stream::{simulate_stream, StreamSimulation, StreamdownRenderer};
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let tokens_per_second: f64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(200.0);
+    let jitter: f64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(0.5);
+    let runs: u64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(3);
+
+    let content = include_str!("data.md");
+    let tokens: Vec<&str> = content.split("<separator>").collect();
+
+    println!(
+        "{} tokens at {} tokens/s, jitter {}, {} runs",
+        tokens.len(),
+        tokens_per_second,
+        jitter,
+        runs
+    );
+    for run in 0..runs {
+        let config = StreamSimulation {
+            tokens_per_second,
+            jitter,
+            seed: run + 1,
+        };
+        let renderer = StreamdownRenderer::new(io::sink(), 80);
+        let report = simulate_stream(renderer, &tokens, &config)?;
+        println!(
+            "run {}: total {:.2?}  p50 {:.2?}  p90 {:.2?}  p99 {:.2?}  max {:.2?}",
+            run + 1,
+            report.total(),
+            report.percentile(50.0),
+            report.percentile(90.0),
+            report.percentile(99.0),
+            report.percentile(100.0)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Async renderer for `tokio::io::AsyncWrite` sinks.
+
+use std::io;
+
+use streamdown_parser::ParseEvent;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::options::RendererOptions;
+use crate::renderer::Renderer;
+use crate::theme::Theme;
+
+/// Renderer that writes to an async writer.
+///
+/// Mirrors [`Renderer`]: each event is laid out into an in-memory buffer,
+/// which is then written to the async writer without blocking the runtime.
+pub struct AsyncRenderer<W: AsyncWrite + Unpin> {
+    writer: W,
+    renderer: Renderer<Vec<u8>>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncRenderer<W> {
+    pub fn new(writer: W, width: usize) -> Self {
+        Self::with_theme(writer, width, Theme::default())
+    }
+
+    pub fn with_theme(writer: W, width: usize, theme: Theme) -> Self {
+        Self {
+            writer,
+            renderer: Renderer::with_theme(Vec::new(), width, theme),
+        }
+    }
+
+    /// Set a new theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.renderer.set_theme(theme);
+    }
+
+    /// Get the current theme.
+    pub fn theme(&self) -> &Theme {
+        self.renderer.theme()
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    /// Get the optional renderer behaviors.
+    pub fn options(&self) -> &RendererOptions {
+        self.renderer.options()
+    }
+
+    /// Render a single parse event.
+    pub async fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.renderer.render_event(event)?;
+        let buffer = self.renderer.take_output()?;
+        if !buffer.is_empty() {
+            self.writer.write_all(&buffer).await?;
+        }
+        self.writer.flush().await
+    }
+
+    /// Consume the renderer and return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlainBackend;
+
+    #[tokio::test]
+    async fn test_writes_rendered_events() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        let mut renderer = AsyncRenderer::with_theme(Vec::new(), 80, theme);
+        renderer
+            .render_event(&ParseEvent::Text("hello".to_string()))
+            .await
+            .unwrap();
+        renderer.render_event(&ParseEvent::Newline).await.unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        assert_eq!(output, "hello\n");
+    }
+}
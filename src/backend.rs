@@ -0,0 +1,236 @@
+//! Output backends that turn styles into concrete escape sequences.
+
+use std::fmt::Debug;
+
+use crate::color::ColorDepth;
+use crate::guard::RESTORE_SEQUENCE;
+use crate::sgr;
+use crate::theme::Style;
+
+/// Emits the sequences used for styled text, hyperlinks, and resets.
+///
+/// `Theme` delegates every escape sequence it produces to its backend, so
+/// swapping the backend changes the output target without touching layout.
+pub trait OutputBackend: Debug + Send + Sync {
+    /// Wrap `text` so that it is displayed with `style`.
+    fn paint(&self, style: &Style, text: &str) -> String;
+
+    /// Wrap `text` in a hyperlink pointing at `url`.
+    fn hyperlink(&self, text: &str, url: &str) -> String;
+
+    /// Sequence that clears every active attribute.
+    fn reset(&self) -> &'static str;
+
+    /// Sequence that restores the terminal after an interrupted render.
+    fn restore(&self) -> &'static str {
+        self.reset()
+    }
+
+    /// Sequence ending a rendered line, so no attribute such as a background
+    /// color reaches past its text into padding or the rest of the row.
+    fn end_line(&self) -> &'static str {
+        self.reset()
+    }
+
+    /// Whether the target can erase output it has shown, so that
+    /// [`erase_lines`](Self::erase_lines) and
+    /// [`erase_columns`](Self::erase_columns) return sequences.
+    fn can_erase(&self) -> bool {
+        false
+    }
+
+    /// Whether the target passes terminal graphics escapes through, so
+    /// images may be drawn inline.
+    fn can_draw_images(&self) -> bool {
+        false
+    }
+
+    /// Sequence that erases the previous `count` lines and moves the cursor
+    /// to the start of the first one, or `None` if the target cannot erase.
+    /// With a `count` of 0 only the current line is cleared.
+    fn erase_lines(&self, _count: usize) -> Option<String> {
+        None
+    }
+
+    /// Sequence that erases the previous `count` columns of the current
+    /// line and moves the cursor back over them, or `None` if the target
+    /// cannot erase.
+    fn erase_columns(&self, _count: usize) -> Option<String> {
+        None
+    }
+
+    /// Sequence that limits scrolling to rows `1..=bottom`, or lifts the
+    /// limit for `None`, keeping the cursor in place. `None` if the target
+    /// has no scroll region.
+    fn scroll_region(&self, _bottom: Option<usize>) -> Option<String> {
+        None
+    }
+
+    /// Sequence that replaces the contents of row `row`, counted from 1,
+    /// with `text` and puts the cursor back. `None` if the target cannot
+    /// address rows.
+    fn write_row(&self, _row: usize, _text: &str) -> Option<String> {
+        None
+    }
+}
+
+/// ANSI backend emitting SGR attributes and OSC 8 hyperlinks.
+///
+/// Colors are downsampled to the backend's color depth.
+#[derive(Debug, Clone, Copy)]
+pub struct AnsiBackend {
+    depth: ColorDepth,
+}
+
+impl AnsiBackend {
+    pub fn new(depth: ColorDepth) -> Self {
+        Self { depth }
+    }
+
+    /// Create a backend for the color depth detected from the environment.
+    /// On Windows this also enables escape sequence processing.
+    ///
+    /// Always emits escapes; use [`TerminalProfile::backend`] to emit none
+    /// for `TERM=dumb`.
+    ///
+    /// [`TerminalProfile::backend`]: crate::TerminalProfile::backend
+    pub fn detect() -> Self {
+        #[cfg(all(windows, feature = "crossterm"))]
+        crate::console::enable_virtual_terminal();
+        Self::new(ColorDepth::detect())
+    }
+
+    pub fn depth(&self) -> ColorDepth {
+        self.depth
+    }
+}
+
+impl Default for AnsiBackend {
+    fn default() -> Self {
+        Self::new(ColorDepth::TrueColor)
+    }
+}
+
+impl OutputBackend for AnsiBackend {
+    fn paint(&self, style: &Style, text: &str) -> String {
+        sgr::encode(style, text, self.depth)
+    }
+
+    fn hyperlink(&self, text: &str, url: &str) -> String {
+        // A control character would end the sequence and let the rest of
+        // the URL through as raw escapes
+        if url.contains(char::is_control) {
+            return text.to_string();
+        }
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    }
+
+    fn reset(&self) -> &'static str {
+        sgr::RESET
+    }
+
+    fn restore(&self) -> &'static str {
+        RESTORE_SEQUENCE
+    }
+
+    fn end_line(&self) -> &'static str {
+        // Clear to the end of the row with default attributes, since some
+        // terminals fill it with the background active at the newline
+        "\x1b[0m\x1b[K"
+    }
+
+    fn can_erase(&self) -> bool {
+        true
+    }
+
+    fn can_draw_images(&self) -> bool {
+        true
+    }
+
+    fn erase_lines(&self, count: usize) -> Option<String> {
+        // `CSI 0 F` moves up one line like `CSI 1 F`, so stay on this line
+        if count == 0 {
+            return Some("\r\x1b[J".to_string());
+        }
+        Some(format!("\x1b[{}F\x1b[J", count))
+    }
+
+    fn erase_columns(&self, count: usize) -> Option<String> {
+        Some(format!("\x1b[{}D\x1b[K", count))
+    }
+
+    fn scroll_region(&self, bottom: Option<usize>) -> Option<String> {
+        // Setting the region homes the cursor, so save and restore it
+        Some(match bottom {
+            Some(bottom) => format!("\x1b7\x1b[1;{}r\x1b8", bottom),
+            None => "\x1b7\x1b[r\x1b8".to_string(),
+        })
+    }
+
+    fn write_row(&self, row: usize, text: &str) -> Option<String> {
+        Some(format!("\x1b7\x1b[{};1H\x1b[2K{}\x1b8", row, text))
+    }
+}
+
+/// Backend that drops all styling, for logs and files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainBackend;
+
+impl OutputBackend for PlainBackend {
+    fn paint(&self, _style: &Style, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn hyperlink(&self, text: &str, _url: &str) -> String {
+        text.to_string()
+    }
+
+    fn reset(&self) -> &'static str {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inline::render_inline_content;
+    use crate::Theme;
+
+    #[test]
+    fn test_plain_paint_is_identity() {
+        let style = Style::new().bold().underline();
+        assert_eq!(PlainBackend.paint(&style, "text"), "text");
+        assert_eq!(PlainBackend.reset(), "");
+    }
+
+    #[test]
+    fn test_ansi_hyperlink() {
+        assert_eq!(
+            AnsiBackend::default().hyperlink("docs", "https://example.com"),
+            "\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\"
+        );
+        let backend = AnsiBackend::default();
+        assert_eq!(backend.hyperlink("docs", "https://a.dev/\x1b\\\x07"), "docs");
+    }
+
+    #[test]
+    fn test_erase_capability() {
+        let ansi = AnsiBackend::default();
+        assert!(ansi.can_erase());
+        assert_eq!(ansi.erase_lines(0).as_deref(), Some("\r\x1b[J"));
+        assert_eq!(ansi.erase_lines(2).as_deref(), Some("\x1b[2F\x1b[J"));
+        assert!(!PlainBackend.can_erase());
+        assert!(ansi.can_draw_images() && !PlainBackend.can_draw_images());
+    }
+
+    #[test]
+    fn test_theme_with_plain_backend_has_no_escapes() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        let result = render_inline_content(
+            "**bold** *italic* `code` __u__ [link](https://example.com)",
+            &theme,
+        );
+        assert!(!result.contains('\x1b'), "unexpected escape in {:?}", result);
+        assert_eq!(result, "bold italic code u link (https://example.com)");
+    }
+}
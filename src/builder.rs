@@ -0,0 +1,152 @@
+//! Fluent construction of renderers.
+
+use std::io::Write;
+#[cfg(feature = "images")]
+use std::path::PathBuf;
+
+#[cfg(feature = "images")]
+use crate::image::ImageProtocol;
+use crate::options::{RendererOptions, WrapMode};
+use crate::renderer::{ImageHandler, Renderer};
+use crate::theme::Theme;
+use crate::utils::detect_terminal_width;
+use crate::StreamdownRenderer;
+
+/// Builder collecting everything a renderer is configured with.
+///
+/// New settings are added here and to [`RendererOptions`] instead of to
+/// constructor signatures, so existing callers keep compiling.
+///
+/// ```no_run
+/// use forge_markdown_stream::{RendererBuilder, Theme};
+///
+/// let renderer = RendererBuilder::new()
+///     .width(100)
+///     .theme(Theme::light())
+///     .configure(|options| options.code_frame = true)
+///     .build(std::io::stdout());
+/// ```
+#[derive(Default)]
+pub struct RendererBuilder {
+    width: Option<usize>,
+    theme: Option<Theme>,
+    options: RendererOptions,
+    image_handler: Option<ImageHandler>,
+}
+
+impl RendererBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output width; defaults to the width of the terminal attached to
+    /// stdout.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Theme to style output with; defaults to [`Theme::default`].
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Replace all optional behaviors.
+    pub fn options(mut self, options: RendererOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adjust optional behaviors in place.
+    pub fn configure(mut self, configure: impl FnOnce(&mut RendererOptions)) -> Self {
+        configure(&mut self.options);
+        self
+    }
+
+    /// Whether links are hyperlinked, overriding the terminal profile.
+    pub fn hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.options.profile.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Whether code blocks are drawn in a frame.
+    pub fn code_frame(mut self, code_frame: bool) -> Self {
+        self.options.code_frame = code_frame;
+        self
+    }
+
+    /// How list items, blockquotes and doc comments are wrapped.
+    pub fn wrap(mut self, wrap: WrapMode) -> Self {
+        self.options.wrap = wrap;
+        self
+    }
+
+    /// Expand tabs in code blocks to stops `tab_width` columns apart.
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.options.tab_width = Some(tab_width);
+        self
+    }
+
+    /// Draw images inline with `protocol`, loading local ones from `dir`.
+    #[cfg(feature = "images")]
+    pub fn images(mut self, protocol: ImageProtocol, dir: impl Into<PathBuf>) -> Self {
+        self.options.image_protocol = Some(protocol);
+        self.options.image_dir = Some(dir.into());
+        self
+    }
+
+    /// Callback deciding how images are rendered.
+    pub fn image_handler(mut self, handler: ImageHandler) -> Self {
+        self.image_handler = Some(handler);
+        self
+    }
+
+    /// Build a streaming renderer writing to `writer`.
+    pub fn build<W: Write>(self, writer: W) -> StreamdownRenderer<W> {
+        StreamdownRenderer::from_renderer(self.build_renderer(writer))
+    }
+
+    /// Build an event renderer writing to `writer`.
+    pub fn build_renderer<W: Write>(self, writer: W) -> Renderer<W> {
+        let width = self.width.unwrap_or_else(detect_terminal_width);
+        let mut renderer = Renderer::with_theme(writer, width, self.theme.unwrap_or_default());
+        renderer.set_options(self.options);
+        if let Some(handler) = self.image_handler {
+            renderer.set_image_handler(handler);
+        }
+        renderer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PlainBackend;
+
+    #[test]
+    fn test_builder_setters() {
+        let renderer = RendererBuilder::new()
+            .width(30)
+            .theme(Theme::dark().with_backend(PlainBackend))
+            .hyperlinks(false)
+            .code_frame(true)
+            .wrap(WrapMode::None)
+            .tab_width(2)
+            .build_renderer(Vec::new());
+        let options = renderer.options();
+        assert!(!options.profile.hyperlinks && options.code_frame);
+        assert_eq!((options.wrap, options.tab_width), (WrapMode::None, Some(2)));
+    }
+
+    #[test]
+    fn test_builder_applies_settings() {
+        let renderer = RendererBuilder::new()
+            .width(30)
+            .theme(Theme::dark().with_backend(PlainBackend))
+            .configure(|options| options.code_line_numbers = true)
+            .build_renderer(Vec::new());
+        assert!(renderer.options().code_line_numbers);
+        assert_eq!(renderer.theme().backend.reset(), "");
+    }
+}
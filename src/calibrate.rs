@@ -0,0 +1,119 @@
+//! Measurement of how wide the terminal draws characters whose width
+//! varies between terminals.
+
+use std::io;
+
+use crate::options::{EmojiNormalization, RendererOptions};
+
+/// East Asian ambiguous-width character, one column in Western terminals
+/// and two in CJK ones.
+const AMBIGUOUS_PROBE: &str = "\u{25cb}";
+
+/// Family emoji joined with ZWJ, two columns where the terminal draws the
+/// sequence as one glyph and six where it draws each member.
+const JOINED_PROBE: &str = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+
+/// Widths the terminal was measured to give characters that terminals
+/// disagree on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// East Asian ambiguous-width characters, such as `○` or `±`, take two
+    /// columns.
+    pub ambiguous_wide: bool,
+    /// Emoji joined with ZWJ are drawn as a single two-column glyph.
+    pub joins_emoji: bool,
+}
+
+impl Calibration {
+    /// Measure the terminal with `columns`, which draws its argument at the
+    /// start of a line and returns the column the cursor ends up in.
+    pub fn measure<F>(mut columns: F) -> io::Result<Self>
+    where
+        F: FnMut(&str) -> io::Result<usize>,
+    {
+        Ok(Self {
+            ambiguous_wide: columns(AMBIGUOUS_PROBE)? >= 2,
+            joins_emoji: columns(JOINED_PROBE)? <= 2,
+        })
+    }
+
+    /// Measure the terminal on stdout by printing each probe and asking for
+    /// the cursor position. The probes are erased again, and the terminal is
+    /// in raw mode only while it answers.
+    ///
+    /// Fails if stdout is not a terminal or the terminal does not report
+    /// its cursor position.
+    #[cfg(feature = "crossterm")]
+    pub fn detect() -> io::Result<Self> {
+        use std::io::Write;
+
+        use crossterm::terminal;
+
+        let was_raw = terminal::is_raw_mode_enabled()?;
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        let result = Self::measure(|probe| {
+            write!(stdout, "\r{}", probe)?;
+            stdout.flush()?;
+            let (column, _) = crossterm::cursor::position()?;
+            write!(stdout, "\r\x1b[K")?;
+            Ok(column as usize)
+        });
+        if !was_raw {
+            terminal::disable_raw_mode()?;
+        }
+        result
+    }
+
+    /// Set `options` to measure text the way the terminal draws it.
+    ///
+    /// Where joined emoji are drawn as their members, the joiners are
+    /// removed so that each member is measured on its own, unless the
+    /// options already normalize emoji.
+    pub fn apply(&self, options: &mut RendererOptions) {
+        options.ambiguous_wide = self.ambiguous_wide;
+        if !self.joins_emoji && options.emoji_normalization == EmojiNormalization::Keep {
+            options.emoji_normalization = EmojiNormalization::SplitJoined;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_cjk_terminal_without_emoji_joining() {
+        let calibration =
+            Calibration::measure(|probe| Ok(if probe == AMBIGUOUS_PROBE { 2 } else { 6 })).unwrap();
+        assert_eq!(
+            calibration,
+            Calibration {
+                ambiguous_wide: true,
+                joins_emoji: false
+            }
+        );
+
+        let mut options = RendererOptions::default();
+        calibration.apply(&mut options);
+        assert!(options.ambiguous_wide);
+        assert_eq!(options.emoji_normalization, EmojiNormalization::SplitJoined);
+    }
+
+    #[test]
+    fn test_apply_keeps_chosen_normalization() {
+        let calibration =
+            Calibration::measure(|probe| Ok(if probe == AMBIGUOUS_PROBE { 1 } else { 2 })).unwrap();
+        let mut options = RendererOptions {
+            ambiguous_wide: true,
+            emoji_normalization: EmojiNormalization::StripSelectors,
+            ..Default::default()
+        };
+        calibration.apply(&mut options);
+        assert!(!options.ambiguous_wide);
+        assert_eq!(
+            options.emoji_normalization,
+            EmojiNormalization::StripSelectors
+        );
+    }
+}
@@ -1,94 +1,613 @@
 //! Code block rendering with syntax highlighting and line wrapping.
 
+use crate::backend::OutputBackend;
+use crate::inline::render_inline_elements;
+use crate::options::HighlightEngine;
+use crate::summary::detect_language;
+use crate::theme::{Style, Theme};
+use crate::width::{grapheme_width, grapheme_width_with, text_width_with};
+use streamdown_parser::{InlineElement, InlineParser};
+use unicode_segmentation::UnicodeSegmentation;
+#[cfg(feature = "tree-sitter")]
+use crate::treesitter::TreeSitterHighlighter;
+#[cfg(feature = "syntect")]
 use crate::utils::{detect_theme_mode, ThemeMode};
-use streamdown_render::code::code_wrap;
-use syntect::easy::HighlightLines;
-use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
-use syntect::util::as_24_bit_terminal_escaped;
-
-const RESET: &str = "\x1b[0m";
+#[cfg(feature = "syntect")]
+use colored::Color;
+#[cfg(feature = "syntect")]
+use std::io;
+#[cfg(feature = "syntect")]
+use std::path::Path;
+#[cfg(feature = "syntect")]
+use std::sync::OnceLock;
+#[cfg(feature = "syntect")]
+use syntect::highlighting::{FontStyle, HighlightIterator, HighlightState, Highlighter, ThemeSet};
+#[cfg(feature = "syntect")]
+use syntect::parsing::{ParseState, ScopeStack, SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
 
 /// Code block highlighter using syntect.
+///
+/// The bundled syntax definitions and themes are loaded on the first
+/// highlighted line and shared by all highlighters, so creating a renderer
+/// for output without code blocks stays cheap.
+///
+/// With the tree-sitter engine selected, languages that have a bundled
+/// tree-sitter grammar are highlighted by it instead.
+///
+/// Without the `highlighting` or `minimal-syntaxes` feature, code is passed
+/// through unstyled.
 pub struct CodeHighlighter {
-    syntax_set: SyntaxSet,
-    theme_set: ThemeSet,
-    theme_mode: ThemeMode,
+    #[cfg(feature = "tree-sitter")]
+    tree_sitter: Option<TreeSitterHighlighter>,
+    // Background the colors are chosen for, detected on first use
+    #[cfg(feature = "syntect")]
+    theme_mode: Option<ThemeMode>,
+    // Bundled syntaxes plus those registered by the user, if any
+    #[cfg(feature = "syntect")]
+    custom_syntaxes: Option<SyntaxSet>,
+    // Parser state carried between the lines of the current code block
+    #[cfg(feature = "syntect")]
+    block: Option<BlockState>,
+    // Whether ambiguous-width characters take two columns when wrapping
+    ambiguous_wide: bool,
+}
+
+/// Highlighting state of a code block, so constructs spanning lines such as
+/// block comments and multi-line strings are highlighted correctly.
+#[cfg(feature = "syntect")]
+struct BlockState {
+    language: Option<String>,
+    parse: ParseState,
+    highlight: HighlightState,
 }
 
 impl Default for CodeHighlighter {
     fn default() -> Self {
         Self {
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            theme_set: ThemeSet::load_defaults(),
-            theme_mode: detect_theme_mode(),
+            #[cfg(feature = "tree-sitter")]
+            tree_sitter: None,
+            #[cfg(feature = "syntect")]
+            theme_mode: None,
+            #[cfg(feature = "syntect")]
+            custom_syntaxes: None,
+            #[cfg(feature = "syntect")]
+            block: None,
+            ambiguous_wide: false,
         }
     }
 }
 
 impl CodeHighlighter {
+    /// Forget the state of the previous code block.
+    pub fn start_block(&mut self) {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree_sitter) = self.tree_sitter.as_mut() {
+            tree_sitter.start_block();
+        }
+        #[cfg(feature = "syntect")]
+        {
+            self.block = None;
+        }
+    }
+
+    /// Select the engine highlighting code blocks.
+    pub(crate) fn set_engine(&mut self, engine: HighlightEngine) {
+        match engine {
+            HighlightEngine::Syntect => {
+                #[cfg(feature = "tree-sitter")]
+                {
+                    self.tree_sitter = None;
+                }
+            }
+            HighlightEngine::TreeSitter => {
+                #[cfg(feature = "tree-sitter")]
+                if self.tree_sitter.is_none() {
+                    self.tree_sitter = Some(TreeSitterHighlighter::new());
+                }
+            }
+        }
+    }
+
+    /// Measure ambiguous-width characters as two columns when wrapping.
+    pub(crate) fn set_ambiguous_wide(&mut self, wide: bool) {
+        self.ambiguous_wide = wide;
+    }
+
+    /// Choose colors for a `mode` background instead of detecting it.
+    #[cfg(feature = "syntect")]
+    pub(crate) fn set_theme_mode(&mut self, mode: ThemeMode) {
+        self.theme_mode = Some(mode);
+    }
+
+    /// Register a `.sublime-syntax` definition given as YAML source, in
+    /// addition to the bundled ones.
+    #[cfg(feature = "syntect")]
+    pub fn add_syntax(&mut self, definition: &str) -> io::Result<()> {
+        let syntax = SyntaxDefinition::load_from_str(definition, true, None)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.extend_syntaxes(|builder| {
+            builder.add(syntax);
+            Ok(())
+        })
+    }
+
+    /// Register every `.sublime-syntax` definition found in `path` and its
+    /// subdirectories.
+    #[cfg(feature = "syntect")]
+    pub fn add_syntax_from_folder(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.extend_syntaxes(|builder| {
+            builder
+                .add_from_folder(path, true)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+    }
+
+    /// Rebuild the syntax set with additional definitions. The current set
+    /// is kept if adding fails.
+    #[cfg(feature = "syntect")]
+    fn extend_syntaxes(
+        &mut self,
+        add: impl FnOnce(&mut SyntaxSetBuilder) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut builder = self.syntaxes().clone().into_builder();
+        add(&mut builder)?;
+        self.custom_syntaxes = Some(builder.build());
+        // Parser state refers into the set it was created from
+        self.block = None;
+        Ok(())
+    }
+
+    /// Syntaxes used for highlighting.
+    #[cfg(feature = "syntect")]
+    fn syntaxes(&self) -> &SyntaxSet {
+        match &self.custom_syntaxes {
+            Some(syntaxes) => syntaxes,
+            None => syntax_set(),
+        }
+    }
+
+    /// Highlight a single line of code with the selected engine.
+    fn highlight_line(
+        &mut self,
+        line: &str,
+        language: Option<&str>,
+        end_of_line: bool,
+        backend: &dyn OutputBackend,
+    ) -> String {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree_sitter) = self.tree_sitter.as_mut() {
+            if let Some(highlighted) =
+                tree_sitter.highlight_line(line, language, end_of_line, backend)
+            {
+                return highlighted;
+            }
+        }
+        self.highlight_syntect(line, language, end_of_line, backend)
+    }
+
     /// Highlight a single line of code.
-    fn highlight_line(&self, line: &str, language: Option<&str>) -> String {
-        let syntax = language
-            .and_then(|lang| self.syntax_set.find_syntax_by_token(lang))
-            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+    #[cfg(not(feature = "syntect"))]
+    fn highlight_syntect(
+        &mut self,
+        line: &str,
+        _language: Option<&str>,
+        _end_of_line: bool,
+        _backend: &dyn OutputBackend,
+    ) -> String {
+        line.to_string()
+    }
 
-        let theme_name = match self.theme_mode {
+    /// Highlight a single line of code, continuing the state of the lines
+    /// before it in the same block.
+    ///
+    /// `end_of_line` marks the last wrapped piece of a source line, after
+    /// which the parser sees a newline.
+    #[cfg(feature = "syntect")]
+    fn highlight_syntect(
+        &mut self,
+        line: &str,
+        language: Option<&str>,
+        end_of_line: bool,
+        backend: &dyn OutputBackend,
+    ) -> String {
+        let theme_name = match *self.theme_mode.get_or_insert_with(detect_theme_mode) {
             ThemeMode::Dark => "base16-ocean.dark",
             ThemeMode::Light => "InspiredGitHub",
         };
-        let theme = &self.theme_set.themes[theme_name];
-        let mut highlighter = HighlightLines::new(syntax, theme);
+        let syntax_set = match &self.custom_syntaxes {
+            Some(syntaxes) => syntaxes,
+            None => syntax_set(),
+        };
+        let highlighter = Highlighter::new(&theme_set().themes[theme_name]);
 
-        match highlighter.highlight_line(line, &self.syntax_set) {
-            Ok(ranges) => as_24_bit_terminal_escaped(&ranges[..], false),
+        if !matches!(&self.block, Some(block) if block.language.as_deref() == language) {
+            let syntax = language
+                .and_then(|lang| syntax_set.find_syntax_by_token(lang))
+                .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+            self.block = Some(BlockState {
+                language: language.map(str::to_string),
+                parse: ParseState::new(syntax),
+                highlight: HighlightState::new(&highlighter, ScopeStack::new()),
+            });
+        }
+        let Some(block) = self.block.as_mut() else {
+            return line.to_string();
+        };
+
+        let text = if end_of_line {
+            format!("{}\n", line)
+        } else {
+            line.to_string()
+        };
+        match block.parse.parse_line(&text, syntax_set) {
+            Ok(ops) => HighlightIterator::new(&mut block.highlight, &ops, &text, &highlighter)
+                .map(|(style, piece)| {
+                    backend.paint(&token_style(&style), piece.trim_end_matches('\n'))
+                })
+                .collect(),
             Err(_) => line.to_string(),
         }
     }
 
     /// Render a code line with margin, wrapping if needed.
     ///
-    /// Returns multiple lines if the code exceeds the available width.
+    /// Returns multiple lines if the code exceeds the available width. The
+    /// first line is prefixed with `first_margin`, wrapped continuation lines
+    /// with `next_margin`.
     pub fn render_code_line(
-        &self,
+        &mut self,
         line: &str,
         language: Option<&str>,
-        margin: &str,
+        first_margin: &str,
+        next_margin: &str,
         width: usize,
+        backend: &dyn OutputBackend,
     ) -> Vec<String> {
+        let reset = backend.reset();
         // Use code_wrap with pretty_broken=true for line wrapping
-        let (indent, wrapped_lines) = code_wrap(line, width, true);
+        let (indent, wrapped_lines) = code_wrap(line, width, true, self.ambiguous_wide);
 
         let mut result = Vec::new();
 
+        let last = wrapped_lines.len().saturating_sub(1);
         for (i, code_line) in wrapped_lines.iter().enumerate() {
-            let highlighted = self.highlight_line(code_line, language);
+            let highlighted = self.highlight_line(code_line, language, i == last, backend);
 
             // Add continuation indent for wrapped lines
             let line_indent = if i == 0 {
-                ""
+                String::new()
             } else {
-                &"  ".repeat(indent.min(4) / 2 + 1)
+                continuation_indent(indent)
             };
 
-            result.push(format!("{}{}{}{}", margin, line_indent, highlighted, RESET));
+            let margin = if i == 0 { first_margin } else { next_margin };
+            result.push(format!("{}{}{}{}", margin, line_indent, highlighted, reset));
         }
 
         if result.is_empty() {
-            result.push(format!("{}{}", margin, RESET));
+            result.push(format!("{}{}", first_margin, reset));
         }
 
         result
     }
 }
 
+/// Bundled syntax definitions, loaded on first use.
+#[cfg(feature = "highlighting")]
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Syntax definitions of common languages, dumped by the build script and
+/// loaded on first use.
+#[cfg(all(feature = "syntect", not(feature = "highlighting")))]
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(|| {
+        let dump = include_bytes!(concat!(env!("OUT_DIR"), "/minimal_syntaxes.packdump"));
+        syntect::dumps::from_uncompressed_data(dump).expect("syntax dump written by build.rs")
+    })
+}
+
+/// Bundled highlighting themes, loaded on first use.
+#[cfg(feature = "syntect")]
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Convert a syntect token style into a theme `Style`.
+#[cfg(feature = "syntect")]
+fn token_style(style: &syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut result = Style::new().fg(Color::TrueColor {
+        r: fg.r,
+        g: fg.g,
+        b: fg.b,
+    });
+    if style.font_style.contains(FontStyle::BOLD) {
+        result = result.bold();
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        result = result.italic();
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        result = result.underline();
+    }
+    result
+}
+
+/// Source file extensions recognized in prose before a code block.
+const EXTENSION_HINTS: &[&str] = &[
+    "bash", "c", "cpp", "cs", "css", "go", "h", "hpp", "html", "java", "js", "json", "jsx", "kt",
+    "lua", "php", "py", "rb", "rs", "sh", "sql", "swift", "toml", "ts", "tsx", "yaml", "yml",
+    "zsh",
+];
+
+/// Extension of the last source file name mentioned in `text`, e.g. `rs`
+/// for "save this as `main.rs`".
+pub(crate) fn extension_hint(text: &str) -> Option<&str> {
+    text.split(|c: char| c.is_whitespace() || "`'\"()[],:;".contains(c))
+        .filter_map(|word| {
+            let (stem, extension) = word.trim_end_matches(['.', '!', '?']).rsplit_once('.')?;
+            let known = !stem.is_empty() && EXTENSION_HINTS.contains(&extension);
+            known.then_some(extension)
+        })
+        .last()
+}
+
+/// Guess the language of a code block whose fence names none, from a
+/// shebang or typical keywords on its first line, or else from a file
+/// extension mentioned before the block.
+pub(crate) fn detect_fence_language(first_line: &str, hint: Option<&str>) -> Option<String> {
+    // An interpreter path, unlike Rust's `#![attribute]`
+    let shebang = first_line
+        .strip_prefix("#!")
+        .filter(|command| command.starts_with('/') || command.starts_with(" /"));
+    if let Some(command) = shebang {
+        let mut words = command.split_whitespace();
+        let program = words.next()?.rsplit('/').next()?;
+        let program = if program == "env" {
+            words.find(|word| !word.starts_with('-'))?
+        } else {
+            program
+        };
+        // python3 and python3.12 are highlighted as python
+        let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let language = match name {
+            "node" | "deno" | "bun" => "javascript",
+            "sh" | "zsh" | "dash" => "bash",
+            other => other,
+        };
+        return Some(language.to_string());
+    }
+    match detect_language(first_line) {
+        Some((language, confidence)) if confidence > 0.5 => Some(language.to_string()),
+        _ => hint.map(str::to_string),
+    }
+}
+
+/// Style of a line of a `diff` or `patch` code block: added, removed and
+/// hunk header lines. Context lines, the `---`/`+++` file headers and other
+/// languages have none.
+pub(crate) fn diff_line_style<'a>(
+    line: &str,
+    language: Option<&str>,
+    theme: &'a Theme,
+) -> Option<&'a Style> {
+    if !matches!(language?, "diff" | "patch") {
+        return None;
+    }
+    let file_header = |marker| line == marker || line.starts_with(&format!("{} ", marker));
+    if file_header("---") || file_header("+++") {
+        None
+    } else if line.starts_with("@@") {
+        Some(&theme.diff_hunk)
+    } else if line.starts_with('+') {
+        Some(&theme.diff_added)
+    } else if line.starts_with('-') {
+        Some(&theme.diff_removed)
+    } else {
+        None
+    }
+}
+
+/// Indentation of the wrapped continuations of a code line indented by
+/// `indent` columns.
+fn continuation_indent(indent: usize) -> String {
+    "  ".repeat(indent.min(4) / 2 + 1)
+}
+
+/// Replace each tab with spaces up to the next multiple of `tab_width`
+/// columns.
+pub(crate) fn expand_tabs(line: &str, tab_width: usize) -> String {
+    if tab_width == 0 || !line.contains('\t') {
+        return line.to_string();
+    }
+    let mut out = String::with_capacity(line.len());
+    let mut column = 0;
+    for grapheme in line.graphemes(true) {
+        if grapheme == "\t" {
+            let spaces = tab_width - column % tab_width;
+            out.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            out.push_str(grapheme);
+            column += grapheme_width(grapheme);
+        }
+    }
+    out
+}
+
+/// Split a code line into pieces fitting `width` columns, never inside a
+/// grapheme cluster. Continuations leave room for their indentation and,
+/// with `pretty_broken`, break after whitespace where possible and drop the
+/// blanks they would start with; otherwise every character is kept. With
+/// `ambiguous_wide`, ambiguous-width characters are measured as two columns.
+///
+/// Returns the indentation of the line in columns and at least one piece.
+pub(crate) fn code_wrap(
+    line: &str,
+    width: usize,
+    pretty_broken: bool,
+    ambiguous_wide: bool,
+) -> (usize, Vec<String>) {
+    let measure = |text: &str| text_width_with(text, ambiguous_wide);
+    let indent = measure(&line[..line.len() - line.trim_start().len()]);
+    if width == 0 || measure(line) <= width {
+        return (indent, vec![line.to_string()]);
+    }
+    let next_width = width.saturating_sub(continuation_indent(indent).len()).max(1);
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    let mut piece_width = 0;
+    // Offset in `piece` after whitespace following code
+    let mut break_at = None;
+    for grapheme in line.graphemes(true) {
+        let limit = if pieces.is_empty() { width } else { next_width };
+        let grapheme_width = grapheme_width_with(grapheme, ambiguous_wide);
+        if piece_width > 0 && piece_width + grapheme_width > limit {
+            let rest = match break_at {
+                Some(at) if pretty_broken => piece.split_off(at),
+                _ => String::new(),
+            };
+            pieces.push(std::mem::replace(&mut piece, rest));
+            piece_width = measure(&piece);
+            break_at = None;
+        }
+        let blank = grapheme.trim().is_empty();
+        if pretty_broken && blank && piece.is_empty() && !pieces.is_empty() {
+            continue;
+        }
+        if blank && !piece.trim().is_empty() {
+            break_at = Some(piece.len() + grapheme.len());
+        }
+        piece.push_str(grapheme);
+        piece_width += grapheme_width;
+    }
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+    (indent, pieces)
+}
+
+/// Render a code line in a single style, wrapping if needed.
+pub(crate) fn render_styled_code_line(
+    line: &str,
+    style: &Style,
+    first_margin: &str,
+    next_margin: &str,
+    width: usize,
+    ambiguous_wide: bool,
+    backend: &dyn OutputBackend,
+) -> Vec<String> {
+    let (_, wrapped_lines) = code_wrap(line, width, true, ambiguous_wide);
+    wrapped_lines
+        .iter()
+        .enumerate()
+        .map(|(i, piece)| {
+            let margin = if i == 0 { first_margin } else { next_margin };
+            format!("{}{}", margin, backend.paint(style, piece))
+        })
+        .collect()
+}
+
+/// Doc comment found on a line of code.
+pub(crate) struct DocLine<'a> {
+    /// Indentation and comment marker or opening quotes.
+    pub prefix: &'a str,
+    /// Markdown prose of the comment.
+    pub prose: &'a str,
+    /// Closing quotes of a docstring, if on this line.
+    pub suffix: &'a str,
+}
+
+const DOCSTRING_QUOTES: &str = "\"\"\"";
+
+/// Split a doc comment line of a Rust or Python code block into its marker
+/// and prose.
+///
+/// Rust `///` and `//!` comments are recognized line by line; Python
+/// docstrings may span lines, so `in_docstring` carries that state between
+/// calls.
+pub(crate) fn split_doc_comment<'a>(
+    line: &'a str,
+    language: Option<&str>,
+    in_docstring: &mut bool,
+) -> Option<DocLine<'a>> {
+    let indent = line.len() - line.trim_start().len();
+    let code = &line[indent..];
+    match language? {
+        "rust" | "rs" => {
+            let is_doc = (code.starts_with("///") && !code.starts_with("////"))
+                || code.starts_with("//!");
+            if !is_doc {
+                return None;
+            }
+            let marker = indent + 3 + usize::from(code[3..].starts_with(' '));
+            Some(DocLine {
+                prefix: &line[..marker],
+                prose: &line[marker..],
+                suffix: "",
+            })
+        }
+        "python" | "py" => {
+            let start = if *in_docstring {
+                indent
+            } else if code.starts_with(DOCSTRING_QUOTES) {
+                indent + DOCSTRING_QUOTES.len()
+            } else {
+                return None;
+            };
+            let body = &line[start..];
+            let end = body.find(DOCSTRING_QUOTES);
+            *in_docstring = end.is_none();
+            let end = end.unwrap_or(body.len());
+            Some(DocLine {
+                prefix: &line[..start],
+                prose: &body[..end],
+                suffix: &body[end..],
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Render doc comment prose as markdown: `# ` lines become headers, and
+/// plain text takes the theme's doc comment style.
+pub(crate) fn render_doc_prose(prose: &str, theme: &Theme) -> String {
+    if let Some(heading) = prose.strip_prefix("# ") {
+        return theme.paint(&theme.doc_heading, heading);
+    }
+    InlineParser::new()
+        .parse(prose)
+        .iter()
+        .map(|element| match element {
+            InlineElement::Text(text) => theme.paint(&theme.doc_comment, text),
+            other => render_inline_elements(std::slice::from_ref(other), theme),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use streamdown_render::code::code_wrap;
+    use super::*;
+    use crate::backend::AnsiBackend;
+    use crate::color::ColorDepth;
+    use crate::width::text_width;
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("\tx", 4), "    x");
+        assert_eq!(expand_tabs("ab\tc", 4), "ab  c");
+        assert_eq!(expand_tabs("日本\tx", 4), "日本    x");
+        assert_eq!(expand_tabs("a\tb", 0), "a\tb");
+    }
 
     #[test]
     fn test_code_wrap_short_line() {
-        let (indent, lines) = code_wrap("let x = 1;", 80, true);
+        let (indent, lines) = code_wrap("let x = 1;", 80, true, false);
         assert_eq!(indent, 0);
         assert_eq!(lines.len(), 1);
         assert_eq!(lines[0], "let x = 1;");
@@ -96,7 +615,7 @@ mod tests {
 
     #[test]
     fn test_code_wrap_with_indent() {
-        let (indent, lines) = code_wrap("    let x = 1;", 80, true);
+        let (indent, lines) = code_wrap("    let x = 1;", 80, true, false);
         assert_eq!(indent, 4);
         assert_eq!(lines.len(), 1);
     }
@@ -104,14 +623,160 @@ mod tests {
     #[test]
     fn test_code_wrap_long_line() {
         let long_line = "x".repeat(100);
-        let (_, lines) = code_wrap(&long_line, 40, true);
+        let (_, lines) = code_wrap(&long_line, 40, true, false);
         assert!(lines.len() > 1);
     }
 
+    #[test]
+    fn test_code_wrap_keeps_grapheme_clusters() {
+        let (_, lines) = code_wrap(&"日本語".repeat(10), 20, true, false);
+        assert!(lines.iter().all(|line| text_width(line) <= 20), "{:?}", lines);
+        assert_eq!(lines.concat(), "日本語".repeat(10));
+
+        let accented = "e\u{301}".repeat(30);
+        let (_, lines) = code_wrap(&accented, 10, true, false);
+        assert_eq!(lines[0], "e\u{301}".repeat(10));
+        assert!(lines.iter().all(|line| line.starts_with('e')), "{:?}", lines);
+    }
+
+    #[test]
+    fn test_code_wrap_breaks_after_whitespace() {
+        let (indent, lines) = code_wrap("  let value = compute(first, second);", 20, true, false);
+        assert_eq!(indent, 2);
+        assert_eq!(lines, vec!["  let value = ", "compute(first, ", "second);"]);
+    }
+
+    #[test]
+    fn test_code_wrap_keeps_whitespace_unless_pretty() {
+        let line = "abc    def";
+        let (_, lines) = code_wrap(line, 4, false, false);
+        assert_eq!(lines.concat(), line);
+        let (_, lines) = code_wrap(line, 4, true, false);
+        assert_eq!(lines.concat(), "abc def");
+        // Indentation is counted in columns, not bytes
+        let (indent, _) = code_wrap("\u{3000}x", 80, true, false);
+        assert_eq!(indent, 2);
+    }
+
     #[test]
     fn test_code_wrap_empty() {
-        let (indent, lines) = code_wrap("", 80, true);
+        let (indent, lines) = code_wrap("", 80, true, false);
         assert_eq!(indent, 0);
         assert_eq!(lines.len(), 1);
     }
+
+    #[test]
+    fn test_split_rust_doc_comment() {
+        let mut in_docstring = false;
+        let doc = split_doc_comment("    /// Adds `a`.", Some("rust"), &mut in_docstring).unwrap();
+        assert_eq!((doc.prefix, doc.prose, doc.suffix), ("    /// ", "Adds `a`.", ""));
+        assert!(split_doc_comment("//// banner", Some("rust"), &mut in_docstring).is_none());
+        assert!(split_doc_comment("// note", Some("rust"), &mut in_docstring).is_none());
+        assert!(split_doc_comment("/// doc", Some("c"), &mut in_docstring).is_none());
+    }
+
+    #[test]
+    fn test_split_python_docstring() {
+        let mut in_docstring = false;
+        let lines = [
+            "def f():",
+            "    \"\"\"Summary.",
+            "    More *text*.",
+            "    \"\"\"",
+            "    return 1",
+        ];
+        let docs: Vec<Option<(&str, &str, &str)>> = lines
+            .iter()
+            .map(|line| {
+                split_doc_comment(line, Some("python"), &mut in_docstring)
+                    .map(|doc| (doc.prefix, doc.prose, doc.suffix))
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                None,
+                Some(("    \"\"\"", "Summary.", "")),
+                Some(("    ", "More *text*.", "")),
+                Some(("    ", "", "\"\"\"")),
+                None,
+            ]
+        );
+    }
+
+    #[cfg(feature = "syntect")]
+    #[test]
+    fn test_block_comment_spans_lines() {
+        let backend = AnsiBackend::new(ColorDepth::TrueColor);
+        let mut highlighter = CodeHighlighter::default();
+        highlighter.start_block();
+        let first = highlighter.render_code_line("/* a", Some("rust"), "", "", 80, &backend);
+        let second = highlighter.render_code_line("b */", Some("rust"), "", "", 80, &backend);
+
+        let mut fresh = CodeHighlighter::default();
+        let alone = fresh.render_code_line("b */", Some("rust"), "", "", 80, &backend);
+        let color = |line: &str| line.split('m').next().unwrap_or_default().to_string();
+        assert_eq!(color(&first[0]), color(&second[0]));
+        assert_ne!(second, alone);
+    }
+
+    #[cfg(feature = "syntect")]
+    #[test]
+    fn test_add_custom_syntax() {
+        let mut highlighter = CodeHighlighter::default();
+        assert!(highlighter.syntaxes().find_syntax_by_token("acme").is_none());
+
+        let definition = r"%YAML 1.2
+---
+name: Acme
+file_extensions: [acme]
+scope: source.acme
+contexts:
+  main:
+    - match: '\bwidget\b'
+      scope: keyword.control.acme
+";
+        highlighter.add_syntax(definition).unwrap();
+        assert!(highlighter.syntaxes().find_syntax_by_token("acme").is_some());
+        assert!(highlighter.syntaxes().find_syntax_by_token("rust").is_some());
+
+        assert!(highlighter.add_syntax("not: [a syntax").is_err());
+        assert!(highlighter.syntaxes().find_syntax_by_token("acme").is_some());
+    }
+
+    #[test]
+    fn test_diff_lines_use_theme_styles() {
+        let theme = Theme::dark();
+        let backend = AnsiBackend::new(ColorDepth::Ansi16);
+        let painted = |line, language| {
+            diff_line_style(line, Some(language), &theme).map(|style| backend.paint(style, "x"))
+        };
+        let expected = |style| Some(backend.paint(style, "x"));
+        assert_eq!(painted("+added", "diff"), expected(&theme.diff_added));
+        assert_eq!(painted("-removed", "patch"), expected(&theme.diff_removed));
+        assert_eq!(painted("@@ -1,2 +1,3 @@", "diff"), expected(&theme.diff_hunk));
+        assert_eq!(painted(" context", "diff"), None);
+        assert_eq!(painted("--- a/src/lib.rs", "diff"), None);
+        assert_eq!(painted("+++ b/src/lib.rs", "diff"), None);
+        assert_eq!(painted("---removed", "diff"), expected(&theme.diff_removed));
+        assert_eq!(painted("+x", "rust"), None);
+    }
+
+    #[test]
+    fn test_detect_fence_language() {
+        assert_eq!(detect_fence_language("#!/usr/bin/env python3", None).as_deref(), Some("python"));
+        assert_eq!(detect_fence_language("#!/bin/sh -e", None).as_deref(), Some("bash"));
+        assert_eq!(detect_fence_language("#! /bin/zsh", None).as_deref(), Some("bash"));
+        assert_eq!(detect_fence_language("#![allow(unused)]", Some("rs")).as_deref(), Some("rs"));
+        assert_eq!(detect_fence_language("fn main() {", Some("py")).as_deref(), Some("rust"));
+        assert_eq!(detect_fence_language("x = 1", Some("py")).as_deref(), Some("py"));
+        assert_eq!(detect_fence_language("x = 1", None), None);
+    }
+
+    #[test]
+    fn test_extension_hint() {
+        assert_eq!(extension_hint("Save this as `src/main.rs`:"), Some("rs"));
+        assert_eq!(extension_hint("Edit config.toml, then run.py."), Some("py"));
+        assert_eq!(extension_hint("See e.g. the docs."), None);
+    }
 }
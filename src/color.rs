@@ -0,0 +1,248 @@
+//! Terminal color capabilities and color downsampling.
+
+use colored::Color;
+
+/// How many colors the output terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// No colors; text attributes such as bold are still emitted.
+    None,
+    /// The 16 basic ANSI colors.
+    Ansi16,
+    /// The 256-color xterm palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// The color depth of the [`TerminalProfile`](crate::TerminalProfile)
+    /// detected from the environment.
+    pub fn detect() -> Self {
+        crate::profile::TerminalProfile::detect().color
+    }
+
+    /// Determine the color depth from the given environment values.
+    pub fn from_env(no_color: Option<&str>, term: Option<&str>, colorterm: Option<&str>) -> Self {
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return Self::None;
+        }
+        if term == Some("dumb") {
+            return Self::None;
+        }
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            return Self::TrueColor;
+        }
+        if term.is_some_and(|term| term.contains("256color")) {
+            return Self::Ansi256;
+        }
+        Self::Ansi16
+    }
+}
+
+/// SGR parameter selecting `color` as foreground, downsampled to `depth`.
+pub fn fg_param(color: Color, depth: ColorDepth) -> Option<String> {
+    color_param(color, depth, false)
+}
+
+/// SGR parameter selecting `color` as background, downsampled to `depth`.
+pub fn bg_param(color: Color, depth: ColorDepth) -> Option<String> {
+    color_param(color, depth, true)
+}
+
+/// SGR parameter selecting `color` for underlines, or `None` at depths
+/// where terminals generally lack underline colors.
+pub fn underline_color_param(color: Color, depth: ColorDepth) -> Option<String> {
+    match (depth, color) {
+        (ColorDepth::TrueColor, Color::TrueColor { r, g, b }) => {
+            Some(format!("58;2;{};{};{}", r, g, b))
+        }
+        (ColorDepth::TrueColor | ColorDepth::Ansi256, _) => {
+            let native = color.to_fg_str();
+            let parts: Vec<u8> = native.split(';').filter_map(|p| p.parse().ok()).collect();
+            let index = match parts.as_slice() {
+                [_, 5, n] => *n,
+                [n @ 30..=37] => n - 30,
+                [n @ 90..=97] => n - 90 + 8,
+                _ => {
+                    let (r, g, b) = color_rgb(color);
+                    rgb_to_256(r, g, b)
+                }
+            };
+            Some(format!("58;5;{}", index))
+        }
+        _ => None,
+    }
+}
+
+fn color_param(color: Color, depth: ColorDepth, background: bool) -> Option<String> {
+    let (base, extended) = if background { (40, 48) } else { (30, 38) };
+    if depth == ColorDepth::None {
+        return None;
+    }
+    if let Color::TrueColor { r, g, b } = color {
+        return Some(match depth {
+            ColorDepth::TrueColor => format!("{};2;{};{};{}", extended, r, g, b),
+            ColorDepth::Ansi256 => format!("{};5;{}", extended, rgb_to_256(r, g, b)),
+            _ => basic_param(rgb_to_16(r, g, b), base),
+        });
+    }
+
+    let native = if background {
+        color.to_bg_str()
+    } else {
+        color.to_fg_str()
+    };
+    let parts: Vec<u8> = native.split(';').filter_map(|p| p.parse().ok()).collect();
+    match (depth, parts.as_slice()) {
+        (ColorDepth::Ansi16, [_, 5, n]) => {
+            let (r, g, b) = palette_rgb(*n);
+            Some(basic_param(rgb_to_16(r, g, b), base))
+        }
+        _ => Some(native.into_owned()),
+    }
+}
+
+/// SGR parameter for a 16-color palette index.
+fn basic_param(index: u8, base: u8) -> String {
+    if index < 8 {
+        (base + index).to_string()
+    } else {
+        (base + 60 + index - 8).to_string()
+    }
+}
+
+/// Nearest entry of the 256-color palette, using the 6x6x6 cube or the
+/// gray ramp, whichever is closer.
+pub fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| -> u8 {
+        if v < 48 {
+            0
+        } else if v < 115 {
+            1
+        } else {
+            (v - 35) / 40
+        }
+    };
+    let (ci, cj, ck) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = 16 + 36 * ci + 6 * cj + ck;
+
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let gray = if average > 238 {
+        255
+    } else {
+        232 + (average.saturating_sub(3) / 10) as u8
+    };
+
+    if distance((r, g, b), palette_rgb(gray)) < distance((r, g, b), palette_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Nearest entry of the 16 basic colors.
+pub fn rgb_to_16(r: u8, g: u8, b: u8) -> u8 {
+    (0..16u8)
+        .min_by_key(|&i| distance((r, g, b), palette_rgb(i)))
+        .unwrap_or(0)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Resolve a terminal color to RGB using the xterm default palette.
+pub fn color_rgb(color: Color) -> (u8, u8, u8) {
+    if let Color::TrueColor { r, g, b } = color {
+        return (r, g, b);
+    }
+    let code = color.to_fg_str();
+    let parts: Vec<u8> = code.split(';').filter_map(|p| p.parse().ok()).collect();
+    match parts.as_slice() {
+        [38, 5, n] => palette_rgb(*n),
+        [n @ 30..=37] => palette_rgb(n - 30),
+        [n @ 90..=97] => palette_rgb(n - 90 + 8),
+        _ => (0, 0, 0),
+    }
+}
+
+/// RGB value of an entry in the 256-color xterm palette.
+pub fn palette_rgb(index: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00),
+        (0xcd, 0x00, 0x00),
+        (0x00, 0xcd, 0x00),
+        (0xcd, 0xcd, 0x00),
+        (0x00, 0x00, 0xee),
+        (0xcd, 0x00, 0xcd),
+        (0x00, 0xcd, 0xcd),
+        (0xe5, 0xe5, 0xe5),
+        (0x7f, 0x7f, 0x7f),
+        (0xff, 0x00, 0x00),
+        (0x00, 0xff, 0x00),
+        (0xff, 0xff, 0x00),
+        (0x5c, 0x5c, 0xff),
+        (0xff, 0x00, 0xff),
+        (0x00, 0xff, 0xff),
+        (0xff, 0xff, 0xff),
+    ];
+    match index {
+        0..=15 => BASIC[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(ColorDepth::from_env(Some("1"), Some("xterm-256color"), None), ColorDepth::None);
+        assert_eq!(ColorDepth::from_env(Some(""), Some("xterm"), None), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::from_env(None, Some("dumb"), Some("truecolor")), ColorDepth::None);
+        assert_eq!(ColorDepth::from_env(None, Some("xterm"), Some("24bit")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env(None, Some("xterm-256color"), None), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_env(None, None, None), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_truecolor_downsampling() {
+        let orange = Color::TrueColor { r: 255, g: 135, b: 0 };
+        assert_eq!(fg_param(orange, ColorDepth::TrueColor).unwrap(), "38;2;255;135;0");
+        assert_eq!(fg_param(orange, ColorDepth::Ansi256).unwrap(), "38;5;208");
+        assert_eq!(bg_param(orange, ColorDepth::Ansi256).unwrap(), "48;5;208");
+        assert_eq!(fg_param(orange, ColorDepth::Ansi16).unwrap(), "33");
+        assert_eq!(fg_param(orange, ColorDepth::None), None);
+    }
+
+    #[test]
+    fn test_basic_colors_pass_through() {
+        assert_eq!(fg_param(Color::Red, ColorDepth::Ansi16).unwrap(), "31");
+        assert_eq!(bg_param(Color::BrightBlue, ColorDepth::Ansi16).unwrap(), "104");
+    }
+
+    #[test]
+    fn test_gray_ramp() {
+        assert_eq!(rgb_to_256(128, 128, 128), 244);
+        assert_eq!(rgb_to_256(0, 0, 0), 16);
+    }
+
+    #[test]
+    fn test_palette_rgb() {
+        assert_eq!(palette_rgb(1), (0xcd, 0x00, 0x00));
+        assert_eq!(palette_rgb(16), (0, 0, 0));
+        assert_eq!(palette_rgb(231), (255, 255, 255));
+        assert_eq!(palette_rgb(232), (8, 8, 8));
+    }
+}
@@ -0,0 +1,81 @@
+//! Renderer that drives several backends from one event stream.
+
+use std::io;
+
+use streamdown_parser::ParseEvent;
+
+use crate::renderer::EventRenderer;
+
+/// Renderer that forwards every event to each of its backends in order,
+/// e.g. the terminal, an HTML transcript, and a recording at once.
+///
+/// All backends see every event even if one fails; the first error is
+/// returned afterwards.
+#[derive(Default)]
+pub struct CompositeRenderer<'a>(pub Vec<Box<dyn EventRenderer + 'a>>);
+
+impl<'a> CompositeRenderer<'a> {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add a backend after the existing ones.
+    pub fn push(&mut self, backend: impl EventRenderer + 'a) {
+        self.0.push(Box::new(backend));
+    }
+
+    fn for_each(
+        &mut self,
+        mut f: impl FnMut(&mut dyn EventRenderer) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut result = Ok(());
+        for backend in &mut self.0 {
+            let outcome = f(backend.as_mut());
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
+    }
+}
+
+impl EventRenderer for CompositeRenderer<'_> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.for_each(|backend| backend.render_event(event))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.for_each(|backend| backend.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HtmlRenderer, Renderer, Theme};
+
+    #[test]
+    fn test_events_reach_every_backend() {
+        let mut terminal = Vec::new();
+        let mut html = Vec::new();
+        {
+            let mut composite = CompositeRenderer::new();
+            composite.push(Renderer::with_theme(&mut terminal, 40, Theme::plain()));
+            composite.push(HtmlRenderer::new(&mut html));
+            let events = [
+                ParseEvent::Heading {
+                    level: 3,
+                    content: "Title".to_string(),
+                },
+                ParseEvent::Text("body".to_string()),
+                ParseEvent::Newline,
+            ];
+            for event in &events {
+                composite.render_event(event).unwrap();
+            }
+            composite.finish().unwrap();
+        }
+        assert_eq!(String::from_utf8(terminal).unwrap(), "### Title\nbody\n");
+        assert!(String::from_utf8(html).unwrap().contains("<p>body\n</p>"));
+    }
+}
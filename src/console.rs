@@ -0,0 +1,170 @@
+//! Windows console support: enabling VT processing, and console attributes
+//! for consoles without it.
+
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use crossterm::queue;
+use crossterm::style::{ResetColor, SetBackgroundColor, SetForegroundColor};
+
+use crate::color::ColorDepth;
+use crate::crossterm_backend::CrosstermBackend;
+use crate::sgr::apply_params;
+use crate::strip::scan_escape;
+use crate::theme::Style;
+
+/// Enable escape sequence processing for the console, once per process.
+///
+/// Returns whether escape sequences are interpreted. Always `true` outside
+/// Windows.
+pub fn enable_virtual_terminal() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        #[cfg(windows)]
+        {
+            crossterm::ansi_support::supports_ansi()
+        }
+        #[cfg(not(windows))]
+        {
+            true
+        }
+    })
+}
+
+/// Writer for consoles that may not interpret escape sequences.
+///
+/// Output passes through unchanged when VT processing could be enabled.
+/// Otherwise the colors of SGR sequences are replayed as crossterm color
+/// commands, which crossterm applies as console attributes, and everything
+/// else, such as bold, hyperlinks and erasing, is dropped.
+///
+/// ```no_run
+/// use forge_markdown_stream::{ConsoleWriter, StreamdownRenderer};
+///
+/// let console = ConsoleWriter::new(std::io::stdout());
+/// let mut renderer = StreamdownRenderer::new(console, 80);
+/// renderer.push("**bold**\n").unwrap();
+/// renderer.finish().unwrap();
+/// ```
+pub struct ConsoleWriter<W: Write> {
+    inner: W,
+    legacy: bool,
+    style: Style,
+    // Incomplete escape sequence at the end of the last write
+    pending: Vec<u8>,
+}
+
+impl<W: Write> ConsoleWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_legacy(inner, !enable_virtual_terminal())
+    }
+
+    pub(crate) fn with_legacy(inner: W, legacy: bool) -> Self {
+        Self {
+            inner,
+            legacy,
+            style: Style::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Whether escape sequences are translated to console attributes.
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Write text, translating complete SGR sequences and dropping other
+    /// escapes. An unfinished sequence at the end is kept for the next call.
+    fn translate(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(buf);
+        let data = std::mem::take(&mut self.pending);
+        let mut text_start = 0;
+        let mut i = 0;
+        while i < data.len() {
+            if data[i] != 0x1b {
+                i += 1;
+                continue;
+            }
+            self.inner.write_all(&data[text_start..i])?;
+            let Some((_, end)) = scan_escape(&data[i..]) else {
+                self.pending = data[i..].to_vec();
+                return Ok(());
+            };
+            let sequence = &data[i..i + end];
+            if let [0x1b, b'[', params @ .., b'm'] = sequence {
+                apply_params(&mut self.style, &String::from_utf8_lossy(params));
+                self.apply_style()?;
+            }
+            i += end;
+            text_start = i;
+        }
+        self.inner.write_all(&data[text_start..])
+    }
+
+    fn apply_style(&mut self) -> io::Result<()> {
+        // Console attributes have the 16 basic colors and no text attributes
+        let style = CrosstermBackend::new(ColorDepth::Ansi16).content_style(&self.style);
+        queue!(self.inner, ResetColor)?;
+        if let Some(color) = style.foreground_color {
+            queue!(self.inner, SetForegroundColor(color))?;
+        }
+        if let Some(color) = style.background_color {
+            queue!(self.inner, SetBackgroundColor(color))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ConsoleWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.legacy {
+            return self.inner.write(buf);
+        }
+        self.translate(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passthrough_when_vt_enabled() {
+        let mut console = ConsoleWriter::with_legacy(Vec::new(), false);
+        console.write_all(b"\x1b]8;;x\x1b\\a\x1b[1mb").unwrap();
+        assert_eq!(console.into_inner(), b"\x1b]8;;x\x1b\\a\x1b[1mb");
+    }
+
+    #[test]
+    fn test_legacy_drops_non_sgr_sequences() {
+        let mut console = ConsoleWriter::with_legacy(Vec::new(), true);
+        // Split inside a hyperlink sequence
+        console.write_all(b"\x1b]8;;https://exa").unwrap();
+        console
+            .write_all(b"mple.com\x1b\\link\x1b]8;;\x1b\\ \x1b[2Fdone")
+            .unwrap();
+        let output = String::from_utf8(console.into_inner()).unwrap();
+        assert_eq!(crate::strip_ansi(&output), "link done");
+        assert!(!output.contains("]8;;"));
+    }
+
+    #[test]
+    fn test_legacy_tracks_sgr_state() {
+        let mut console = ConsoleWriter::with_legacy(Vec::new(), true);
+        console.write_all(b"\x1b[1;31mred\x1b[0m").unwrap();
+        assert_eq!(console.style, Style::new());
+        console.write_all(b"\x1b[3").unwrap();
+        assert_eq!(console.pending, b"\x1b[3");
+        console.write_all(b"m").unwrap();
+        assert!(console.style.italic);
+    }
+}
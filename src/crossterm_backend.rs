@@ -0,0 +1,342 @@
+//! Backend emitting styles through crossterm commands.
+
+use colored::Color;
+use std::io::Write;
+
+use crossterm::cursor::{
+    MoveLeft, MoveTo, MoveToColumn, MoveToPreviousLine, RestorePosition, SavePosition,
+};
+use crossterm::style::{
+    Attribute, Attributes, Color as CrosstermColor, ContentStyle, SetAttribute, SetStyle,
+    StyledContent,
+};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::Command;
+
+use crate::backend::{AnsiBackend, OutputBackend};
+use crate::console::{enable_virtual_terminal, ConsoleWriter};
+use crate::color::{color_rgb, palette_rgb, rgb_to_16, rgb_to_256, ColorDepth};
+use crate::guard::RESTORE_SEQUENCE;
+use crate::sgr::{BASIC_COLORS, RESET};
+use crate::theme::{Style, UnderlineStyle};
+
+/// The 16 basic colors in palette order, as crossterm names them.
+const NAMED_COLORS: [CrosstermColor; 16] = [
+    CrosstermColor::Black,
+    CrosstermColor::DarkRed,
+    CrosstermColor::DarkGreen,
+    CrosstermColor::DarkYellow,
+    CrosstermColor::DarkBlue,
+    CrosstermColor::DarkMagenta,
+    CrosstermColor::DarkCyan,
+    CrosstermColor::Grey,
+    CrosstermColor::DarkGrey,
+    CrosstermColor::Red,
+    CrosstermColor::Green,
+    CrosstermColor::Yellow,
+    CrosstermColor::Blue,
+    CrosstermColor::Magenta,
+    CrosstermColor::Cyan,
+    CrosstermColor::White,
+];
+
+/// Backend producing its sequences with crossterm commands.
+///
+/// Output should go through [`writer`](Self::writer), which on legacy
+/// Windows consoles applies colors through the console API. The backend
+/// then reports no erasing, scroll regions or hyperlinks, which the
+/// console API cannot replay. Embedders writing through crossterm can also
+/// call [`content_style`](Self::content_style) or [`styled`](Self::styled)
+/// and queue the result with `PrintStyledContent`.
+///
+/// Colors are downsampled to the backend's color depth.
+///
+/// ```no_run
+/// use forge_markdown_stream::{CrosstermBackend, Renderer, Theme};
+///
+/// let backend = CrosstermBackend::detect();
+/// let writer = backend.writer(std::io::stdout());
+/// let mut renderer = Renderer::with_theme(writer, 80, Theme::default().with_backend(backend));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CrosstermBackend {
+    depth: ColorDepth,
+    // Escape sequences are not interpreted by the console
+    legacy: bool,
+}
+
+impl CrosstermBackend {
+    pub fn new(depth: ColorDepth) -> Self {
+        Self {
+            depth,
+            legacy: false,
+        }
+    }
+
+    /// Create a backend for the color depth detected from the environment,
+    /// enabling escape sequences on Windows consoles where possible.
+    pub fn detect() -> Self {
+        Self {
+            depth: ColorDepth::detect(),
+            legacy: !enable_virtual_terminal(),
+        }
+    }
+
+    /// Wrap `inner` so the backend's output reaches the console, through
+    /// the console API if escape sequences are not interpreted.
+    pub fn writer<W: Write>(&self, inner: W) -> ConsoleWriter<W> {
+        ConsoleWriter::with_legacy(inner, self.legacy)
+    }
+
+    pub fn depth(&self) -> ColorDepth {
+        self.depth
+    }
+
+    /// The crossterm style equivalent to `style` at this color depth.
+    pub fn content_style(&self, style: &Style) -> ContentStyle {
+        let extended = matches!(self.depth, ColorDepth::Ansi256 | ColorDepth::TrueColor);
+        let mut attributes = Attributes::default();
+        if style.bold {
+            attributes.set(Attribute::Bold);
+        }
+        if style.dimmed {
+            attributes.set(Attribute::Dim);
+        }
+        if style.italic {
+            attributes.set(Attribute::Italic);
+        }
+        if style.underline {
+            attributes.set(match style.underline_style {
+                _ if !extended => Attribute::Underlined,
+                UnderlineStyle::Straight => Attribute::Underlined,
+                UnderlineStyle::Double => Attribute::DoubleUnderlined,
+                UnderlineStyle::Curly => Attribute::Undercurled,
+                UnderlineStyle::Dotted => Attribute::Underdotted,
+                UnderlineStyle::Dashed => Attribute::Underdashed,
+            });
+        }
+        if style.strikethrough {
+            attributes.set(Attribute::CrossedOut);
+        }
+        ContentStyle {
+            foreground_color: style.fg.and_then(|color| self.color(color)),
+            background_color: style.bg.and_then(|color| self.color(color)),
+            underline_color: style
+                .underline_color
+                .filter(|_| style.underline && extended)
+                .and_then(|color| self.color(color)),
+            attributes,
+        }
+    }
+
+    /// `text` with `style` applied, ready to queue with `PrintStyledContent`.
+    pub fn styled<'a>(&self, style: &Style, text: &'a str) -> StyledContent<&'a str> {
+        StyledContent::new(self.content_style(style), text)
+    }
+
+    /// `color` downsampled to this backend's depth, or `None` without colors.
+    fn color(&self, color: Color) -> Option<CrosstermColor> {
+        let index = match (self.depth, color) {
+            (ColorDepth::None, _) => return None,
+            (ColorDepth::TrueColor, Color::TrueColor { r, g, b }) => {
+                return Some(CrosstermColor::Rgb { r, g, b })
+            }
+            (ColorDepth::Ansi16, Color::TrueColor { r, g, b }) => rgb_to_16(r, g, b),
+            (_, Color::TrueColor { r, g, b }) => rgb_to_256(r, g, b),
+            _ => match BASIC_COLORS.iter().position(|basic| *basic == color) {
+                Some(index) => index as u8,
+                None => {
+                    let (r, g, b) = color_rgb(color);
+                    rgb_to_256(r, g, b)
+                }
+            },
+        };
+        let index = match self.depth {
+            ColorDepth::Ansi16 if index >= 16 => {
+                let (r, g, b) = palette_rgb(index);
+                rgb_to_16(r, g, b)
+            }
+            _ => index,
+        };
+        Some(match NAMED_COLORS.get(index as usize) {
+            Some(named) => *named,
+            None => CrosstermColor::AnsiValue(index),
+        })
+    }
+}
+
+impl Default for CrosstermBackend {
+    fn default() -> Self {
+        Self::new(ColorDepth::TrueColor)
+    }
+}
+
+impl OutputBackend for CrosstermBackend {
+    fn paint(&self, style: &Style, text: &str) -> String {
+        let content_style = self.content_style(style);
+        let styled = content_style.foreground_color.is_some()
+            || content_style.background_color.is_some()
+            || content_style.underline_color.is_some()
+            || !content_style.attributes.is_empty();
+        if !styled {
+            return text.to_string();
+        }
+        let open = ansi(&[&SetStyle(content_style)]);
+        // Resume the style after resets closing nested spans
+        let body = text.replace(RESET, &format!("{}{}", RESET, open));
+        format!(
+            "{}{}{}",
+            open,
+            body,
+            ansi(&[&SetAttribute(Attribute::Reset)])
+        )
+    }
+
+    fn hyperlink(&self, text: &str, url: &str) -> String {
+        if self.legacy {
+            return text.to_string();
+        }
+        // crossterm has no hyperlink command
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    }
+
+    fn reset(&self) -> &'static str {
+        RESET
+    }
+
+    fn restore(&self) -> &'static str {
+        RESTORE_SEQUENCE
+    }
+
+    fn end_line(&self) -> &'static str {
+        "\x1b[0m\x1b[K"
+    }
+
+    fn can_erase(&self) -> bool {
+        !self.legacy
+    }
+
+    fn can_draw_images(&self) -> bool {
+        !self.legacy
+    }
+
+    fn erase_lines(&self, count: usize) -> Option<String> {
+        if self.legacy {
+            return None;
+        }
+        // A count of 0 would still move up a line, so stay on this one
+        if count == 0 {
+            return Some(ansi(&[&MoveToColumn(0), &Clear(ClearType::FromCursorDown)]));
+        }
+        let count = u16::try_from(count).unwrap_or(u16::MAX);
+        Some(ansi(&[
+            &MoveToPreviousLine(count),
+            &Clear(ClearType::FromCursorDown),
+        ]))
+    }
+
+    fn erase_columns(&self, count: usize) -> Option<String> {
+        if self.legacy {
+            return None;
+        }
+        if count == 0 {
+            return Some(ansi(&[&Clear(ClearType::UntilNewLine)]));
+        }
+        let count = u16::try_from(count).unwrap_or(u16::MAX);
+        Some(ansi(&[&MoveLeft(count), &Clear(ClearType::UntilNewLine)]))
+    }
+
+    fn scroll_region(&self, bottom: Option<usize>) -> Option<String> {
+        if self.legacy {
+            return None;
+        }
+        // crossterm has no scroll region command
+        AnsiBackend::default().scroll_region(bottom)
+    }
+
+    fn write_row(&self, row: usize, text: &str) -> Option<String> {
+        if self.legacy {
+            return None;
+        }
+        let row = u16::try_from(row.saturating_sub(1)).unwrap_or(u16::MAX);
+        Some(format!(
+            "{}{}{}",
+            ansi(&[&SavePosition, &MoveTo(0, row), &Clear(ClearType::CurrentLine)]),
+            text,
+            ansi(&[&RestorePosition])
+        ))
+    }
+}
+
+/// The ANSI sequences of `commands`, in order.
+fn ansi(commands: &[&dyn AnsiCommand]) -> String {
+    let mut out = String::new();
+    for command in commands {
+        command.write(&mut out);
+    }
+    out
+}
+
+/// Object-safe view of [`Command::write_ansi`].
+trait AnsiCommand {
+    fn write(&self, out: &mut String);
+}
+
+impl<C: Command> AnsiCommand for C {
+    fn write(&self, out: &mut String) {
+        // Writing to a String cannot fail
+        let _ = self.write_ansi(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_matches_ansi_backend_text() {
+        let style = Style::new()
+            .bold()
+            .fg(Color::TrueColor { r: 255, g: 0, b: 0 });
+        let painted = CrosstermBackend::default().paint(&style, "red");
+        assert!(painted.starts_with('\x1b'));
+        assert!(painted.ends_with(RESET));
+        assert_eq!(crate::strip_ansi(&painted), "red");
+        assert_eq!(CrosstermBackend::default().paint(&Style::new(), "x"), "x");
+    }
+
+    #[test]
+    fn test_colors_are_downsampled() {
+        let red = Color::TrueColor { r: 205, g: 0, b: 0 };
+        assert_eq!(
+            CrosstermBackend::new(ColorDepth::Ansi16).color(red),
+            Some(CrosstermColor::DarkRed)
+        );
+        assert_eq!(
+            CrosstermBackend::new(ColorDepth::Ansi256).color(Color::BrightBlue),
+            Some(CrosstermColor::Blue)
+        );
+        assert_eq!(CrosstermBackend::new(ColorDepth::None).color(red), None);
+    }
+
+    #[test]
+    fn test_erase_sequences_match_ansi_backend() {
+        let ansi = AnsiBackend::default();
+        let crossterm = CrosstermBackend::default();
+        assert_eq!(crossterm.erase_lines(3), ansi.erase_lines(3));
+        assert_eq!(crossterm.erase_columns(2), ansi.erase_columns(2));
+        assert_eq!(crossterm.erase_lines(0).as_deref(), Some("\x1b[1G\x1b[J"));
+    }
+
+    #[test]
+    fn test_legacy_console_reports_no_escapes() {
+        let backend = CrosstermBackend {
+            depth: ColorDepth::Ansi16,
+            legacy: true,
+        };
+        assert!(!backend.can_erase());
+        assert_eq!(backend.erase_lines(2), None);
+        assert_eq!(backend.hyperlink("docs", "https://docs.rs"), "docs");
+        assert!(backend.writer(Vec::new()).is_legacy());
+    }
+}
@@ -0,0 +1,294 @@
+//! Side-by-side HTML diff of the layout produced by two configurations.
+
+use std::io;
+
+use crate::html::escape;
+use crate::options::RendererOptions;
+use crate::strip::strip_ansi;
+use crate::theme::Theme;
+use crate::StreamdownRenderer;
+
+/// One configuration of a layout comparison.
+#[derive(Clone)]
+pub struct LayoutConfig {
+    /// Column heading of this side in the report.
+    pub label: String,
+    pub width: usize,
+    pub theme: Theme,
+    pub options: RendererOptions,
+}
+
+impl LayoutConfig {
+    pub fn new(label: impl Into<String>, width: usize) -> Self {
+        Self {
+            label: label.into(),
+            width,
+            theme: Theme::plain(),
+            options: RendererOptions::default(),
+        }
+    }
+
+    /// Render `markdown` and return the plain text of each output line.
+    pub fn render(&self, markdown: &str) -> io::Result<Vec<String>> {
+        let mut out = Vec::new();
+        let mut renderer = StreamdownRenderer::with_theme(&mut out, self.width, self.theme.clone());
+        renderer.set_options(self.options.clone());
+        renderer.push(markdown)?;
+        renderer.finish()?;
+        let text = strip_ansi(&String::from_utf8_lossy(&out));
+        Ok(text.lines().map(|line| line.trim_end().to_string()).collect())
+    }
+}
+
+/// How a row of the diff differs between the two sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Change {
+    Same,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// Render every document of `corpus`, given as name and markdown pairs,
+/// with both configurations and return an HTML page showing the output
+/// lines side by side with differing lines highlighted.
+///
+/// Only the text layout is compared; styles are ignored.
+pub fn layout_diff_html(
+    corpus: &[(&str, &str)],
+    left: &LayoutConfig,
+    right: &LayoutConfig,
+) -> io::Result<String> {
+    let mut documents = Vec::new();
+    for (name, markdown) in corpus {
+        documents.push((*name, left.render(markdown)?, right.render(markdown)?));
+    }
+    Ok(recorded_diff_html(&documents, &left.label, &right.label))
+}
+
+/// Return an HTML page showing recorded output lines side by side, e.g.
+/// the lines of the same documents rendered by two versions of this crate.
+/// Documents are given as name, left lines and right lines.
+///
+/// Only the text layout is compared; escape sequences and trailing
+/// whitespace in the lines are ignored.
+pub fn recorded_diff_html(
+    documents: &[(&str, Vec<String>, Vec<String>)],
+    left_label: &str,
+    right_label: &str,
+) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><style>\n\
+         td { font-family: monospace; white-space: pre; vertical-align: top; }\n\
+         .changed { background: #fff3c4; }\n\
+         .removed { background: #ffd7d5; }\n\
+         .added { background: #d4f8db; }\n\
+         </style></head><body>\n",
+    );
+    let plain = |lines: &[String]| -> Vec<String> {
+        lines.iter().map(|line| strip_ansi(line).trim_end().to_string()).collect()
+    };
+    for (name, left, right) in documents {
+        let (before, after) = (plain(left), plain(right));
+        let rows = diff_rows(&before, &after);
+        let changes = rows.iter().filter(|(change, ..)| *change != Change::Same).count();
+        html.push_str(&format!(
+            "<h2>{} ({} changed lines)</h2>\n<table>\n<tr><th>{}</th><th>{}</th></tr>\n",
+            escape(name),
+            changes,
+            escape(left_label),
+            escape(right_label)
+        ));
+        for (change, before, after) in rows {
+            let class = match change {
+                Change::Same => "",
+                Change::Changed => " class=\"changed\"",
+                Change::Removed => " class=\"removed\"",
+                Change::Added => " class=\"added\"",
+            };
+            html.push_str(&format!(
+                "<tr{}><td>{}</td><td>{}</td></tr>\n",
+                class,
+                escape(before.unwrap_or("")),
+                escape(after.unwrap_or(""))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Step of an edit script turning one set of lines into another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Align two sets of lines by a shortest edit script, pairing runs of
+/// removed and added lines as changed rows.
+fn diff_rows<'a>(
+    before: &'a [String],
+    after: &'a [String],
+) -> Vec<(Change, Option<&'a str>, Option<&'a str>)> {
+    let mut rows = Vec::new();
+    let mut removed: Vec<&str> = Vec::new();
+    let mut added: Vec<&str> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for edit in edit_script(before, after) {
+        match edit {
+            Edit::Keep => {
+                flush_run(&mut rows, &mut removed, &mut added);
+                rows.push((Change::Same, Some(before[i].as_str()), Some(after[j].as_str())));
+                i += 1;
+                j += 1;
+            }
+            Edit::Remove => {
+                removed.push(&before[i]);
+                i += 1;
+            }
+            Edit::Add => {
+                added.push(&after[j]);
+                j += 1;
+            }
+        }
+    }
+    flush_run(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Shortest edit script turning `before` into `after`, found with Myers'
+/// algorithm in time and memory growing with the number of differences
+/// rather than the product of the lengths.
+fn edit_script(before: &[String], after: &[String]) -> Vec<Edit> {
+    let (n, m) = (before.len() as isize, after.len() as isize);
+    let max = n + m;
+    // v[k + max] is the furthest x reached on diagonal k = x - y
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    // Diagonals -d..=d of `v` after each number of differences d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let at = (k + max) as usize;
+            let mut x = if k == -d || (k != d && v[at - 1] < v[at + 1]) {
+                v[at + 1]
+            } else {
+                v[at - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && before[x as usize] == after[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at] = x;
+            if x >= n && y >= m {
+                trace.push(v[(max - d) as usize..=(max + d) as usize].to_vec());
+                break 'search;
+            }
+        }
+        trace.push(v[(max - d) as usize..=(max + d) as usize].to_vec());
+    }
+
+    // Walk back from the end, one difference at a time
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let previous = &trace[d as usize - 1];
+        let reached = |k: isize| previous[(k + d - 1) as usize];
+        let k = x - y;
+        let from = if k == -d || (k != d && reached(k - 1) < reached(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (from_x, from_y) = (reached(from), reached(from) - from);
+        while x > from_x && y > from_y {
+            script.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        script.push(if x == from_x { Edit::Add } else { Edit::Remove });
+        (x, y) = (from_x, from_y);
+    }
+    script.extend((0..x).map(|_| Edit::Keep));
+    script.reverse();
+    script
+}
+
+/// Emit a run of removed and added lines, pairing them up as changes.
+fn flush_run<'a>(
+    rows: &mut Vec<(Change, Option<&'a str>, Option<&'a str>)>,
+    removed: &mut Vec<&'a str>,
+    added: &mut Vec<&'a str>,
+) {
+    let len = removed.len().max(added.len());
+    for k in 0..len {
+        let row = match (removed.get(k), added.get(k)) {
+            (Some(before), Some(after)) => (Change::Changed, Some(*before), Some(*after)),
+            (Some(before), None) => (Change::Removed, Some(*before), None),
+            (None, after) => (Change::Added, None, after.copied()),
+        };
+        rows.push(row);
+    }
+    removed.clear();
+    added.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_rows_pair_changed_lines() {
+        let before = lines("a\nb\nc\nd");
+        let after = lines("a\nB\nc\nd\ne");
+        let changes: Vec<_> = diff_rows(&before, &after)
+            .into_iter()
+            .map(|(change, ..)| change)
+            .collect();
+        assert_eq!(
+            changes,
+            [Change::Same, Change::Changed, Change::Same, Change::Same, Change::Added]
+        );
+    }
+
+    #[test]
+    fn test_width_change_is_highlighted() {
+        let corpus = [("wrap", "one two three four five six seven eight\n")];
+        let html =
+            layout_diff_html(&corpus, &LayoutConfig::new("80", 80), &LayoutConfig::new("20", 20))
+                .unwrap();
+        assert!(html.contains("<h2>wrap ("));
+        assert!(html.contains("class=\"changed\""));
+        assert!(html.contains("<th>80</th><th>20</th>"));
+    }
+
+    #[test]
+    fn test_recorded_lines_are_compared_as_text() {
+        let before = lines("\x1b[1mtitle\x1b[0m\nbody  ");
+        let after = lines("title\nbody\nmore");
+        let html = recorded_diff_html(&[("doc", before, after)], "v1", "v2");
+        assert!(html.contains("<h2>doc (1 changed lines)</h2>"), "{}", html);
+        assert!(html.contains("<tr class=\"added\"><td></td><td>more</td></tr>"));
+    }
+
+    #[test]
+    fn test_edit_script_of_long_similar_documents() {
+        let before: Vec<String> = (0..20_000).map(|i| i.to_string()).collect();
+        let mut after = before.clone();
+        after[10_000] = "changed".to_string();
+        after.remove(15_000);
+        let script = edit_script(&before, &after);
+        let count = |edit| script.iter().filter(|&&e| e == edit).count();
+        assert_eq!((count(Edit::Remove), count(Edit::Add)), (2, 1));
+        assert_eq!(count(Edit::Keep), 19_998);
+        assert_eq!(edit_script(&[], &lines("a")), [Edit::Add]);
+        assert_eq!(edit_script(&lines("a"), &[]), [Edit::Remove]);
+    }
+}
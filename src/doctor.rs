@@ -0,0 +1,102 @@
+//! Capability report for bug reports about layout and color glitches.
+
+use std::io::{self, Write};
+
+use crate::color::ColorDepth;
+use crate::options::RendererOptions;
+use crate::profile::TerminalProfile;
+use crate::theme::Theme;
+use crate::utils::{detect_terminal_width, detect_theme_mode, ThemeMode};
+use crate::StreamdownRenderer;
+
+/// Markdown exercising every block type.
+const SAMPLE: &str = "\
+# Heading 1
+## Heading 2
+Paragraph with **bold**, *italic*, ~~strike~~, `code` and a [link](https://example.com).
+
+- bullet
+  1. ordered
+- [x] task
+
+> quote
+
+| left | right |
+|------|------:|
+| a    | 1.5   |
+
+```rust
+fn main() {}
+```
+
+---
+";
+
+/// Emoji whose width terminals disagree on.
+const EMOJI: [&str; 3] = [
+    "\u{1f600}",
+    "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+    "\u{2764}\u{fe0f}",
+];
+
+/// Write a report of the detected terminal capabilities followed by a
+/// sample of every block type, for attaching to bug reports.
+pub fn doctor<W: Write>(writer: W) -> io::Result<()> {
+    report(writer, detect_theme_mode)
+}
+
+/// Write the report, asking `theme_mode` for the terminal background.
+fn report<W: Write>(mut writer: W, theme_mode: impl FnOnce() -> ThemeMode) -> io::Result<()> {
+    let width = detect_terminal_width();
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let profile = TerminalProfile::detect();
+    let mode = theme_mode();
+    let background = match mode {
+        ThemeMode::Dark => "dark",
+        ThemeMode::Light => "light",
+    };
+
+    writeln!(writer, "width         {}", width)?;
+    writeln!(writer, "color depth   {:?}", ColorDepth::detect())?;
+    writeln!(writer, "hyperlinks    {}", if profile.hyperlinks { "likely" } else { "unknown" })?;
+    writeln!(writer, "unicode       {}", if profile.unicode { "yes" } else { "ascii only" })?;
+    writeln!(writer, "theme         {}", background)?;
+    for name in ["TERM", "TERM_PROGRAM", "COLORTERM", "COLORFGBG"] {
+        writeln!(writer, "{:<13} {}", name, var(name))?;
+    }
+    // Each emoji should fill exactly the two cells between the bars
+    writeln!(writer, "emoji width   |{}|", EMOJI.join("|"))?;
+    writeln!(writer, "              |{}|", ["--"; 3].join("|"))?;
+    writeln!(writer)?;
+
+    let mut theme = match mode {
+        ThemeMode::Dark => Theme::dark(),
+        ThemeMode::Light => Theme::light(),
+    };
+    theme.backend = profile.backend();
+    let mut renderer = StreamdownRenderer::with_theme(&mut writer, width, theme);
+    renderer.set_options(RendererOptions {
+        profile,
+        ..Default::default()
+    });
+    #[cfg(feature = "syntect")]
+    renderer.highlighter_mut().set_theme_mode(mode);
+    renderer.push(SAMPLE)?;
+    renderer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_includes_capabilities_and_sample() {
+        let mut out = Vec::new();
+        report(&mut out, || ThemeMode::Light).unwrap();
+        let report = crate::strip_ansi(&String::from_utf8(out).unwrap());
+        assert!(report.contains("color depth"));
+        assert!(report.contains("theme         light"));
+        assert!(report.contains("emoji width"));
+        assert!(report.contains("fn main"));
+    }
+}
@@ -0,0 +1,622 @@
+//! Retained document model with sections that can be folded.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::options::RendererOptions;
+use crate::repair::Fence;
+use crate::strip::strip_ansi;
+use crate::style::InlineStyler;
+use crate::theme::{Style, Theme, UnderlineStyle};
+use crate::utils::{fnv1a, SharedBuffer};
+use crate::StreamdownRenderer;
+
+/// A heading and the content up to the next heading.
+#[derive(Clone, Debug)]
+pub struct Section {
+    /// Heading level, or 0 for the content before the first heading.
+    pub level: u8,
+    /// Lines of the markdown source the section was rendered from.
+    pub source: Range<usize>,
+    /// Hash of the section's markdown source. It is the same across runs
+    /// and releases, so the sections of a regenerated message can be
+    /// compared with the previous attempt's to skip identical ones.
+    pub hash: u64,
+    /// Rendered lines of the heading.
+    pub heading: Vec<String>,
+    /// Rendered lines of the content below the heading.
+    pub body: Vec<String>,
+    /// Source lines that each rendered line of the heading and then the
+    /// body was rendered from.
+    pub line_sources: Vec<Range<usize>>,
+    /// Whether the body and all subsections are hidden.
+    pub folded: bool,
+    /// Whether the section was edited or regenerated since it was rendered,
+    /// and is shown dimmed.
+    pub stale: bool,
+}
+
+/// Rendered markdown split into sections at its headings, for interactive
+/// views that fold sections and redraw the visible lines.
+///
+/// Each section is rendered on its own, so state such as link numbering
+/// restarts at every heading.
+pub struct Document {
+    sections: Vec<Section>,
+    source: Vec<String>,
+    width: usize,
+    theme: Theme,
+    options: RendererOptions,
+    // Columns shown of tables narrowed by `set_visible_columns`, by the
+    // index of the table in the document
+    visible_columns: HashMap<usize, Vec<usize>>,
+    // Byte offset of each source line, for mapping annotations
+    line_starts: Vec<usize>,
+    // Notes on byte ranges of the source, with their source lines
+    annotations: Vec<(Range<usize>, String)>,
+}
+
+impl Document {
+    /// Render `markdown` into sections.
+    pub fn render(
+        markdown: &str,
+        width: usize,
+        theme: Theme,
+        options: RendererOptions,
+    ) -> io::Result<Self> {
+        let lines: Vec<&str> = markdown.lines().collect();
+        let mut document = Self {
+            sections: Vec::new(),
+            source: lines.iter().map(|line| line.to_string()).collect(),
+            width,
+            theme,
+            options,
+            visible_columns: HashMap::new(),
+            line_starts: markdown
+                .split_inclusive('\n')
+                .scan(0, |start, line| {
+                    let line_start = *start;
+                    *start += line.len();
+                    Some(line_start)
+                })
+                .collect(),
+            annotations: Vec::new(),
+        };
+        let tables = find_tables(&lines);
+        for (level, source) in split_sections(&lines) {
+            let section = document.render_section(level, source, &tables)?;
+            document.sections.push(section);
+        }
+        Ok(document)
+    }
+
+    /// Render the section at `level` spanning the `source` lines, with the
+    /// columns of the `tables` narrowed by `set_visible_columns`.
+    fn render_section(
+        &self,
+        level: u8,
+        source: Range<usize>,
+        tables: &[Range<usize>],
+    ) -> io::Result<Section> {
+        let original = &self.source[source.clone()];
+        let mut lines = original.to_vec();
+        for (index, table) in tables.iter().enumerate() {
+            let Some(columns) = self.visible_columns.get(&index) else {
+                continue;
+            };
+            for i in table.clone().filter(|i| source.contains(i)) {
+                lines[i - source.start] = select_cells(&lines[i - source.start], columns);
+            }
+        }
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        // The preamble has no heading line
+        let heading_lines = if level == 0 { 0 } else { 1 };
+        let (heading, body) = lines.split_at(heading_lines.min(lines.len()));
+        let body_start = source.start + heading.len();
+        let (width, theme, options) = (self.width, &self.theme, &self.options);
+        let (heading, mut line_sources) =
+            render_lines(heading, source.start, width, theme, options)?;
+        let (body, body_sources) = render_lines(body, body_start, width, theme, options)?;
+        line_sources.extend(body_sources);
+        Ok(Section {
+            level,
+            hash: content_hash(original),
+            heading,
+            body,
+            line_sources,
+            source,
+            folded: false,
+            stale: false,
+        })
+    }
+
+    /// Show only the `columns` of the table at index `table`, counting the
+    /// tables of the document in order, and render its section again, e.g.
+    /// when the user hides columns of a table too wide for the terminal.
+    /// Columns are shown in the order given.
+    pub fn set_visible_columns(&mut self, table: usize, columns: &[usize]) -> io::Result<()> {
+        self.visible_columns.insert(table, columns.to_vec());
+        self.render_table_section(table)
+    }
+
+    /// Show every column of the table at index `table` again.
+    pub fn show_all_columns(&mut self, table: usize) -> io::Result<()> {
+        if self.visible_columns.remove(&table).is_none() {
+            return Ok(());
+        }
+        self.render_table_section(table)
+    }
+
+    fn render_table_section(&mut self, table: usize) -> io::Result<()> {
+        let tables = find_tables(&self.source);
+        let Some(lines) = tables.get(table) else {
+            return Ok(());
+        };
+        let Some(index) = self
+            .sections
+            .iter()
+            .position(|section| section.source.contains(&lines.start))
+        else {
+            return Ok(());
+        };
+        let previous = &self.sections[index];
+        let mut section = self.render_section(previous.level, previous.source.clone(), &tables)?;
+        section.folded = previous.folded;
+        section.stale = previous.stale;
+        self.sections[index] = section;
+        Ok(())
+    }
+
+    /// Sections in document order.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Hide the body and subsections of the section at `index`.
+    pub fn fold(&mut self, index: usize) {
+        self.set_folded(index, true);
+    }
+
+    /// Show the body and subsections of the section at `index` again.
+    pub fn unfold(&mut self, index: usize) {
+        self.set_folded(index, false);
+    }
+
+    /// Fold the section at `index` if it is unfolded, and the other way
+    /// around.
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(section) = self.sections.get_mut(index) {
+            section.folded = !section.folded;
+        }
+    }
+
+    fn set_folded(&mut self, index: usize, folded: bool) {
+        if let Some(section) = self.sections.get_mut(index) {
+            section.folded = folded;
+        }
+    }
+
+    /// Show the section at `index` dimmed, e.g. while the message it belongs
+    /// to is being edited or regenerated, or normally again.
+    pub fn mark_stale(&mut self, index: usize, stale: bool) {
+        if let Some(section) = self.sections.get_mut(index) {
+            section.stale = stale;
+        }
+    }
+
+    /// Attach `note` to the `bytes` of the markdown source, e.g. a linter
+    /// warning or a fact-check flag, which may arrive after the lines were
+    /// rendered. The lines rendered from those bytes are underlined, and
+    /// the note is shown below them.
+    pub fn annotate(&mut self, bytes: Range<usize>, note: impl Into<String>) {
+        let starts = &self.line_starts;
+        let first = starts.partition_point(|&start| start <= bytes.start);
+        let last = starts.partition_point(|&start| start < bytes.end);
+        let lines = first.saturating_sub(1)..last.max(first);
+        self.annotations.push((lines, note.into()));
+    }
+
+    /// Remove every annotation.
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    /// Lines currently visible, with a marker in place of each folded body.
+    pub fn visible_lines(&self) -> Vec<String> {
+        self.visible().into_iter().map(|(line, _)| line).collect()
+    }
+
+    /// The markdown source of the visible lines in `lines`, e.g. to quote a
+    /// selection in a reply.
+    ///
+    /// Lines map to the source of the whole block they belong to, and a
+    /// folded marker to everything it hides, so the result is whole blocks
+    /// of the original markdown, without surrounding blank lines.
+    pub fn markdown_for_lines(&self, lines: Range<usize>) -> String {
+        let visible = self.visible();
+        let selected = visible.get(lines).unwrap_or_default();
+        let start = selected.iter().map(|(_, source)| source.start).min();
+        let end = selected.iter().map(|(_, source)| source.end).max();
+        let (Some(start), Some(end)) = (start, end) else {
+            return String::new();
+        };
+        let source = &self.source[start..end];
+        let first = source.iter().position(|line| !line.trim().is_empty());
+        let last = source.iter().rposition(|line| !line.trim().is_empty());
+        match (first, last) {
+            (Some(first), Some(last)) => source[first..=last].join("\n"),
+            _ => String::new(),
+        }
+    }
+
+    /// Visible lines with the source lines each was rendered from.
+    fn visible(&self) -> Vec<(String, Range<usize>)> {
+        let mut lines = Vec::new();
+        let mut index = 0;
+        while let Some(section) = self.sections.get(index) {
+            let rendered = section.heading.iter().chain(&section.body).map(|line| {
+                if section.stale {
+                    self.theme.dimmed(&strip_ansi(line))
+                } else {
+                    line.clone()
+                }
+            });
+            let mut sourced = rendered.zip(section.line_sources.iter().cloned());
+            lines.extend(sourced.by_ref().take(section.heading.len()));
+            index += 1;
+            if !section.folded {
+                lines.extend(sourced);
+                continue;
+            }
+            // Subsections are folded away with their parent
+            let mut hidden = section.body.len();
+            let mut end = section.source.end;
+            while let Some(next) = self.sections.get(index) {
+                if section.level == 0 || next.level <= section.level {
+                    break;
+                }
+                hidden += next.heading.len() + next.body.len();
+                end = next.source.end;
+                index += 1;
+            }
+            let body_start = if section.level == 0 {
+                section.source.start
+            } else {
+                section.source.start + 1
+            };
+            let labels = &self.options.labels;
+            let marker = format!(
+                "⋯ {} {} {}",
+                self.options.number_locale.format_int(hidden),
+                labels.lines,
+                labels.hidden
+            );
+            lines.push((self.theme.dimmed(&marker), body_start..end));
+        }
+        self.annotate_lines(&mut lines);
+        lines
+    }
+
+    /// Underline the visible lines rendered from annotated source and add
+    /// each note after the last of its lines.
+    fn annotate_lines(&self, lines: &mut Vec<(String, Range<usize>)>) {
+        let underline = Style::new().underline_style(UnderlineStyle::Curly);
+        let arrow = if self.options.profile.unicode { "↳" } else { "->" };
+        for (source, note) in &self.annotations {
+            let overlaps = |line: &Range<usize>| line.start < source.end && source.start < line.end;
+            let Some(last) = lines.iter().rposition(|(_, line)| overlaps(line)) else {
+                continue;
+            };
+            for (text, _) in lines.iter_mut().filter(|(_, line)| overlaps(line)) {
+                *text = self.theme.paint(&underline, text);
+            }
+            let note = self.theme.dimmed(&format!("{} {}", arrow, note));
+            lines.insert(last + 1, (note, source.clone()));
+        }
+    }
+
+    /// Write the visible lines to `writer`.
+    pub fn write_visible<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for line in self.visible_lines() {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Split source lines into sections at headings outside code fences,
+/// returning each section's heading level and line range.
+fn split_sections(lines: &[&str]) -> Vec<(u8, Range<usize>)> {
+    let mut sections: Vec<(u8, Range<usize>)> = Vec::new();
+    let mut fence: Option<Fence> = None;
+    let mut start = 0;
+    let mut level = 0;
+    for (i, line) in lines.iter().enumerate() {
+        match fence {
+            Some(open) if open.is_closed_by(line) => fence = None,
+            Some(_) => {}
+            None => fence = Fence::parse(line),
+        }
+        if fence.is_some() {
+            continue;
+        }
+        if let Some(heading_level) = heading_level(line) {
+            if i > start || level != 0 {
+                sections.push((level, start..i));
+            }
+            start = i;
+            level = heading_level;
+        }
+    }
+    if lines.len() > start || level != 0 {
+        sections.push((level, start..lines.len()));
+    }
+    sections
+}
+
+/// Line ranges of the pipe tables in `lines`, outside code fences.
+fn find_tables<S: AsRef<str>>(lines: &[S]) -> Vec<Range<usize>> {
+    let mut tables: Vec<Range<usize>> = Vec::new();
+    let mut fence: Option<Fence> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+        match fence {
+            Some(open) if open.is_closed_by(line) => fence = None,
+            Some(_) => {}
+            None => fence = Fence::parse(line),
+        }
+        if fence.is_some() || !line.trim_start().starts_with('|') {
+            continue;
+        }
+        match tables.last_mut() {
+            Some(table) if table.end == i => table.end = i + 1,
+            _ => tables.push(i..i + 1),
+        }
+    }
+    tables
+}
+
+/// The table row `line` with only the cells at `columns`, in that order.
+fn select_cells(line: &str, columns: &[usize]) -> String {
+    let trimmed = line.trim();
+    let inner = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let inner = match inner.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => inner,
+    };
+    // Split at pipes that are not escaped
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '|' if !escaped => {
+                cells.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(&inner[start..]);
+    let selected: Vec<&str> = columns
+        .iter()
+        .filter_map(|&column| cells.get(column))
+        .map(|cell| cell.trim())
+        .collect();
+    format!("| {} |", selected.join(" | "))
+}
+
+/// FNV-1a hash of source lines, each followed by a newline.
+fn content_hash<S: AsRef<str>>(lines: &[S]) -> u64 {
+    fnv1a(lines.iter().flat_map(|line| line.as_ref().bytes().chain([b'\n'])))
+}
+
+/// Level of an ATX heading line, if `line` is one.
+fn heading_level(line: &str) -> Option<u8> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let after = &rest[hashes..];
+    if (1..=6).contains(&hashes) && (after.is_empty() || after.starts_with(' ')) {
+        Some(hashes as u8)
+    } else {
+        None
+    }
+}
+
+/// Render source lines, the first of which is line `first` of the
+/// document, with a fresh renderer and return the output lines with the
+/// source lines each came from.
+///
+/// Output appears once a block is complete, so each output line maps to
+/// the source lines pushed since the previous output.
+fn render_lines(
+    lines: &[&str],
+    first: usize,
+    width: usize,
+    theme: &Theme,
+    options: &RendererOptions,
+) -> io::Result<(Vec<String>, Vec<Range<usize>>)> {
+    if lines.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let buffer = SharedBuffer::default();
+    let mut renderer = StreamdownRenderer::with_theme(buffer.clone(), width, theme.clone());
+    renderer.set_options(options.clone());
+    let mut output = String::new();
+    let mut sources = Vec::new();
+    // First source line not yet seen in the output
+    let mut pending = first;
+    for (i, line) in lines.iter().enumerate() {
+        renderer.push(line)?;
+        renderer.push("\n")?;
+        output.push_str(&buffer.take());
+        let complete = output.matches('\n').count();
+        if complete > sources.len() {
+            let end = first + i + 1;
+            sources.resize(complete, pending..end);
+            pending = end;
+        }
+    }
+    renderer.finish()?;
+    output.push_str(&buffer.take());
+    let output: Vec<String> = output.lines().map(str::to_string).collect();
+    let end = first + lines.len();
+    sources.resize(output.len(), pending.min(end - 1)..end);
+    Ok((output, sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "intro\n# One\nbody one\n## Sub\nbody sub\n# Two\nbody two";
+
+    fn document() -> Document {
+        Document::render(MARKDOWN, 40, Theme::plain(), RendererOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_sections_record_source_lines() {
+        let document = document();
+        let sections: Vec<_> = document
+            .sections()
+            .iter()
+            .map(|section| (section.level, section.source.clone()))
+            .collect();
+        assert_eq!(sections, vec![(0, 0..1), (1, 1..3), (2, 3..5), (1, 5..7)]);
+    }
+
+    #[test]
+    fn test_section_hashes_follow_source() {
+        let hashes = |markdown: &str| -> Vec<u64> {
+            let document =
+                Document::render(markdown, 40, Theme::plain(), RendererOptions::default()).unwrap();
+            let sections = document.sections().iter();
+            sections.map(|section| section.hash).collect()
+        };
+        let first = hashes(MARKDOWN);
+        let regenerated = hashes(&MARKDOWN.replace("body sub", "new body"));
+        assert_eq!(first.len(), regenerated.len());
+        assert_eq!(first[..2], regenerated[..2]);
+        assert_ne!(first[2], regenerated[2]);
+        assert_eq!(first[3], regenerated[3]);
+        assert_eq!(content_hash::<&str>(&[]), 0xcbf2_9ce4_8422_2325);
+    }
+
+    #[test]
+    fn test_folding_hides_subsections() {
+        let mut document = document();
+        let unfolded = document.visible_lines().len();
+        document.fold(1);
+        let folded = document.visible_lines().join("\n");
+        assert!(folded.contains("ONE"));
+        assert!(!folded.contains("body one"));
+        assert!(!folded.contains("Sub"));
+        assert!(folded.contains("body two"));
+        assert!(folded.contains("lines hidden"));
+
+        document.toggle(1);
+        assert_eq!(document.visible_lines().len(), unfolded);
+    }
+
+    #[test]
+    fn test_stale_sections_are_dimmed() {
+        let theme = Theme::dark().with_backend(crate::AnsiBackend::default());
+        let mut document =
+            Document::render(MARKDOWN, 40, theme, RendererOptions::default()).unwrap();
+        let fresh = document.visible_lines();
+        document.mark_stale(3, true);
+        let stale = document.visible_lines();
+        assert_eq!(stale[..4], fresh[..4]);
+        let body = strip_ansi(&fresh[fresh.len() - 1]);
+        assert_eq!(stale[stale.len() - 1], document.theme.dimmed(&body));
+
+        document.mark_stale(3, false);
+        assert_eq!(document.visible_lines(), fresh);
+    }
+
+    #[test]
+    fn test_markdown_for_lines() {
+        let markdown = "# Title\n\nfirst **para**\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\nlast";
+        let mut document =
+            Document::render(markdown, 40, Theme::plain(), RendererOptions::default()).unwrap();
+        let lines = document.visible_lines();
+        let para = lines.iter().position(|l| l.contains("first")).unwrap();
+        assert_eq!(document.markdown_for_lines(para..para + 1), "first **para**");
+        let row = lines.iter().position(|l| l.contains("│ 1")).unwrap();
+        assert_eq!(
+            document.markdown_for_lines(row..row + 1),
+            "| a | b |\n|---|---|\n| 1 | 2 |"
+        );
+        assert_eq!(document.markdown_for_lines(0..lines.len()), markdown);
+        assert_eq!(document.markdown_for_lines(lines.len()..lines.len() + 2), "");
+
+        // A folded marker quotes everything it hides
+        document.fold(0);
+        let marker = document.visible_lines().len() - 1;
+        assert_eq!(document.markdown_for_lines(marker..marker + 1), markdown[9..]);
+    }
+
+    #[test]
+    fn test_hidden_table_columns() {
+        let markdown = "# Data\n| a | b | c |\n|---|---|---|\n| 1 | 2 | 3 |\n\n```\n| x |\n```";
+        let mut document =
+            Document::render(markdown, 40, Theme::plain(), RendererOptions::default()).unwrap();
+        let all = document.visible_lines().join("\n");
+        assert!(all.contains("│ a │ b │ c │"), "{}", all);
+
+        document.set_visible_columns(0, &[2, 0]).unwrap();
+        let narrowed = document.visible_lines().join("\n");
+        assert!(narrowed.contains("│ c │ a │"), "{}", narrowed);
+        assert!(narrowed.contains("│ 3 │ 1 │"), "{}", narrowed);
+        // The pipe line inside the code block is not a table
+        assert!(narrowed.contains("| x |"), "{}", narrowed);
+        assert_eq!(document.markdown_for_lines(0..20), markdown);
+
+        document.show_all_columns(0).unwrap();
+        assert_eq!(document.visible_lines().join("\n"), all);
+    }
+
+    #[test]
+    fn test_annotations_mark_rendered_lines() {
+        let options = RendererOptions {
+            profile: crate::TerminalProfile::unicode(),
+            ..Default::default()
+        };
+        let mut document = Document::render(MARKDOWN, 40, Theme::plain(), options).unwrap();
+        let plain = document.visible_lines();
+        let start = MARKDOWN.find("body sub").unwrap();
+        document.annotate(start + 5..start + 8, "check this");
+        let annotated = document.visible_lines();
+        assert_eq!(annotated.len(), plain.len() + 1);
+        let line = plain.iter().position(|line| line.contains("body sub")).unwrap();
+        assert_eq!(annotated[line + 1], "↳ check this");
+        assert_eq!(annotated[..line], plain[..line]);
+        assert_eq!(annotated[line + 2..], plain[line + 1..]);
+
+        document.clear_annotations();
+        assert_eq!(document.visible_lines(), plain);
+    }
+
+    #[test]
+    fn test_select_cells() {
+        assert_eq!(select_cells("| a | b \\| c | d |", &[1]), "| b \\| c |");
+        assert_eq!(select_cells("a | b", &[1, 0]), "| b | a |");
+        assert_eq!(select_cells("|:--|--:|", &[1]), "| --: |");
+    }
+
+    #[test]
+    fn test_headings_in_code_fences_do_not_split() {
+        let lines = ["# A", "```", "# not a heading", "```"];
+        assert_eq!(split_sections(&lines), vec![(1, 0..4)]);
+        // Only a fence of the same character, at least as long, closes
+        let lines = ["# A", "````md", "```rust", "# not a heading", "~~~~", "````", "# B"];
+        assert_eq!(split_sections(&lines), vec![(1, 0..6), (1, 6..7)]);
+    }
+}
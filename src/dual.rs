@@ -0,0 +1,129 @@
+//! Renderer that writes styled and plain copies of the same output.
+
+use std::io::{self, Write};
+
+use streamdown_parser::ParseEvent;
+
+use crate::backend::AnsiBackend;
+use crate::color::ColorDepth;
+use crate::options::RendererOptions;
+use crate::renderer::{EventRenderer, Renderer};
+use crate::strip::EscapeStripper;
+use crate::theme::Theme;
+
+/// Renderer that writes a truecolor stream and a plain stream at once.
+///
+/// Each event is laid out a single time; the styled bytes go to the
+/// terminal writer and the same bytes with escape sequences removed go to
+/// the plain writer, so both outputs have identical line structure.
+pub struct DualRenderer<T: Write, P: Write> {
+    terminal: T,
+    plain: P,
+    renderer: Renderer<Vec<u8>>,
+}
+
+impl<T: Write, P: Write> DualRenderer<T, P> {
+    pub fn new(terminal: T, plain: P, width: usize) -> Self {
+        let theme = Theme::default().with_backend(AnsiBackend::new(ColorDepth::TrueColor));
+        Self::with_theme(terminal, plain, width, theme)
+    }
+
+    pub fn with_theme(terminal: T, plain: P, width: usize, theme: Theme) -> Self {
+        Self {
+            terminal,
+            plain,
+            renderer: Renderer::with_theme(Vec::new(), width, theme),
+        }
+    }
+
+    /// Set a new theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.renderer.set_theme(theme);
+    }
+
+    /// Get the current theme.
+    pub fn theme(&self) -> &Theme {
+        self.renderer.theme()
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    /// Get the optional renderer behaviors.
+    pub fn options(&self) -> &RendererOptions {
+        self.renderer.options()
+    }
+
+    /// Render a single parse event to both writers.
+    pub fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.renderer.render_event(event)?;
+        self.drain()
+    }
+
+    /// Complete the document on both writers.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.renderer.finish()?;
+        self.drain()
+    }
+
+    /// Move rendered output to the terminal and plain writers.
+    fn drain(&mut self) -> io::Result<()> {
+        let buffer = self.renderer.take_output()?;
+        if !buffer.is_empty() {
+            self.terminal.write_all(&buffer)?;
+            let mut plain = Vec::with_capacity(buffer.len());
+            EscapeStripper::default().feed(&buffer, &mut plain);
+            self.plain.write_all(&plain)?;
+        }
+        self.terminal.flush()?;
+        self.plain.flush()
+    }
+
+    /// Consume the renderer and return the terminal and plain writers.
+    pub fn into_inner(self) -> (T, P) {
+        (self.terminal, self.plain)
+    }
+}
+
+impl<T: Write, P: Write> EventRenderer for DualRenderer<T, P> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        DualRenderer::render_event(self, event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        DualRenderer::finish(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_share_line_structure() {
+        let theme = Theme::dark().with_backend(AnsiBackend::new(ColorDepth::TrueColor));
+        let mut renderer = DualRenderer::with_theme(Vec::new(), Vec::new(), 20, theme);
+        let events = [
+            ParseEvent::Heading {
+                level: 3,
+                content: "Title".to_string(),
+            },
+            ParseEvent::Bold("some bold words that wrap around".to_string()),
+            ParseEvent::Newline,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        let (terminal, plain) = renderer.into_inner();
+        let terminal = String::from_utf8(terminal).unwrap();
+        let plain = String::from_utf8(plain).unwrap();
+        assert!(terminal.contains("\x1b["));
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(
+            String::from_utf8(strip_ansi_escapes::strip(&terminal)).unwrap(),
+            plain
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Renderer emitting each output line as a frame, for remote UIs reading
+//! the output over a socket.
+
+use std::io::{self, Write};
+
+use streamdown_parser::ParseEvent;
+
+use crate::backend::AnsiBackend;
+use crate::color::ColorDepth;
+use crate::json::{quote, spans_json};
+use crate::options::RendererOptions;
+use crate::renderer::{ends_block, is_inline, EventRenderer, Renderer};
+use crate::styled::{LineBuffer, SpanDecoder};
+use crate::theme::Theme;
+
+/// How frames are delimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// One JSON object per line of text.
+    #[default]
+    JsonLines,
+    /// Each JSON object preceded by its length in bytes, as a big-endian
+    /// `u32`, with no separator.
+    LengthPrefixed,
+}
+
+/// Renderer that writes each complete output line as a frame holding its
+/// index, the kind of block it belongs to, its ANSI text and its styled
+/// spans.
+///
+/// Each frame looks like:
+///
+/// ```text
+/// {"kind":"line","line":0,"block":"heading","ansi":"\u001b[1mTitle\u001b[0m","spans":[{"text":"Title","bold":true}]}
+/// ```
+///
+/// Blocks are `heading`, `paragraph`, `code`, `list`, `table`,
+/// `blockquote`, `think` and `rule`. Spans are as in
+/// [`JsonRenderer`](crate::JsonRenderer). `finish` writes any unfinished
+/// line and a last frame `{"kind":"end","lines":n}`.
+pub struct FramedRenderer<W: Write> {
+    writer: W,
+    format: FrameFormat,
+    renderer: Renderer<Vec<u8>>,
+    output: LineBuffer,
+    decoder: SpanDecoder,
+    lines: usize,
+    block: &'static str,
+    // Block the line still being written belongs to
+    line_block: &'static str,
+    // Whether the last event ended its block
+    block_ended: bool,
+}
+
+impl<W: Write> FramedRenderer<W> {
+    pub fn new(writer: W, width: usize, format: FrameFormat) -> Self {
+        let theme = Theme::default().with_backend(AnsiBackend::new(ColorDepth::TrueColor));
+        Self::with_theme(writer, width, format, theme)
+    }
+
+    /// Create a renderer with a custom theme. Its backend decides the
+    /// `ansi` text of each frame, and so which styles reach the spans.
+    pub fn with_theme(writer: W, width: usize, format: FrameFormat, theme: Theme) -> Self {
+        Self {
+            writer,
+            format,
+            renderer: Renderer::with_theme(Vec::new(), width, theme),
+            output: LineBuffer::default(),
+            decoder: SpanDecoder::default(),
+            lines: 0,
+            block: "paragraph",
+            line_block: "paragraph",
+            block_ended: true,
+        }
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Render a single parse event and write a frame per completed line.
+    pub fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        if let Some(block) = block_started(event) {
+            self.block = block;
+        } else if self.block_ended && is_inline(event) {
+            self.block = "paragraph";
+        }
+        self.renderer.render_event(event)?;
+        self.block_ended = ends_block(event);
+        self.write_lines()
+    }
+
+    /// Complete the document, writing any unfinished line and the end frame.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.renderer.finish()?;
+        self.write_lines()?;
+        if !self.output.partial().is_empty() {
+            let line = self.output.take_partial();
+            self.write_line(&line)?;
+        }
+        let end = format!("{{\"kind\":\"end\",\"lines\":{}}}", self.lines);
+        self.write_frame(&end)?;
+        self.writer.flush()
+    }
+
+    fn write_lines(&mut self) -> io::Result<()> {
+        // A line begun by an earlier event stays with that event's block
+        if self.output.partial().is_empty() {
+            self.line_block = self.block;
+        }
+        for line in self.output.take_lines(&mut self.renderer)? {
+            self.write_line(&line)?;
+            self.line_block = self.block;
+        }
+        self.writer.flush()
+    }
+
+    fn write_line(&mut self, ansi: &str) -> io::Result<()> {
+        let spans = spans_json(&self.decoder.line(ansi));
+        let frame = format!(
+            "{{\"kind\":\"line\",\"line\":{},\"block\":\"{}\",\"ansi\":{},\"spans\":{}}}",
+            self.lines,
+            self.line_block,
+            quote(ansi),
+            spans
+        );
+        self.lines += 1;
+        self.write_frame(&frame)
+    }
+
+    fn write_frame(&mut self, frame: &str) -> io::Result<()> {
+        match self.format {
+            FrameFormat::JsonLines => writeln!(self.writer, "{}", frame),
+            FrameFormat::LengthPrefixed => {
+                let length = u32::try_from(frame.len())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "frame too long"))?;
+                self.writer.write_all(&length.to_be_bytes())?;
+                self.writer.write_all(frame.as_bytes())
+            }
+        }
+    }
+}
+
+impl<W: Write> EventRenderer for FramedRenderer<W> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        FramedRenderer::render_event(self, event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        FramedRenderer::finish(self)
+    }
+}
+
+/// Kind of the block that `event` starts, if any.
+fn block_started(event: &ParseEvent) -> Option<&'static str> {
+    Some(match event {
+        ParseEvent::Heading { .. } => "heading",
+        ParseEvent::CodeBlockStart { .. } => "code",
+        ParseEvent::ListItem { .. } => "list",
+        ParseEvent::TableHeader(_) => "table",
+        ParseEvent::BlockquoteStart { .. } => "blockquote",
+        ParseEvent::ThinkBlockStart => "think",
+        ParseEvent::HorizontalRule => "rule",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: FrameFormat) -> Vec<u8> {
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = FramedRenderer::with_theme(Vec::new(), 40, format, theme);
+        renderer
+            .render_event(&ParseEvent::Heading {
+                level: 1,
+                content: "Title".to_string(),
+            })
+            .unwrap();
+        renderer.render_event(&ParseEvent::EmptyLine).unwrap();
+        renderer
+            .render_event(&ParseEvent::Text("body".to_string()))
+            .unwrap();
+        renderer.finish().unwrap();
+        renderer.into_inner()
+    }
+
+    #[test]
+    fn test_json_frames_carry_block_and_index() {
+        let output = String::from_utf8(render(FrameFormat::JsonLines)).unwrap();
+        let frames: Vec<&str> = output.lines().collect();
+        assert!(frames[0].starts_with(r#"{"kind":"line","line":0,"block":"heading""#));
+        let body = frames[frames.len() - 2];
+        assert!(
+            body.contains(r#""block":"paragraph","ansi":"body""#),
+            "{}",
+            body
+        );
+        assert_eq!(
+            frames[frames.len() - 1],
+            format!(r#"{{"kind":"end","lines":{}}}"#, frames.len() - 1)
+        );
+    }
+
+    #[test]
+    fn test_line_ended_by_a_new_block_keeps_its_block() {
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = FramedRenderer::with_theme(Vec::new(), 40, FrameFormat::JsonLines, theme);
+        renderer
+            .render_event(&ParseEvent::Text("body".to_string()))
+            .unwrap();
+        renderer.render_event(&ParseEvent::HorizontalRule).unwrap();
+        renderer.finish().unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        let body = output.lines().find(|frame| frame.contains(r#""ansi":"body""#));
+        assert!(body.unwrap().contains(r#""block":"paragraph""#), "{}", output);
+        assert!(output.contains(r#""block":"rule""#), "{}", output);
+    }
+
+    #[test]
+    fn test_length_prefixed_frames() {
+        let output = render(FrameFormat::LengthPrefixed);
+        let mut rest = output.as_slice();
+        let mut frames = Vec::new();
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            frames.push(String::from_utf8(rest[4..4 + length].to_vec()).unwrap());
+            rest = &rest[4 + length..];
+        }
+        assert!(frames[0].starts_with(r#"{"kind":"line""#));
+        assert!(frames.last().unwrap().starts_with(r#"{"kind":"end""#));
+    }
+}
@@ -0,0 +1,326 @@
+//! Guard that restores terminal state if rendering is interrupted.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+
+/// Clears SGR attributes, closes any open OSC 8 hyperlink, lifts any scroll
+/// region, keeping the cursor in place, and shows the cursor.
+pub const RESTORE_SEQUENCE: &str = "\x1b[0m\x1b]8;;\x1b\\\x1b7\x1b[r\x1b8\x1b[?25h";
+
+/// Wait between attempts to write to a writer that would block.
+const BLOCKED_RETRY: Duration = Duration::from_millis(1);
+
+/// Attempts in a row without progress after which waiting for a writer
+/// that would block is given up.
+const BLOCKED_ATTEMPTS: usize = 5000;
+
+/// Writer wrapper that emits a restore sequence if dropped mid-render.
+///
+/// The renderer arms the guard while an event is being written and disarms
+/// it once the event completes. If the guard is dropped while armed, or while
+/// the thread is panicking, the restore sequence is written so the terminal is
+/// not left with active styles or an open hyperlink.
+///
+/// With a queue capacity set, writes go into a bounded queue that `flush`
+/// empties only as far as the writer accepts without blocking. Dropping the
+/// guard never waits: output the writer does not take at once is discarded
+/// and the restore sequence written after it.
+pub struct TerminalGuard<W: Write> {
+    writer: W,
+    restore: &'static str,
+    armed: bool,
+    // Terminal state outlives single events, e.g. a scroll region
+    persistent: bool,
+    queue: VecDeque<u8>,
+    queue_capacity: Option<usize>,
+}
+
+impl<W: Write> TerminalGuard<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_restore(writer, RESTORE_SEQUENCE)
+    }
+
+    /// Create a guard that writes `restore` instead of the default sequence.
+    pub fn with_restore(writer: W, restore: &'static str) -> Self {
+        Self {
+            writer,
+            restore,
+            armed: false,
+            persistent: false,
+            queue: VecDeque::new(),
+            queue_capacity: None,
+        }
+    }
+
+    /// Mark the terminal as possibly holding partial state.
+    pub fn arm(&mut self) {
+        self.armed = true;
+    }
+
+    /// Mark the terminal state as clean.
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub(crate) fn set_restore(&mut self, restore: &'static str) {
+        self.restore = restore;
+    }
+
+    /// Restore the terminal on drop even while disarmed, for state that
+    /// lasts between events such as a scroll region.
+    pub(crate) fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    /// Queue up to `capacity` bytes of output instead of writing through,
+    /// or write through again with `None` once the queue is drained.
+    pub fn set_queue_capacity(&mut self, capacity: Option<usize>) {
+        self.queue_capacity = capacity;
+    }
+
+    /// Number of bytes written but not yet accepted by the writer.
+    pub fn queued_bytes(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Write queued output until the writer would block. `Pending` means
+    /// output is left in the queue.
+    pub fn poll_flush(&mut self) -> Poll<io::Result<()>> {
+        while !self.queue.is_empty() {
+            let (front, _) = self.queue.as_slices();
+            match self.writer.write(front) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(written) => {
+                    self.queue.drain(..written);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        match self.writer.flush() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+
+    /// Write all queued output, waiting while the writer would block.
+    ///
+    /// Fails with `TimedOut` if the writer takes nothing for several
+    /// seconds.
+    pub fn drain(&mut self) -> io::Result<()> {
+        self.drain_to(0)
+    }
+
+    /// Wait for the writer until at most `limit` bytes are queued.
+    fn drain_to(&mut self, limit: usize) -> io::Result<()> {
+        let mut attempts = 0;
+        while self.queue.len() > limit {
+            let queued = self.queue.len();
+            match self.poll_flush() {
+                Poll::Ready(result) => result?,
+                Poll::Pending if self.queue.len() < queued => attempts = 0,
+                Poll::Pending if attempts < BLOCKED_ATTEMPTS => {
+                    attempts += 1;
+                    thread::sleep(BLOCKED_RETRY);
+                }
+                Poll::Pending => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "writer stayed blocked"))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the restore sequence now and disarm the guard.
+    pub fn restore(&mut self) -> io::Result<()> {
+        self.armed = false;
+        // Queued output goes first, so the restore is not undone by it
+        if self.drain().is_err() {
+            self.queue.clear();
+        }
+        self.write_restore()
+    }
+
+    fn write_restore(&mut self) -> io::Result<()> {
+        if !self.restore.is_empty() {
+            self.writer.write_all(self.restore.as_bytes())?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Write for TerminalGuard<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.queue_capacity {
+            Some(capacity) => {
+                // Only a full queue makes the caller wait
+                self.drain_to(capacity.saturating_sub(buf.len()))?;
+                self.queue.extend(buf);
+                Ok(buf.len())
+            }
+            None => {
+                if !self.queue.is_empty() {
+                    self.drain()?;
+                }
+                self.writer.write(buf)
+            }
+        }
+    }
+
+    /// Fails with `WouldBlock` while output is left in the queue.
+    fn flush(&mut self) -> io::Result<()> {
+        match self.poll_flush() {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<W: Write> Drop for TerminalGuard<W> {
+    fn drop(&mut self) {
+        let _ = self.poll_flush();
+        // Dropped output may end inside a style or an escape sequence
+        let discarded = !self.queue.is_empty();
+        self.queue.clear();
+        if self.armed || self.persistent || discarded || std::thread::panicking() {
+            let _ = self.write_restore();
+        }
+    }
+}
+
+/// Write the restore sequence to stdout.
+///
+/// Intended for interrupt handlers: call it before exiting on Ctrl-C so the
+/// shell prompt is not left styled or inside a hyperlink.
+pub fn emergency_reset() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(RESTORE_SEQUENCE.as_bytes())?;
+    stdout.flush()
+}
+
+/// Install a Ctrl-C handler that restores the terminal and exits.
+///
+/// The process exits with status 130, the conventional code for SIGINT.
+#[cfg(feature = "signal")]
+pub fn install_sigint_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        let _ = emergency_reset();
+        std::process::exit(130);
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disarmed_guard_writes_nothing_on_drop() {
+        let mut out = Vec::new();
+        {
+            let mut guard = TerminalGuard::new(&mut out);
+            guard.write_all(b"text").unwrap();
+        }
+        assert_eq!(out, b"text");
+    }
+
+    #[test]
+    fn test_armed_guard_restores_on_drop() {
+        let mut out = Vec::new();
+        {
+            let mut guard = TerminalGuard::new(&mut out);
+            guard.arm();
+            guard.write_all(b"\x1b[1mtext").unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(), format!("\x1b[1mtext{}", RESTORE_SEQUENCE));
+    }
+
+    /// Writer accepting `budget` bytes before it would block.
+    pub(crate) struct Blocking {
+        pub(crate) written: Vec<u8>,
+        pub(crate) budget: usize,
+    }
+
+    impl Blocking {
+        pub(crate) fn new(budget: usize) -> Self {
+            Self {
+                written: Vec::new(),
+                budget,
+            }
+        }
+    }
+
+    impl Write for Blocking {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let len = buf.len().min(self.budget);
+            self.budget -= len;
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_queue_holds_output_the_writer_would_block_on() {
+        let writer = Blocking::new(4);
+        let mut guard = TerminalGuard::new(writer);
+        guard.set_queue_capacity(Some(64));
+        guard.write_all(b"abcdefgh").unwrap();
+        guard.flush().unwrap();
+        assert_eq!(guard.get_ref().written, b"abcd");
+        assert_eq!(guard.queued_bytes(), 4);
+        assert!(guard.poll_flush().is_pending());
+
+        guard.get_mut().budget = 100;
+        assert!(guard.poll_flush().is_ready());
+        assert_eq!(guard.get_ref().written, b"abcdefgh");
+        assert_eq!(guard.queued_bytes(), 0);
+    }
+
+    #[test]
+    fn test_blocked_writer_does_not_hang() {
+        let mut writer = Blocking::new(4);
+        {
+            let mut guard = TerminalGuard::new(&mut writer);
+            guard.set_queue_capacity(Some(64));
+            guard.write_all(b"\x1b[1mtext").unwrap();
+            let error = guard.flush().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        }
+        // Dropping discarded the queue instead of waiting for the writer
+        assert_eq!(writer.written, b"\x1b[1m");
+    }
+
+    #[test]
+    fn test_guard_restores_during_panic() {
+        let mut out = Vec::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut guard = TerminalGuard::new(&mut out);
+            guard.write_all(b"\x1b]8;;https://example.com\x1b\\link").unwrap();
+            panic!("downstream failure");
+        }));
+        assert!(result.is_err());
+        assert!(String::from_utf8(out).unwrap().ends_with(RESTORE_SEQUENCE));
+    }
+}
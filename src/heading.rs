@@ -2,80 +2,133 @@
 
 use crate::inline::render_inline_content;
 use crate::style::{HeadingStyler, InlineStyler};
-use streamdown_render::simple_wrap;
+use crate::table::wrap;
+use crate::width::visible_length_with;
+
+/// How a heading level is set apart from body text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadingDecoration {
+    /// Dimmed `#` marks before the text, as in the source.
+    #[default]
+    Prefix,
+    /// Text centered in the available width, optionally followed by a
+    /// full-width rule drawn with the given character.
+    Centered { rule: Option<char> },
+    /// Styled text only.
+    Plain,
+}
 
 /// Render a heading with appropriate styling.
+///
+/// With `echo_markers`, every decoration keeps the dimmed `#` marks and h1
+/// keeps its case, so plain transcripts can be searched by markdown syntax.
+/// H1 and H2 are preceded by a blank line only with `leading_blank`.
 pub fn render_heading<S: InlineStyler + HeadingStyler>(
     level: u8,
     content: &str,
     width: usize,
     margin: &str,
     styler: &S,
+    echo_markers: bool,
+    leading_blank: bool,
 ) -> Vec<String> {
-    // Create the dimmed heading prefix (e.g., "# ", "## ", etc.)
-    let prefix = "#".repeat(level as usize);
-    let dimmed_prefix = styler.dimmed(&prefix);
-    
     // For h1, uppercase the content before rendering inline elements
-    let content_to_render = if level == 1 {
+    let content_to_render = if level == 1 && !echo_markers {
         content.to_uppercase()
     } else {
         content.to_string()
     };
-    
+
     // First render inline elements (bold, italic, etc.) in the content
     let rendered_content = render_inline_content(&content_to_render, styler);
-    
-    // Adjust width to account for the prefix (e.g., "# " = 2 chars, "## " = 3 chars, etc.)
-    let prefix_display_width = level as usize + 1;
-    let content_width = width.saturating_sub(prefix_display_width);
-    let lines = simple_wrap(&rendered_content, content_width);
-    let mut result = Vec::new();
-
-    for line in lines {
-        let formatted = match level {
-            1 => {
-                // H1: Bold, left-aligned, uppercase, with dimmed prefix
-                format!("{}\n{}{} {}", margin, margin, dimmed_prefix, styler.h1(&line))
-            }
-            2 => {
-                // H2: Bold, bright color, left-aligned, with dimmed prefix
-                format!("{}\n{}{} {}", margin, margin, dimmed_prefix, styler.h2(&line))
-            }
-            3 => {
-                format!("{}{} {}", margin, dimmed_prefix, styler.h3(&line))
-            }
-            4 => {
-                format!("{}{} {}", margin, dimmed_prefix, styler.h4(&line))
-            }
-            5 => {
-                format!("{}{} {}", margin, dimmed_prefix, styler.h5(&line))
+    let wide = styler.ambiguous_wide();
+
+    let styled = |line: &str| match level {
+        1 => styler.h1(line),
+        2 => styler.h2(line),
+        3 => styler.h3(line),
+        4 => styler.h4(line),
+        5 => styler.h5(line),
+        _ => styler.h6(line),
+    };
+    // H1 and H2 are preceded by a blank line
+    let leading_blank = if leading_blank && level <= 2 {
+        format!("{}\n", margin)
+    } else {
+        String::new()
+    };
+
+    let decoration = match styler.decoration(level) {
+        HeadingDecoration::Plain if echo_markers => HeadingDecoration::Prefix,
+        decoration => decoration,
+    };
+    match decoration {
+        HeadingDecoration::Prefix => {
+            // Create the dimmed heading prefix (e.g., "# ", "## ", etc.)
+            let prefix = "#".repeat(level as usize);
+            let dimmed_prefix = styler.dimmed(&prefix);
+
+            // Adjust width to account for the prefix (e.g., "# " = 2 chars, "## " = 3 chars, etc.)
+            let prefix_display_width = level as usize + 1;
+            let content_width = width.saturating_sub(prefix_display_width);
+            wrap(&rendered_content, content_width, wide)
+                .iter()
+                .map(|line| {
+                    format!("{}{}{} {}", leading_blank, margin, dimmed_prefix, styled(line))
+                })
+                .collect()
+        }
+        HeadingDecoration::Centered { rule } => {
+            let marker = if echo_markers {
+                format!("{} ", styler.dimmed(&"#".repeat(level as usize)))
+            } else {
+                String::new()
+            };
+            let marker_width = visible_length_with(&marker, wide);
+            let content_width = width.saturating_sub(marker_width);
+            let mut result: Vec<String> = wrap(&rendered_content, content_width, wide)
+                .iter()
+                .map(|line| {
+                    let line_width = marker_width + visible_length_with(line, wide);
+                    let padding = width.saturating_sub(line_width) / 2;
+                    format!("{}{}{}{}", margin, " ".repeat(padding), marker, styled(line))
+                })
+                .collect();
+            if let Some(rule) = rule {
+                let rule = rule.to_string().repeat(width);
+                result.push(format!("{}{}", margin, styler.dimmed(&rule)));
             }
-            _ => {
-                format!("{}{} {}", margin, dimmed_prefix, styler.h6(&line))
+            if let Some(first) = result.first_mut() {
+                first.insert_str(0, &leading_blank);
             }
-        };
-        result.push(formatted);
+            result
+        }
+        HeadingDecoration::Plain => wrap(&rendered_content, width, wide)
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let blank = if i == 0 { leading_blank.as_str() } else { "" };
+                format!("{}{}{}", blank, margin, styled(line))
+            })
+            .collect(),
     }
-
-    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::theme::TagStyler;
+    use crate::theme::{TagStyler, Theme};
 
     fn render(level: u8, content: &str) -> String {
-        render_heading(level, content, 80, "  ", &TagStyler).join("\n")
+        render_heading(level, content, 80, "  ", &TagStyler, false, true).join("\n")
     }
 
     fn render_with_width(level: u8, content: &str, width: usize) -> String {
-        render_heading(level, content, width, "  ", &TagStyler).join("\n")
+        render_heading(level, content, width, "  ", &TagStyler, false, true).join("\n")
     }
 
     fn render_with_margin(level: u8, content: &str, margin: &str) -> String {
-        render_heading(level, content, 80, margin, &TagStyler).join("\n")
+        render_heading(level, content, 80, margin, &TagStyler, false, true).join("\n")
     }
 
     #[test]
@@ -222,4 +275,48 @@ mod tests {
         assert!(h2.contains("\n"), "H2 should have newline");
         assert!(!h3.starts_with("\n"), "H3 should not start with newline");
     }
+
+    fn render_decorated(
+        level: u8,
+        content: &str,
+        width: usize,
+        decoration: HeadingDecoration,
+    ) -> String {
+        let mut theme = Theme::plain();
+        theme.heading_decorations = [decoration; 6];
+        render_heading(level, content, width, "", &theme, false, true).join("\n")
+    }
+
+    #[test]
+    fn test_centered_with_rule() {
+        let decoration = HeadingDecoration::Centered { rule: Some('═') };
+        insta::assert_snapshot!(render_decorated(1, "Title", 11, decoration), @r"
+
+           TITLE
+        ═══════════
+        ");
+    }
+
+    #[test]
+    fn test_plain_decoration() {
+        insta::assert_snapshot!(render_decorated(3, "Section", 40, HeadingDecoration::Plain), @"Section");
+    }
+
+    #[test]
+    fn test_echo_markers_keep_hashes_and_case() {
+        let mut theme = Theme::plain();
+        theme.heading_decorations = [HeadingDecoration::Plain; 6];
+        let plain = render_heading(1, "Title", 40, "", &theme, true, true).join("\n");
+        assert_eq!(plain, "\n# Title");
+
+        theme.heading_decorations = [HeadingDecoration::Centered { rule: None }; 6];
+        let centered = render_heading(2, "Intro", 12, "", &theme, true, true).join("\n");
+        assert_eq!(centered, "\n  ## Intro");
+    }
+
+    #[test]
+    fn test_without_leading_blank() {
+        let lines = render_heading(1, "Title", 40, "", &TagStyler, false, false);
+        assert_eq!(lines, vec!["<dim>#</dim> <h1>TITLE</h1>"]);
+    }
 }
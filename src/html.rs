@@ -0,0 +1,664 @@
+//! HTML renderer that turns parse events into semantic markup.
+
+use std::io::{self, Write};
+
+use colored::Color;
+use streamdown_parser::{decode_html_entities, ListBullet, ParseEvent};
+
+use crate::color::color_rgb;
+use crate::inline::{render_inline_content, render_inline_elements};
+use crate::links::is_linkable;
+use crate::options::RendererOptions;
+use crate::renderer::{continues_list, EventRenderer};
+use crate::style::InlineStyler;
+use crate::theme::{Style, Theme, UnderlineStyle};
+
+/// Renderer that emits HTML instead of terminal escape sequences.
+///
+/// Consumes the same `ParseEvent` stream as [`Renderer`](crate::Renderer) and
+/// derives inline CSS from the `Theme` styles.
+pub struct HtmlRenderer<W: Write> {
+    writer: W,
+    theme: Theme,
+    options: RendererOptions,
+    in_paragraph: bool,
+    // Stack of (indent, ordered) for open lists
+    lists: Vec<(usize, bool)>,
+    // Number of open <blockquote> elements
+    quote_depth: usize,
+    table_rows: Vec<Vec<String>>,
+    // Tags of the event being rendered, and of the buffered table
+    tags: Vec<String>,
+    table_tags: Vec<String>,
+}
+
+impl<W: Write> HtmlRenderer<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_theme(writer, Theme::default())
+    }
+
+    pub fn with_theme(writer: W, theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            options: RendererOptions::default(),
+            in_paragraph: false,
+            lists: Vec::new(),
+            quote_depth: 0,
+            table_rows: Vec::new(),
+            tags: Vec::new(),
+            table_tags: Vec::new(),
+        }
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.options = options;
+    }
+
+    /// Get the optional renderer behaviors.
+    pub fn options(&self) -> &RendererOptions {
+        &self.options
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        write!(self.writer, "{}", s)
+    }
+
+    fn styler(&self) -> HtmlStyler<'_> {
+        HtmlStyler {
+            theme: &self.theme,
+            schemes: self.options.link_schemes.as_deref(),
+        }
+    }
+
+    fn open_paragraph(&mut self) -> io::Result<()> {
+        if !self.in_paragraph {
+            self.in_paragraph = true;
+            self.write("<p>")?;
+        }
+        Ok(())
+    }
+
+    fn close_paragraph(&mut self) -> io::Result<()> {
+        if self.in_paragraph {
+            self.in_paragraph = false;
+            self.write("</p>\n")?;
+        }
+        Ok(())
+    }
+
+    fn close_lists_deeper_than(&mut self, indent: Option<usize>) -> io::Result<()> {
+        while let Some(&(top, ordered)) = self.lists.last() {
+            if indent.is_some_and(|i| top <= i) {
+                break;
+            }
+            self.lists.pop();
+            self.write(if ordered { "</li></ol>\n" } else { "</li></ul>\n" })?;
+        }
+        Ok(())
+    }
+
+    /// Open or close `<blockquote>` elements until `depth` are open.
+    fn set_quote_depth(&mut self, depth: usize) -> io::Result<()> {
+        while self.quote_depth > depth {
+            self.quote_depth -= 1;
+            self.write("</blockquote>\n")?;
+        }
+        while self.quote_depth < depth {
+            self.quote_depth += 1;
+            let html = format!(
+                "<blockquote{}{}>\n",
+                self.class_attr(),
+                style_attr(&self.theme.blockquote)
+            );
+            self.write(&html)?;
+        }
+        Ok(())
+    }
+
+    fn write_inline(&mut self, html: String) -> io::Result<()> {
+        self.open_paragraph()?;
+        if self.tags.is_empty() {
+            self.write(&html)
+        } else {
+            let html = format!("<span{}>{}</span>", self.class_attr(), html);
+            self.write(&html)
+        }
+    }
+
+    /// `class` attribute listing the tags of the event being rendered.
+    fn class_attr(&self) -> String {
+        class_attr(&self.tags)
+    }
+
+    fn flush_table(&mut self) -> io::Result<()> {
+        let rows = std::mem::take(&mut self.table_rows);
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let styler = self.styler();
+        let mut out = format!("<table{}>\n", class_attr(&self.table_tags));
+        for (ri, row) in rows.iter().enumerate() {
+            let tag = if ri == 0 { "th" } else { "td" };
+            out.push_str("<tr>");
+            for cell in row {
+                out.push_str(&format!(
+                    "<{tag}>{}</{tag}>",
+                    render_inline_content(cell, &styler)
+                ));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+        self.write(&out)
+    }
+
+    /// Render a parse event with tags attached, e.g. by middleware that
+    /// classifies blocks.
+    ///
+    /// The tags become the `class` of the element the event opens: the
+    /// heading, list item, table, quote, or code block, or a `<span>` around
+    /// inline content. Events that open no element ignore them.
+    pub fn render_tagged_event(&mut self, event: &ParseEvent, tags: &[&str]) -> io::Result<()> {
+        self.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        let result = self.render_event(event);
+        self.tags.clear();
+        result
+    }
+
+    /// Render a single parse event.
+    pub fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        if !continues_list(event) {
+            self.close_lists_deeper_than(None)?;
+        }
+
+        match event {
+            // === Inline elements ===
+            ParseEvent::Text(text) => {
+                let html = self.styler().text(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::InlineCode(code) => {
+                let html = self.styler().code(code);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Bold(text) => {
+                let html = self.styler().bold(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Italic(text) => {
+                let html = self.styler().italic(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::BoldItalic(text) => {
+                let html = self.styler().bold_italic(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Underline(text) => {
+                let html = self.styler().underline(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Strikeout(text) => {
+                let html = self.styler().strikethrough(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Link { text, url } => {
+                let html = self.styler().link(text, url);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Image { alt, url } => {
+                let html = self.styler().image(alt, url);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Footnote(text) => {
+                let html = self.styler().footnote(text);
+                self.write_inline(html)?;
+            }
+            ParseEvent::Prompt(prompt) => {
+                self.write_inline(escape(prompt))?;
+            }
+            ParseEvent::InlineElements(elements) => {
+                let html = render_inline_elements(elements, &self.styler());
+                self.write_inline(html)?;
+            }
+
+            // === Block elements ===
+            ParseEvent::Heading { level, content } => {
+                self.close_paragraph()?;
+                let level = self.options.heading_levels.apply(*level);
+                let style = match level {
+                    1 => &self.theme.heading1,
+                    2 => &self.theme.heading2,
+                    3 => &self.theme.heading3,
+                    4 => &self.theme.heading4,
+                    5 => &self.theme.heading5,
+                    _ => &self.theme.heading6,
+                };
+                let html = format!(
+                    "<h{level}{}{}>{}</h{level}>\n",
+                    self.class_attr(),
+                    style_attr(style),
+                    render_inline_content(content, &self.styler())
+                );
+                self.write(&html)?;
+            }
+
+            ParseEvent::CodeBlockStart { language, .. } => {
+                self.close_paragraph()?;
+                let pre = format!("<pre{}>", self.class_attr());
+                self.write(&pre)?;
+                match language {
+                    Some(lang) => {
+                        self.write(&format!("<code class=\"language-{}\">", escape(lang)))?
+                    }
+                    None => self.write("<code>")?,
+                }
+            }
+
+            ParseEvent::CodeBlockLine(line) => {
+                self.write(&format!("{}\n", escape(line)))?;
+            }
+
+            ParseEvent::CodeBlockEnd => {
+                self.write("</code></pre>\n")?;
+            }
+
+            ParseEvent::ListItem {
+                indent,
+                bullet,
+                content,
+            } => {
+                self.close_paragraph()?;
+                self.close_lists_deeper_than(Some(*indent))?;
+                let ordered = matches!(bullet, ListBullet::Ordered(_));
+                let nested = self.lists.last().map(|(i, _)| indent > i).unwrap_or(true);
+                if nested {
+                    self.lists.push((*indent, ordered));
+                    self.write(if ordered { "<ol>\n" } else { "<ul>\n" })?;
+                } else {
+                    self.write("</li>\n")?;
+                }
+                let html = format!(
+                    "<li{}>{}",
+                    self.class_attr(),
+                    render_inline_content(content, &self.styler())
+                );
+                self.write(&html)?;
+            }
+
+            ParseEvent::ListEnd => {}
+
+            ParseEvent::TableHeader(cols) | ParseEvent::TableRow(cols) => {
+                self.close_paragraph()?;
+                if self.table_rows.is_empty() {
+                    self.table_tags = self.tags.clone();
+                }
+                self.table_rows.push(cols.clone());
+            }
+
+            ParseEvent::TableSeparator => {}
+
+            ParseEvent::TableEnd => {
+                self.flush_table()?;
+            }
+
+            ParseEvent::BlockquoteStart { depth } => {
+                self.close_paragraph()?;
+                self.set_quote_depth((*depth).max(1))?;
+            }
+
+            ParseEvent::BlockquoteLine(text) => {
+                let html = format!("<p>{}</p>\n", render_inline_content(text, &self.styler()));
+                self.write(&html)?;
+            }
+
+            ParseEvent::BlockquoteEnd => {
+                self.set_quote_depth(0)?;
+            }
+
+            ParseEvent::ThinkBlockStart => {
+                self.close_paragraph()?;
+                let html = format!(
+                    "<details{}{}><summary>{}</summary>\n",
+                    self.class_attr(),
+                    style_attr(&self.theme.think),
+                    escape(&self.options.labels.thinking)
+                );
+                self.write(&html)?;
+            }
+
+            ParseEvent::ThinkBlockLine(text) => {
+                self.write(&format!("<p>{}</p>\n", escape(text)))?;
+            }
+
+            ParseEvent::ThinkBlockEnd => {
+                self.write("</details>\n")?;
+            }
+
+            ParseEvent::HorizontalRule => {
+                self.close_paragraph()?;
+                let html = format!("<hr{}{}>\n", self.class_attr(), style_attr(&self.theme.hr));
+                self.write(&html)?;
+            }
+
+            ParseEvent::EmptyLine => {
+                self.close_paragraph()?;
+            }
+
+            ParseEvent::Newline => {
+                if self.in_paragraph {
+                    self.write("\n")?;
+                }
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    /// Close any open paragraph, list, or table.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.close_paragraph()?;
+        self.close_lists_deeper_than(None)?;
+        self.set_quote_depth(0)?;
+        self.flush_table()?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> EventRenderer for HtmlRenderer<W> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        HtmlRenderer::render_event(self, event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        HtmlRenderer::finish(self)
+    }
+}
+
+/// ` class="..."` attribute for `tags`, or nothing if there are none.
+fn class_attr(tags: &[String]) -> String {
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"{}\"", escape(&tags.join(" ")))
+    }
+}
+
+/// Inline styler producing HTML spans with CSS derived from a theme.
+struct HtmlStyler<'a> {
+    theme: &'a Theme,
+    // URL schemes that may become an `href`, or `None` for the defaults
+    schemes: Option<&'a [String]>,
+}
+
+impl HtmlStyler<'_> {
+    fn span(&self, tag: &str, style: &Style, text: &str) -> String {
+        format!(
+            "<{tag}{}>{}</{tag}>",
+            style_attr(style),
+            escape(&decode_html_entities(text))
+        )
+    }
+}
+
+impl InlineStyler for HtmlStyler<'_> {
+    fn text(&self, text: &str) -> String {
+        escape(&decode_html_entities(text))
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.span("strong", &self.theme.bold, text)
+    }
+
+    fn italic(&self, text: &str) -> String {
+        self.span("em", &self.theme.italic, text)
+    }
+
+    fn bold_italic(&self, text: &str) -> String {
+        let style = self.theme.bold.merge(&self.theme.italic);
+        format!(
+            "<strong{}><em>{}</em></strong>",
+            style_attr(&style),
+            escape(&decode_html_entities(text))
+        )
+    }
+
+    fn strikethrough(&self, text: &str) -> String {
+        self.span("del", &self.theme.strikethrough, text)
+    }
+
+    fn underline(&self, text: &str) -> String {
+        self.span("u", &self.theme.underline, text)
+    }
+
+    fn code(&self, text: &str) -> String {
+        format!("<code{}>{}</code>", style_attr(&self.theme.code), escape(text))
+    }
+
+    fn link(&self, text: &str, url: &str) -> String {
+        // Schemes such as `javascript:` must never become clickable
+        if !is_linkable(url, self.schemes) {
+            return self.span("span", &self.theme.link, text);
+        }
+        format!(
+            "<a href=\"{}\"{}>{}</a>",
+            escape(url),
+            style_attr(&self.theme.link),
+            escape(&decode_html_entities(text))
+        )
+    }
+
+    fn image(&self, alt: &str, url: &str) -> String {
+        format!("<img alt=\"{}\" src=\"{}\">", escape(alt), escape(url))
+    }
+
+    fn footnote(&self, text: &str) -> String {
+        format!("<sup>{}</sup>", escape(text))
+    }
+
+    fn dimmed(&self, text: &str) -> String {
+        self.span("span", &Style::new().dimmed(), text)
+    }
+}
+
+/// Escape text for inclusion in HTML content or attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Build a ` style="..."` attribute for a theme style, or nothing if unstyled.
+fn style_attr(style: &Style) -> String {
+    let mut css = Vec::new();
+    if let Some(fg) = style.fg {
+        css.push(format!("color:{}", css_color(fg)));
+    }
+    if let Some(bg) = style.bg {
+        css.push(format!("background-color:{}", css_color(bg)));
+    }
+    if style.bold {
+        css.push("font-weight:bold".to_string());
+    }
+    if style.italic {
+        css.push("font-style:italic".to_string());
+    }
+    match (style.underline, style.strikethrough) {
+        (true, true) => css.push("text-decoration:underline line-through".to_string()),
+        (true, false) => css.push("text-decoration:underline".to_string()),
+        (false, true) => css.push("text-decoration:line-through".to_string()),
+        (false, false) => {}
+    }
+    if style.underline {
+        let shape = match style.underline_style {
+            UnderlineStyle::Straight => None,
+            UnderlineStyle::Double => Some("double"),
+            UnderlineStyle::Curly => Some("wavy"),
+            UnderlineStyle::Dotted => Some("dotted"),
+            UnderlineStyle::Dashed => Some("dashed"),
+        };
+        if let Some(shape) = shape {
+            css.push(format!("text-decoration-style:{}", shape));
+        }
+        if let Some(color) = style.underline_color {
+            css.push(format!("text-decoration-color:{}", css_color(color)));
+        }
+    }
+    if style.dimmed {
+        css.push("opacity:0.6".to_string());
+    }
+    if css.is_empty() {
+        String::new()
+    } else {
+        format!(" style=\"{}\"", css.join(";"))
+    }
+}
+
+/// Convert a terminal color into a CSS hex color.
+fn css_color(color: Color) -> String {
+    let (r, g, b) = color_rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(events: &[ParseEvent]) -> String {
+        let mut out = Vec::new();
+        let mut renderer = HtmlRenderer::with_theme(&mut out, Theme::dark());
+        for event in events {
+            renderer.render_event(event).unwrap();
+        }
+        renderer.finish().unwrap();
+        drop(renderer);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn test_heading() {
+        let html = render(&[ParseEvent::Heading {
+            level: 2,
+            content: "Hello **world**".to_string(),
+        }]);
+        insta::assert_snapshot!(html, @r#"<h2 style="color:#0000ee;font-weight:bold">Hello <strong style="font-weight:bold">world</strong></h2>"#);
+    }
+
+    #[test]
+    fn test_underline_uses_theme_style() {
+        let mut theme = Theme::dark();
+        theme.underline = Style::new()
+            .underline_style(UnderlineStyle::Curly)
+            .underline_color(Color::TrueColor { r: 255, g: 0, b: 0 });
+        let mut out = Vec::new();
+        let mut renderer = HtmlRenderer::with_theme(&mut out, theme);
+        renderer.render_event(&ParseEvent::Underline("x".to_string())).unwrap();
+        renderer.finish().unwrap();
+        drop(renderer);
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("text-decoration-style:wavy"), "{}", html);
+        assert!(html.contains("text-decoration-color:#ff0000"), "{}", html);
+    }
+
+    #[test]
+    fn test_paragraph_text_is_escaped() {
+        let html = render(&[ParseEvent::Text("a < b".to_string()), ParseEvent::EmptyLine]);
+        assert_eq!(html, "<p>a &lt; b</p>\n");
+    }
+
+    #[test]
+    fn test_nested_list() {
+        let html = render(&[
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "one".to_string(),
+            },
+            ParseEvent::ListItem {
+                indent: 1,
+                bullet: ListBullet::Ordered(1),
+                content: "child".to_string(),
+            },
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "two".to_string(),
+            },
+        ]);
+        insta::assert_snapshot!(html, @r"
+        <ul>
+        <li>one<ol>
+        <li>child</li></ol>
+        </li>
+        <li>two</li></ul>
+        ");
+    }
+
+    #[test]
+    fn test_unsafe_link_schemes_are_not_linked() {
+        let html = render(&[
+            ParseEvent::Link {
+                text: "click".to_string(),
+                url: "javascript:alert(1)".to_string(),
+            },
+            ParseEvent::Link {
+                text: "docs".to_string(),
+                url: "https://docs.rs".to_string(),
+            },
+        ]);
+        assert!(!html.contains("javascript:"), "{}", html);
+        assert!(html.contains("<a href=\"https://docs.rs\""), "{}", html);
+    }
+
+    #[test]
+    fn test_nested_blockquotes() {
+        let html = render(&[
+            ParseEvent::BlockquoteStart { depth: 1 },
+            ParseEvent::BlockquoteLine("outer".to_string()),
+            ParseEvent::BlockquoteStart { depth: 2 },
+            ParseEvent::BlockquoteLine("inner".to_string()),
+            ParseEvent::BlockquoteEnd,
+        ]);
+        let plain = html.replace(&style_attr(&Theme::dark().blockquote), "");
+        insta::assert_snapshot!(plain, @r"
+        <blockquote>
+        <p>outer</p>
+        <blockquote>
+        <p>inner</p>
+        </blockquote>
+        </blockquote>
+        ");
+    }
+
+    #[test]
+    fn test_tagged_events_get_classes() {
+        let mut out = Vec::new();
+        let mut renderer = HtmlRenderer::with_theme(&mut out, Theme::dark());
+        renderer
+            .render_tagged_event(&ParseEvent::HorizontalRule, &["divider", "x<y"])
+            .unwrap();
+        renderer
+            .render_tagged_event(&ParseEvent::Text("note".to_string()), &["aside"])
+            .unwrap();
+        renderer.finish().unwrap();
+        drop(renderer);
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.starts_with("<hr class=\"divider x&lt;y\""), "{}", html);
+        assert!(html.contains("<p><span class=\"aside\">note</span></p>"), "{}", html);
+    }
+}
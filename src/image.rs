@@ -0,0 +1,426 @@
+//! Inline image rendering through terminal graphics protocols.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Terminal graphics protocol used to draw images inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// Kitty graphics protocol; PNG images only.
+    Kitty,
+    /// iTerm2 inline images, also supported by WezTerm.
+    Iterm2,
+}
+
+impl ImageProtocol {
+    /// Detect a supported protocol from the environment.
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        Self::from_env(
+            &var("TERM"),
+            &var("TERM_PROGRAM"),
+            !var("KITTY_WINDOW_ID").is_empty(),
+        )
+    }
+
+    fn from_env(term: &str, term_program: &str, kitty_window: bool) -> Option<Self> {
+        if kitty_window || term == "xterm-kitty" {
+            Some(Self::Kitty)
+        } else if matches!(term_program, "iTerm.app" | "WezTerm") {
+            Some(Self::Iterm2)
+        } else {
+            None
+        }
+    }
+
+    /// Encode image file contents as an escape sequence that draws them, or
+    /// `None` if this protocol cannot display the format.
+    pub fn encode(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Kitty => {
+                if !bytes.starts_with(PNG_MAGIC) {
+                    return None;
+                }
+                let data = base64(bytes);
+                let chunks: Vec<&str> = data
+                    .as_bytes()
+                    .chunks(KITTY_CHUNK)
+                    .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                    .collect();
+                let mut out = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(i + 1 < chunks.len());
+                    if i == 0 {
+                        out.push_str(&format!("\x1b_Gf=100,a=T,m={};{}\x1b\\", more, chunk));
+                    } else {
+                        out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                    }
+                }
+                Some(out)
+            }
+            Self::Iterm2 => Some(format!(
+                "\x1b]1337;File=inline=1;size={}:{}\x07",
+                bytes.len(),
+                base64(bytes)
+            )),
+        }
+    }
+}
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest local image file that is read.
+const MAX_LOCAL_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Maximum payload of a single Kitty graphics escape.
+const KITTY_CHUNK: usize = 4096;
+
+/// Read a local image referenced by `url` from inside `base`.
+///
+/// Only plain paths and `file://` URLs are loaded; remote images keep the
+/// placeholder unless an [`ImageCache`] fetches them. Relative paths are
+/// resolved against `base`, and paths leading outside it, files that are
+/// not regular, larger than [`MAX_LOCAL_BYTES`] or not in a known image
+/// format are rejected.
+pub(crate) fn load_local(url: &str, base: &Path) -> Option<Vec<u8>> {
+    if is_remote(url) {
+        return None;
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    let base = base.canonicalize().ok()?;
+    let path = base.join(path).canonicalize().ok()?;
+    if !path.starts_with(&base) {
+        return None;
+    }
+    // Checked before opening, since opening a FIFO blocks
+    let metadata = fs::metadata(&path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_LOCAL_BYTES {
+        return None;
+    }
+    let file = fs::File::open(&path).ok()?;
+    let mut bytes = Vec::new();
+    file.take(MAX_LOCAL_BYTES).read_to_end(&mut bytes).ok()?;
+    is_image(&bytes).then_some(bytes)
+}
+
+/// Whether `bytes` start like a PNG, JPEG, GIF or WebP image.
+fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(PNG_MAGIC)
+        || bytes.starts_with(b"\xff\xd8\xff")
+        || bytes.starts_with(b"GIF87a")
+        || bytes.starts_with(b"GIF89a")
+        || (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP"))
+}
+
+fn is_remote(url: &str) -> bool {
+    url.contains("://") && !url.starts_with("file://")
+}
+
+/// Downloads the contents of a remote image URL.
+pub type ImageFetcher = Arc<dyn Fn(&str) -> io::Result<Vec<u8>> + Send + Sync>;
+
+/// On-disk cache of remote images, so repeated renders of the same
+/// transcript don't download them again.
+///
+/// Each image is stored in a file named after the hash of its URL. Images
+/// missing from the cache are downloaded with the fetcher; when it fails,
+/// as when offline, or the download is larger than
+/// [`max_image_bytes`](Self::max_image_bytes), the image keeps its
+/// placeholder. Once the cache grows past its maximum size the least
+/// recently used images are removed.
+///
+/// ```no_run
+/// use forge_markdown_stream::ImageCache;
+///
+/// let cache = ImageCache::new("/tmp/markdown-images").fetcher(|url| {
+///     let output = std::process::Command::new("curl").args(["-sf", url]).output()?;
+///     Ok(output.stdout)
+/// });
+/// ```
+#[derive(Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_image_bytes: u64,
+    fetcher: Option<ImageFetcher>,
+}
+
+impl fmt::Debug for ImageCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageCache")
+            .field("dir", &self.dir)
+            .field("max_bytes", &self.max_bytes)
+            .field("max_image_bytes", &self.max_image_bytes)
+            .field("fetcher", &self.fetcher.is_some())
+            .finish()
+    }
+}
+
+impl ImageCache {
+    /// Default maximum size of the cache: 64 MiB.
+    pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+    /// Cache images in `dir`, which is created when the first image is
+    /// stored. Without a fetcher only images already cached are shown.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            max_image_bytes: MAX_LOCAL_BYTES,
+            fetcher: None,
+        }
+    }
+
+    /// Total size the cached images may reach before the least recently
+    /// used are removed.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Largest image kept from a download, 16 MiB by default like local
+    /// images.
+    pub fn max_image_bytes(mut self, max_image_bytes: u64) -> Self {
+        self.max_image_bytes = max_image_bytes;
+        self
+    }
+
+    /// Download images missing from the cache with `fetcher`.
+    pub fn fetcher(
+        mut self,
+        fetcher: impl Fn(&str) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.fetcher = Some(Arc::new(fetcher));
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Contents of the remote image at `url`, from the cache or downloaded
+    /// into it, or `None` if it is neither cached nor downloadable.
+    pub fn load(&self, url: &str) -> Option<Vec<u8>> {
+        if !is_remote(url) {
+            return None;
+        }
+        let path = self.dir.join(cache_key(url));
+        if let Ok(bytes) = fs::read(&path) {
+            // Mark the entry as recently used for eviction
+            if let Ok(file) = fs::File::options().append(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            return Some(bytes);
+        }
+        let bytes = (self.fetcher.as_ref()?)(url).ok()?;
+        if bytes.len() as u64 > self.max_image_bytes {
+            return None;
+        }
+        // A failure to store the image still leaves it displayable
+        if self.store(&path, &bytes).is_ok() {
+            let _ = self.evict();
+        }
+        Some(bytes)
+    }
+
+    /// Write an entry through a temporary file, so concurrent renderers
+    /// never read a partial image.
+    fn store(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, path)
+    }
+
+    /// Remove the least recently used entries until the cache fits.
+    /// Temporary files, which other processes may still be writing, are
+    /// left alone.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let is_entry = name.len() == 16
+                && name.to_str().is_some_and(|name| name.bytes().all(|b| b.is_ascii_hexdigit()));
+            if !is_entry {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((used, metadata.len(), entry.path()));
+            }
+        }
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+/// File name of the cache entry for `url`: its 64-bit FNV-1a hash, which
+/// unlike `DefaultHasher` is stable across Rust versions.
+fn cache_key(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_detect_from_env() {
+        let detect = ImageProtocol::from_env;
+        assert_eq!(detect("xterm-kitty", "", false), Some(ImageProtocol::Kitty));
+        assert_eq!(detect("xterm", "", true), Some(ImageProtocol::Kitty));
+        assert_eq!(detect("xterm", "iTerm.app", false), Some(ImageProtocol::Iterm2));
+        assert_eq!(detect("xterm-256color", "Apple_Terminal", false), None);
+    }
+
+    #[test]
+    fn test_kitty_requires_png() {
+        assert_eq!(ImageProtocol::Kitty.encode(b"GIF89a"), None);
+        let png = [PNG_MAGIC, b"data"].concat();
+        let encoded = ImageProtocol::Kitty.encode(&png).unwrap();
+        assert!(encoded.starts_with("\x1b_Gf=100,a=T,m=0;iVBORw0KGgo"));
+        assert!(encoded.ends_with("\x1b\\"));
+    }
+
+    #[test]
+    fn test_iterm2_inline_file() {
+        assert_eq!(
+            ImageProtocol::Iterm2.encode(b"fo").unwrap(),
+            "\x1b]1337;File=inline=1;size=2:Zm8=\x07"
+        );
+    }
+
+    #[test]
+    fn test_remote_images_are_not_loaded() {
+        assert_eq!(load_local("https://example.com/a.png", Path::new(".")), None);
+    }
+
+    #[test]
+    fn test_local_images_stay_inside_base() {
+        let root = std::env::temp_dir().join(format!("image-base-{}", std::process::id()));
+        let base = root.join("docs");
+        fs::create_dir_all(base.join("img")).unwrap();
+        fs::write(base.join("img/a.png"), PNG_MAGIC).unwrap();
+        fs::write(base.join("notes.txt"), b"secret").unwrap();
+        fs::write(root.join("outside.png"), PNG_MAGIC).unwrap();
+
+        assert_eq!(load_local("img/a.png", &base).as_deref(), Some(PNG_MAGIC));
+        let absolute = format!("file://{}", base.join("img/a.png").display());
+        assert!(load_local(&absolute, &base).is_some());
+        assert_eq!(load_local("../outside.png", &base), None);
+        assert_eq!(load_local(&root.join("outside.png").display().to_string(), &base), None);
+        assert_eq!(load_local("notes.txt", &base), None);
+        assert_eq!(load_local("img", &base), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    fn temp_cache(name: &str) -> ImageCache {
+        let dir = std::env::temp_dir().join(format!("image-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ImageCache::new(dir)
+    }
+
+    #[test]
+    fn test_cache_fetches_once() {
+        let fetches = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let cache = temp_cache("once").fetcher(move |url| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(url.as_bytes().to_vec())
+        });
+        let url = "https://example.com/a.png";
+        assert_eq!(cache.load(url).unwrap(), url.as_bytes());
+        assert_eq!(cache.load(url).unwrap(), url.as_bytes());
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+        // Cached images are shown offline
+        let offline = ImageCache::new(cache.dir());
+        assert_eq!(offline.load(url).unwrap(), url.as_bytes());
+        assert_eq!(offline.load("https://example.com/b.png"), None);
+        assert_eq!(cache.load("a.png"), None);
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_evicts_to_max_size() {
+        let cache = temp_cache("evict")
+            .max_bytes(10)
+            .fetcher(|_| Ok(vec![0; 6]));
+        cache.load("https://example.com/a.png").unwrap();
+        cache.load("https://example.com/b.png").unwrap();
+        let entries = fs::read_dir(cache.dir()).unwrap().count();
+        assert_eq!(entries, 1);
+        assert!(cache
+            .dir()
+            .join(cache_key("https://example.com/b.png"))
+            .exists());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_eviction_keeps_temporary_files_and_oversized_downloads_fail() {
+        let cache = temp_cache("temp")
+            .max_bytes(4)
+            .max_image_bytes(8)
+            .fetcher(|url| Ok(vec![0; if url.ends_with("big.png") { 9 } else { 6 }]));
+        fs::create_dir_all(cache.dir()).unwrap();
+        let temp = cache.dir().join(format!("{}.tmp1", cache_key("https://example.com/c.png")));
+        fs::write(&temp, [0; 6]).unwrap();
+        cache.load("https://example.com/a.png").unwrap();
+        assert!(temp.exists());
+        assert_eq!(cache.load("https://example.com/big.png"), None);
+        assert!(!cache.dir().join(cache_key("https://example.com/big.png")).exists());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_key_is_stable() {
+        assert_eq!(cache_key(""), "cbf29ce484222325");
+        assert_ne!(cache_key("https://a"), cache_key("https://b"));
+    }
+}
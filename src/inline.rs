@@ -154,10 +154,19 @@ mod tests {
         insta::assert_snapshot!(render("![](image.png)"), @r#"<img alt="" src="image.png"/>"#);
     }
 
+    #[test]
+    fn test_theme_image_placeholder() {
+        let mut theme = Theme::dark();
+        insta::assert_snapshot!(render_inline_content("![logo](a.png)", &theme), @"[\u{1f5bc} logo]");
+        theme.image_icon = "img".to_string();
+        theme.image_format = "<{icon}: {alt} {url}>".to_string();
+        insta::assert_snapshot!(render_inline_content("![logo](a.png)", &theme), @"<img: logo a.png>");
+    }
+
     // Verify Theme implementation produces ANSI
     #[test]
     fn test_theme_produces_ansi() {
-        let theme = Theme::default();
+        let theme = Theme::dark();
         let result = render_inline_content("**bold**", &theme);
         assert!(result.contains("\x1b["), "Expected ANSI codes from Theme");
     }
@@ -0,0 +1,361 @@
+//! Renderer emitting its layout as JSON Lines.
+
+use std::io::{self, Write};
+
+use streamdown_parser::{InlineElement, ListBullet, ParseEvent};
+
+use colored::Color;
+
+use crate::backend::AnsiBackend;
+use crate::color::{color_rgb, ColorDepth};
+use crate::options::RendererOptions;
+use crate::renderer::{EventRenderer, Renderer};
+use crate::sgr::BASIC_COLORS;
+use crate::styled::{Span, SpanDecoder, StyledLine};
+use crate::theme::Theme;
+
+/// Names of the basic colors, in SGR order.
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+/// Renderer that writes one JSON object per parse event, holding the event
+/// and the output lines it produced as styled spans, so GUI frontends can
+/// use the terminal layout without parsing escape sequences.
+///
+/// Each record looks like:
+///
+/// ```text
+/// {"kind":"Bold","event":{"text":"hi"},"lines":[{"spans":[{"text":"hi","bold":true}],"newline":false}]}
+/// ```
+///
+/// `event` holds the fields of the parse event, named as in the parser:
+/// `text`, `url`, `alt`, `level`, `content`, `language`, `indent`,
+/// `bullet` (`dash`, `asterisk`, `plus`, `plus_expand` or `ordered` with a
+/// `number`), `depth`, `cells`, and `elements` for inline elements, each an
+/// object with its own `kind`.
+///
+/// A line with `newline` false continues in the next record. Colors are
+/// `#rrggbb` or a basic color name such as `red` or `bright_blue`. `finish`
+/// writes a last record of kind `Finish`.
+pub struct JsonRenderer<W: Write> {
+    writer: W,
+    renderer: Renderer<Vec<u8>>,
+    // Style and hyperlink in effect at the end of the previous record
+    decoder: SpanDecoder,
+}
+
+impl<W: Write> JsonRenderer<W> {
+    pub fn new(writer: W, width: usize) -> Self {
+        Self::with_theme(writer, width, Theme::default())
+    }
+
+    /// Create a renderer with a custom theme, whose colors are reported in
+    /// full whatever the theme's backend.
+    pub fn with_theme(writer: W, width: usize, theme: Theme) -> Self {
+        let theme = theme.with_backend(AnsiBackend::new(ColorDepth::TrueColor));
+        Self {
+            writer,
+            renderer: Renderer::with_theme(Vec::new(), width, theme),
+            decoder: SpanDecoder::default(),
+        }
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    /// Render a single parse event and write its record.
+    pub fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.renderer.render_event(event)?;
+        let (kind, fields) = event_json(event);
+        self.write_record(kind, Some(&format!("{{{}}}", fields.join(","))))
+    }
+
+    /// Complete the document and write the `Finish` record.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.renderer.finish()?;
+        self.write_record("Finish", None)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_record(&mut self, kind: &str, event: Option<&str>) -> io::Result<()> {
+        let buffer = self.renderer.take_output()?;
+        let output = String::from_utf8_lossy(&buffer);
+        let mut record = format!("{{\"kind\":{}", quote(kind));
+        if let Some(event) = event {
+            record.push_str(&format!(",\"event\":{}", event));
+        }
+        let lines: Vec<String> = output
+            .split_inclusive('\n')
+            .map(|line| (self.decoder.line(line), line.ends_with('\n')))
+            .filter(|(styled, newline)| *newline || !styled.spans.is_empty())
+            .map(|(styled, newline)| {
+                format!("{{\"spans\":{},\"newline\":{}}}", spans_json(&styled), newline)
+            })
+            .collect();
+        record.push_str(&format!(",\"lines\":[{}]}}", lines.join(",")));
+        writeln!(self.writer, "{}", record)?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> EventRenderer for JsonRenderer<W> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        JsonRenderer::render_event(self, event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        JsonRenderer::finish(self)
+    }
+}
+
+/// Kind and JSON fields of a parse event.
+fn event_json(event: &ParseEvent) -> (&'static str, Vec<String>) {
+    let field = |name: &str, value: String| format!("\"{}\":{}", name, value);
+    let text = |text: &str| vec![field("text", quote(text))];
+    let cells = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| quote(cell)).collect();
+        vec![field("cells", format!("[{}]", cells.join(",")))]
+    };
+    match event {
+        ParseEvent::Text(value) => ("Text", text(value)),
+        ParseEvent::InlineCode(value) => ("InlineCode", text(value)),
+        ParseEvent::Bold(value) => ("Bold", text(value)),
+        ParseEvent::Italic(value) => ("Italic", text(value)),
+        ParseEvent::BoldItalic(value) => ("BoldItalic", text(value)),
+        ParseEvent::Underline(value) => ("Underline", text(value)),
+        ParseEvent::Strikeout(value) => ("Strikeout", text(value)),
+        ParseEvent::Link { text, url } => (
+            "Link",
+            vec![field("text", quote(text)), field("url", quote(url))],
+        ),
+        ParseEvent::Image { alt, url } => (
+            "Image",
+            vec![field("alt", quote(alt)), field("url", quote(url))],
+        ),
+        ParseEvent::Footnote(value) => ("Footnote", text(value)),
+        ParseEvent::Prompt(value) => ("Prompt", text(value)),
+        ParseEvent::InlineElements(elements) => {
+            let elements: Vec<String> = elements.iter().map(element_json).collect();
+            (
+                "InlineElements",
+                vec![field("elements", format!("[{}]", elements.join(",")))],
+            )
+        }
+        ParseEvent::Heading { level, content } => (
+            "Heading",
+            vec![field("level", level.to_string()), field("content", quote(content))],
+        ),
+        ParseEvent::CodeBlockStart { language, .. } => (
+            "CodeBlockStart",
+            vec![field(
+                "language",
+                language.as_deref().map_or("null".to_string(), quote),
+            )],
+        ),
+        ParseEvent::CodeBlockLine(value) => ("CodeBlockLine", text(value)),
+        ParseEvent::CodeBlockEnd => ("CodeBlockEnd", Vec::new()),
+        ParseEvent::ListItem {
+            indent,
+            bullet,
+            content,
+        } => {
+            let mut fields = vec![field("indent", indent.to_string())];
+            let name = match bullet {
+                ListBullet::Ordered(number) => {
+                    fields.push(field("number", number.to_string()));
+                    "ordered"
+                }
+                ListBullet::Dash => "dash",
+                ListBullet::Asterisk => "asterisk",
+                ListBullet::Plus => "plus",
+                ListBullet::PlusExpand => "plus_expand",
+            };
+            fields.insert(1, field("bullet", quote(name)));
+            fields.push(field("content", quote(content)));
+            ("ListItem", fields)
+        }
+        ParseEvent::ListEnd => ("ListEnd", Vec::new()),
+        ParseEvent::TableHeader(row) => ("TableHeader", cells(row)),
+        ParseEvent::TableRow(row) => ("TableRow", cells(row)),
+        ParseEvent::TableSeparator => ("TableSeparator", Vec::new()),
+        ParseEvent::TableEnd => ("TableEnd", Vec::new()),
+        ParseEvent::BlockquoteStart { depth } => {
+            ("BlockquoteStart", vec![field("depth", depth.to_string())])
+        }
+        ParseEvent::BlockquoteLine(value) => ("BlockquoteLine", text(value)),
+        ParseEvent::BlockquoteEnd => ("BlockquoteEnd", Vec::new()),
+        ParseEvent::ThinkBlockStart => ("ThinkBlockStart", Vec::new()),
+        ParseEvent::ThinkBlockLine(value) => ("ThinkBlockLine", text(value)),
+        ParseEvent::ThinkBlockEnd => ("ThinkBlockEnd", Vec::new()),
+        ParseEvent::HorizontalRule => ("HorizontalRule", Vec::new()),
+        ParseEvent::EmptyLine => ("EmptyLine", Vec::new()),
+        ParseEvent::Newline => ("Newline", Vec::new()),
+    }
+}
+
+/// Inline element as a JSON object with its kind and fields.
+fn element_json(element: &InlineElement) -> String {
+    let (kind, fields) = match element {
+        InlineElement::Text(text) => ("Text", format!(",\"text\":{}", quote(text))),
+        InlineElement::Bold(text) => ("Bold", format!(",\"text\":{}", quote(text))),
+        InlineElement::Italic(text) => ("Italic", format!(",\"text\":{}", quote(text))),
+        InlineElement::BoldItalic(text) => ("BoldItalic", format!(",\"text\":{}", quote(text))),
+        InlineElement::Strikeout(text) => ("Strikeout", format!(",\"text\":{}", quote(text))),
+        InlineElement::Underline(text) => ("Underline", format!(",\"text\":{}", quote(text))),
+        InlineElement::Code(text) => ("Code", format!(",\"text\":{}", quote(text))),
+        InlineElement::Link { text, url } => (
+            "Link",
+            format!(",\"text\":{},\"url\":{}", quote(text), quote(url)),
+        ),
+        InlineElement::Image { alt, url } => (
+            "Image",
+            format!(",\"alt\":{},\"url\":{}", quote(alt), quote(url)),
+        ),
+        InlineElement::Footnote(text) => ("Footnote", format!(",\"text\":{}", quote(text))),
+    };
+    format!("{{\"kind\":{}{}}}", quote(kind), fields)
+}
+
+/// The spans of `line` as a JSON array.
+pub(crate) fn spans_json(line: &StyledLine) -> String {
+    let spans: Vec<String> = line.spans.iter().map(span_json).collect();
+    format!("[{}]", spans.join(","))
+}
+
+fn span_json(span: &Span) -> String {
+    let style = &span.style;
+    let mut json = format!("{{\"text\":{}", quote(&span.text));
+    let flags = [
+        ("bold", style.bold),
+        ("dim", style.dimmed),
+        ("italic", style.italic),
+        ("underline", style.underline),
+        ("strikethrough", style.strikethrough),
+    ];
+    for (name, set) in flags {
+        if set {
+            json.push_str(&format!(",\"{}\":true", name));
+        }
+    }
+    for (name, color) in [("fg", style.fg), ("bg", style.bg)] {
+        if let Some(color) = color {
+            json.push_str(&format!(",\"{}\":{}", name, quote(&color_name(color))));
+        }
+    }
+    if let Some(link) = &span.link {
+        json.push_str(&format!(",\"link\":{}", quote(link)));
+    }
+    json.push('}');
+    json
+}
+
+/// Name of a basic color, or `#rrggbb` for any other.
+fn color_name(color: Color) -> String {
+    match BASIC_COLORS.iter().position(|basic| *basic == color) {
+        Some(index) => COLOR_NAMES[index].to_string(),
+        None => {
+            let (r, g, b) = color_rgb(color);
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        }
+    }
+}
+
+/// Quote `text` as a JSON string.
+pub(crate) fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_follow_sgr_and_hyperlinks() {
+        let mut decoder = SpanDecoder::default();
+        let line = decoder
+            .line("\x1b[1;38;2;255;0;0mred\x1b[0m \x1b]8;;https://a.b\x1b\\link\x1b]8;;\x1b\\\n");
+        assert_eq!(
+            spans_json(&line),
+            concat!(
+                r##"[{"text":"red","bold":true,"fg":"#ff0000"},{"text":" "},"##,
+                r#"{"text":"link","link":"https://a.b"}]"#
+            )
+        );
+        assert_eq!(spans_json(&decoder.line("next")), r#"[{"text":"next"}]"#);
+    }
+
+    #[test]
+    fn test_writes_a_record_per_event() {
+        let mut renderer = JsonRenderer::with_theme(Vec::new(), 40, Theme::dark());
+        renderer
+            .render_event(&ParseEvent::Text("say \"hi\"".to_string()))
+            .unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        renderer.finish().unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        let records: Vec<&str> = output.lines().collect();
+        assert_eq!(records.len(), 3);
+        assert!(records[0].starts_with(r#"{"kind":"Text","event":{"text":"say \"hi\""}"#));
+        assert!(records[0].contains(r#""text":"say \"hi\"""#));
+        assert!(records[2].starts_with(r#"{"kind":"Finish","lines":["#));
+    }
+
+    #[test]
+    fn test_events_are_serialized_by_field() {
+        let (kind, fields) = event_json(&ParseEvent::ListItem {
+            indent: 2,
+            bullet: ListBullet::Ordered(3),
+            content: "item".to_string(),
+        });
+        assert_eq!(kind, "ListItem");
+        assert_eq!(
+            fields.join(","),
+            r#""indent":2,"bullet":"ordered","number":3,"content":"item""#
+        );
+        let (_, fields) = event_json(&ParseEvent::TableRow(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(fields.join(","), r#""cells":["a","b"]"#);
+        let (_, fields) = event_json(&ParseEvent::InlineElements(vec![InlineElement::Link {
+            text: "x".to_string(),
+            url: "https://a.b".to_string(),
+        }]));
+        assert_eq!(
+            fields.join(","),
+            r#""elements":[{"kind":"Link","text":"x","url":"https://a.b"}]"#
+        );
+        assert!(event_json(&ParseEvent::EmptyLine).1.is_empty());
+    }
+}
@@ -22,24 +22,125 @@
 //! }
 //! ```
 
+#[cfg(feature = "tokio")]
+mod async_renderer;
+mod backend;
+mod builder;
+mod calibrate;
 mod code;
+mod color;
+mod composite;
+#[cfg(feature = "crossterm")]
+mod console;
+#[cfg(feature = "crossterm")]
+mod crossterm_backend;
+mod diff;
+mod doctor;
+mod document;
+mod dual;
+mod frame;
+mod guard;
 mod heading;
+mod html;
+#[cfg(feature = "images")]
+mod image;
 mod inline;
+mod json;
+mod linebreak;
+mod links;
 mod list;
+mod locale;
+mod multiplex;
+#[cfg(feature = "net")]
+mod net;
+mod options;
+#[cfg(feature = "pager")]
+mod pager;
+mod profile;
 mod renderer;
 mod repair;
+mod sgr;
+mod simulate;
+mod smooth;
+mod strip;
 mod table;
 mod theme;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
 mod style;
+mod styled;
+mod summary;
 mod utils;
+mod validate;
+mod width;
 
+use std::collections::VecDeque;
 use std::io::{self, Write};
+use std::task::Poll;
+use std::time::{Duration, Instant};
 
 pub use streamdown_parser::Parser;
+use code::extension_hint;
+use list::ordered_marker_number;
+use repair::Fence;
+use utils::typeset_units;
+use streamdown_parser::ParseEvent;
 
-pub use renderer::Renderer;
+#[cfg(feature = "tokio")]
+pub use async_renderer::AsyncRenderer;
+pub use backend::{AnsiBackend, OutputBackend, PlainBackend};
+pub use builder::RendererBuilder;
+pub use calibrate::Calibration;
+pub use code::CodeHighlighter;
+pub use color::ColorDepth;
+pub use composite::CompositeRenderer;
+#[cfg(feature = "crossterm")]
+pub use console::{enable_virtual_terminal, ConsoleWriter};
+#[cfg(feature = "crossterm")]
+pub use crossterm_backend::CrosstermBackend;
+pub use diff::{layout_diff_html, recorded_diff_html, LayoutConfig};
+pub use doctor::doctor;
+pub use document::{Document, Section};
+pub use dual::DualRenderer;
+pub use frame::{FrameFormat, FramedRenderer};
+#[cfg(feature = "signal")]
+pub use guard::install_sigint_handler;
+pub use guard::{emergency_reset, TerminalGuard, RESTORE_SEQUENCE};
+pub use heading::HeadingDecoration;
+pub use html::HtmlRenderer;
+pub use json::JsonRenderer;
+pub use links::DEFAULT_LINK_SCHEMES;
+#[cfg(feature = "images")]
+pub use image::{ImageCache, ImageFetcher, ImageProtocol};
+pub use locale::NumberLocale;
+pub use multiplex::MultiplexRenderer;
+#[cfg(feature = "net")]
+pub use net::{OutputFormat, RenderServer};
+pub use options::{
+    BlankLinePolicy, CodeRenderMode, EmojiNormalization, HeadingLevels, HighlightEngine, Labels,
+    NumberingStyle, Pacing, QueuePolicy, RendererOptions, StatusKind, StatusMarkers, TableMode,
+    ThinkMode, WrapMode,
+};
+#[cfg(feature = "pager")]
+pub use pager::Pager;
+pub use profile::TerminalProfile;
+pub use renderer::{
+    Clock, Degradation, EventRenderer, FenceHandler, ImageAction, ImageHandler, Renderer,
+};
 pub use repair::repair_line;
-pub use theme::{Style, Theme};
+pub use simulate::{simulate_stream, LatencyReport, StreamSimulation};
+pub use smooth::{Chunking, DelayCurve, IdleHook, SmoothOptions, SmoothWriter};
+pub use strip::{strip_ansi, StrippingWriter};
+pub use styled::{Span, SpanRenderer, StyledLine};
+pub use summary::{CodeBlockStats, RenderSummary};
+pub use table::{detect_numeric_columns, parse_alignments, ColumnAlignment};
+pub use theme::{
+    BadgeKind, Glyphs, Style, Theme, UnderlineStyle, DEFAULT_IMAGE_FORMAT, DEFAULT_IMAGE_ICON,
+};
+pub use utils::TerminalHandle;
+pub use validate::{StrictValidator, Violation};
 
 /// Streaming markdown renderer for terminal output.
 ///
@@ -51,6 +152,21 @@ pub struct StreamdownRenderer<W: Write> {
     parser: Parser,
     renderer: Renderer<W>,
     line_buffer: String,
+    // Fence of the open code block, so shorter fences inside it stay content
+    open_fence: Option<Fence>,
+    // Complete lines held back by pacing, and when the next one is due
+    paced_lines: VecDeque<String>,
+    paced_until: Option<Instant>,
+    // Whether the last complete line ended every open block
+    at_boundary: bool,
+    // Renderer also receiving every event
+    mirror: Option<Box<dyn EventRenderer + Send>>,
+}
+
+/// Point in a stream that [`StreamdownRenderer::rollback`] returns to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    lines_written: usize,
 }
 
 impl<W: Write> StreamdownRenderer<W> {
@@ -60,6 +176,29 @@ impl<W: Write> StreamdownRenderer<W> {
             parser: Parser::new(),
             renderer: Renderer::new(writer, width),
             line_buffer: String::new(),
+            open_fence: None,
+            paced_lines: VecDeque::new(),
+            paced_until: None,
+            at_boundary: true,
+            mirror: None,
+        }
+    }
+
+    /// Create a new renderer sized to the width of the terminal `writer` is
+    /// attached to.
+    pub fn auto_width(writer: W) -> Self
+    where
+        W: TerminalHandle,
+    {
+        Self {
+            parser: Parser::new(),
+            renderer: Renderer::auto_width(writer),
+            line_buffer: String::new(),
+            open_fence: None,
+            paced_lines: VecDeque::new(),
+            paced_until: None,
+            at_boundary: true,
+            mirror: None,
         }
     }
 
@@ -69,25 +208,259 @@ impl<W: Write> StreamdownRenderer<W> {
             parser: Parser::new(),
             renderer: Renderer::with_theme(writer, width, theme),
             line_buffer: String::new(),
+            open_fence: None,
+            paced_lines: VecDeque::new(),
+            paced_until: None,
+            at_boundary: true,
+            mirror: None,
+        }
+    }
+
+    /// Wrap an already configured renderer, e.g. one built by
+    /// [`RendererBuilder`].
+    fn from_renderer(renderer: Renderer<W>) -> Self {
+        Self {
+            parser: Parser::new(),
+            renderer,
+            line_buffer: String::new(),
+            open_fence: None,
+            paced_lines: VecDeque::new(),
+            paced_until: None,
+            at_boundary: true,
+            mirror: None,
+        }
+    }
+
+    /// Change the output width, e.g. after the terminal was resized.
+    ///
+    /// See [`Renderer::resize`] for how blocks in progress are laid out again.
+    pub fn resize(&mut self, width: usize) -> io::Result<()> {
+        self.renderer.resize(width)
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    /// Statistics of the content rendered so far.
+    pub fn summary(&self) -> &RenderSummary {
+        self.renderer.summary()
+    }
+
+    /// Get the code block highlighter, e.g. to register additional syntax
+    /// definitions.
+    pub fn highlighter_mut(&mut self) -> &mut CodeHighlighter {
+        self.renderer.highlighter_mut()
+    }
+
+    /// Erase the last `count` rendered lines so revised content can be
+    /// pushed in their place, e.g. when a model replaces a placeholder.
+    /// Returns `false` if the backend cannot erase.
+    ///
+    /// Only output is retracted: the parser keeps its state, so retract
+    /// whole blocks such as paragraphs and push their revised source.
+    pub fn retract_lines(&mut self, count: usize) -> io::Result<bool> {
+        self.renderer.retract_lines(count)
+    }
+
+    /// Mark the current point of the stream, e.g. the start of a message,
+    /// to [`rollback`](Self::rollback) to when it is regenerated.
+    ///
+    /// Checkpoints can only be taken between blocks: at the start, or after
+    /// a blank line outside a code block with no partial line pushed.
+    /// Returns `None` elsewhere.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        let pending = !self.line_buffer.is_empty() || !self.paced_lines.is_empty();
+        (self.at_boundary && !pending).then(|| Checkpoint {
+            lines_written: self.renderer.lines_written(),
+        })
+    }
+
+    /// Erase the output written since `checkpoint` and discard the parser
+    /// and block state built up since, so that regenerated content can be
+    /// pushed in its place. Returns `false`, changing nothing, if the
+    /// backend cannot erase or the output before the checkpoint was
+    /// already retracted.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) -> io::Result<bool> {
+        let Some(count) = self.renderer.lines_written().checked_sub(checkpoint.lines_written)
+        else {
+            return Ok(false);
+        };
+        if !self.renderer.retract_lines(count)? {
+            return Ok(false);
         }
+        self.parser = Parser::new();
+        self.renderer.reset_blocks();
+        self.line_buffer.clear();
+        self.paced_lines.clear();
+        self.paced_until = None;
+        self.open_fence = None;
+        self.at_boundary = true;
+        Ok(true)
+    }
+
+    /// Keep the bottom `lines` terminal rows free for the host's prompt or
+    /// status line; `0` releases them. Returns `false` if the backend has no
+    /// scroll region.
+    pub fn reserve_bottom_lines(&mut self, lines: usize) -> io::Result<bool>
+    where
+        W: TerminalHandle,
+    {
+        self.renderer.reserve_bottom_lines(lines)
+    }
+
+    /// Like [`reserve_bottom_lines`](Self::reserve_bottom_lines), for a
+    /// terminal `height` rows tall.
+    pub fn reserve_bottom_lines_in(&mut self, lines: usize, height: usize) -> io::Result<bool> {
+        self.renderer.reserve_bottom_lines_in(lines, height)
+    }
+
+    /// Number of bottom rows kept free for the host.
+    pub fn reserved_bottom_lines(&self) -> usize {
+        self.renderer.reserved_bottom_lines()
+    }
+
+    /// Measure text the way the terminal was calibrated to draw it, redrawing
+    /// the last table if its borders were misaligned. See
+    /// [`Renderer::apply_calibration`].
+    pub fn apply_calibration(&mut self, calibration: &Calibration) -> io::Result<bool> {
+        self.renderer.apply_calibration(calibration)
+    }
+
+    /// Write queued output until the writer would block, when
+    /// `output_queue` is set. `Pending` means output is left in the queue.
+    pub fn poll_flush(&mut self) -> Poll<io::Result<()>> {
+        self.renderer.poll_flush()
+    }
+
+    /// Write all queued output, waiting while the writer would block.
+    pub fn drain(&mut self) -> io::Result<()> {
+        self.renderer.drain()
+    }
+
+    /// Number of bytes rendered but not yet accepted by the writer.
+    pub fn queued_bytes(&self) -> usize {
+        self.renderer.queued_bytes()
+    }
+
+    /// Output skipped so far because the output queue was backed up.
+    pub fn degradation(&self) -> Degradation {
+        self.renderer.degradation()
+    }
+
+    /// Immediately write the terminal restore sequence, e.g. on Ctrl-C.
+    pub fn emergency_reset(&mut self) -> io::Result<()> {
+        self.renderer.emergency_reset()
     }
 
     /// Push a token to the renderer.
     ///
     /// Tokens are buffered until a complete line is received, then rendered.
+    /// With `pacing` set, complete lines are rendered once they are due;
+    /// see [`poll_pacing`](Self::poll_pacing).
     pub fn push(&mut self, token: &str) -> io::Result<()> {
         self.line_buffer.push_str(token);
 
         while let Some(pos) = self.line_buffer.find('\n') {
             let line = self.line_buffer[..pos].to_string();
+            self.line_buffer = self.line_buffer[pos + 1..].to_string();
+            if self.renderer.options().pacing.is_some() || !self.paced_lines.is_empty() {
+                self.paced_lines.push_back(line);
+            } else {
+                self.render_source_line(&line)?;
+            }
+        }
+        if !self.paced_lines.is_empty() {
+            self.poll_pacing()?;
+        }
+        self.renderer.show_cursor()
+    }
 
-            for repaired in repair_line(&line, self.parser.state()) {
-                for event in self.parser.parse_line(&repaired) {
-                    self.renderer.render_event(&event)?;
+    /// Render the lines held back by `pacing` whose time has come, without
+    /// waiting. Returns how long until the next one is due, or `None` if no
+    /// lines are waiting.
+    ///
+    /// `push` polls too; call this from a timer or event loop while the
+    /// producer is quiet, so held back lines keep appearing.
+    pub fn poll_pacing(&mut self) -> io::Result<Option<Duration>> {
+        let pacing = self.renderer.options().pacing;
+        while !self.paced_lines.is_empty() {
+            let now = Instant::now();
+            if let Some(until) = self.paced_until.filter(|_| pacing.is_some()) {
+                if until > now {
+                    self.renderer.show_cursor()?;
+                    return Ok(Some(until - now));
                 }
             }
+            let Some(line) = self.paced_lines.pop_front() else {
+                break;
+            };
+            // Every complete line waiting counts towards catching up
+            let duration = pacing.map_or(Duration::ZERO, |pacing| {
+                pacing.duration(line.chars().count(), self.paced_lines.len())
+            });
+            self.paced_until = now.checked_add(duration);
+            self.render_source_line(&line)?;
+        }
+        self.renderer.show_cursor()?;
+        Ok(None)
+    }
 
-            self.line_buffer = self.line_buffer[pos + 1..].to_string();
+    /// Render one complete line of source.
+    fn render_source_line(&mut self, line: &str) -> io::Result<()> {
+        for repaired in repair_line(line, self.parser.state()) {
+            self.render_line(&repaired)?;
+        }
+        self.at_boundary = line.trim().is_empty() && !self.parser.state().is_in_code();
+        Ok(())
+    }
+
+    /// Parse one complete line and render its events.
+    fn render_line(&mut self, line: &str) -> io::Result<()> {
+        let typeset;
+        let line = if self.renderer.options().typeset_units && !self.parser.state().is_in_code() {
+            typeset = typeset_units(line);
+            typeset.as_str()
+        } else {
+            line
+        };
+        // A ``` example inside a ```` block is content, not the block's end
+        if let Some(fence) = self.open_fence {
+            let nested = Fence::parse(line).is_some() && !fence.is_closed_by(line);
+            if nested && self.parser.state().is_in_code() {
+                let event = ParseEvent::CodeBlockLine(line.to_string());
+                return self.emit(&event);
+            }
+        }
+        let mut hint = None;
+        if !self.parser.state().is_in_code() {
+            hint = extension_hint(line);
+            // Numbered lines inside code blocks are content, not list items
+            if let Some(number) = ordered_marker_number(line) {
+                self.renderer.set_list_start(number);
+            }
+        }
+        // Indented lines after a list item continue that item
+        if !line.trim().is_empty() {
+            let indented = line.starts_with("  ") || line.starts_with('\t');
+            self.renderer.set_list_continuation(indented);
+        }
+        for event in self.parser.parse_line(line) {
+            match event {
+                ParseEvent::TableSeparator => {
+                    self.renderer.set_table_alignments(parse_alignments(line));
+                }
+                ParseEvent::CodeBlockStart { .. } => self.open_fence = Fence::parse(line),
+                ParseEvent::CodeBlockEnd => self.open_fence = None,
+                _ => {}
+            }
+            self.emit(&event)?;
+        }
+        // Only once the line's own block has started, which forgets hints
+        // from earlier blocks
+        if let Some(extension) = hint {
+            self.renderer.set_language_hint(extension);
         }
         Ok(())
     }
@@ -95,17 +468,84 @@ impl<W: Write> StreamdownRenderer<W> {
     /// Finish rendering, flushing any remaining buffered content.
     /// Returns the underlying writer.
     pub fn finish(mut self) -> io::Result<()> {
+        while let Some(line) = self.paced_lines.pop_front() {
+            self.render_source_line(&line)?;
+        }
         if !self.line_buffer.is_empty() {
             for repaired in repair_line(&self.line_buffer, self.parser.state()) {
-                for event in self.parser.parse_line(&repaired) {
-                    self.renderer.render_event(&event)?;
-                }
+                self.render_line(&repaired)?;
             }
         }
         for event in self.parser.finalize() {
-            self.renderer.render_event(&event)?;
+            self.emit(&event)?;
         }
-        Ok(())
+        if let Some(mirror) = &mut self.mirror {
+            mirror.finish()?;
+        }
+        self.renderer.finish()
+    }
+
+    /// Also render every event with `mirror`, e.g. to produce another
+    /// format from the same source lines.
+    pub(crate) fn mirror_events(&mut self, mirror: impl EventRenderer + Send + 'static) {
+        self.mirror = Some(Box::new(mirror));
+    }
+
+    fn emit(&mut self, event: &ParseEvent) -> io::Result<()> {
+        if let Some(mirror) = &mut self.mirror {
+            mirror.render_event(event)?;
+        }
+        self.renderer.render_event(event)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_to_checkpoint() {
+        let mut out = Vec::new();
+        let theme = Theme::plain().with_backend(AnsiBackend::default());
+        let mut renderer = StreamdownRenderer::with_theme(&mut out, 40, theme);
+        renderer.push("intro\n\n").unwrap();
+        let checkpoint = renderer.checkpoint().unwrap();
+        renderer.push("1. first\n2. second\n```\ncode").unwrap();
+        assert_eq!(renderer.checkpoint(), None);
+        assert!(renderer.rollback(checkpoint).unwrap());
+        // The list and code block were discarded along with their output
+        renderer.push("1. again\n").unwrap();
+        renderer.finish().unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let (before, regenerated) = output.rsplit_once("F\x1b[J").unwrap();
+        assert!(before.contains("second"), "{:?}", output);
+        assert!(regenerated.contains("1. again"), "{:?}", output);
+        assert!(!regenerated.contains("second"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_pacing_holds_lines_without_waiting() {
+        let output = utils::SharedBuffer::default();
+        let mut renderer = StreamdownRenderer::with_theme(output.clone(), 40, Theme::plain());
+        renderer.set_options(RendererOptions {
+            pacing: Some(Pacing {
+                chars_per_second: 1.0,
+                catch_up_lines: 4,
+            }),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        renderer.push("first\nsecond\nthird\n").unwrap();
+        let wait = renderer.poll_pacing().unwrap().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // Five characters, sped up by the two lines waiting behind them
+        assert!(wait > Duration::from_secs(3) && wait <= Duration::from_secs_f64(10.0 / 3.0));
+        let shown = output.take();
+        assert!(shown.contains("first") && !shown.contains("second"), "{:?}", shown);
+
+        renderer.finish().unwrap();
+        let rest = output.take();
+        assert!(rest.contains("second") && rest.contains("third"), "{:?}", rest);
+    }
+}
@@ -0,0 +1,102 @@
+//! Wrapping of prose at spaces, soft hyphens and zero-width spaces, with
+//! UAX #14 line breaking when the `linebreak` feature is enabled so text
+//! without spaces, such as Chinese or Japanese, wraps too.
+
+#[cfg(feature = "linebreak")]
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+#[cfg(feature = "linebreak")]
+use crate::strip::{tokens, Token};
+use crate::table::wrap;
+#[cfg(feature = "linebreak")]
+use crate::table::ZERO_WIDTH_SPACE;
+use crate::width::visible_length_with;
+
+/// Width that leaves prose unwrapped.
+pub(crate) const NO_WRAP: usize = usize::MAX;
+
+/// Wrap rendered prose to `width` columns, starting the first line with
+/// `first` and every other line with `next`. Returns at least one line.
+///
+/// With `ambiguous_wide`, ambiguous-width characters are measured as two
+/// columns.
+pub(crate) fn wrap_prose(
+    text: &str,
+    width: usize,
+    first: &str,
+    next: &str,
+    ambiguous_wide: bool,
+) -> Vec<String> {
+    if width == NO_WRAP {
+        return vec![format!("{}{}", first, text)];
+    }
+    // Every line gets the width left beside the wider prefix
+    let prefix = visible_length_with(first, ambiguous_wide)
+        .max(visible_length_with(next, ambiguous_wide));
+    wrap(text, width.saturating_sub(prefix).max(1), ambiguous_wide)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| format!("{}{}", if i == 0 { first } else { next }, line))
+        .collect()
+}
+
+/// Insert zero-width spaces at the UAX #14 break opportunities of `text`
+/// that are not already at whitespace, leaving escape sequences intact.
+#[cfg(feature = "linebreak")]
+pub(crate) fn mark_breaks(text: &str) -> String {
+    // Visible text, and the offset in `text` of each of its bytes
+    let mut plain = String::new();
+    let mut offsets = Vec::new();
+    let mut start = 0;
+    for token in tokens(text) {
+        if let Token::Text(run) = token {
+            plain.push_str(run);
+            offsets.extend(start..start + run.len());
+        }
+        start += token.as_str().len();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (index, opportunity) in linebreaks(&plain) {
+        let at_space = plain[..index].ends_with(char::is_whitespace);
+        if opportunity != BreakOpportunity::Allowed || index >= plain.len() || at_space {
+            continue;
+        }
+        let at = offsets[index];
+        out.push_str(&text[copied..at]);
+        out.push(ZERO_WIDTH_SPACE);
+        copied = at;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wraps_at_soft_hyphens() {
+        let lines = wrap_prose("Donau\u{ad}dampf\u{ad}schiff fährt", 14, "- ", "  ", false);
+        assert_eq!(lines, ["- Donaudampf-", "  schiff fährt"]);
+    }
+
+    #[cfg(feature = "linebreak")]
+    #[test]
+    fn test_marks_breaks_between_ideographs() {
+        assert_eq!(mark_breaks("中文"), "中\u{200b}文");
+        assert_eq!(
+            mark_breaks("\x1b[1m中\x1b[0m文"),
+            "\x1b[1m中\x1b[0m\u{200b}文"
+        );
+        assert_eq!(mark_breaks("two words"), "two words");
+    }
+
+    #[cfg(feature = "linebreak")]
+    #[test]
+    fn test_wraps_text_without_spaces() {
+        let lines = wrap_prose("这是一个没有空格的句子", 8, "> ", "  ", false);
+        assert_eq!(lines, ["> 这是一", "  个没有", "  空格的", "  句子"]);
+    }
+}
@@ -0,0 +1,416 @@
+//! Link rendering: numbered links with a reference list, for terminals
+//! without hyperlinks, and filtering of the URL schemes that are linked.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use streamdown_parser::decode_html_entities;
+
+use crate::heading::HeadingDecoration;
+use crate::locale::NumberLocale;
+use crate::options::{StatusKind, StatusMarkers};
+use crate::profile::TerminalProfile;
+use crate::style::{HeadingStyler, InlineStyler, ListStyler, TableStyler};
+use crate::theme::{Glyphs, Theme};
+
+/// URL schemes turned into hyperlinks unless configured otherwise.
+pub const DEFAULT_LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Styler that renders links as `text[n]` and records their URLs.
+///
+/// Every other element is styled by the wrapped theme. Without a reference
+/// list, links are rendered by the theme as well, except that URLs with a
+/// scheme outside the allowed ones are shown as plain text instead of a
+/// clickable hyperlink.
+pub(crate) struct LinkStyler<'a> {
+    theme: &'a Theme,
+    references: Option<&'a RefCell<Vec<String>>>,
+    schemes: Option<&'a [String]>,
+    hide_urls: bool,
+    profile: TerminalProfile,
+    ambiguous_wide: bool,
+    status_markers: Option<&'a StatusMarkers>,
+    locale: Option<&'a NumberLocale>,
+}
+
+impl<'a> LinkStyler<'a> {
+    pub(crate) fn new(
+        theme: &'a Theme,
+        references: Option<&'a RefCell<Vec<String>>>,
+        schemes: Option<&'a [String]>,
+    ) -> Self {
+        Self {
+            theme,
+            references,
+            schemes,
+            hide_urls: false,
+            profile: TerminalProfile::default(),
+            ambiguous_wide: false,
+            status_markers: None,
+            locale: None,
+        }
+    }
+
+    /// Show only the label of links, still hyperlinked where allowed, and
+    /// never their URL.
+    pub(crate) fn hide_urls(mut self, hide: bool) -> Self {
+        self.hide_urls = hide;
+        self
+    }
+
+    /// Capabilities deciding glyphs and whether links are hyperlinked.
+    pub(crate) fn with_profile(mut self, profile: TerminalProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Measure ambiguous-width characters as two columns. Box drawing
+    /// characters are among them, so glyphs fall back to ASCII ones.
+    pub(crate) fn with_ambiguous_wide(mut self, wide: bool) -> Self {
+        self.ambiguous_wide = wide;
+        self
+    }
+
+    /// Rewrite these status markers in text to the theme's status glyphs.
+    pub(crate) fn status_markers(mut self, markers: Option<&'a StatusMarkers>) -> Self {
+        self.status_markers = markers;
+        self
+    }
+
+    /// Format link numbers and footnote indices with this locale.
+    pub(crate) fn number_locale(mut self, locale: &'a NumberLocale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// The theme's glyphs, or ASCII ones if the profile lacks unicode or
+    /// ambiguous-width characters are wide.
+    fn profile_glyphs(&self) -> &Glyphs {
+        if self.profile.unicode && !self.ambiguous_wide {
+            &self.theme.glyphs
+        } else {
+            Glyphs::shared(false)
+        }
+    }
+
+    fn status_glyph(&self, kind: StatusKind) -> &str {
+        let glyphs = self.profile_glyphs();
+        match kind {
+            StatusKind::Done => &glyphs.status_done,
+            StatusKind::Failed => &glyphs.status_failed,
+            StatusKind::Pending => &glyphs.status_pending,
+        }
+    }
+
+    fn status(&self, kind: StatusKind) -> String {
+        let style = match kind {
+            StatusKind::Done => &self.theme.status_done,
+            StatusKind::Failed => &self.theme.status_failed,
+            StatusKind::Pending => &self.theme.status_pending,
+        };
+        self.theme.paint(style, self.status_glyph(kind))
+    }
+
+    /// Style `text` with `style`, with any status markers in it rewritten
+    /// to status glyphs styled on their own.
+    fn with_markers(&self, text: &str, style: impl Fn(&str) -> String) -> String {
+        match self.status_markers {
+            Some(markers) => markers.replace(text, style, |kind| self.status(kind)),
+            None => style(text),
+        }
+    }
+
+    /// Link label with status markers rewritten to unstyled glyphs, since
+    /// the label is styled and hyperlinked as a whole.
+    fn link_label<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match self.status_markers {
+            Some(markers) => {
+                let glyph = |kind| self.status_glyph(kind).to_string();
+                Cow::Owned(markers.replace(text, str::to_string, glyph))
+            }
+            None => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Whether `url` has a scheme that may be hyperlinked. `None` allows the
+/// [`DEFAULT_LINK_SCHEMES`]. URLs without a scheme, or with control
+/// characters that could end the escape sequence early, are never linked.
+pub(crate) fn is_linkable(url: &str, schemes: Option<&[String]>) -> bool {
+    if url.contains(char::is_control) {
+        return false;
+    }
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid {
+        return false;
+    }
+    match schemes {
+        Some(schemes) => schemes.iter().any(|known| known.eq_ignore_ascii_case(scheme)),
+        None => DEFAULT_LINK_SCHEMES
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(scheme)),
+    }
+}
+
+/// `url` without control characters, so showing it cannot inject escape
+/// sequences.
+pub(crate) fn strip_controls(url: &str) -> Cow<'_, str> {
+    if url.contains(char::is_control) {
+        Cow::Owned(url.chars().filter(|c| !c.is_control()).collect())
+    } else {
+        Cow::Borrowed(url)
+    }
+}
+
+/// Render the collected URLs as `[n] url` lines.
+pub(crate) fn render_references(
+    theme: &Theme,
+    references: &[String],
+    locale: &NumberLocale,
+) -> Vec<String> {
+    references
+        .iter()
+        .enumerate()
+        .map(|(i, url)| {
+            let number = theme.paint(&theme.link, &format!("[{}]", locale.format_int(i + 1)));
+            let url = theme.paint(&theme.link_url, &strip_controls(url));
+            format!("{} {}", number, url)
+        })
+        .collect()
+}
+
+impl InlineStyler for LinkStyler<'_> {
+    fn text(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.text(text))
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.bold(text))
+    }
+
+    fn italic(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.italic(text))
+    }
+
+    fn bold_italic(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.bold_italic(text))
+    }
+
+    fn strikethrough(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.strikethrough(text))
+    }
+
+    fn underline(&self, text: &str) -> String {
+        self.with_markers(text, |text| self.theme.underline(text))
+    }
+
+    fn code(&self, text: &str) -> String {
+        self.theme.code(text)
+    }
+
+    fn link(&self, text: &str, url: &str) -> String {
+        let text = &*self.link_label(text);
+        let Some(references) = self.references else {
+            let linkable = self.profile.hyperlinks && is_linkable(url, self.schemes);
+            if self.hide_urls {
+                let label = self.theme.paint(&self.theme.link, &decode_html_entities(text));
+                if linkable {
+                    return self.theme.backend.hyperlink(&label, url);
+                }
+                return label;
+            }
+            if linkable {
+                return self.theme.link(text, url);
+            }
+            let label = self.theme.paint(&self.theme.link, &decode_html_entities(text));
+            let url = format!("({})", strip_controls(url));
+            let url = self.theme.paint(&self.theme.link_url, &url);
+            return format!("{} {}", label, url);
+        };
+        let mut references = references.borrow_mut();
+        // Repeated URLs share one entry
+        let number = match references.iter().position(|known| known == url) {
+            Some(index) => index + 1,
+            None => {
+                references.push(url.to_string());
+                references.len()
+            }
+        };
+        let label = self.theme.paint(&self.theme.link, &decode_html_entities(text));
+        let number = match self.locale {
+            Some(locale) => locale.format_int(number),
+            None => number.to_string(),
+        };
+        let marker = self.theme.paint(&self.theme.link_url, &format!("[{}]", number));
+        format!("{}{}", label, marker)
+    }
+
+    fn image(&self, alt: &str, url: &str) -> String {
+        self.theme.image(alt, url)
+    }
+
+    fn footnote(&self, text: &str) -> String {
+        match self.locale {
+            Some(locale) => self.theme.footnote(&locale.format_superscript(text)),
+            None => self.theme.footnote(text),
+        }
+    }
+
+    fn dimmed(&self, text: &str) -> String {
+        self.theme.dimmed(text)
+    }
+
+    fn ambiguous_wide(&self) -> bool {
+        self.ambiguous_wide
+    }
+}
+
+impl HeadingStyler for LinkStyler<'_> {
+    fn h1(&self, text: &str) -> String {
+        self.theme.h1(text)
+    }
+
+    fn h2(&self, text: &str) -> String {
+        self.theme.h2(text)
+    }
+
+    fn h3(&self, text: &str) -> String {
+        self.theme.h3(text)
+    }
+
+    fn h4(&self, text: &str) -> String {
+        self.theme.h4(text)
+    }
+
+    fn h5(&self, text: &str) -> String {
+        self.theme.h5(text)
+    }
+
+    fn h6(&self, text: &str) -> String {
+        self.theme.h6(text)
+    }
+
+    fn decoration(&self, level: u8) -> HeadingDecoration {
+        self.theme.decoration(level)
+    }
+}
+
+impl ListStyler for LinkStyler<'_> {
+    fn bullet_dash(&self, text: &str) -> String {
+        self.theme.bullet_dash(text)
+    }
+
+    fn bullet_asterisk(&self, text: &str) -> String {
+        self.theme.bullet_asterisk(text)
+    }
+
+    fn bullet_plus(&self, text: &str) -> String {
+        self.theme.bullet_plus(text)
+    }
+
+    fn bullet_plus_expand(&self, text: &str) -> String {
+        self.theme.bullet_plus_expand(text)
+    }
+
+    fn number(&self, text: &str) -> String {
+        self.theme.number(text)
+    }
+
+    fn glyphs(&self) -> &Glyphs {
+        self.profile_glyphs()
+    }
+}
+
+impl TableStyler for LinkStyler<'_> {
+    fn border(&self, text: &str) -> String {
+        self.theme.border(text)
+    }
+
+    fn header(&self, text: &str) -> String {
+        self.theme.header(text)
+    }
+
+    fn glyphs(&self) -> &Glyphs {
+        self.profile_glyphs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PlainBackend;
+    use crate::inline::render_inline_content;
+
+    #[test]
+    fn test_links_are_numbered_once_per_url() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        let references = RefCell::new(Vec::new());
+        let styler = LinkStyler::new(&theme, Some(&references), None);
+        let result = render_inline_content(
+            "[a](https://a.dev) [b](https://b.dev) [again](https://a.dev)",
+            &styler,
+        );
+        assert_eq!(result, "a[1] b[2] again[1]");
+        assert_eq!(
+            render_references(&theme, &references.borrow(), &NumberLocale::default()),
+            vec!["[1] https://a.dev", "[2] https://b.dev"]
+        );
+    }
+
+    #[test]
+    fn test_numbers_follow_locale() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        let references = RefCell::new(vec![String::new(); 999]);
+        let locale = NumberLocale::en();
+        let styler = LinkStyler::new(&theme, Some(&references), None).number_locale(&locale);
+        assert_eq!(styler.link("a", "https://a.dev"), "a[1,000]");
+        assert_eq!(styler.footnote("¹²³⁴"), theme.footnote("¹,²³⁴"));
+    }
+
+    #[test]
+    fn test_only_allowed_schemes_are_hyperlinked() {
+        assert!(is_linkable("https://a.dev", None));
+        assert!(is_linkable("MAILTO:me@a.dev", None));
+        assert!(!is_linkable("javascript:alert(1)", None));
+        assert!(!is_linkable("file:///etc/passwd", None));
+        assert!(!is_linkable("./README.md", None));
+
+        let schemes = vec!["file".to_string()];
+        assert!(is_linkable("file:///tmp/x", Some(&schemes)));
+        assert!(!is_linkable("https://a.dev", Some(&schemes)));
+
+        let theme = Theme::dark().with_backend(crate::backend::AnsiBackend::default());
+        let styler = LinkStyler::new(&theme, None, None);
+        assert!(styler.link("a", "https://a.dev").contains("\x1b]8;;"));
+        assert!(!styler.link("a", "javascript:alert(1)").contains("\x1b]8;;"));
+    }
+
+    #[test]
+    fn test_control_characters_are_never_linked_or_shown() {
+        let url = "https://a.dev/\x1b]8;;evil\x07x";
+        assert!(!is_linkable(url, None));
+        assert_eq!(strip_controls(url), "https://a.dev/]8;;evilx");
+
+        let theme = Theme::dark().with_backend(crate::backend::AnsiBackend::default());
+        let styler = LinkStyler::new(&theme, None, None);
+        let result = styler.link("a", url);
+        assert!(!result.contains("\x1b]8;;"), "{:?}", result);
+        assert!(!result.contains('\x07'), "{:?}", result);
+        assert!(result.contains("https://a.dev/]8;;evilx"), "{:?}", result);
+
+        let theme = Theme::dark().with_backend(PlainBackend);
+        let references = RefCell::new(Vec::new());
+        let styler = LinkStyler::new(&theme, Some(&references), None);
+        styler.link("a", url);
+        assert_eq!(
+            render_references(&theme, &references.borrow(), &NumberLocale::default()),
+            vec!["[1] https://a.dev/]8;;evilx"]
+        );
+    }
+}
@@ -1,19 +1,13 @@
 //! List rendering with nested indentation and bullet cycling.
 
 use crate::inline::render_inline_content;
+use crate::linebreak::wrap_prose;
+use crate::locale::NumberLocale;
+use crate::options::NumberingStyle;
 use crate::style::{InlineStyler, ListStyler};
-use streamdown_ansi::utils::visible_length;
+use crate::theme::Glyphs;
+use crate::width::visible_length_with;
 use streamdown_parser::ListBullet;
-use streamdown_render::text::text_wrap;
-
-/// Bullet characters for dash lists at different nesting levels.
-const BULLETS_DASH: [&str; 4] = ["•", "◦", "▪", "‣"];
-
-/// Bullet characters for asterisk lists at different nesting levels.
-const BULLETS_ASTERISK: [&str; 4] = ["∗", "⁎", "✱", "✳"];
-
-/// Bullet characters for plus lists at different nesting levels.
-const BULLETS_PLUS: [&str; 4] = ["⊕", "⊙", "⊛", "⊜"];
 
 /// List rendering state for tracking nesting and numbering.
 #[derive(Default)]
@@ -24,6 +18,14 @@ pub struct ListState {
     numbers: Vec<usize>,
     /// Whether we're in a "pending" state (saw ListEnd but might continue)
     pending_reset: bool,
+    /// Formatting for generated ordered-list numbers
+    locale: NumberLocale,
+    /// Column where the content of the last item starts
+    content_column: Option<usize>,
+    /// Numbering style of ordered lists, cycled by nesting level
+    numbering: Vec<NumberingStyle>,
+    /// Source number of the next item, if known
+    start_hint: Option<usize>,
 }
 
 impl ListState {
@@ -42,11 +44,17 @@ impl ListState {
     }
 
     pub fn next_number(&mut self) -> usize {
+        self.next_number_from(1)
+    }
+
+    /// Advance the counter of the current level, starting it at `start` if
+    /// this is the level's first item.
+    fn next_number_from(&mut self, start: usize) -> usize {
         if let Some(n) = self.numbers.last_mut() {
-            *n += 1;
+            *n = if *n == 0 { start } else { *n + 1 };
             *n
         } else {
-            1
+            start
         }
     }
 
@@ -71,6 +79,44 @@ impl ListState {
         self.stack.clear();
         self.numbers.clear();
         self.pending_reset = false;
+        self.content_column = None;
+    }
+
+    /// Column where the content of the last rendered item starts, used to
+    /// indent continuation paragraphs and code blocks under it.
+    pub fn content_column(&self) -> Option<usize> {
+        self.content_column
+    }
+
+    /// Set the formatting used for ordered-list numbers.
+    pub fn set_locale(&mut self, locale: NumberLocale) {
+        self.locale = locale;
+    }
+
+    /// Set the numbering styles of ordered lists, cycled by nesting level.
+    pub fn set_numbering(&mut self, numbering: Vec<NumberingStyle>) {
+        self.numbering = numbering;
+    }
+
+    /// Record the number written in the source for the next item, since the
+    /// parser reports every ordered item as number 1.
+    pub fn set_start_hint(&mut self, number: usize) {
+        self.start_hint = Some(number);
+    }
+
+    /// Format an ordered-list number for the given nesting level.
+    fn format_number(&self, number: usize, level: usize) -> String {
+        let style = match self.numbering.len() {
+            0 => NumberingStyle::Decimal,
+            len => self.numbering[level % len],
+        };
+        match style {
+            NumberingStyle::Decimal => self.locale.format_int(number),
+            NumberingStyle::LowerAlpha => to_alpha(number),
+            NumberingStyle::UpperAlpha => to_alpha(number).to_uppercase(),
+            NumberingStyle::LowerRoman => to_roman(number),
+            NumberingStyle::UpperRoman => to_roman(number).to_uppercase(),
+        }
     }
 
     /// Mark list as pending reset (saw ListEnd, but might continue with more items)
@@ -104,22 +150,27 @@ pub fn render_list_item<S: InlineStyler + ListStyler>(
     let level = list_state.level().saturating_sub(1);
 
     // Calculate marker - use our own counter for ordered lists to work around
-    // the parser bug that normalizes all numbers to 1
+    // the parser bug that normalizes all numbers to 1; only the first item's
+    // source number is used, to start the counter
+    let hint = list_state.start_hint.take();
+    let glyphs = styler.glyphs();
     let marker = match bullet {
-        ListBullet::Ordered(_) => {
-            let num = list_state.next_number();
-            format!("{}.", num)
+        ListBullet::Ordered(n) => {
+            let start = hint.unwrap_or((*n).max(1));
+            let num = list_state.next_number_from(start);
+            format!("{}.", list_state.format_number(num, level))
         }
-        ListBullet::PlusExpand => "⊞".to_string(),
-        ListBullet::Dash => BULLETS_DASH[level % BULLETS_DASH.len()].to_string(),
-        ListBullet::Asterisk => BULLETS_ASTERISK[level % BULLETS_ASTERISK.len()].to_string(),
-        ListBullet::Plus => BULLETS_PLUS[level % BULLETS_PLUS.len()].to_string(),
+        ListBullet::PlusExpand => glyphs.bullet_plus_expand.clone(),
+        ListBullet::Dash => Glyphs::bullet(&glyphs.bullets_dash, level).to_string(),
+        ListBullet::Asterisk => Glyphs::bullet(&glyphs.bullets_asterisk, level).to_string(),
+        ListBullet::Plus => Glyphs::bullet(&glyphs.bullets_plus, level).to_string(),
     };
 
     // Calculate indentation
     let indent_spaces = indent * 2;
-    let marker_width = visible_length(&marker);
+    let marker_width = visible_length_with(&marker, styler.ambiguous_wide());
     let content_indent = indent_spaces + marker_width + 1;
+    list_state.content_column = Some(content_indent);
 
     // Color the marker based on bullet type
     let colored_marker = match bullet {
@@ -138,13 +189,64 @@ pub fn render_list_item<S: InlineStyler + ListStyler>(
     let next_prefix = format!("{}{}", margin, " ".repeat(content_indent));
 
     // Wrap the content
-    let wrapped = text_wrap(&rendered_content, width, 0, &first_prefix, &next_prefix, false, true);
+    let wide = styler.ambiguous_wide();
+    wrap_prose(&rendered_content, width, &first_prefix, &next_prefix, wide)
+}
+
+/// Number written before an ordered list item's `.` or `)` in a source line.
+pub(crate) fn ordered_marker_number(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    let digits = trimmed.len() - trimmed.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = &trimmed[digits..];
+    let after = rest.strip_prefix('.').or_else(|| rest.strip_prefix(')'))?;
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+    trimmed[..digits].parse().ok()
+}
 
-    if wrapped.is_empty() {
-        vec![first_prefix]
-    } else {
-        wrapped.lines
+/// Format `n` as a letter sequence: a, b, ..., z, aa, ab, ...
+fn to_alpha(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
     }
+    letters.iter().rev().collect()
+}
+
+/// Format `n` as a lowercase roman numeral, falling back to digits past 3999.
+fn to_roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    if n == 0 || n > 3999 {
+        return n.to_string();
+    }
+    let mut result = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            result.push_str(numeral);
+            n -= value;
+        }
+    }
+    result
 }
 
 #[cfg(test)]
@@ -309,6 +411,49 @@ mod tests {
         assert!(unordered.contains("<dash>"), "Unordered should use dash style");
     }
 
+    #[test]
+    fn test_ordered_number_locale() {
+        let mut state = ListState::default();
+        state.set_locale(NumberLocale::en());
+        state.push(0, true);
+        state.numbers[0] = 999;
+        let item = render_with_state(0, ListBullet::Ordered(1), "Grouped", &mut state);
+        insta::assert_snapshot!(item, @"  <num>1,000.</num> Grouped");
+    }
+
+    #[test]
+    fn test_ordered_start_hint() {
+        let mut state = ListState::default();
+        state.set_start_hint(7);
+        let first = render_with_state(0, ListBullet::Ordered(1), "Seventh", &mut state);
+        let second = render_with_state(0, ListBullet::Ordered(1), "Eighth", &mut state);
+        insta::assert_snapshot!(first, @"  <num>7.</num> Seventh");
+        insta::assert_snapshot!(second, @"  <num>8.</num> Eighth");
+    }
+
+    #[test]
+    fn test_numbering_styles_by_level() {
+        let mut state = ListState::default();
+        state.set_numbering(vec![NumberingStyle::LowerAlpha, NumberingStyle::UpperRoman]);
+        let _ = render_with_state(0, ListBullet::Ordered(1), "One", &mut state);
+        let second = render_with_state(0, ListBullet::Ordered(1), "Two", &mut state);
+        let nested = render_with_state(1, ListBullet::Ordered(1), "Nested", &mut state);
+        let nested_next = render_with_state(1, ListBullet::Ordered(1), "Next", &mut state);
+        insta::assert_snapshot!(second, @"  <num>b.</num> Two");
+        insta::assert_snapshot!(nested, @"    <num>I.</num> Nested");
+        insta::assert_snapshot!(nested_next, @"    <num>II.</num> Next");
+    }
+
+    #[test]
+    fn test_ordered_marker_number() {
+        assert_eq!(ordered_marker_number("3. three"), Some(3));
+        assert_eq!(ordered_marker_number("  12) twelve"), Some(12));
+        assert_eq!(ordered_marker_number("2024.01 report"), None);
+        assert_eq!(ordered_marker_number("- item"), None);
+        assert_eq!(to_alpha(28), "ab");
+        assert_eq!(to_roman(1994), "mcmxciv");
+    }
+
     #[test]
     fn test_list_state_level() {
         let mut state = ListState::default();
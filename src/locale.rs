@@ -0,0 +1,133 @@
+//! Locale conventions for numbers generated by the renderer.
+
+use crate::utils::SUPERSCRIPT_DIGITS;
+
+/// Number formatting used for numbers the renderer generates itself, such as
+/// ordered list markers, footnote and link indices, counts of hidden lines
+/// and elapsed times.
+///
+/// The default uses no grouping and `.` as the decimal separator, matching
+/// plain `format!` output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumberLocale {
+    /// Separator inserted between digit groups, if any.
+    pub grouping_separator: Option<char>,
+    /// Number of digits per group.
+    pub group_size: usize,
+    /// Separator between the integer and fractional parts.
+    pub decimal_separator: char,
+}
+
+impl Default for NumberLocale {
+    fn default() -> Self {
+        Self {
+            grouping_separator: None,
+            group_size: 3,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl NumberLocale {
+    /// English conventions: `1,234.5`.
+    pub fn en() -> Self {
+        Self {
+            grouping_separator: Some(','),
+            ..Self::default()
+        }
+    }
+
+    /// German conventions: `1.234,5`.
+    pub fn de() -> Self {
+        Self {
+            grouping_separator: Some('.'),
+            group_size: 3,
+            decimal_separator: ',',
+        }
+    }
+
+    /// French conventions: `1 234,5` with a narrow no-break space.
+    pub fn fr() -> Self {
+        Self {
+            grouping_separator: Some('\u{202f}'),
+            group_size: 3,
+            decimal_separator: ',',
+        }
+    }
+
+    /// Format an integer with digit grouping.
+    pub fn format_int(&self, n: usize) -> String {
+        let digits = n.to_string();
+        let Some(separator) = self.grouping_separator else {
+            return digits;
+        };
+        if self.group_size == 0 {
+            return digits;
+        }
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % self.group_size == 0 {
+                out.push(separator);
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// Group the digits of a number written in superscript digits, such as
+    /// a footnote index. Other text is returned unchanged.
+    pub fn format_superscript(&self, text: &str) -> String {
+        let digit = |c: char| SUPERSCRIPT_DIGITS.iter().position(|&d| d == c);
+        let number = text
+            .chars()
+            .try_fold(0usize, |n, c| n.checked_mul(10)?.checked_add(digit(c)?));
+        let Some(n) = number.filter(|_| !text.is_empty()) else {
+            return text.to_string();
+        };
+        self.format_int(n)
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(d) => SUPERSCRIPT_DIGITS[d as usize],
+                None => c,
+            })
+            .collect()
+    }
+
+    /// Format an integer part and fractional digits, e.g. `(3, "4")` as `3.4`.
+    pub fn format_decimal(&self, int: usize, fraction: &str) -> String {
+        format!("{}{}{}", self.format_int(int), self.decimal_separator, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_has_no_grouping() {
+        assert_eq!(NumberLocale::default().format_int(1234567), "1234567");
+        assert_eq!(NumberLocale::default().format_decimal(3, "4"), "3.4");
+    }
+
+    #[test]
+    fn test_grouping() {
+        assert_eq!(NumberLocale::en().format_int(0), "0");
+        assert_eq!(NumberLocale::en().format_int(999), "999");
+        assert_eq!(NumberLocale::en().format_int(1000), "1,000");
+        assert_eq!(NumberLocale::en().format_int(1234567), "1,234,567");
+        assert_eq!(NumberLocale::de().format_int(1234567), "1.234.567");
+    }
+
+    #[test]
+    fn test_superscript_grouping() {
+        assert_eq!(NumberLocale::en().format_superscript("¹²³⁴"), "¹,²³⁴");
+        assert_eq!(NumberLocale::en().format_superscript("¹²"), "¹²");
+        assert_eq!(NumberLocale::en().format_superscript("a"), "a");
+    }
+
+    #[test]
+    fn test_decimal_separator() {
+        assert_eq!(NumberLocale::de().format_decimal(1234, "5"), "1.234,5");
+        assert_eq!(NumberLocale::fr().format_decimal(12, "5"), "12,5");
+    }
+}
@@ -0,0 +1,191 @@
+//! Renderer interleaving several concurrent markdown streams in one output.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use colored::Color;
+
+use crate::options::RendererOptions;
+use crate::theme::{Style, Theme};
+use crate::utils::{fnv1a, SharedBuffer};
+use crate::width::visible_length;
+use crate::StreamdownRenderer;
+
+/// Colors of the gutters marking each stream's lines.
+const ACCENTS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
+/// Gutter glyphs, combined with the accents so that streams stay apart
+/// without colors too.
+const GUTTERS: [&str; 4] = ["┃", "│", "┆", "╏"];
+const ASCII_GUTTERS: [&str; 4] = ["|", ":", "!", "#"];
+
+/// Output of one stream, rendered at the width left by the gutter.
+struct Stream {
+    renderer: StreamdownRenderer<SharedBuffer>,
+    output: SharedBuffer,
+    // Rendered text after the last complete line
+    pending: String,
+    gutter: String,
+}
+
+/// Renderer for several streams whose tokens arrive interleaved, such as
+/// the output of concurrent tool calls.
+///
+/// Each stream is rendered on its own and its complete lines are written
+/// as they are ready, after a gutter whose color and glyph are chosen by
+/// the stream id, so a stream keeps its look across runs without the host
+/// assigning one. Streams render append-only: output is never retracted.
+pub struct MultiplexRenderer<W: Write> {
+    writer: W,
+    width: usize,
+    theme: Theme,
+    options: RendererOptions,
+    streams: BTreeMap<String, Stream>,
+}
+
+impl<W: Write> MultiplexRenderer<W> {
+    pub fn new(writer: W, width: usize) -> Self {
+        Self::with_theme(writer, width, Theme::default())
+    }
+
+    pub fn with_theme(writer: W, width: usize, theme: Theme) -> Self {
+        Self {
+            writer,
+            width,
+            theme,
+            options: RendererOptions::default(),
+            streams: BTreeMap::new(),
+        }
+    }
+
+    /// Set the optional renderer behaviors of streams started from now on.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.options = options;
+    }
+
+    /// Gutter written before each line of the stream `id`.
+    pub fn gutter(&self, id: &str) -> String {
+        let hash = fnv1a(id.bytes());
+        let accent = ACCENTS[(hash % ACCENTS.len() as u64) as usize];
+        let glyphs = if self.options.profile.unicode {
+            GUTTERS
+        } else {
+            ASCII_GUTTERS
+        };
+        let glyph = glyphs[(hash / ACCENTS.len() as u64 % glyphs.len() as u64) as usize];
+        format!("{} ", self.theme.paint(&Style::new().fg(accent), glyph))
+    }
+
+    /// Push a token of the stream `id`, starting the stream if it is new.
+    pub fn push(&mut self, id: &str, token: &str) -> io::Result<()> {
+        if !self.streams.contains_key(id) {
+            let stream = self.start(id);
+            self.streams.insert(id.to_string(), stream);
+        }
+        let Some(stream) = self.streams.get_mut(id) else {
+            return Ok(());
+        };
+        stream.renderer.push(token)?;
+        stream.pending.push_str(&stream.output.take());
+        let Some(end) = stream.pending.rfind('\n') else {
+            return Ok(());
+        };
+        let complete: String = stream.pending.drain(..=end).collect();
+        write_lines(&mut self.writer, &stream.gutter, &complete)?;
+        self.writer.flush()
+    }
+
+    /// Finish the stream `id`, writing the rest of its output.
+    pub fn finish_stream(&mut self, id: &str) -> io::Result<()> {
+        let Some(stream) = self.streams.remove(id) else {
+            return Ok(());
+        };
+        let Stream {
+            renderer,
+            output,
+            mut pending,
+            gutter,
+        } = stream;
+        renderer.finish()?;
+        pending.push_str(&output.take());
+        write_lines(&mut self.writer, &gutter, &pending)?;
+        self.writer.flush()
+    }
+
+    /// Finish every stream still open, in the order of their ids.
+    pub fn finish(mut self) -> io::Result<()> {
+        let ids: Vec<String> = self.streams.keys().cloned().collect();
+        for id in ids {
+            self.finish_stream(&id)?;
+        }
+        Ok(())
+    }
+
+    fn start(&self, id: &str) -> Stream {
+        let gutter = self.gutter(id);
+        let width = self.width.saturating_sub(visible_length(&gutter)).max(1);
+        let output = SharedBuffer::default();
+        let theme = self.theme.clone();
+        let mut renderer = StreamdownRenderer::with_theme(output.clone(), width, theme);
+        renderer.set_options(self.options.clone());
+        Stream {
+            renderer,
+            output,
+            pending: String::new(),
+            gutter,
+        }
+    }
+}
+
+/// Write each line of `text` after `gutter`, ending the last one too.
+fn write_lines<W: Write>(writer: &mut W, gutter: &str, text: &str) -> io::Result<()> {
+    for line in text.lines() {
+        writeln!(writer, "{}{}", gutter, line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_ansi;
+
+    #[test]
+    fn test_streams_keep_their_gutters() {
+        let mut out = Vec::new();
+        let mut renderer = MultiplexRenderer::with_theme(&mut out, 40, Theme::plain());
+        renderer.set_options(RendererOptions {
+            profile: crate::TerminalProfile::unicode(),
+            ..Default::default()
+        });
+        let (build, test) = (renderer.gutter("build"), renderer.gutter("test"));
+        assert_ne!(build, test);
+        assert_eq!(renderer.gutter("build"), build);
+
+        renderer.push("build", "compiling ").unwrap();
+        renderer.push("test", "running 3 tests\n\n").unwrap();
+        renderer.push("build", "**crate**\n\n").unwrap();
+        renderer.push("test", "all passed").unwrap();
+        renderer.finish().unwrap();
+        let output = strip_ansi(&String::from_utf8(out).unwrap());
+        let lines: Vec<&str> = output
+            .lines()
+            .filter(|line| line.split_once(' ').is_some_and(|(_, text)| !text.is_empty()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                format!("{}running 3 tests", test),
+                format!("{}compiling crate", build),
+                format!("{}all passed", test),
+            ]
+        );
+    }
+}
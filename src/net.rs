@@ -0,0 +1,258 @@
+//! WebSocket server rendering markdown chunks, for using the crate as a
+//! rendering service for chat backends.
+
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::Message;
+
+use crate::backend::{AnsiBackend, PlainBackend};
+use crate::color::ColorDepth;
+use crate::html::HtmlRenderer;
+use crate::theme::Theme;
+use crate::utils::SharedBuffer;
+use crate::StreamdownRenderer;
+
+/// Widest output a client may ask for.
+const MAX_WIDTH: usize = 1000;
+
+/// Longest markdown line, in bytes, a client may send before its newline.
+const MAX_LINE_BYTES: usize = 1 << 20;
+
+/// Output sent back to WebSocket clients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Text without escape sequences.
+    Plain,
+    /// Text with ANSI styling, for web terminals.
+    #[default]
+    Ansi,
+    /// HTML fragments.
+    Html,
+}
+
+impl OutputFormat {
+    fn from_path(path: &str) -> Option<Self> {
+        match path.trim_matches('/') {
+            "plain" => Some(Self::Plain),
+            "ansi" => Some(Self::Ansi),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Server accepting markdown chunks over WebSocket and replying with the
+/// output rendered from each chunk.
+///
+/// Clients connect to `/plain`, `/ansi` or `/html` to pick the output, or to
+/// `/` for the server's default, and may set the width with `?width=100`,
+/// up to 1000 columns. Each text message is a chunk of a markdown stream; the reply holds the
+/// output its complete lines produced, and nothing is sent for chunks that
+/// produce none. An empty message ends the document: the rest is rendered
+/// and the next message starts a new one. A connection sending a line
+/// longer than a megabyte is closed.
+///
+/// ```no_run
+/// use forge_markdown_stream::{OutputFormat, RenderServer};
+///
+/// let listener = std::net::TcpListener::bind("127.0.0.1:9000").unwrap();
+/// RenderServer::new().format(OutputFormat::Html).serve(listener).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderServer {
+    width: usize,
+    format: OutputFormat,
+    max_connections: usize,
+}
+
+impl Default for RenderServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderServer {
+    pub fn new() -> Self {
+        Self {
+            width: 80,
+            format: OutputFormat::default(),
+            max_connections: 64,
+        }
+    }
+
+    /// Output width used unless the client sets one.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width.clamp(1, MAX_WIDTH);
+        self
+    }
+
+    /// Output used for clients connecting to `/`.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Most connections served at once, 64 by default. Connections beyond
+    /// it are closed right away.
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Accept connections until the listener fails, serving each on its
+    /// own thread.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let active = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming() {
+            let stream = stream?;
+            if active.fetch_add(1, Ordering::SeqCst) >= self.max_connections {
+                active.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let server = self.clone();
+            let active = Arc::clone(&active);
+            thread::spawn(move || {
+                let result = server.handle(stream);
+                active.fetch_sub(1, Ordering::SeqCst);
+                result
+            });
+        }
+        Ok(())
+    }
+
+    /// Serve one connection until the client closes it.
+    pub fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut format = self.format;
+        let mut width = self.width;
+        let callback = |request: &Request, response: Response| {
+            let uri = request.uri();
+            if let Some(requested) = OutputFormat::from_path(uri.path()) {
+                format = requested;
+            }
+            let requested_width = uri
+                .query()
+                .into_iter()
+                .flat_map(|query| query.split('&'))
+                .find_map(|pair| pair.strip_prefix("width="))
+                .and_then(|value| value.parse().ok())
+                .filter(|&width: &usize| width > 0);
+            if let Some(requested) = requested_width {
+                width = requested.min(MAX_WIDTH);
+            }
+            Ok::<_, ErrorResponse>(response)
+        };
+        let mut socket = tungstenite::accept_hdr(stream, callback).map_err(io::Error::other)?;
+
+        let mut session = Session::new(format, width);
+        // Bytes received since the last newline
+        let mut line_bytes = 0;
+        loop {
+            let chunk = match socket.read() {
+                Ok(Message::Text(chunk)) => chunk,
+                Ok(Message::Close(_)) => return Ok(()),
+                Ok(_) => continue,
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(error) => return Err(io::Error::other(error)),
+            };
+            line_bytes = match chunk.rfind('\n') {
+                Some(end) => chunk.len() - end - 1,
+                None => line_bytes + chunk.len(),
+            };
+            if line_bytes > MAX_LINE_BYTES {
+                let _ = socket.close(Some(CloseFrame {
+                    code: CloseCode::Size,
+                    reason: "line too long".into(),
+                }));
+                let _ = socket.flush();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+            }
+            let output = if chunk.is_empty() {
+                let finished = std::mem::replace(&mut session, Session::new(format, width));
+                finished.finish()?
+            } else {
+                session.push(&chunk)?
+            };
+            if !output.is_empty() {
+                socket
+                    .send(Message::Text(output))
+                    .map_err(io::Error::other)?;
+            }
+        }
+    }
+}
+
+/// Rendering state of one document.
+struct Session {
+    renderer: StreamdownRenderer<Box<dyn Write>>,
+    output: SharedBuffer,
+}
+
+impl Session {
+    fn new(format: OutputFormat, width: usize) -> Self {
+        let output = SharedBuffer::default();
+        let terminal: Box<dyn Write> = match format {
+            OutputFormat::Html => Box::new(io::sink()),
+            _ => Box::new(output.clone()),
+        };
+        let theme = match format {
+            OutputFormat::Ansi => {
+                Theme::default().with_backend(AnsiBackend::new(ColorDepth::TrueColor))
+            }
+            _ => Theme::default().with_backend(PlainBackend),
+        };
+        let mut renderer = StreamdownRenderer::with_theme(terminal, width, theme);
+        if format == OutputFormat::Html {
+            // Source lines take the terminal path, which hands its events
+            // to the HTML renderer
+            renderer.mirror_events(HtmlRenderer::new(output.clone()));
+        }
+        Self { renderer, output }
+    }
+
+    /// Render a chunk and return the output it produced.
+    fn push(&mut self, chunk: &str) -> io::Result<String> {
+        self.renderer.push(chunk)?;
+        Ok(self.output.take())
+    }
+
+    /// Render the rest of the document and return its output.
+    fn finish(self) -> io::Result<String> {
+        self.renderer.finish()?;
+        Ok(self.output.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_renders_complete_lines() {
+        let mut session = Session::new(OutputFormat::Plain, 40);
+        assert_eq!(session.push("**bo").unwrap(), "");
+        assert!(session.push("ld**\n").unwrap().contains("bold"));
+        let mut session = Session::new(OutputFormat::Html, 40);
+        session.push("# Title\n").unwrap();
+        let html = session.push("text").unwrap() + &session.finish().unwrap();
+        assert!(html.contains("text"), "{}", html);
+    }
+
+    #[test]
+    fn test_width_is_clamped() {
+        assert_eq!(RenderServer::new().width(1 << 40).width, MAX_WIDTH);
+        assert_eq!(RenderServer::new().width(0).width, 1);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(OutputFormat::from_path("/html"), Some(OutputFormat::Html));
+        assert_eq!(OutputFormat::from_path("/"), None);
+    }
+}
@@ -0,0 +1,530 @@
+//! Optional renderer behaviors.
+
+#[cfg(feature = "images")]
+use crate::image::{ImageCache, ImageProtocol};
+use crate::locale::NumberLocale;
+use crate::profile::TerminalProfile;
+#[cfg(feature = "images")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Options controlling optional renderer behaviors.
+///
+/// Behaviors default to off, which reproduces the plain renderer output,
+/// and the terminal profile to one displaying everything.
+#[derive(Clone, Debug, Default)]
+pub struct RendererOptions {
+    /// Print the arrival time of each block in a right-aligned gutter.
+    pub timestamp_gutter: bool,
+    /// Conventions for numbers the renderer generates, such as list markers.
+    pub number_locale: NumberLocale,
+    /// User-visible strings generated by the renderer.
+    pub labels: Labels,
+    /// Print line numbers in a gutter beside fenced code blocks.
+    pub code_line_numbers: bool,
+    /// Draw a rounded box around code blocks with the language in the top border.
+    pub code_frame: bool,
+    /// Guess the language of code blocks whose fence names none, from a
+    /// shebang, typical keywords or a file name mentioned just before.
+    pub detect_code_language: bool,
+    /// Show a dimmed footer with the line count, character count and
+    /// language below each code block.
+    pub code_stats: bool,
+    /// Replace the source's blank lines with a fixed number between blocks.
+    pub blank_lines: Option<BlankLinePolicy>,
+    /// How fenced code block contents are rendered.
+    pub code_mode: CodeRenderMode,
+    /// How think blocks are shown.
+    pub think_mode: ThinkMode,
+    /// Show how long each think block took in its closing border.
+    pub think_duration: bool,
+    /// Render markdown inside Rust doc comments and Python docstrings of
+    /// highlighted code blocks.
+    pub doc_comments: bool,
+    /// Collapse runs of consecutive blank lines to at most this many.
+    pub max_blank_lines: Option<usize>,
+    /// When table rows are drawn.
+    pub table_mode: TableMode,
+    /// Numbering styles of ordered lists, cycled by nesting level.
+    /// Empty means decimal at every level.
+    pub ordered_numbering: Vec<NumberingStyle>,
+    /// Show a `▌` cursor after the output while the stream is active.
+    pub typing_cursor: bool,
+    /// Align table columns of numbers on their decimal point, unless the
+    /// separator row sets another alignment.
+    pub align_numeric_columns: bool,
+    /// Rewrite emoji sequences whose width varies between terminals, so
+    /// tables and wrapping stay aligned. Code blocks are left untouched.
+    pub emoji_normalization: EmojiNormalization,
+    /// Write common fractions and units in prose with Unicode characters,
+    /// e.g. `1/2` as `½`, `m^2` as `m²` and `degC` as `°C`.
+    pub typeset_units: bool,
+    /// Rewrite status markers such as `✅`, `[x]` or `(done)` in prose,
+    /// lists and tables as the theme's status glyphs, so status reports
+    /// render uniformly. Code is left untouched.
+    pub status_markers: Option<StatusMarkers>,
+    /// Render links as `text[n]` and print their URLs as a numbered list at
+    /// the end of the document, for terminals without OSC 8 hyperlinks.
+    pub numbered_links: bool,
+    /// URL schemes that are turned into clickable hyperlinks; links with any
+    /// other scheme are shown as plain text. `None` allows
+    /// [`DEFAULT_LINK_SCHEMES`](crate::DEFAULT_LINK_SCHEMES).
+    pub link_schemes: Option<Vec<String>>,
+    /// Keep the `#` marks and original case of every heading, whatever its
+    /// decoration, so saved plain transcripts can be grepped for `## `.
+    pub heading_markers: bool,
+    /// Shift and clamp heading levels, e.g. so an answer embedded under an
+    /// app-owned title has no competing top-level headings.
+    pub heading_levels: HeadingLevels,
+    /// End all attributes before padding and at every line end, so
+    /// background colors never bleed into padding or the rest of the row
+    /// and copied selections carry no stray styling.
+    pub selection_safe: bool,
+    /// Engine highlighting fenced code blocks.
+    pub highlighter: HighlightEngine,
+    /// Below this output width, keep headings, lists and code but elide
+    /// images, link URLs, table borders and think blocks, so narrow panes
+    /// stay legible.
+    pub compact_below: Option<usize>,
+    /// Throttle rendering to a typing speed. Complete lines are held back
+    /// until they are due and rendered by
+    /// [`StreamdownRenderer::push`](crate::StreamdownRenderer::push) and
+    /// [`StreamdownRenderer::poll_pacing`](crate::StreamdownRenderer::poll_pacing),
+    /// neither of which waits; `finish` renders what is left at once.
+    pub pacing: Option<Pacing>,
+    /// Queue up to this many bytes of output instead of waiting for a slow
+    /// writer, e.g. a non-blocking PTY. Queued output is written by
+    /// [`Renderer::poll_flush`](crate::Renderer::poll_flush) and
+    /// [`Renderer::drain`](crate::Renderer::drain).
+    pub output_queue: Option<usize>,
+    /// What rendering gives up while the `output_queue` is backed up.
+    pub queue_policy: QueuePolicy,
+    /// Capabilities of the terminal, deciding whether tables, lists, rules
+    /// and think blocks use box drawing characters and whether links are
+    /// hyperlinked. Defaults to [`TerminalProfile::unicode`]; set it to
+    /// [`TerminalProfile::detect`] to follow the environment.
+    pub profile: TerminalProfile,
+    /// Measure East Asian ambiguous-width characters, such as `○`, `±` or
+    /// Cyrillic and Greek letters, as two columns, matching CJK terminals
+    /// configured that way, so tables, headings, prose and code wrap and
+    /// align. Box drawing characters are ambiguous-width too, so borders,
+    /// bullets and rules fall back to ASCII.
+    pub ambiguous_wide: bool,
+    /// Draw images inline with this graphics protocol instead of showing
+    /// a placeholder. Ignored by backends that cannot draw images.
+    #[cfg(feature = "images")]
+    pub image_protocol: Option<ImageProtocol>,
+    /// Directory local images drawn with `image_protocol` are loaded from;
+    /// paths resolving outside it are not read. Without it no local images
+    /// are drawn.
+    #[cfg(feature = "images")]
+    pub image_dir: Option<PathBuf>,
+    /// Load remote images drawn with `image_protocol` through this cache;
+    /// without it only local images are drawn.
+    #[cfg(feature = "images")]
+    pub image_cache: Option<ImageCache>,
+    /// How list items, blockquotes and doc comments are wrapped.
+    pub wrap: WrapMode,
+    /// Expand tabs in code blocks to stops this many columns apart, so
+    /// frames and wrapping measure them. `None` leaves tabs to the terminal.
+    pub tab_width: Option<usize>,
+    /// Show the headings enclosing the latest output, such as
+    /// `Install › Linux`, in the last row kept free by
+    /// [`Renderer::reserve_bottom_lines`](crate::Renderer::reserve_bottom_lines).
+    pub breadcrumbs: bool,
+}
+
+/// How prose is fitted to the output width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Break lines at word boundaries.
+    #[default]
+    Words,
+    /// Leave long lines for the terminal to wrap.
+    None,
+}
+
+/// How the renderer reacts to a writer slower than the event stream, when
+/// `output_queue` is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Render everything, waiting for the writer whenever the queue is full.
+    #[default]
+    Block,
+    /// While the queue is at least half full, skip optional output:
+    /// provisional table redraws, which the next redraw or the final table
+    /// replaces, the typing cursor, and syntax highlighting for the rest of
+    /// the code block. Text events are held back and written as one styled
+    /// run once another event arrives.
+    /// [`Renderer::degradation`](crate::Renderer::degradation) counts what
+    /// was skipped.
+    Degrade,
+    /// Degrade, and while the queue is at least three quarters full also
+    /// drop inline events other than prompts instead of waiting for the
+    /// writer. Blocks are still written, so the layout stays intact.
+    Drop,
+}
+
+/// Engine used to highlight fenced code blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighlightEngine {
+    /// syntect with the bundled Sublime Text syntaxes.
+    #[default]
+    Syntect,
+    /// tree-sitter grammars for the languages that have one bundled, with
+    /// syntect for the rest. Without the `tree-sitter` feature, syntect
+    /// highlights every language.
+    TreeSitter,
+}
+
+/// Typing speed of a paced renderer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pacing {
+    /// Characters of source rendered per second.
+    pub chars_per_second: f64,
+    /// Complete lines waiting at which the speed doubles. The speed keeps
+    /// growing with the backlog, so bursts from the producer are caught up.
+    pub catch_up_lines: usize,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            chars_per_second: 400.0,
+            catch_up_lines: 4,
+        }
+    }
+}
+
+impl Pacing {
+    /// Time given to `chars` characters while `backlog` more complete lines
+    /// are waiting. A speed that is not a positive number does not throttle.
+    pub fn duration(&self, chars: usize, backlog: usize) -> Duration {
+        if self.chars_per_second.is_nan() || self.chars_per_second <= 0.0 {
+            return Duration::ZERO;
+        }
+        let speedup = 1.0 + backlog as f64 / self.catch_up_lines.max(1) as f64;
+        let seconds = chars as f64 / (self.chars_per_second * speedup);
+        Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+    }
+}
+
+/// How ordered-list item numbers are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberingStyle {
+    /// 1., 2., 3.
+    #[default]
+    Decimal,
+    /// a., b., c.
+    LowerAlpha,
+    /// A., B., C.
+    UpperAlpha,
+    /// i., ii., iii.
+    LowerRoman,
+    /// I., II., III.
+    UpperRoman,
+}
+
+/// Rewriting of emoji sequences with unpredictable terminal widths.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmojiNormalization {
+    /// Leave text unchanged.
+    #[default]
+    Keep,
+    /// Remove variation selectors.
+    StripSelectors,
+    /// Remove variation selectors and the zero-width joiners between emoji,
+    /// splitting joined sequences into their component emoji.
+    SplitJoined,
+}
+
+/// Meaning of a status marker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusKind {
+    Done,
+    Failed,
+    Pending,
+}
+
+/// Status markers to rewrite, with the status each stands for.
+///
+/// Markers match regardless of ASCII case, longest first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusMarkers {
+    // Longest first, without empty markers
+    markers: Vec<(String, StatusKind)>,
+}
+
+impl Default for StatusMarkers {
+    fn default() -> Self {
+        let done = ["✅", "✔️", "✔", "✓", "☑️", "☑", "[x]", "(done)"];
+        let failed = ["❌", "✖️", "✖", "✗", "✘", "(failed)"];
+        let pending = ["⏳", "🔄", "[ ]", "(pending)", "(todo)"];
+        let markers = [
+            (&done[..], StatusKind::Done),
+            (&failed[..], StatusKind::Failed),
+            (&pending[..], StatusKind::Pending),
+        ];
+        Self::new(
+            markers
+                .into_iter()
+                .flat_map(|(markers, kind)| markers.iter().map(move |m| (m.to_string(), kind))),
+        )
+    }
+}
+
+impl StatusMarkers {
+    /// Markers with the status each stands for. Empty markers are ignored.
+    pub fn new(markers: impl IntoIterator<Item = (String, StatusKind)>) -> Self {
+        let mut markers: Vec<(String, StatusKind)> = markers
+            .into_iter()
+            .filter(|(marker, _)| !marker.is_empty())
+            .collect();
+        markers.sort_by_key(|(marker, _)| std::cmp::Reverse(marker.len()));
+        Self { markers }
+    }
+
+    /// The markers with the status each stands for, longest first.
+    pub fn markers(&self) -> &[(String, StatusKind)] {
+        &self.markers
+    }
+
+    /// Replace every marker in `text` with `glyph` of its status, styling
+    /// the text between markers with `plain`.
+    pub(crate) fn replace(
+        &self,
+        text: &str,
+        plain: impl Fn(&str) -> String,
+        glyph: impl Fn(StatusKind) -> String,
+    ) -> String {
+        let mut result = String::with_capacity(text.len());
+        // Start of the text not yet styled
+        let mut start = 0;
+        let mut at = 0;
+        'scan: while let Some(c) = text[at..].chars().next() {
+            for (marker, kind) in &self.markers {
+                let matches = text[at..]
+                    .get(..marker.len())
+                    .is_some_and(|head| head.eq_ignore_ascii_case(marker));
+                if matches {
+                    if start < at {
+                        result.push_str(&plain(&text[start..at]));
+                    }
+                    result.push_str(&glyph(*kind));
+                    at += marker.len();
+                    start = at;
+                    continue 'scan;
+                }
+            }
+            at += c.len_utf8();
+        }
+        if start < text.len() || text.is_empty() {
+            result.push_str(&plain(&text[start..]));
+        }
+        result
+    }
+}
+
+/// How think blocks (chain-of-thought content) are shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThinkMode {
+    /// Show every line inside a bordered block.
+    #[default]
+    Full,
+    /// Replace the block with one line giving the size of its content.
+    Summary,
+    /// Render nothing.
+    Hidden,
+}
+
+/// Adjustment of heading levels before headings are rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeadingLevels {
+    /// Levels added to every heading; negative values promote headings.
+    pub shift: i8,
+    /// Lowest level rendered; shallower headings are demoted to it.
+    pub min: u8,
+    /// Deepest level rendered; deeper headings are promoted to it.
+    pub max: u8,
+}
+
+impl Default for HeadingLevels {
+    fn default() -> Self {
+        Self {
+            shift: 0,
+            min: 1,
+            max: 6,
+        }
+    }
+}
+
+impl HeadingLevels {
+    /// Demote every heading by `levels`, so H1 renders as H(1 + `levels`).
+    pub fn demote(levels: u8) -> Self {
+        Self {
+            shift: levels.min(5) as i8,
+            ..Self::default()
+        }
+    }
+
+    /// The level a heading of `level` is rendered at, between 1 and 6.
+    pub fn apply(&self, level: u8) -> u8 {
+        let shifted = (i16::from(level) + i16::from(self.shift)).clamp(1, 6) as u8;
+        let max = self.max.clamp(1, 6);
+        shifted.clamp(self.min.clamp(1, max), max)
+    }
+}
+
+/// When table rows are drawn while a table streams in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableMode {
+    /// Buffer the table and draw it once `TableEnd` arrives.
+    #[default]
+    Buffered,
+    /// Draw the header and each row as it arrives, using column widths
+    /// estimated from the header.
+    Progressive {
+        /// Replace the progressive rows with the fitted layout once the
+        /// table is complete, if the backend can erase lines.
+        redraw: bool,
+    },
+}
+
+/// Rendering mode for fenced code block contents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodeRenderMode {
+    /// Syntax highlight and wrap lines to the terminal width.
+    #[default]
+    Highlighted,
+    /// Print lines verbatim, without wrapping, highlighting, or decorations,
+    /// so the output can be piped or copy-pasted losslessly.
+    Raw,
+}
+
+/// Number of blank lines enforced between blocks when normalizing spacing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlankLinePolicy {
+    /// Blank lines before a heading.
+    pub before_heading: usize,
+    /// Blank lines between a heading and the block that follows it.
+    pub after_heading: usize,
+    /// Blank lines between any other two blocks.
+    pub between_blocks: usize,
+}
+
+impl Default for BlankLinePolicy {
+    fn default() -> Self {
+        Self {
+            before_heading: 1,
+            after_heading: 0,
+            between_blocks: 1,
+        }
+    }
+}
+
+/// Every user-visible string the renderer generates, for localization.
+#[derive(Clone, Debug)]
+pub struct Labels {
+    /// Title of the think-block border.
+    pub thinking: String,
+    /// Suffix of the size of a collapsed think block.
+    pub hidden: String,
+    /// Text before the duration in a think block's closing border.
+    pub thought_for: String,
+    /// Unit of the line count in code block footers.
+    pub lines: String,
+    /// Unit of the character count in code block footers.
+    pub chars: String,
+    /// Text before the code in a command's exit badge.
+    pub exit: String,
+    /// Units of the size of a collapsed think block.
+    pub bytes: String,
+    pub kilobytes: String,
+    pub megabytes: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self {
+            thinking: "thinking".to_string(),
+            hidden: "hidden".to_string(),
+            thought_for: "thought for".to_string(),
+            lines: "lines".to_string(),
+            chars: "chars".to_string(),
+            exit: "exit".to_string(),
+            bytes: "B".to_string(),
+            kilobytes: "KB".to_string(),
+            megabytes: "MB".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing_catches_up_with_backlog() {
+        let pacing = Pacing {
+            chars_per_second: 100.0,
+            catch_up_lines: 2,
+        };
+        assert_eq!(pacing.duration(50, 0), Duration::from_millis(500));
+        assert_eq!(pacing.duration(50, 2), Duration::from_millis(250));
+        assert_eq!(pacing.duration(50, 6), Duration::from_millis(125));
+        for chars_per_second in [0.0, -1.0, f64::NAN] {
+            let pacing = Pacing {
+                chars_per_second,
+                ..Default::default()
+            };
+            assert_eq!(pacing.duration(50, 0), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_heading_levels() {
+        let demoted = HeadingLevels::demote(1);
+        assert_eq!((demoted.apply(1), demoted.apply(2), demoted.apply(6)), (2, 3, 6));
+        let clamped = HeadingLevels {
+            min: 2,
+            max: 4,
+            ..Default::default()
+        };
+        assert_eq!((clamped.apply(1), clamped.apply(3), clamped.apply(6)), (2, 3, 4));
+        let promoted = HeadingLevels {
+            shift: -2,
+            ..Default::default()
+        };
+        assert_eq!((promoted.apply(2), promoted.apply(5)), (1, 3));
+        assert_eq!(HeadingLevels::default().apply(0), 1);
+    }
+
+    #[test]
+    fn test_status_markers() {
+        let markers = StatusMarkers::default();
+        let glyph = |kind| format!("<{:?}>", kind);
+        let plain = str::to_string;
+        assert_eq!(
+            markers.replace("✅ a, [X] b, c (Done), ✔️ d", plain, glyph),
+            "<Done> a, <Done> b, c <Done>, <Done> d"
+        );
+        assert_eq!(
+            markers.replace("❌ tests [ ] docs ⏳", plain, glyph),
+            "<Failed> tests <Pending> docs <Pending>"
+        );
+        assert_eq!(markers.replace("[link] (doing)", plain, glyph), "[link] (doing)");
+
+        // Text between markers is styled on its own, so no glyph ends it
+        let bold = |text: &str| format!("<b>{}</b>", text);
+        assert_eq!(markers.replace("a ✔ b", bold, glyph), "<b>a </b><Done><b> b</b>");
+        assert_eq!(markers.replace("[x]", bold, glyph), "<Done>");
+
+        let custom = StatusMarkers::new([
+            ("ok".to_string(), StatusKind::Done),
+            ("ok!".to_string(), StatusKind::Failed),
+            (String::new(), StatusKind::Pending),
+        ]);
+        assert_eq!(custom.markers().len(), 2);
+        assert_eq!(custom.replace("ok! ok", plain, glyph), "<Failed> <Done>");
+    }
+}
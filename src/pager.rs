@@ -0,0 +1,317 @@
+//! Interactive pager for viewing rendered output.
+
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::backend::{AnsiBackend, OutputBackend};
+use crate::color::ColorDepth;
+use crate::profile::TerminalProfile;
+use crate::strip::{strip_ansi, tokens, Token};
+use crate::theme::Style;
+use crate::width::grapheme_width;
+
+/// Ends the styling and any hyperlink left open by a line, so it does not
+/// bleed into the rest of the row or the next line.
+const LINE_RESET: &str = "\x1b[0m\x1b]8;;\x1b\\";
+
+/// Writer capturing rendered lines into a scrollback buffer, then showing
+/// them on the alternate screen with keyboard scrolling and search.
+///
+/// ```no_run
+/// use forge_markdown_stream::{Pager, StreamdownRenderer};
+///
+/// let mut pager = Pager::new();
+/// let mut renderer = StreamdownRenderer::new(&mut pager, 80);
+/// renderer.push("# Long document\n").unwrap();
+/// renderer.finish().unwrap();
+/// pager.run().unwrap();
+/// ```
+///
+/// Keys: `j`/`k` or arrows scroll a line, space/`b` or page keys scroll a
+/// page, `g`/`G` jump to the start or end, `/` searches, `n`/`N` move between
+/// matches and `q` quits.
+#[derive(Default)]
+pub struct Pager {
+    lines: Vec<String>,
+    partial: Vec<u8>,
+    view: View,
+}
+
+impl Pager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines captured so far. A line without its newline yet is not
+    /// included.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Show the captured lines until the user quits.
+    pub fn run(&mut self) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            let partial = std::mem::take(&mut self.partial);
+            self.lines.push(String::from_utf8_lossy(&partial).into_owned());
+        }
+        let mut stdout = io::stdout();
+        let _screen = Screen::enter(&mut stdout)?;
+        let mut search: Option<String> = None;
+        loop {
+            let (columns, rows) = terminal::size()?;
+            self.view.width = usize::from(columns).max(1);
+            self.view.height = usize::from(rows.saturating_sub(1)).max(1);
+            self.view.clamp(self.lines.len());
+            self.draw(&mut stdout, search.as_deref())?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(query) = search.as_mut() {
+                match key.code {
+                    KeyCode::Enter => {
+                        self.view.query = Some(std::mem::take(query));
+                        search = None;
+                        self.view.find(&self.lines, true);
+                    }
+                    KeyCode::Esc => search = None,
+                    KeyCode::Backspace => {
+                        query.pop();
+                    }
+                    KeyCode::Char(c) => query.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            if is_quit(&key) {
+                return Ok(());
+            }
+            match key.code {
+                KeyCode::Char('/') => search = Some(String::new()),
+                code => self.view.handle(code, &self.lines),
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write, search: Option<&str>) -> io::Result<()> {
+        let end = (self.view.top + self.view.height).min(self.lines.len());
+        for (row, line) in self.lines[self.view.top..end].iter().enumerate() {
+            let line = clip(line, self.view.width);
+            queue!(out, MoveTo(0, row as u16), Print(line), Print(LINE_RESET))?;
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+        for row in end - self.view.top..self.view.height {
+            queue!(out, MoveTo(0, row as u16), Clear(ClearType::CurrentLine))?;
+        }
+        let status = match search {
+            Some(query) => clip(&format!("/{}", query), self.view.width),
+            None => {
+                let position = format!("{}-{} of {}", self.view.top + 1, end, self.lines.len());
+                let position = clip(&position, self.view.width);
+                match TerminalProfile::detect().color {
+                    ColorDepth::None => position,
+                    depth => AnsiBackend::new(depth).paint(&Style::new().dimmed(), &position),
+                }
+            }
+        };
+        queue!(
+            out,
+            MoveTo(0, self.view.height as u16),
+            Clear(ClearType::CurrentLine),
+            Print(status)
+        )?;
+        out.flush()
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        while let Some(pos) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line[..pos]).into_owned();
+            self.lines.push(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The part of the rendered `line` that fits in `width` columns, keeping
+/// its escape sequences. Rows that would wrap would shift the whole screen.
+fn clip(line: &str, width: usize) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut columns = 0;
+    for token in tokens(line) {
+        match token {
+            Token::Text(text) => {
+                for grapheme in text.graphemes(true) {
+                    columns += grapheme_width(grapheme);
+                    if columns > width {
+                        return result;
+                    }
+                    result.push_str(grapheme);
+                }
+            }
+            Token::Escape(..) => result.push_str(token.as_str()),
+        }
+    }
+    result
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// Scroll position and search state of the pager.
+#[derive(Debug, Default)]
+struct View {
+    top: usize,
+    width: usize,
+    height: usize,
+    query: Option<String>,
+}
+
+impl View {
+    /// Keep the last page full and the top line in range.
+    fn clamp(&mut self, len: usize) {
+        self.top = self.top.min(len.saturating_sub(self.height));
+    }
+
+    fn handle(&mut self, code: KeyCode, lines: &[String]) {
+        match code {
+            KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => self.top += 1,
+            KeyCode::Char('k') | KeyCode::Up => self.top = self.top.saturating_sub(1),
+            KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => {
+                self.top += self.height
+            }
+            KeyCode::Char('b') | KeyCode::PageUp => {
+                self.top = self.top.saturating_sub(self.height)
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.top = 0,
+            KeyCode::Char('G') | KeyCode::End => self.top = lines.len(),
+            KeyCode::Char('n') => self.find(lines, true),
+            KeyCode::Char('N') => self.find(lines, false),
+            _ => {}
+        }
+        self.clamp(lines.len());
+    }
+
+    /// Scroll to the next line after the top one, or the previous line
+    /// before it, whose text contains the query.
+    fn find(&mut self, lines: &[String], forward: bool) {
+        let Some(query) = self.query.as_deref().filter(|query| !query.is_empty()) else {
+            return;
+        };
+        let matches = |index: &usize| strip_ansi(&lines[*index]).contains(query);
+        let found = if forward {
+            (self.top + 1..lines.len()).find(matches)
+        } else {
+            (0..self.top).rev().find(matches)
+        };
+        if let Some(index) = found {
+            self.top = index;
+        }
+    }
+}
+
+/// Raw mode on the alternate screen, restored when dropped.
+struct Screen;
+
+impl Screen {
+    fn enter(out: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("\x1b[1mline {}\x1b[0m", i)).collect()
+    }
+
+    #[test]
+    fn test_captures_complete_lines() {
+        let mut pager = Pager::new();
+        pager.write_all(b"one\ntw").unwrap();
+        pager.write_all(b"o\nthree").unwrap();
+        assert_eq!(pager.lines(), ["one", "two"]);
+    }
+
+    #[test]
+    fn test_long_lines_are_clipped() {
+        assert_eq!(clip("\x1b[1mline 1\x1b[0m", 6), "\x1b[1mline 1\x1b[0m");
+        assert_eq!(clip("\x1b[1mline 1\x1b[0m", 4), "\x1b[1mline");
+        assert_eq!(clip("a\u{4e2d}\u{6587}", 4), "a\u{4e2d}");
+    }
+
+    #[test]
+    fn test_rows_end_with_a_plain_reset() {
+        let mut pager = Pager::new();
+        pager.write_all(b"\x1b]8;;https://example.com\x1b\\a long link\n").unwrap();
+        pager.view.width = 6;
+        pager.view.height = 2;
+        let mut out = Vec::new();
+        pager.draw(&mut out, None).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!("a long{}", LINE_RESET)));
+        assert!(!out.contains("link"));
+    }
+
+    #[test]
+    fn test_scrolling_stays_in_range() {
+        let lines = lines(25);
+        let mut view = View {
+            height: 10,
+            ..Default::default()
+        };
+        view.handle(KeyCode::PageDown, &lines);
+        assert_eq!(view.top, 10);
+        view.handle(KeyCode::PageDown, &lines);
+        assert_eq!(view.top, 15);
+        view.handle(KeyCode::Char('g'), &lines);
+        view.handle(KeyCode::Up, &lines);
+        assert_eq!(view.top, 0);
+    }
+
+    #[test]
+    fn test_search_ignores_escape_sequences() {
+        let lines = lines(30);
+        let mut view = View {
+            height: 5,
+            query: Some("line 2".to_string()),
+            ..Default::default()
+        };
+        view.handle(KeyCode::Char('n'), &lines);
+        assert_eq!(view.top, 2);
+        view.handle(KeyCode::Char('n'), &lines);
+        assert_eq!(view.top, 20);
+        view.handle(KeyCode::Char('N'), &lines);
+        assert_eq!(view.top, 2);
+    }
+}
@@ -0,0 +1,193 @@
+//! Terminal capabilities deciding which glyphs and features output uses.
+
+use std::sync::{Arc, OnceLock};
+
+use crate::backend::{AnsiBackend, OutputBackend, PlainBackend};
+use crate::color::ColorDepth;
+
+/// What the output terminal can display.
+///
+/// Tables, lists, rules and think blocks use the theme's
+/// [`Glyphs`](crate::Glyphs) only with `unicode`, falling back to
+/// [`Glyphs::ascii`](crate::Glyphs::ascii); links are hyperlinked only with
+/// `hyperlinks`. Without `escapes` nothing is styled at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerminalProfile {
+    /// Box drawing characters and symbol bullets are displayed.
+    pub unicode: bool,
+    /// OSC 8 hyperlinks are supported; otherwise URLs follow link labels.
+    pub hyperlinks: bool,
+    /// SGR attributes and OSC 8 hyperlinks are understood; off for
+    /// `TERM=dumb`.
+    pub escapes: bool,
+    /// Colors the terminal displays.
+    pub color: ColorDepth,
+    /// Emoji are displayed, two cells wide. Without them, joined emoji
+    /// sequences are split into their components, as the terminal draws
+    /// them.
+    pub emoji: bool,
+}
+
+impl Default for TerminalProfile {
+    /// A terminal displaying everything; use [`detect`](Self::detect) for
+    /// the profile of the environment.
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl TerminalProfile {
+    /// A terminal displaying everything.
+    pub fn unicode() -> Self {
+        Self {
+            unicode: true,
+            hyperlinks: true,
+            escapes: true,
+            color: ColorDepth::TrueColor,
+            emoji: true,
+        }
+    }
+
+    /// A terminal displaying only ASCII, such as a serial console or a log.
+    pub fn ascii() -> Self {
+        Self {
+            unicode: false,
+            hyperlinks: false,
+            escapes: false,
+            color: ColorDepth::None,
+            emoji: false,
+        }
+    }
+
+    /// Detect the profile from the environment, once per process.
+    pub fn detect() -> Self {
+        static PROFILE: OnceLock<TerminalProfile> = OnceLock::new();
+        *PROFILE.get_or_init(|| Self::from_env(|name| std::env::var(name).unwrap_or_default()))
+    }
+
+    /// Backend emitting the sequences this profile understands.
+    pub fn backend(&self) -> Arc<dyn OutputBackend> {
+        if self.escapes {
+            #[cfg(all(windows, feature = "crossterm"))]
+            crate::console::enable_virtual_terminal();
+            Arc::new(AnsiBackend::new(self.color))
+        } else {
+            Arc::new(PlainBackend)
+        }
+    }
+
+    /// Determine the profile from environment variables read with `var`.
+    ///
+    /// `CLICOLOR_FORCE` other than `0` keeps colors for `TERM=dumb` and
+    /// `CLICOLOR=0`, but not for `NO_COLOR`.
+    fn from_env(var: impl Fn(&str) -> String) -> Self {
+        let forced = !matches!(var("CLICOLOR_FORCE").as_str(), "" | "0");
+        let term = var("TERM");
+        if term == "dumb" && !forced {
+            return Self::ascii();
+        }
+        let color = if var("CLICOLOR") == "0" && !forced {
+            ColorDepth::None
+        } else {
+            let term = Some(term.as_str()).filter(|term| *term != "dumb");
+            let no_color = var("NO_COLOR");
+            let colorterm = var("COLORTERM");
+            ColorDepth::from_env(Some(no_color.as_str()), term, Some(colorterm.as_str()))
+        };
+        // The first locale variable that is set decides the encoding
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .map(&var)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let utf8 = cfg!(windows) || locale.contains("utf-8") || locale.contains("utf8");
+        Self {
+            unicode: utf8,
+            hyperlinks: hyperlinks_likely(
+                &var("TERM_PROGRAM"),
+                &var("VTE_VERSION"),
+                !var("KITTY_WINDOW_ID").is_empty() || !var("WT_SESSION").is_empty(),
+            ),
+            escapes: true,
+            color,
+            // The Linux console has no emoji glyphs
+            emoji: utf8 && term != "linux",
+        }
+    }
+}
+
+/// Whether the terminal is known to support OSC 8 hyperlinks.
+pub(crate) fn hyperlinks_likely(
+    term_program: &str,
+    vte_version: &str,
+    known_terminal: bool,
+) -> bool {
+    // VTE-based terminals support hyperlinks since 0.50
+    let vte = vte_version
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000);
+    vte || known_terminal || matches!(term_program, "iTerm.app" | "WezTerm" | "vscode")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(vars: &[(&str, &str)]) -> TerminalProfile {
+        TerminalProfile::from_env(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    #[test]
+    fn test_profile_from_env() {
+        assert_eq!(
+            profile(&[("TERM", "dumb"), ("LANG", "en_US.UTF-8")]),
+            TerminalProfile::ascii()
+        );
+        let utf8 = profile(&[("LANG", "en_US.UTF-8"), ("COLORTERM", "truecolor")]);
+        assert!(utf8.unicode && utf8.emoji && !utf8.hyperlinks);
+        assert_eq!(utf8.color, ColorDepth::TrueColor);
+        // LC_ALL takes precedence over LANG
+        let posix = profile(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]);
+        assert_eq!(posix.unicode, cfg!(windows));
+        let console = profile(&[("TERM", "linux"), ("LANG", "C.utf8")]);
+        assert!(console.unicode && !console.emoji);
+    }
+
+    #[test]
+    fn test_color_from_env() {
+        let dumb = profile(&[("TERM", "dumb")]);
+        assert!(!dumb.escapes && !dumb.hyperlinks);
+        let forced = profile(&[("TERM", "dumb"), ("CLICOLOR_FORCE", "1")]);
+        assert!(forced.escapes);
+        assert_eq!(forced.color, ColorDepth::Ansi16);
+        let off = profile(&[("TERM", "xterm-256color"), ("CLICOLOR", "0")]);
+        assert!(off.escapes);
+        assert_eq!(off.color, ColorDepth::None);
+        let on = profile(&[("TERM", "xterm-256color"), ("CLICOLOR", "1")]);
+        assert_eq!(on.color, ColorDepth::Ansi256);
+        let no_color = profile(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]);
+        assert_eq!(no_color.color, ColorDepth::None);
+    }
+
+    #[test]
+    fn test_backend_follows_escapes() {
+        let theme = crate::Theme::dark();
+        let style = theme.bold;
+        assert_eq!(TerminalProfile::ascii().backend().paint(&style, "a"), "a");
+        assert_ne!(TerminalProfile::unicode().backend().paint(&style, "a"), "a");
+    }
+
+    #[test]
+    fn test_hyperlink_detection() {
+        assert!(hyperlinks_likely("iTerm.app", "", false));
+        assert!(hyperlinks_likely("", "6003", false));
+        assert!(hyperlinks_likely("", "", true));
+        assert!(!hyperlinks_likely("Apple_Terminal", "4800", false));
+    }
+}
@@ -1,36 +1,184 @@
 //! Main renderer that handles all parse events.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::ops::Range;
+use std::task::Poll;
+use std::time::{Duration, Instant};
 
 use streamdown_parser::ParseEvent;
 
-use crate::code::CodeHighlighter;
+use crate::calibrate::Calibration;
+use crate::code::{
+    detect_fence_language, diff_line_style, expand_tabs, render_doc_prose,
+    render_styled_code_line, split_doc_comment, CodeHighlighter,
+};
+use crate::guard::TerminalGuard;
 use crate::heading::render_heading;
 use crate::inline::{render_inline_content, render_inline_elements};
+use crate::linebreak::{wrap_prose, NO_WRAP};
+use crate::links::{render_references, LinkStyler};
 use crate::list::{render_list_item, ListState};
-use crate::table::render_table;
-use crate::theme::Theme;
+use crate::locale::NumberLocale;
+use crate::options::{
+    CodeRenderMode, EmojiNormalization, Labels, QueuePolicy, RendererOptions, TableMode, ThinkMode,
+    WrapMode,
+};
+use crate::strip::strip_ansi;
+use crate::table::{
+    detect_numeric_columns, render_table_aligned, render_table_borderless, wrap, ColumnAlignment,
+    TableLayout,
+};
+use crate::theme::{BadgeKind, Glyphs, Theme};
 use crate::style::InlineStyler;
-use streamdown_render::text::text_wrap;
+use crate::summary::{CodeBlockStats, RenderSummary};
+use crate::utils::{
+    normalize_emoji, normalize_event, terminal_height_of, terminal_width_of, TerminalHandle,
+};
+use crate::width::visible_length_with;
+#[cfg(feature = "tracing")]
+use crate::trace::EventTracer;
+use streamdown_render::simple_wrap;
+
+/// Check if this event keeps a pending list alive.
+/// List continues only for ListItem, ListEnd, and EmptyLine/Newline events.
+pub(crate) fn continues_list(event: &ParseEvent) -> bool {
+    matches!(
+        event,
+        ParseEvent::ListItem { .. }
+            | ParseEvent::ListEnd
+            | ParseEvent::EmptyLine
+            | ParseEvent::Newline
+    )
+}
+
+/// How an `Image` event is rendered, as decided by an image handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageAction {
+    /// Show the theme's image placeholder.
+    Placeholder,
+    /// Write this text in place of the image, e.g. an ASCII-art preview or
+    /// a graphics protocol escape sequence for an image the handler fetched.
+    Replace(String),
+    /// Render nothing.
+    Skip,
+}
+
+/// Callback deciding how an image is rendered, given its alt text and URL.
+pub type ImageHandler = Box<dyn Fn(&str, &str) -> ImageAction + Send>;
+
+/// Callback rendering the complete source of a fenced code block, e.g. a
+/// diagram; the returned text replaces the block.
+pub type FenceHandler = Box<dyn Fn(&str) -> String + Send>;
+
+/// Output target driven by parse events.
+///
+/// Implemented by every renderer, so applications can choose a backend at
+/// runtime behind a `Box<dyn EventRenderer>`.
+pub trait EventRenderer {
+    /// Render a single parse event.
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()>;
+
+    /// Complete the document once the event stream has ended.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Source of the time elapsed since rendering started, replaceable so
+/// tests can control the clock.
+pub type Clock = Box<dyn Fn() -> Duration + Send>;
+
+/// Output skipped under [`QueuePolicy::Degrade`] while the writer lagged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Degradation {
+    /// Provisional table redraws left to a later one.
+    pub skipped_redraws: usize,
+    /// Times the typing cursor was not drawn.
+    pub skipped_cursors: usize,
+    /// Code lines written without syntax highlighting.
+    pub unhighlighted_lines: usize,
+    /// Text events held back and written as one styled run.
+    pub coalesced_texts: usize,
+    /// Inline events dropped under [`QueuePolicy::Drop`].
+    pub dropped_events: usize,
+}
 
 /// Main renderer for markdown events.
 pub struct Renderer<W: Write> {
-    writer: W,
+    writer: TerminalGuard<W>,
     width: usize,
     theme: Theme,
     // Code highlighting
     highlighter: CodeHighlighter,
     current_language: Option<String>,
+    // Whether the language was guessed rather than named by the fence, and
+    // the file extension last mentioned in prose to guess from
+    language_detected: bool,
+    language_hint: Option<String>,
     code_buffer: String,
+    code_line_number: usize,
+    // Inside a Python docstring spanning lines
+    in_docstring: bool,
     // Table buffering
     table_rows: Vec<Vec<String>>,
+    table_alignments: Vec<ColumnAlignment>,
+    // Layout of a table drawn progressively
+    table_layout: Option<TableLayout>,
+    // Last table drawn in full, to lay out again after a calibration
+    last_table: Option<DrawnTable>,
     // Blockquote state
     in_blockquote: bool,
     blockquote_depth: usize,
     // List state
     list_state: ListState,
+    // Indent of list item continuation content, or 0 outside one
+    list_indent: usize,
     // Column tracking
     column: usize,
+    // Optional behaviors
+    options: RendererOptions,
+    // Time since rendering started, for timestamps and think durations
+    clock: Clock,
+    // When the open think block started
+    think_started: Option<Duration>,
+    // Timestamp gutter
+    gutter_armed: bool,
+    pending_stamp: Option<String>,
+    // Provisional lines drawn by `render_partial`
+    partial_lines: usize,
+    // Blank-line normalization
+    current_block: Option<BlockKind>,
+    block_broken: bool,
+    // Consecutive blank lines written, for `max_blank_lines`
+    blank_run: usize,
+    // Whether the typing cursor is drawn after the output
+    cursor_shown: bool,
+    // Output skipped under `QueuePolicy::Degrade`
+    degradation: Degradation,
+    // Whether the open code block has fallen back to plain code
+    plain_code: bool,
+    // Text held back while the output queue is backed up
+    pending_text: String,
+    image_handler: Option<ImageHandler>,
+    // Fence handlers by language, and whether the open block is handled
+    fence_handlers: HashMap<String, FenceHandler>,
+    fenced: bool,
+    // Bytes of think-block content not shown
+    think_hidden: usize,
+    // Lines completed so far, for retracting them
+    lines_written: usize,
+    // Bottom rows kept out of the scroll region, and the terminal height
+    // they were reserved for
+    reserved_lines: usize,
+    terminal_height: usize,
+    // Headings enclosing the latest output, with their levels
+    headings: Vec<(u8, String)>,
+    // URLs of links numbered so far, for `numbered_links`
+    link_references: RefCell<Vec<String>>,
+    summary: RenderSummary,
+    // Tracing integration
+    #[cfg(feature = "tracing")]
+    tracer: EventTracer,
 }
 
 impl<W: Write> Renderer<W> {
@@ -38,25 +186,124 @@ impl<W: Write> Renderer<W> {
         Self::with_theme(writer, width, Theme::default())
     }
 
+    /// Create a renderer sized to the width of the terminal `writer` is
+    /// attached to, or to `COLUMNS` or 80 columns if it is not a terminal.
+    pub fn auto_width(writer: W) -> Self
+    where
+        W: TerminalHandle,
+    {
+        let width = terminal_width_of(&writer);
+        Self::new(writer, width)
+    }
+
     pub fn with_theme(writer: W, width: usize, theme: Theme) -> Self {
+        let restore = theme.backend.restore();
         Self {
-            writer,
+            writer: TerminalGuard::with_restore(writer, restore),
             width,
             theme,
             highlighter: CodeHighlighter::default(),
             current_language: None,
+            language_detected: false,
+            language_hint: None,
             code_buffer: String::new(),
+            code_line_number: 0,
+            in_docstring: false,
             table_rows: Vec::new(),
+            table_alignments: Vec::new(),
+            table_layout: None,
+            last_table: None,
             in_blockquote: false,
             blockquote_depth: 0,
             list_state: ListState::default(),
+            list_indent: 0,
             column: 0,
+            options: RendererOptions::default(),
+            clock: {
+                let started = Instant::now();
+                Box::new(move || started.elapsed())
+            },
+            think_started: None,
+            gutter_armed: true,
+            pending_stamp: None,
+            partial_lines: 0,
+            current_block: None,
+            block_broken: false,
+            blank_run: 0,
+            cursor_shown: false,
+            degradation: Degradation::default(),
+            plain_code: false,
+            pending_text: String::new(),
+            image_handler: None,
+            fence_handlers: HashMap::new(),
+            fenced: false,
+            think_hidden: 0,
+            lines_written: 0,
+            reserved_lines: 0,
+            terminal_height: 0,
+            headings: Vec::new(),
+            link_references: RefCell::new(Vec::new()),
+            summary: RenderSummary::default(),
+            #[cfg(feature = "tracing")]
+            tracer: EventTracer::default(),
         }
     }
 
+    /// Change the output width, e.g. after the terminal was resized.
+    ///
+    /// Blocks that are still buffered, such as a table waiting for
+    /// `TableEnd`, are laid out with the new width when flushed. Provisional
+    /// tables, drawn by [`render_partial`](Self::render_partial) or by a
+    /// progressive table that is redrawn when complete, are erased and drawn
+    /// again at the new width. Lines of an in-progress code block wrap to
+    /// the new width from the next line on.
+    pub fn resize(&mut self, width: usize) -> io::Result<()> {
+        if width == self.width {
+            return Ok(());
+        }
+        self.width = width;
+        if self.partial_lines == 0 {
+            return Ok(());
+        }
+        if self.table_layout.is_some() {
+            // Estimate the columns again and draw the rows seen so far
+            self.erase_partial()?;
+            self.table_layout = None;
+            let rows = std::mem::take(&mut self.table_rows);
+            for row in rows {
+                self.table_rows.push(row);
+                self.draw_table_row(true)?;
+            }
+        } else {
+            self.draw_partial_table()?;
+        }
+        self.flush_writer()
+    }
+
+    /// Get the current output width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.list_state.set_locale(options.number_locale.clone());
+        self.list_state.set_numbering(options.ordered_numbering.clone());
+        self.highlighter.set_engine(options.highlighter);
+        self.highlighter.set_ambiguous_wide(options.ambiguous_wide);
+        self.writer.set_queue_capacity(options.output_queue);
+        self.options = options;
+    }
+
+    /// Get the optional renderer behaviors.
+    pub fn options(&self) -> &RendererOptions {
+        &self.options
+    }
+
     /// Set a new theme.
     #[allow(dead_code)]
     pub fn set_theme(&mut self, theme: Theme) {
+        self.writer.set_restore(theme.backend.restore());
         self.theme = theme;
     }
 
@@ -66,16 +313,117 @@ impl<W: Write> Renderer<W> {
         &self.theme
     }
 
+    /// Statistics of the content rendered so far.
+    pub fn summary(&self) -> &RenderSummary {
+        &self.summary
+    }
+
+    /// Get the code block highlighter, e.g. to register additional syntax
+    /// definitions.
+    pub fn highlighter_mut(&mut self) -> &mut CodeHighlighter {
+        &mut self.highlighter
+    }
+
+    /// Immediately write the terminal restore sequence to the writer.
+    ///
+    /// Use this when aborting a stream, e.g. on Ctrl-C, so active styles and
+    /// open hyperlinks do not leak into the shell prompt.
+    pub fn emergency_reset(&mut self) -> io::Result<()> {
+        self.writer.restore()
+    }
+
+    /// Write queued output until the writer would block, when
+    /// `output_queue` is set. `Pending` means output is left in the queue,
+    /// so call again once the writer is ready.
+    pub fn poll_flush(&mut self) -> Poll<io::Result<()>> {
+        self.writer.poll_flush()
+    }
+
+    /// Write all queued output, waiting while the writer would block.
+    pub fn drain(&mut self) -> io::Result<()> {
+        self.writer.drain()
+    }
+
+    /// Number of bytes rendered but not yet accepted by the writer.
+    pub fn queued_bytes(&self) -> usize {
+        self.writer.queued_bytes()
+    }
+
+    /// Output skipped so far because the output queue was backed up.
+    pub fn degradation(&self) -> Degradation {
+        self.degradation
+    }
+
+    /// Whether optional output should be skipped to let the writer catch up.
+    fn backed_up(&self) -> bool {
+        self.options.queue_policy != QueuePolicy::Block
+            && self
+                .options
+                .output_queue
+                .is_some_and(|capacity| self.writer.queued_bytes() * 2 >= capacity)
+    }
+
+    /// Whether inline events should be dropped to let the writer catch up.
+    fn queue_full(&self) -> bool {
+        self.options.queue_policy == QueuePolicy::Drop
+            && self
+                .options
+                .output_queue
+                .is_some_and(|capacity| self.writer.queued_bytes() * 4 >= capacity * 3)
+    }
+
+    /// Write the text held back while the queue was backed up.
+    fn write_pending_text(&mut self) -> io::Result<()> {
+        if self.pending_text.is_empty() {
+            return Ok(());
+        }
+        let text = std::mem::take(&mut self.pending_text);
+        self.write(&self.styler().text(&text))
+    }
+
+    /// Get mutable access to the underlying writer.
+    pub(crate) fn writer_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+
+    /// Take the output written to an in-memory writer so far, including any
+    /// still queued, leaving an empty one in its place.
+    pub(crate) fn take_output(&mut self) -> io::Result<W>
+    where
+        W: Default,
+    {
+        self.writer.drain()?;
+        Ok(std::mem::take(self.writer.get_mut()))
+    }
+
+    /// Flush the writer. Output a non-blocking writer did not take yet
+    /// stays queued for `poll_flush` and `drain` rather than failing the
+    /// event that wrote it.
+    fn flush_writer(&mut self) -> io::Result<()> {
+        match self.writer.poll_flush() {
+            Poll::Ready(result) => result,
+            Poll::Pending => Ok(()),
+        }
+    }
+
     /// Calculate the left margin based on blockquote depth.
     fn left_margin(&self) -> String {
+        let indent = " ".repeat(self.list_indent);
         if self.in_blockquote {
-            let border = self.theme.blockquote_border.apply("│").to_string();
-            format!("{} ", border).repeat(self.blockquote_depth)
+            let glyph = &self.glyphs().blockquote_border;
+            let border = self.theme.paint(&self.theme.blockquote_border, glyph);
+            format!("{}{}", format!("{} ", border).repeat(self.blockquote_depth), indent)
         } else {
-            String::new()
+            indent
         }
     }
 
+    /// Columns `text` takes in the terminal, measuring ambiguous-width
+    /// characters as the options say.
+    fn columns(&self, text: &str) -> usize {
+        visible_length_with(text, self.options.ambiguous_wide)
+    }
+
     /// Calculate the current available width.
     fn current_width(&self) -> usize {
         let margin_width = if self.in_blockquote {
@@ -83,57 +431,902 @@ impl<W: Write> Renderer<W> {
         } else {
             0
         };
-        self.width.saturating_sub(margin_width)
+        self.width.saturating_sub(margin_width + self.list_indent)
+    }
+
+    /// Width prose of `width` columns is wrapped to under the wrap mode.
+    fn prose_width(&self, width: usize) -> usize {
+        match self.options.wrap {
+            WrapMode::Words => width,
+            WrapMode::None => NO_WRAP,
+        }
+    }
+
+    /// Record a file extension mentioned in prose, used to guess the
+    /// language of a following code block whose fence names none.
+    pub fn set_language_hint(&mut self, extension: &str) {
+        self.language_hint = Some(extension.to_string());
+    }
+
+    /// Record the number written in the source for the next ordered list
+    /// item, so lists can start at numbers other than 1.
+    pub fn set_list_start(&mut self, number: usize) {
+        self.list_state.set_start_hint(number);
+    }
+
+    /// Mark the following events as continuation content of the current
+    /// list item, such as a second paragraph or a nested code block.
+    ///
+    /// Continuation content is indented under the item's text and does not
+    /// end the list. The parser does not report indentation, so callers that
+    /// see the source lines set this for indented lines following an item.
+    pub fn set_list_continuation(&mut self, continuation: bool) {
+        self.list_indent = match self.list_state.content_column() {
+            Some(column) if continuation => column,
+            _ => 0,
+        };
     }
 
     fn write(&mut self, s: &str) -> io::Result<()> {
+        self.lines_written += s.matches('\n').count();
+        match s.rfind('\n') {
+            Some(pos) => self.column = self.columns(&s[pos + 1..]),
+            None => self.column += self.columns(s),
+        }
         write!(self.writer, "{}", s)
     }
 
     fn writeln(&mut self, s: &str) -> io::Result<()> {
-        writeln!(self.writer, "{}", s)?;
+        let end_column = match s.rfind('\n') {
+            Some(pos) => self.columns(&s[pos + 1..]),
+            None => self.column + self.columns(s),
+        };
+        let stamp = self.take_stamp(end_column);
+        let end = if self.options.selection_safe {
+            self.theme.backend.end_line()
+        } else {
+            ""
+        };
+        writeln!(self.writer, "{}{}{}", s, end, stamp)?;
+        self.lines_written += s.matches('\n').count() + 1;
         self.column = 0;
         Ok(())
     }
 
+    /// Record the arrival time of the first event of a block.
+    fn arm_gutter(&mut self, event: &ParseEvent) {
+        if !self.options.timestamp_gutter {
+            return;
+        }
+        match event {
+            ParseEvent::EmptyLine | ParseEvent::Newline => {}
+            _ if self.gutter_armed => {
+                self.gutter_armed = false;
+                let elapsed = (self.clock)();
+                self.pending_stamp = Some(format_elapsed(elapsed, &self.options.number_locale));
+            }
+            _ => {}
+        }
+        if ends_block(event) {
+            self.gutter_armed = true;
+        }
+    }
+
+    /// Right-align the pending timestamp after a line ending at `end_column`.
+    ///
+    /// The stamp stays pending until a non-blank line has room for it.
+    fn take_stamp(&mut self, end_column: usize) -> String {
+        let stamp_width = match &self.pending_stamp {
+            Some(stamp) => self.columns(stamp),
+            None => return String::new(),
+        };
+        if end_column == 0 || end_column + stamp_width >= self.width {
+            return String::new();
+        }
+        let stamp = self.pending_stamp.take().unwrap_or_default();
+        let padding = " ".repeat(self.width.saturating_sub(end_column + stamp_width));
+        format!("{}{}", padding, self.theme.paint(&self.theme.timestamp, &stamp))
+    }
+
     fn flush_table(&mut self) -> io::Result<()> {
+        self.erase_partial()?;
         if self.table_rows.is_empty() {
             return Ok(());
         }
         let rows = std::mem::take(&mut self.table_rows);
+        let alignments = std::mem::take(&mut self.table_alignments);
+        let start = self.lines_written;
+        self.draw_table(&rows, alignments.clone())?;
+        self.last_table = Some(DrawnTable {
+            rows,
+            alignments,
+            lines: start..self.lines_written,
+        });
+        Ok(())
+    }
+
+    fn draw_table(
+        &mut self,
+        rows: &[Vec<String>],
+        mut alignments: Vec<ColumnAlignment>,
+    ) -> io::Result<()> {
+        if self.options.align_numeric_columns {
+            let numeric = detect_numeric_columns(rows);
+            alignments.resize(alignments.len().max(numeric.len()), ColumnAlignment::Left);
+            for (alignment, numeric) in alignments.iter_mut().zip(numeric) {
+                if numeric && *alignment == ColumnAlignment::Left {
+                    *alignment = ColumnAlignment::Decimal;
+                }
+            }
+        }
         let margin = self.left_margin();
-        let lines = render_table(&rows, &margin, &self.theme, self.width);
+        let lines = if self.compact() {
+            render_table_borderless(rows, &alignments, &margin, &self.styler(), self.width)
+        } else {
+            render_table_aligned(rows, &alignments, &margin, &self.styler(), self.width)
+        };
         for line in lines {
             self.writeln(&line)?;
         }
         Ok(())
     }
 
-    /// Check if this event should reset a pending list.
-    /// List continues only for ListItem, ListEnd, and EmptyLine/Newline events.
-    fn should_reset_list(event: &ParseEvent) -> bool {
-        !matches!(
-            event,
-            ParseEvent::ListItem { .. }
-                | ParseEvent::ListEnd
-                | ParseEvent::EmptyLine
-                | ParseEvent::Newline
+    /// Draw the most recent table row with the progressive layout,
+    /// estimating the layout from the header on the first row.
+    fn draw_table_row(&mut self, redraw: bool) -> io::Result<()> {
+        let Some(row) = self.table_rows.last() else {
+            return Ok(());
+        };
+        let layout = self.table_layout.take();
+        let header = layout.is_none();
+        let margin = self.left_margin();
+        let styler = self.styler();
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| render_inline_content(cell, &styler))
+            .collect();
+        let wide = self.options.ambiguous_wide;
+        let layout = layout.unwrap_or_else(|| {
+            TableLayout::estimate(&cells, self.columns(&margin), self.width, wide)
+        });
+        let mut lines = Vec::new();
+        if header {
+            lines.push(layout.top(&margin, &styler));
+        } else {
+            lines.push(layout.separator(&margin, &styler));
+        }
+        lines.extend(layout.row(&cells, &self.table_alignments, header, &margin, &styler));
+        self.table_layout = Some(layout);
+        for line in &lines {
+            self.writeln(line)?;
+        }
+        if redraw {
+            self.partial_lines += lines.len();
+        }
+        Ok(())
+    }
+
+    /// Complete a progressively drawn table, either closing it with the
+    /// estimated layout or redrawing it with the fitted one.
+    fn finish_progressive_table(&mut self, redraw: bool) -> io::Result<()> {
+        let layout = self.table_layout.take();
+        if redraw && self.theme.backend.can_erase() {
+            return self.flush_table();
+        }
+        self.partial_lines = 0;
+        self.table_rows.clear();
+        self.table_alignments.clear();
+        if let Some(layout) = layout {
+            let margin = self.left_margin();
+            let bottom = layout.bottom(&margin, &self.theme);
+            self.writeln(&bottom)?;
+        }
+        Ok(())
+    }
+
+    /// Check whether this event is a blank line beyond `max_blank_lines`.
+    fn suppress_blank(&mut self, event: &ParseEvent) -> bool {
+        let Some(max) = self.options.max_blank_lines else {
+            return false;
+        };
+        let blank = match event {
+            ParseEvent::EmptyLine => true,
+            ParseEvent::Newline => self.column == 0,
+            _ => false,
+        };
+        if !blank {
+            self.blank_run = 0;
+            return false;
+        }
+        self.blank_run += 1;
+        self.blank_run > max
+    }
+
+    /// Enforce the configured blank lines between blocks.
+    ///
+    /// Blank lines from the source are swallowed and the policy's count is
+    /// emitted when the next block starts. Returns true if the event was
+    /// consumed.
+    fn normalize_spacing(&mut self, event: &ParseEvent) -> io::Result<bool> {
+        let Some(policy) = self.options.blank_lines else {
+            return Ok(false);
+        };
+        let kind = match event {
+            ParseEvent::EmptyLine => {
+                self.block_broken = true;
+                return Ok(true);
+            }
+            ParseEvent::Newline if self.column == 0 => {
+                self.block_broken = true;
+                return Ok(true);
+            }
+            ParseEvent::Newline
+            | ParseEvent::CodeBlockLine(_)
+            | ParseEvent::CodeBlockEnd
+            | ParseEvent::ListEnd
+            | ParseEvent::TableRow(_)
+            | ParseEvent::TableSeparator
+            | ParseEvent::TableEnd
+            | ParseEvent::BlockquoteLine(_)
+            | ParseEvent::BlockquoteEnd
+            | ParseEvent::ThinkBlockLine(_)
+            | ParseEvent::ThinkBlockEnd => return Ok(false),
+            ParseEvent::Heading { .. } => BlockKind::Heading,
+            ParseEvent::CodeBlockStart { .. } => BlockKind::Code,
+            ParseEvent::ListItem { .. } => BlockKind::List,
+            ParseEvent::TableHeader(_) => BlockKind::Table,
+            ParseEvent::BlockquoteStart { .. } => BlockKind::Blockquote,
+            ParseEvent::ThinkBlockStart => BlockKind::Think,
+            ParseEvent::HorizontalRule => BlockKind::Rule,
+            _ => BlockKind::Paragraph,
+        };
+
+        let continues = match kind {
+            BlockKind::List => self.current_block == Some(BlockKind::List),
+            BlockKind::Paragraph => {
+                self.current_block == Some(BlockKind::Paragraph)
+                    && (self.column > 0 || !self.block_broken)
+            }
+            _ => false,
+        };
+        if !continues {
+            // A file mentioned before an earlier block names none of this one
+            if kind != BlockKind::Code {
+                self.language_hint = None;
+            }
+            if let Some(previous) = self.current_block {
+                let blanks = if kind == BlockKind::Heading {
+                    policy.before_heading
+                } else if previous == BlockKind::Heading {
+                    policy.after_heading
+                } else {
+                    policy.between_blocks
+                };
+                for _ in 0..blanks {
+                    self.writeln("")?;
+                }
+            }
+            self.current_block = Some(kind);
+        }
+        self.block_broken = false;
+        Ok(false)
+    }
+
+    /// Whether code block contents are printed verbatim.
+    fn raw_code(&self) -> bool {
+        self.options.code_mode == CodeRenderMode::Raw
+    }
+
+    /// Line-number gutters for the current code line and its wrapped
+    /// continuation lines.
+    fn code_gutter(&self) -> (String, String) {
+        let digits = self.code_line_number.to_string().len().max(3);
+        let vertical = &self.glyphs().table_vertical;
+        let number = format!("{:>width$} {} ", self.code_line_number, vertical, width = digits);
+        let blank = format!("{} {} ", " ".repeat(digits), vertical);
+        (
+            self.theme.paint(&self.theme.code_gutter, &number),
+            self.theme.paint(&self.theme.code_gutter, &blank),
+        )
+    }
+
+    /// Top border of a code frame, with the language in the border.
+    fn code_frame_top(&self) -> String {
+        let margin = self.left_margin();
+        let inner = self.current_width().saturating_sub(2);
+        let frame = &self.theme.code_frame;
+        let glyphs = self.glyphs();
+        let [left, right, ..] = &glyphs.frame_corners;
+        let horizontal = &glyphs.horizontal;
+        match self.current_language.as_deref() {
+            Some(lang) if !lang.is_empty() && self.columns(lang) + 3 <= inner => {
+                let label = format!(" {} ", lang);
+                let rest = inner - 1 - self.columns(&label);
+                format!(
+                    "{}{}{}{}",
+                    margin,
+                    self.theme.paint(frame, &format!("{}{}", left, horizontal)),
+                    self.theme.paint(&self.theme.code_block_lang, &label),
+                    self.theme.paint(frame, &format!("{}{}", horizontal.repeat(rest), right))
+                )
+            }
+            _ => format!(
+                "{}{}",
+                margin,
+                self.theme.paint(frame, &format!("{}{}{}", left, horizontal.repeat(inner), right))
+            ),
+        }
+    }
+
+    /// Bottom border of a code frame.
+    fn code_frame_bottom(&self) -> String {
+        let inner = self.current_width().saturating_sub(2);
+        let glyphs = self.glyphs();
+        let [.., left, right] = &glyphs.frame_corners;
+        let bottom = format!("{}{}{}", left, glyphs.horizontal.repeat(inner), right);
+        format!(
+            "{}{}",
+            self.left_margin(),
+            self.theme.paint(&self.theme.code_frame, &bottom)
         )
     }
 
+    /// Dimmed line giving the size and language of a finished code block.
+    fn code_stats_footer(&self, stats: &CodeBlockStats) -> String {
+        let locale = &self.options.number_locale;
+        let labels = &self.options.labels;
+        let mut footer = format!(
+            "{} {} · {} {}",
+            locale.format_int(stats.lines),
+            labels.lines,
+            locale.format_int(stats.chars),
+            labels.chars
+        );
+        if let Some(language) = &stats.language {
+            footer.push_str(" · ");
+            footer.push_str(language);
+            if stats.confidence < 1.0 {
+                let percent = (stats.confidence * 100.0).round() as usize;
+                footer.push_str(&format!(" ({}%)", percent));
+            }
+        }
+        format!("{}{}", self.left_margin(), self.theme.dimmed(&footer))
+    }
+
+    /// Pad a framed code line and append the right border.
+    fn close_frame_line(&self, line: &str, margin: &str) -> String {
+        let target = self.columns(margin) + self.current_width().saturating_sub(2);
+        let padding = target.saturating_sub(self.columns(line));
+        let reset = if self.options.selection_safe {
+            self.theme.backend.reset()
+        } else {
+            ""
+        };
+        format!(
+            "{}{}{} {}",
+            line,
+            reset,
+            " ".repeat(padding),
+            self.theme.paint(&self.theme.code_frame, &self.glyphs().table_vertical)
+        )
+    }
+
+    /// Number of complete lines written so far.
+    pub fn lines_written(&self) -> usize {
+        self.lines_written
+    }
+
+    /// Erase the last `count` complete lines and any partial line after
+    /// them, leaving the cursor where the first erased line began, so
+    /// revised content can be rendered in their place. Ordered lists number
+    /// from their start again, and a table being drawn is drawn anew.
+    ///
+    /// Returns `false`, writing nothing, if the backend cannot erase.
+    pub fn retract_lines(&mut self, count: usize) -> io::Result<bool> {
+        self.hide_cursor()?;
+        self.last_table = None;
+        let count = count.min(self.lines_written);
+        let erase = match (count, self.column) {
+            (0, 0) => Some(String::new()),
+            (0, column) => self.theme.backend.erase_columns(column),
+            (count, _) => self.theme.backend.erase_lines(count),
+        };
+        let Some(erase) = erase else {
+            return Ok(false);
+        };
+        write!(self.writer, "{}", erase)?;
+        self.lines_written -= count;
+        self.column = 0;
+        self.reset_numbering_and_layout();
+        self.flush_writer()?;
+        Ok(true)
+    }
+
+    /// Restart list numbering and forget the layout of the table being
+    /// drawn, both of which may describe erased output. The rows of an
+    /// unfinished table are kept and drawn afresh with its next row.
+    fn reset_numbering_and_layout(&mut self) {
+        self.table_layout = None;
+        self.list_state = ListState::default();
+        self.list_state.set_locale(self.options.number_locale.clone());
+        self.list_state.set_numbering(self.options.ordered_numbering.clone());
+        self.list_indent = 0;
+    }
+
+    /// Forget the blocks in progress, such as an open list, table or code
+    /// block, so the next event starts afresh as at the start of a document.
+    pub(crate) fn reset_blocks(&mut self) {
+        self.partial_lines = 0;
+        self.table_rows.clear();
+        self.table_alignments.clear();
+        self.last_table = None;
+        self.reset_numbering_and_layout();
+        self.in_blockquote = false;
+        self.blockquote_depth = 0;
+        self.current_language = None;
+        self.language_detected = false;
+        self.code_buffer.clear();
+        self.code_line_number = 0;
+        self.in_docstring = false;
+        self.plain_code = false;
+        self.fenced = false;
+        self.think_started = None;
+        self.current_block = None;
+        self.block_broken = false;
+        self.blank_run = 0;
+        self.gutter_armed = true;
+        self.pending_stamp = None;
+    }
+
+    /// Measure text the way `calibration` found the terminal draws it.
+    ///
+    /// If this changes how widths are measured and the most recent output
+    /// is a table with non-ASCII cells, such as emoji, the table is erased
+    /// and drawn again so that its borders line up. Returns whether it was
+    /// redrawn.
+    pub fn apply_calibration(&mut self, calibration: &Calibration) -> io::Result<bool> {
+        let mut options = self.options.clone();
+        calibration.apply(&mut options);
+        let changed = options.ambiguous_wide != self.options.ambiguous_wide
+            || options.emoji_normalization != self.options.emoji_normalization;
+        self.set_options(options);
+        let Some(table) = self.last_table.take() else {
+            return Ok(false);
+        };
+        let last = table.lines.end == self.lines_written && self.column == 0;
+        let affected = table.rows.iter().flatten().any(|cell| !cell.is_ascii());
+        if !(changed && last && affected && self.theme.backend.can_erase()) {
+            self.last_table = Some(table);
+            return Ok(false);
+        }
+        self.retract_lines(table.lines.len())?;
+        let mode = self.emoji_normalization();
+        let rows: Vec<Vec<String>> = table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| normalize_emoji(cell, mode)).collect())
+            .collect();
+        let start = self.lines_written;
+        self.draw_table(&rows, table.alignments.clone())?;
+        self.last_table = Some(DrawnTable {
+            rows,
+            alignments: table.alignments,
+            lines: start..self.lines_written,
+        });
+        self.flush_writer()?;
+        Ok(true)
+    }
+
+    /// Keep the bottom `lines` rows of the terminal out of the scroll
+    /// region, so a prompt or status line the host draws there is never
+    /// overwritten by streamed output. `0` releases the rows again.
+    ///
+    /// When more rows are reserved at the start of a line, the output
+    /// scrolls up to clear them. Call again after the terminal is resized.
+    /// `finish` releases the rows. Returns `false`, writing nothing, if the
+    /// backend has no scroll region.
+    pub fn reserve_bottom_lines(&mut self, lines: usize) -> io::Result<bool>
+    where
+        W: TerminalHandle,
+    {
+        let height = terminal_height_of(self.writer.get_ref());
+        self.reserve_bottom_lines_in(lines, height)
+    }
+
+    /// Number of bottom rows kept out of the scroll region.
+    pub fn reserved_bottom_lines(&self) -> usize {
+        self.reserved_lines
+    }
+
+    /// Like [`reserve_bottom_lines`](Self::reserve_bottom_lines), for a
+    /// terminal `height` rows tall, e.g. when the writer is a pipe to it.
+    pub fn reserve_bottom_lines_in(&mut self, lines: usize, height: usize) -> io::Result<bool> {
+        let bottom = (lines > 0).then(|| height.saturating_sub(lines).max(1));
+        let Some(region) = self.theme.backend.scroll_region(bottom) else {
+            return Ok(false);
+        };
+        let added = lines.saturating_sub(self.reserved_lines);
+        if added > 0 && self.column == 0 {
+            if let Some(erase) = self.theme.backend.erase_lines(added) {
+                write!(self.writer, "{}{}", "\n".repeat(added), erase)?;
+            }
+        }
+        write!(self.writer, "{}", region)?;
+        self.writer.set_persistent(lines > 0);
+        self.reserved_lines = lines;
+        self.terminal_height = height;
+        self.draw_breadcrumb()?;
+        self.flush_writer()?;
+        Ok(true)
+    }
+
+    /// Headings enclosing the latest output, outermost first, as plain text.
+    pub fn breadcrumb(&self) -> Vec<&str> {
+        self.headings.iter().map(|(_, text)| text.as_str()).collect()
+    }
+
+    /// Make a heading the innermost one of the breadcrumb, replacing those
+    /// of the same or a deeper level.
+    fn enter_heading(&mut self, level: u8, content: &str) -> io::Result<()> {
+        self.headings.retain(|&(enclosing, _)| enclosing < level);
+        let text = strip_ansi(&render_inline_content(content, &self.styler()));
+        self.headings.push((level, text));
+        self.draw_breadcrumb()
+    }
+
+    /// Draw the breadcrumb in the last reserved row, with `breadcrumbs`.
+    fn draw_breadcrumb(&mut self) -> io::Result<()> {
+        if !self.options.breadcrumbs || self.reserved_lines == 0 {
+            return Ok(());
+        }
+        let trail = self.breadcrumb().join(&self.glyphs().breadcrumb_separator);
+        // Writing past the last column would scroll the terminal
+        let trail = wrap(&trail, self.width, self.options.ambiguous_wide)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let row = self.theme.dimmed(&trail);
+        match self.theme.backend.write_row(self.terminal_height, &row) {
+            Some(sequence) => write!(self.writer, "{}", sequence),
+            None => Ok(()),
+        }
+    }
+
+    /// Erase the provisional lines drawn by `render_partial`.
+    fn erase_partial(&mut self) -> io::Result<()> {
+        if self.partial_lines == 0 {
+            return Ok(());
+        }
+        if let Some(erase) = self.theme.backend.erase_lines(self.partial_lines) {
+            write!(self.writer, "{}", erase)?;
+            self.lines_written = self.lines_written.saturating_sub(self.partial_lines);
+            self.column = 0;
+        }
+        self.partial_lines = 0;
+        Ok(())
+    }
+
+    /// Render an event, drawing a provisional view of an in-progress table.
+    ///
+    /// Tables are normally buffered until `TableEnd`. With this method each
+    /// header or row redraws the table laid out from the rows seen so far;
+    /// the provisional lines are erased and replaced when the table completes
+    /// or [`commit`](Self::commit) is called. Backends that cannot erase
+    /// lines fall back to [`render_event`](Self::render_event).
+    pub fn render_partial(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.hide_cursor()?;
+        self.render_event(event)?;
+        let is_row = matches!(event, ParseEvent::TableHeader(_) | ParseEvent::TableRow(_));
+        if !is_row || !self.theme.backend.can_erase() {
+            return Ok(());
+        }
+        if self.backed_up() {
+            self.degradation.skipped_redraws += 1;
+            return Ok(());
+        }
+
+        self.draw_partial_table()?;
+        self.flush_writer()
+    }
+
+    /// Replace the provisional lines with the buffered table laid out from
+    /// the rows seen so far.
+    fn draw_partial_table(&mut self) -> io::Result<()> {
+        self.erase_partial()?;
+        let margin = self.left_margin();
+        let lines = render_table_aligned(
+            &self.table_rows,
+            &self.table_alignments,
+            &margin,
+            &self.styler(),
+            self.width,
+        );
+        // The lines are replaced later, so the final layout keeps the stamp
+        let stamp = self.pending_stamp.clone();
+        for line in &lines {
+            self.writeln(line)?;
+        }
+        self.pending_stamp = stamp;
+        self.partial_lines = lines.len();
+        Ok(())
+    }
+
+    /// Set the column alignments of the table currently being buffered.
+    ///
+    /// `TableSeparator` carries no markers, so callers that see the source
+    /// separator row pass its parsed alignments here.
+    pub fn set_table_alignments(&mut self, alignments: Vec<ColumnAlignment>) {
+        self.table_alignments = alignments;
+    }
+
+    /// Render the result of running a command.
+    ///
+    /// Stdout and stderr lines are drawn behind distinct borders at the
+    /// current margin, so the block lines up inside quotes and list items,
+    /// followed by a badge with the exit code.
+    pub fn render_command_output(
+        &mut self,
+        stdout: &str,
+        stderr: &str,
+        exit_code: i32,
+    ) -> io::Result<()> {
+        self.hide_cursor()?;
+        if self.column > 0 {
+            self.writeln("")?;
+        }
+        let margin = self.left_margin();
+        let width = self.current_width().saturating_sub(2);
+        let (theme, glyphs) = (&self.theme, self.glyphs());
+        let stdout_border = theme.paint(&theme.output_border, &glyphs.output_border);
+        let stderr_border = theme.paint(&theme.stderr_border, &glyphs.stderr_border);
+        let streams = [(stdout, stdout_border), (stderr, stderr_border)];
+        for (text, border) in streams {
+            for line in text.lines() {
+                let wrapped = simple_wrap(line, width);
+                if wrapped.is_empty() {
+                    self.writeln(&format!("{}{}", margin, border))?;
+                }
+                for part in wrapped {
+                    self.writeln(&format!("{}{} {}", margin, border, part))?;
+                }
+            }
+        }
+        let kind = if exit_code == 0 {
+            BadgeKind::Success
+        } else {
+            BadgeKind::Failure
+        };
+        let (options, magnitude) = (&self.options, exit_code.unsigned_abs() as usize);
+        let code = options.number_locale.format_int(magnitude);
+        let sign = if exit_code < 0 { "-" } else { "" };
+        let label = format!("{} {}{}", options.labels.exit, sign, code);
+        let badge = self.theme.badge(&label, kind);
+        self.writeln(&format!("{}{}", margin, badge))?;
+        self.flush_writer()
+    }
+
+    /// Let `handler` decide how `Image` events are rendered.
+    ///
+    /// Returning [`ImageAction::Placeholder`] keeps the default behavior.
+    pub fn set_image_handler(&mut self, handler: ImageHandler) {
+        self.image_handler = Some(handler);
+    }
+
+    /// Route fenced code blocks of `language` to `handler` instead of
+    /// highlighting them. The block is buffered until its closing fence.
+    pub fn set_fence_handler(&mut self, language: &str, handler: FenceHandler) {
+        self.fence_handlers.insert(language.to_string(), handler);
+    }
+
+    /// Replace the clock used for timestamps and think durations.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Escape sequence drawing the image at `url` inline, if an image
+    /// protocol is configured and the image can be loaded and displayed.
+    #[cfg(feature = "images")]
+    fn inline_image(&self, url: &str) -> Option<String> {
+        let protocol = self.options.image_protocol?;
+        if !self.theme.backend.can_draw_images() {
+            return None;
+        }
+        let local = || crate::image::load_local(url, self.options.image_dir.as_deref()?);
+        let bytes = local().or_else(|| self.options.image_cache.as_ref()?.load(url))?;
+        protocol.encode(&bytes)
+    }
+
+    #[cfg(not(feature = "images"))]
+    fn inline_image(&self, _url: &str) -> Option<String> {
+        None
+    }
+
+    /// Draw the typing cursor after the output, if enabled.
+    ///
+    /// The cursor is erased before anything else is written, so callers
+    /// show it again after each batch of events while the stream is active.
+    pub fn show_cursor(&mut self) -> io::Result<()> {
+        if !self.backed_up() {
+            self.write_pending_text()?;
+        }
+        if !self.options.typing_cursor || self.cursor_shown || self.column + 1 >= self.width {
+            return Ok(());
+        }
+        if self.theme.backend.erase_columns(1).is_none() {
+            return Ok(());
+        }
+        if self.backed_up() {
+            self.degradation.skipped_cursors += 1;
+            return Ok(());
+        }
+        let cursor = self.glyphs().cursor.clone();
+        write!(self.writer, "{}", cursor)?;
+        self.cursor_shown = true;
+        self.flush_writer()
+    }
+
+    /// Erase the typing cursor if it is drawn.
+    pub fn hide_cursor(&mut self) -> io::Result<()> {
+        if !self.cursor_shown {
+            return Ok(());
+        }
+        self.cursor_shown = false;
+        if let Some(erase) = self.theme.backend.erase_columns(1) {
+            write!(self.writer, "{}", erase)?;
+        }
+        Ok(())
+    }
+
+    /// Styler for inline content, numbering links if `numbered_links` is set.
+    fn styler(&self) -> LinkStyler<'_> {
+        let compact = self.compact();
+        link_styler(&self.theme, &self.options, &self.link_references, compact)
+    }
+
+    /// Bullet, border and rule characters: the theme's, or ASCII ones if
+    /// the terminal profile lacks unicode or ambiguous-width characters,
+    /// box drawing among them, take two columns.
+    fn glyphs(&self) -> &Glyphs {
+        if self.options.profile.unicode && !self.options.ambiguous_wide {
+            &self.theme.glyphs
+        } else {
+            Glyphs::shared(false)
+        }
+    }
+
+    /// How emoji sequences are rewritten. A terminal without emoji draws
+    /// joined sequences as their components, so they are split to match.
+    fn emoji_normalization(&self) -> EmojiNormalization {
+        if self.options.profile.emoji {
+            self.options.emoji_normalization
+        } else {
+            EmojiNormalization::SplitJoined
+        }
+    }
+
+    /// Whether the output is narrow enough to elide low-priority content.
+    fn compact(&self) -> bool {
+        self.options
+            .compact_below
+            .is_some_and(|width| self.width < width)
+    }
+
+    /// How think blocks are shown, hiding them in compact output.
+    fn think_mode(&self) -> ThinkMode {
+        if self.compact() {
+            ThinkMode::Hidden
+        } else {
+            self.options.think_mode
+        }
+    }
+
+    /// Write the reference list of links numbered so far and start a new
+    /// numbering. Does nothing if no links were numbered.
+    pub fn write_link_references(&mut self) -> io::Result<()> {
+        let references = self.link_references.take();
+        if references.is_empty() {
+            return Ok(());
+        }
+        if self.column > 0 {
+            self.writeln("")?;
+        }
+        self.writeln("")?;
+        for line in render_references(&self.theme, &references, &self.options.number_locale) {
+            self.writeln(&line)?;
+        }
+        self.flush_writer()
+    }
+
+    /// Complete the document: draw any buffered table and the link
+    /// reference list.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.hide_cursor()?;
+        self.write_pending_text()?;
+        self.flush_table()?;
+        self.write_link_references()?;
+        if self.reserved_lines > 0 {
+            self.reserve_bottom_lines_in(0, self.terminal_height)?;
+        }
+        self.writer.drain()?;
+        self.writer.flush()
+    }
+
+    /// Replace any provisional output with the final layout of the
+    /// in-progress block.
+    pub fn commit(&mut self) -> io::Result<()> {
+        self.hide_cursor()?;
+        self.write_pending_text()?;
+        self.flush_table()?;
+        self.flush_writer()
+    }
+
     /// Render a single parse event.
     pub fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        #[cfg(feature = "tracing")]
+        let span = self.tracer.span_for(event);
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        #[cfg(feature = "tracing")]
+        let started = Instant::now();
+
+        let normalized;
+        let event = match self.emoji_normalization() {
+            EmojiNormalization::Keep => event,
+            mode => {
+                normalized = normalize_event(event, mode);
+                &normalized
+            }
+        };
+        self.writer.arm();
+        let result = self.dispatch_event(event);
+        if result.is_ok() {
+            self.writer.disarm();
+        }
+
+        #[cfg(feature = "tracing")]
+        self.tracer.record(event, started.elapsed());
+        result
+    }
+
+    fn dispatch_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.hide_cursor()?;
+        if is_inline(event) && !matches!(event, ParseEvent::Prompt(_)) && self.queue_full() {
+            self.degradation.dropped_events += 1;
+            return self.flush_writer();
+        }
+        if !matches!(event, ParseEvent::Text(_)) {
+            self.write_pending_text()?;
+        }
+
         // Reset pending list if this event breaks the list context
-        if Self::should_reset_list(event) {
+        if matches!(event, ParseEvent::ListItem { .. }) {
+            self.list_indent = 0;
+        }
+        if !continues_list(event) && self.list_indent == 0 {
             self.list_state.reset();
         }
+        self.arm_gutter(event);
+        if self.suppress_blank(event) {
+            return self.flush_writer();
+        }
+        if self.normalize_spacing(event)? {
+            return self.flush_writer();
+        }
+        if self.list_indent > 0 && self.column == 0 && is_inline(event) {
+            self.write(&" ".repeat(self.list_indent))?;
+        }
 
         match event {
             // === Inline elements ===
+            ParseEvent::Text(text) if self.backed_up() => {
+                // Styled once as a single run when the held text is written
+                self.pending_text.push_str(text);
+                self.degradation.coalesced_texts += 1;
+            }
+
             ParseEvent::Text(text) => {
-                let styled = self.theme.text(text);
-                self.write(&styled)?;
-                self.column += styled.chars().count();
+                self.write_pending_text()?;
+                self.write(&self.styler().text(text))?;
             }
 
             ParseEvent::InlineCode(code) => {
@@ -141,35 +1334,52 @@ impl<W: Write> Renderer<W> {
             }
 
             ParseEvent::Bold(text) => {
-                self.write(&self.theme.bold(text))?;
+                self.write(&self.styler().bold(text))?;
             }
 
             ParseEvent::Italic(text) => {
-                self.write(&self.theme.italic(text))?;
+                self.write(&self.styler().italic(text))?;
             }
 
             ParseEvent::BoldItalic(text) => {
-                self.write(&self.theme.bold_italic(text))?;
+                self.write(&self.styler().bold_italic(text))?;
             }
 
             ParseEvent::Underline(text) => {
-                self.write(&self.theme.underline(text))?;
+                self.write(&self.styler().underline(text))?;
             }
 
             ParseEvent::Strikeout(text) => {
-                self.write(&self.theme.strikethrough(text))?;
+                self.write(&self.styler().strikethrough(text))?;
             }
 
             ParseEvent::Link { text, url } => {
-                self.write(&self.theme.link(text, url))?;
+                self.write(&self.styler().link(text, url))?;
             }
 
             ParseEvent::Image { alt, url } => {
-                self.write(&self.theme.image(alt, url))?;
+                let action = match &self.image_handler {
+                    Some(handler) => handler(alt, url),
+                    None => ImageAction::Placeholder,
+                };
+                match action {
+                    ImageAction::Replace(text) => self.write(&text)?,
+                    ImageAction::Skip => {}
+                    ImageAction::Placeholder if self.compact() => {}
+                    ImageAction::Placeholder => match self.inline_image(url) {
+                        Some(image) => {
+                            if self.column > 0 {
+                                self.writeln("")?;
+                            }
+                            self.writeln(&image)?;
+                        }
+                        None => self.write(&self.theme.image(alt, url))?,
+                    },
+                }
             }
 
             ParseEvent::Footnote(superscript) => {
-                self.write(&self.theme.footnote(superscript))?;
+                self.write(&self.styler().footnote(superscript))?;
             }
 
             ParseEvent::Prompt(prompt) => {
@@ -180,39 +1390,182 @@ impl<W: Write> Renderer<W> {
             ParseEvent::Heading { level, content } => {
                 let margin = self.left_margin();
                 let width = self.current_width();
-                let lines = render_heading(*level, content, width, &margin, &self.theme);
+                let lines = render_heading(
+                    self.options.heading_levels.apply(*level),
+                    content,
+                    width,
+                    &margin,
+                    &self.styler(),
+                    self.options.heading_markers,
+                    // Spacing is owned by the normalizer when it is on
+                    self.options.blank_lines.is_none(),
+                );
                 for line in lines {
                     self.writeln(&line)?;
                 }
+                self.enter_heading(*level, content)?;
             }
 
             ParseEvent::CodeBlockStart { language, .. } => {
                 self.current_language = language.clone();
+                self.language_detected = false;
                 self.code_buffer.clear();
+                self.code_line_number = 0;
+                self.in_docstring = false;
+                self.highlighter.start_block();
+                self.plain_code = false;
+                self.fenced = language
+                    .as_ref()
+                    .is_some_and(|language| self.fence_handlers.contains_key(language));
+                if self.options.code_frame && !self.raw_code() && !self.fenced {
+                    let top = self.code_frame_top();
+                    self.writeln(&top)?;
+                }
             }
 
             ParseEvent::CodeBlockLine(line) => {
+                let unlabeled = self.current_language.is_none() && self.code_buffer.is_empty();
+                if unlabeled && self.options.detect_code_language {
+                    let hint = self.language_hint.as_deref();
+                    self.current_language = detect_fence_language(line, hint);
+                    self.language_detected = self.current_language.is_some();
+                }
                 if !self.code_buffer.is_empty() {
                     self.code_buffer.push('\n');
                 }
                 self.code_buffer.push_str(line);
 
+                if self.fenced {
+                    return self.flush_writer();
+                }
+                if self.raw_code() {
+                    // Verbatim, without timestamp or line-end sequences
+                    self.write(&format!("{}\n", line))?;
+                    return self.flush_writer();
+                }
+
+                let expanded = match self.options.tab_width {
+                    Some(tab_width) => expand_tabs(line, tab_width),
+                    None => line.clone(),
+                };
+                let line = &expanded;
+                self.code_line_number += 1;
                 let margin = self.left_margin();
-                let width = self.current_width();
-                let rendered_lines = self.highlighter.render_code_line(
-                    line,
-                    self.current_language.as_deref(),
-                    &margin,
-                    width,
-                );
+                let frame = self.options.code_frame;
+                let frame_left = if frame {
+                    let side = format!("{} ", self.glyphs().table_vertical);
+                    self.theme.paint(&self.theme.code_frame, &side)
+                } else {
+                    String::new()
+                };
+                let (gutter_first, gutter_next) = if self.options.code_line_numbers {
+                    self.code_gutter()
+                } else {
+                    (String::new(), String::new())
+                };
+                let frame_width = if frame { 4 } else { 0 };
+                let width = self
+                    .current_width()
+                    .saturating_sub(frame_width + self.columns(&gutter_first));
+                let first_margin = format!("{}{}{}", margin, frame_left, gutter_first);
+                let next_margin = format!("{}{}{}", margin, frame_left, gutter_next);
+                let doc = if self.options.doc_comments {
+                    let language = self.current_language.as_deref();
+                    split_doc_comment(line, language, &mut self.in_docstring)
+                } else {
+                    None
+                };
+                if !self.plain_code && doc.is_none() && self.backed_up() {
+                    // Highlighting state would be stale, so the rest of the
+                    // block stays plain
+                    self.plain_code = true;
+                }
+                let rendered_lines = match doc {
+                    Some(doc) => {
+                        let marker = self.theme.paint(&self.theme.doc_comment, doc.prefix);
+                        let prose = format!(
+                            "{}{}",
+                            render_doc_prose(doc.prose, &self.theme),
+                            self.theme.paint(&self.theme.doc_comment, doc.suffix)
+                        );
+                        let first = format!("{}{}", first_margin, marker);
+                        let next = format!("{}{}", next_margin, marker);
+                        let wide = self.options.ambiguous_wide;
+                        wrap_prose(&prose, self.prose_width(width), &first, &next, wide)
+                    }
+                    None => {
+                        let language = self.current_language.as_deref();
+                        match diff_line_style(line, language, &self.theme) {
+                            Some(style) => render_styled_code_line(
+                                line,
+                                style,
+                                &first_margin,
+                                &next_margin,
+                                width,
+                                self.options.ambiguous_wide,
+                                &*self.theme.backend,
+                            ),
+                            None if self.plain_code => {
+                                self.degradation.unhighlighted_lines += 1;
+                                render_styled_code_line(
+                                    line,
+                                    &self.theme.code,
+                                    &first_margin,
+                                    &next_margin,
+                                    width,
+                                    self.options.ambiguous_wide,
+                                    &*self.theme.backend,
+                                )
+                            }
+                            None => self.highlighter.render_code_line(
+                                line,
+                                language,
+                                &first_margin,
+                                &next_margin,
+                                width,
+                                &*self.theme.backend,
+                            ),
+                        }
+                    }
+                };
                 for rendered in rendered_lines {
+                    let rendered = if frame {
+                        self.close_frame_line(&rendered, &margin)
+                    } else {
+                        rendered
+                    };
                     self.writeln(&rendered)?;
                 }
             }
 
             ParseEvent::CodeBlockEnd => {
+                let handler = self
+                    .current_language
+                    .as_ref()
+                    .filter(|_| self.fenced)
+                    .and_then(|language| self.fence_handlers.get(language));
+                if let Some(handler) = handler {
+                    let output = handler(&self.code_buffer);
+                    let margin = self.left_margin();
+                    for line in output.lines() {
+                        self.writeln(&format!("{}{}", margin, line))?;
+                    }
+                } else if self.options.code_frame && !self.raw_code() {
+                    let bottom = self.code_frame_bottom();
+                    self.writeln(&bottom)?;
+                }
+                let language = self.current_language.as_deref();
+                let language = language.filter(|_| !self.language_detected);
+                let stats = CodeBlockStats::new(&self.code_buffer, language);
+                if self.options.code_stats && !self.fenced && !self.raw_code() {
+                    let footer = self.code_stats_footer(&stats);
+                    self.writeln(&footer)?;
+                }
+                self.summary.code_blocks.push(stats);
                 self.current_language = None;
+                self.language_hint = None;
                 self.code_buffer.clear();
+                self.fenced = false;
             }
 
             ParseEvent::ListItem {
@@ -221,14 +1574,18 @@ impl<W: Write> Renderer<W> {
                 content,
             } => {
                 let margin = self.left_margin();
-                let width = self.current_width();
+                let width = self.prose_width(self.current_width());
+                let compact = self.compact();
+                // Borrow fields rather than `self`, which the list state needs mutably
+                let styler =
+                    link_styler(&self.theme, &self.options, &self.link_references, compact);
                 let lines = render_list_item(
                     *indent,
                     bullet,
                     content,
                     width,
                     &margin,
-                    &self.theme,
+                    &styler,
                     &mut self.list_state,
                 );
                 for line in lines {
@@ -243,13 +1600,22 @@ impl<W: Write> Renderer<W> {
 
             ParseEvent::TableHeader(cols) | ParseEvent::TableRow(cols) => {
                 self.table_rows.push(cols.clone());
+                if let TableMode::Progressive { redraw } = self.options.table_mode {
+                    // Compact tables are borderless, so they are only drawn buffered
+                    if !self.compact() {
+                        self.draw_table_row(redraw)?;
+                    }
+                }
             }
 
             ParseEvent::TableSeparator => {}
 
-            ParseEvent::TableEnd => {
-                self.flush_table()?;
-            }
+            ParseEvent::TableEnd => match self.options.table_mode {
+                TableMode::Progressive { redraw } if self.table_layout.is_some() => {
+                    self.finish_progressive_table(redraw)?;
+                }
+                _ => self.flush_table()?,
+            },
 
             ParseEvent::BlockquoteStart { depth } => {
                 self.in_blockquote = true;
@@ -260,14 +1626,11 @@ impl<W: Write> Renderer<W> {
                 let margin = self.left_margin();
                 let width = self.current_width();
                 // Parse inline formatting (bold, italic, etc.) in blockquote content
-                let rendered_content = render_inline_content(text, &self.theme);
-                let wrapped = text_wrap(&rendered_content, width, 0, &margin, &margin, false, true);
-                if wrapped.is_empty() {
-                    self.writeln(&margin)?;
-                } else {
-                    for line in wrapped.lines {
-                        self.writeln(&line)?;
-                    }
+                let rendered_content = render_inline_content(text, &self.styler());
+                let wide = self.options.ambiguous_wide;
+                let width = self.prose_width(width);
+                for line in wrap_prose(&rendered_content, width, &margin, &margin, wide) {
+                    self.writeln(&line)?;
                 }
             }
 
@@ -276,37 +1639,1413 @@ impl<W: Write> Renderer<W> {
                 self.blockquote_depth = 0;
             }
 
-            ParseEvent::ThinkBlockStart => {
-                self.writeln(&self.theme.think_border.apply("┌─ thinking ─").to_string())?;
-                self.in_blockquote = true;
-                self.blockquote_depth = 1;
-            }
+            ParseEvent::ThinkBlockStart => match self.think_mode() {
+                ThinkMode::Full => {
+                    let glyphs = self.glyphs();
+                    let (corner, line) = (&glyphs.think_top, &glyphs.horizontal);
+                    let label = &self.options.labels.thinking;
+                    let title = format!("{}{} {} {}", corner, line, label, line);
+                    self.writeln(&self.theme.paint(&self.theme.think_border, &title))?;
+                    self.in_blockquote = true;
+                    self.blockquote_depth = 1;
+                    self.think_started = Some((self.clock)());
+                }
+                ThinkMode::Summary | ThinkMode::Hidden => self.think_hidden = 0,
+            },
 
-            ParseEvent::ThinkBlockLine(text) => {
-                let border = self.theme.think_border.apply("│").to_string();
-                self.writeln(&format!("{} {}", border, self.theme.think.apply(text)))?;
-            }
+            ParseEvent::ThinkBlockLine(text) => match self.think_mode() {
+                ThinkMode::Full => {
+                    let glyph = &self.glyphs().think_border;
+                    let border = self.theme.paint(&self.theme.think_border, glyph);
+                    let text = self.theme.paint(&self.theme.think, text);
+                    self.writeln(&format!("{} {}", border, text))?;
+                }
+                ThinkMode::Summary | ThinkMode::Hidden => self.think_hidden += text.len() + 1,
+            },
 
-            ParseEvent::ThinkBlockEnd => {
-                self.writeln(&self.theme.think_border.apply("└").to_string())?;
-                self.in_blockquote = false;
-                self.blockquote_depth = 0;
-            }
+            ParseEvent::ThinkBlockEnd => match self.think_mode() {
+                ThinkMode::Full => {
+                    let glyphs = self.glyphs();
+                    let (corner, line) = (glyphs.think_bottom.clone(), glyphs.horizontal.clone());
+                    let bottom = match self.think_started.take() {
+                        Some(started) if self.options.think_duration => {
+                            let elapsed = (self.clock)().saturating_sub(started);
+                            let tenths = elapsed.as_millis() / 100;
+                            let locale = &self.options.number_locale;
+                            let fraction = (tenths % 10).to_string();
+                            let seconds = locale.format_decimal((tenths / 10) as usize, &fraction);
+                            let label = &self.options.labels.thought_for;
+                            format!("{}{} {} {}s", corner, line, label, seconds)
+                        }
+                        _ => corner,
+                    };
+                    self.writeln(&self.theme.paint(&self.theme.think_border, &bottom))?;
+                    self.in_blockquote = false;
+                    self.blockquote_depth = 0;
+                }
+                ThinkMode::Summary => {
+                    let labels = &self.options.labels;
+                    let glyphs = self.glyphs();
+                    let (corner, line) = (&glyphs.think_top, &glyphs.horizontal);
+                    let title = format!(
+                        "{}{} {} ({} {}) {}",
+                        corner,
+                        line,
+                        labels.thinking,
+                        format_size(self.think_hidden, &self.options.number_locale, labels),
+                        labels.hidden,
+                        line
+                    );
+                    self.writeln(&self.theme.paint(&self.theme.think_border, &title))?;
+                }
+                ThinkMode::Hidden => {}
+            },
 
             ParseEvent::HorizontalRule => {
                 let margin = self.left_margin();
-                let rule = "─".repeat(self.current_width());
-                self.writeln(&format!("{}{}", margin, self.theme.hr.apply(&rule)))?;
+                let rule = self.glyphs().horizontal.repeat(self.current_width());
+                self.writeln(&format!("{}{}", margin, self.theme.paint(&self.theme.hr, &rule)))?;
             }
 
             ParseEvent::EmptyLine | ParseEvent::Newline => {
                 self.writeln("")?;
             }
             ParseEvent::InlineElements(elements) => {
-                self.write(&render_inline_elements(elements, &self.theme))?;
+                self.write(&render_inline_elements(elements, &self.styler()))?;
             }
         }
 
-        self.writer.flush()
+        self.flush_writer()
+    }
+}
+
+/// Check if this event is inline content written at the cursor.
+pub(crate) fn is_inline(event: &ParseEvent) -> bool {
+    matches!(
+        event,
+        ParseEvent::Text(_)
+            | ParseEvent::InlineCode(_)
+            | ParseEvent::Bold(_)
+            | ParseEvent::Italic(_)
+            | ParseEvent::BoldItalic(_)
+            | ParseEvent::Underline(_)
+            | ParseEvent::Strikeout(_)
+            | ParseEvent::Link { .. }
+            | ParseEvent::Image { .. }
+            | ParseEvent::Footnote(_)
+            | ParseEvent::Prompt(_)
+            | ParseEvent::InlineElements(_)
+    )
+}
+
+/// A table drawn in full, kept while it is the most recent output.
+struct DrawnTable {
+    rows: Vec<Vec<String>>,
+    alignments: Vec<ColumnAlignment>,
+    // Range of `lines_written` the table occupies
+    lines: Range<usize>,
+}
+
+/// Kinds of blocks distinguished by blank-line normalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Heading,
+    Paragraph,
+    Code,
+    List,
+    Table,
+    Blockquote,
+    Think,
+    Rule,
+}
+
+/// Check if this event closes a block, so the next event starts a new one.
+pub(crate) fn ends_block(event: &ParseEvent) -> bool {
+    matches!(
+        event,
+        ParseEvent::EmptyLine
+            | ParseEvent::Heading { .. }
+            | ParseEvent::CodeBlockEnd
+            | ParseEvent::ListEnd
+            | ParseEvent::TableEnd
+            | ParseEvent::BlockquoteEnd
+            | ParseEvent::ThinkBlockEnd
+            | ParseEvent::HorizontalRule
+    )
+}
+
+/// Format an elapsed duration as `mm:ss.d`.
+fn format_elapsed(elapsed: Duration, locale: &NumberLocale) -> String {
+    let tenths = elapsed.as_millis() / 100;
+    format!(
+        "{:02}:{:02}{}{}",
+        tenths / 600,
+        (tenths / 10) % 60,
+        locale.decimal_separator,
+        tenths % 10
+    )
+}
+
+impl<W: Write> EventRenderer for Renderer<W> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        Renderer::render_event(self, event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Renderer::finish(self)
+    }
+}
+
+/// Styler for inline content built from borrowed fields, so callers can
+/// hold other fields of the renderer mutably. Links are numbered into
+/// `references` if `numbered_links` is set, unless `compact` output hides
+/// their URLs.
+fn link_styler<'a>(
+    theme: &'a Theme,
+    options: &'a RendererOptions,
+    references: &'a RefCell<Vec<String>>,
+    compact: bool,
+) -> LinkStyler<'a> {
+    let references = (options.numbered_links && !compact).then_some(references);
+    LinkStyler::new(theme, references, options.link_schemes.as_deref())
+        .hide_urls(compact)
+        .with_profile(options.profile)
+        .with_ambiguous_wide(options.ambiguous_wide)
+        .status_markers(options.status_markers.as_ref())
+        .number_locale(&options.number_locale)
+}
+
+/// Format a byte count as `512 B`, `1.2 KB` or `3.4 MB`, with the units
+/// taken from `labels`.
+fn format_size(bytes: usize, locale: &NumberLocale, labels: &Labels) -> String {
+    if bytes < 1024 {
+        return format!("{} {}", locale.format_int(bytes), labels.bytes);
+    }
+    let (tenths, unit) = if bytes < 1024 * 1024 {
+        (bytes * 10 / 1024, &labels.kilobytes)
+    } else {
+        (bytes * 10 / (1024 * 1024), &labels.megabytes)
+    };
+    let number = locale.format_decimal(tenths / 10, &(tenths % 10).to_string());
+    format!("{} {}", number, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use streamdown_parser::ListBullet;
+    use crate::guard::tests::Blocking;
+    use crate::width::visible_length;
+    use crate::options::BlankLinePolicy;
+
+    /// The event opening a code block fenced with `language`.
+    fn code_block_start(language: &str) -> ParseEvent {
+        let mut parser = streamdown_parser::Parser::new();
+        let events = parser.parse_line(&format!("```{}", language));
+        let start = events
+            .into_iter()
+            .find(|event| matches!(event, ParseEvent::CodeBlockStart { .. }));
+        start.expect("fence line opens a code block")
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        let locale = NumberLocale::default();
+        assert_eq!(format_elapsed(Duration::from_millis(0), &locale), "00:00.0");
+        assert_eq!(format_elapsed(Duration::from_millis(3_450), &locale), "00:03.4");
+        assert_eq!(format_elapsed(Duration::from_secs(754), &locale), "12:34.0");
+        assert_eq!(format_elapsed(Duration::from_secs(754), &NumberLocale::de()), "12:34,0");
+    }
+
+    #[test]
+    fn test_timestamp_gutter_right_aligned() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 30, Theme::dark().with_backend(crate::PlainBackend));
+        renderer.set_options(RendererOptions {
+            timestamp_gutter: true,
+            ..Default::default()
+        });
+        renderer.render_event(&ParseEvent::Text("hello".to_string())).unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        renderer.render_event(&ParseEvent::Text("world".to_string())).unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], format!("hello{}00:00.0", " ".repeat(18)));
+        assert_eq!(lines[1], "world");
+    }
+
+    #[test]
+    fn test_timestamp_gutter_measures_columns() {
+        let render = |width: usize| {
+            let mut out = Vec::new();
+            let theme = Theme::dark().with_backend(crate::PlainBackend);
+            let mut renderer = Renderer::with_theme(&mut out, width, theme);
+            renderer.set_options(RendererOptions {
+                timestamp_gutter: true,
+                number_locale: NumberLocale {
+                    decimal_separator: '·',
+                    ..NumberLocale::default()
+                },
+                ..Default::default()
+            });
+            renderer.render_event(&ParseEvent::Text("hello".to_string())).unwrap();
+            renderer.render_event(&ParseEvent::Newline).unwrap();
+            drop(renderer);
+            String::from_utf8(out).unwrap()
+        };
+        // The separator takes two bytes but one column
+        assert_eq!(render(20).lines().next(), Some("hello        00:00·0"));
+        // A stamp that does not fit is left out
+        assert_eq!(render(12).lines().next(), Some("hello"));
+    }
+
+    #[test]
+    fn test_emoji_normalized_in_prose_only() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 40, Theme::dark().with_backend(crate::PlainBackend));
+        renderer.set_options(RendererOptions {
+            emoji_normalization: EmojiNormalization::SplitJoined,
+            ..Default::default()
+        });
+        let pair = "\u{1f469}\u{200d}\u{1f4bb}";
+        renderer.render_event(&ParseEvent::Bold(pair.to_string())).unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        let mut parser = streamdown_parser::Parser::new();
+        for line in ["```", pair] {
+            for event in parser.parse_line(line) {
+                renderer.render_event(&event).unwrap();
+            }
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("\u{1f469}\u{1f4bb}"), "{:?}", output);
+        assert!(output.contains(pair), "{:?}", output);
+    }
+
+    #[test]
+    fn test_render_partial_redraws_table() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 40, Theme::dark());
+        let header = vec!["Name".to_string(), "Age".to_string()];
+        let row = vec!["Alice".to_string(), "30".to_string()];
+        renderer.render_partial(&ParseEvent::TableHeader(header)).unwrap();
+        renderer.render_partial(&ParseEvent::TableRow(row)).unwrap();
+        renderer.render_partial(&ParseEvent::TableEnd).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let first_erase = output.find("\x1b[3F\x1b[J").expect("header-only draw erased");
+        let second_erase = output.find("\x1b[5F\x1b[J").expect("two-row draw erased");
+        assert!(first_erase < second_erase);
+    }
+
+    #[test]
+    fn test_render_partial_tracks_written_lines() {
+        let mut renderer = Renderer::with_theme(Vec::new(), 40, Theme::dark());
+        let header = vec!["Name".to_string(), "Age".to_string()];
+        renderer.render_partial(&ParseEvent::TableHeader(header)).unwrap();
+        assert_eq!(renderer.lines_written(), 3);
+        let row = vec!["Alice".to_string(), "30".to_string()];
+        renderer.render_partial(&ParseEvent::TableRow(row)).unwrap();
+        assert_eq!(renderer.lines_written(), 5);
+        renderer.render_partial(&ParseEvent::TableEnd).unwrap();
+        assert_eq!(renderer.lines_written(), 5);
+    }
+
+    #[test]
+    fn test_resize_redraws_provisional_table() {
+        let mut renderer = Renderer::with_theme(Vec::new(), 60, Theme::dark());
+        let header = vec!["Name of item".to_string(), "Description".to_string()];
+        let row = vec!["a".to_string(), "a fairly long description".to_string()];
+        renderer.render_partial(&ParseEvent::TableHeader(header)).unwrap();
+        renderer.render_partial(&ParseEvent::TableRow(row)).unwrap();
+        renderer.writer_mut().clear();
+        renderer.resize(30).unwrap();
+        let redrawn = String::from_utf8(renderer.writer_mut().clone()).unwrap();
+        assert!(redrawn.starts_with("\x1b[5F\x1b[J"), "{:?}", redrawn);
+        let plain = crate::strip_ansi(&redrawn);
+        assert!(plain.lines().all(|line| visible_length(line) <= 30), "{}", plain);
+        assert!(plain.contains("description"), "{}", plain);
+    }
+
+    #[test]
+    fn test_resize_relayouts_redrawn_progressive_table() {
+        let mut renderer = Renderer::with_theme(Vec::new(), 40, Theme::dark());
+        renderer.set_options(RendererOptions {
+            table_mode: TableMode::Progressive { redraw: true },
+            ..Default::default()
+        });
+        renderer
+            .render_event(&ParseEvent::TableHeader(vec!["A".into(), "B".into()]))
+            .unwrap();
+        renderer.writer_mut().clear();
+        renderer.resize(21).unwrap();
+        let plain = crate::strip_ansi(&String::from_utf8(renderer.writer_mut().clone()).unwrap());
+        assert_eq!(plain.lines().next(), Some("┌─────────┬─────────┐"), "{}", plain);
+    }
+
+    #[test]
+    fn test_code_line_numbers_align_wrapped_lines() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 20, theme);
+        renderer.set_options(RendererOptions {
+            code_line_numbers: true,
+            ..Default::default()
+        });
+        renderer.render_event(&ParseEvent::CodeBlockLine("let x = 1;".to_string())).unwrap();
+        renderer
+            .render_event(&ParseEvent::CodeBlockLine("a".repeat(20)))
+            .unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "  1 │ let x = 1;");
+        assert!(lines[1].starts_with("  2 │ a"));
+        assert!(lines[2].starts_with("    │ "));
+    }
+
+    #[test]
+    fn test_blank_line_normalization() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            blank_lines: Some(BlankLinePolicy::default()),
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::Text("a".to_string()),
+            ParseEvent::Newline,
+            ParseEvent::EmptyLine,
+            ParseEvent::EmptyLine,
+            ParseEvent::EmptyLine,
+            ParseEvent::Heading {
+                level: 2,
+                content: "T".to_string(),
+            },
+            ParseEvent::EmptyLine,
+            ParseEvent::Text("b".to_string()),
+            ParseEvent::Newline,
+            ParseEvent::Text("c".to_string()),
+            ParseEvent::Newline,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "a\n\n## T\nb\nc\n");
+    }
+
+    #[test]
+    fn test_raw_code_mode_is_verbatim() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 20, Theme::dark());
+        renderer.set_options(RendererOptions {
+            code_mode: CodeRenderMode::Raw,
+            code_line_numbers: true,
+            code_frame: true,
+            timestamp_gutter: true,
+            selection_safe: true,
+            ..Default::default()
+        });
+        let line = "let value = some_function(argument_one, argument_two);";
+        renderer
+            .render_event(&ParseEvent::CodeBlockLine(line.to_string()))
+            .unwrap();
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}\n", line));
+    }
+
+    #[test]
+    fn test_max_blank_lines_collapses_runs() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            max_blank_lines: Some(1),
+            ..Default::default()
+        });
+        let mut events = vec![ParseEvent::Text("a".to_string()), ParseEvent::Newline];
+        events.extend(std::iter::repeat_n(ParseEvent::EmptyLine, 5));
+        events.push(ParseEvent::Text("b".to_string()));
+        events.push(ParseEvent::Newline);
+        events.push(ParseEvent::EmptyLine);
+        events.push(ParseEvent::Newline);
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "a\n\nb\n\n");
+    }
+
+    #[test]
+    fn test_progressive_table_draws_rows_as_they_arrive() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 21, theme);
+        renderer.set_options(RendererOptions {
+            table_mode: TableMode::Progressive { redraw: false },
+            ..Default::default()
+        });
+        renderer
+            .render_event(&ParseEvent::TableHeader(vec!["A".into(), "B".into()]))
+            .unwrap();
+        renderer
+            .render_event(&ParseEvent::TableRow(vec!["1".into(), "2".into()]))
+            .unwrap();
+        let newlines = renderer.writer_mut().iter().filter(|&&b| b == b'\n').count();
+        assert_eq!(newlines, 4);
+        renderer.render_event(&ParseEvent::TableEnd).unwrap();
+        drop(renderer);
+        insta::assert_snapshot!(String::from_utf8(out).unwrap(), @r"
+        ┌─────────┬─────────┐
+        │ A       │ B       │
+        ├─────────┼─────────┤
+        │ 1       │ 2       │
+        └─────────┴─────────┘
+        ");
+    }
+
+    #[test]
+    fn test_progressive_table_keeps_extra_body_cells() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 21, theme);
+        renderer.set_options(RendererOptions {
+            table_mode: TableMode::Progressive { redraw: false },
+            ..Default::default()
+        });
+        renderer
+            .render_event(&ParseEvent::TableHeader(vec!["A".into(), "B".into()]))
+            .unwrap();
+        renderer
+            .render_event(&ParseEvent::TableRow(vec!["1".into(), "2".into(), "3".into()]))
+            .unwrap();
+        renderer.render_event(&ParseEvent::TableEnd).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().nth(3), Some("│ 1       │ 2 │ 3   │"));
+    }
+
+    #[test]
+    fn test_wrap_mode_none_leaves_lines_long() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 20, theme);
+        renderer.set_options(RendererOptions {
+            wrap: WrapMode::None,
+            ..Default::default()
+        });
+        let item = ParseEvent::ListItem {
+            indent: 0,
+            bullet: ListBullet::Dash,
+            content: "a list item longer than the width".to_string(),
+        };
+        renderer.render_event(&item).unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_list_continuation_paragraph() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        let item = |content: &str| ParseEvent::ListItem {
+            indent: 0,
+            bullet: ListBullet::Ordered(1),
+            content: content.to_string(),
+        };
+        renderer.render_event(&item("first")).unwrap();
+        renderer.render_event(&ParseEvent::ListEnd).unwrap();
+        renderer.render_event(&ParseEvent::EmptyLine).unwrap();
+        renderer.set_list_continuation(true);
+        renderer.render_event(&ParseEvent::Text("more".to_string())).unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        renderer.set_list_continuation(false);
+        renderer.render_event(&item("second")).unwrap();
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1. first\n\n   more\n2. second\n"
+        );
+    }
+
+    #[test]
+    fn test_command_output_inside_blockquote() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer
+            .render_event(&ParseEvent::BlockquoteStart { depth: 1 })
+            .unwrap();
+        renderer.render_command_output("ok\n", "warn", 1).unwrap();
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "│ │ ok\n│ ┃ warn\n│ [ exit 1 ]\n"
+        );
+    }
+
+    #[test]
+    fn test_command_output_uses_ascii_glyphs_and_labels() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            profile: crate::TerminalProfile::ascii(),
+            number_locale: NumberLocale {
+                grouping_separator: Some('.'),
+                group_size: 3,
+                decimal_separator: ',',
+            },
+            labels: Labels {
+                exit: "Code".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        renderer.render_command_output("ok\n", "warn", -1024).unwrap();
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "| ok\n! warn\n[ Code -1.024 ]\n"
+        );
+    }
+
+    #[test]
+    fn test_typing_cursor_is_erased_before_output() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::AnsiBackend::default());
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            typing_cursor: true,
+            ..Default::default()
+        });
+        renderer.render_event(&ParseEvent::Text("hi".to_string())).unwrap();
+        renderer.show_cursor().unwrap();
+        renderer.show_cursor().unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "hi▌\x1b[1D\x1b[K\n");
+    }
+
+    #[test]
+    fn test_typing_cursor_is_erased_by_finish_and_retract() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::AnsiBackend::default());
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            typing_cursor: true,
+            ..Default::default()
+        });
+        renderer.render_event(&ParseEvent::Text("hi".to_string())).unwrap();
+        renderer.show_cursor().unwrap();
+        assert!(renderer.retract_lines(0).unwrap());
+        renderer.render_event(&ParseEvent::Text("ok".to_string())).unwrap();
+        renderer.show_cursor().unwrap();
+        renderer.finish().unwrap();
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "hi▌\x1b[1D\x1b[K\x1b[2D\x1b[Kok▌\x1b[1D\x1b[K"
+        );
+    }
+
+    #[test]
+    fn test_image_handler() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_image_handler(Box::new(|alt, url| match url {
+            "skip.png" => ImageAction::Skip,
+            "art.png" => ImageAction::Replace(format!("<{}>", alt)),
+            _ => ImageAction::Placeholder,
+        }));
+        for url in ["skip.png", "art.png", "other.png"] {
+            let image = ParseEvent::Image {
+                alt: "cat".to_string(),
+                url: url.to_string(),
+            };
+            renderer.render_event(&image).unwrap();
+        }
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "<cat>[\u{1f5bc} cat]");
+    }
+
+    #[test]
+    fn test_language_hint_is_dropped_by_the_next_block() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 40, Theme::plain());
+        renderer.render_event(&ParseEvent::Text("Save as a.py:".to_string())).unwrap();
+        renderer.set_language_hint("py");
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        renderer.render_event(&code_block_start("python")).unwrap();
+        assert_eq!(renderer.language_hint.as_deref(), Some("py"));
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+
+        renderer.set_language_hint("py");
+        renderer.render_event(&ParseEvent::EmptyLine).unwrap();
+        renderer.render_event(&ParseEvent::Text("Unrelated.".to_string())).unwrap();
+        assert_eq!(renderer.language_hint, None);
+    }
+
+    #[test]
+    fn test_code_frame() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 20, theme);
+        renderer.set_options(RendererOptions {
+            code_frame: true,
+            ..Default::default()
+        });
+        renderer.render_event(&code_block_start("rs")).unwrap();
+        renderer.render_event(&ParseEvent::CodeBlockLine("let x = 1;".to_string())).unwrap();
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        insta::assert_snapshot!(output, @r"
+        ╭─ rs ─────────────╮
+        │ let x = 1;       │
+        ╰──────────────────╯
+        ");
+    }
+
+    #[test]
+    fn test_ascii_profile_code_frame_and_cursor() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = Theme::monochrome().with_backend(backend);
+        let mut renderer = Renderer::with_theme(&mut out, 20, theme);
+        renderer.set_options(RendererOptions {
+            code_frame: true,
+            typing_cursor: true,
+            profile: crate::TerminalProfile::ascii(),
+            ..Default::default()
+        });
+        renderer.render_event(&code_block_start("rs")).unwrap();
+        renderer.render_event(&ParseEvent::CodeBlockLine("let x = 1;".to_string())).unwrap();
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+        renderer.render_event(&ParseEvent::Text("hi".to_string())).unwrap();
+        renderer.show_cursor().unwrap();
+        drop(renderer);
+        let output = crate::strip_ansi(&String::from_utf8(out).unwrap());
+        let frame = "+- rs -------------+\n| let x = 1;       |\n+------------------+\n";
+        assert!(output.starts_with(frame), "{:?}", output);
+        assert!(output.ends_with("hi_"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_retract_lines() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = Theme::monochrome().with_backend(backend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.writeln("first").unwrap();
+        renderer.writeln("second").unwrap();
+        assert_eq!(renderer.lines_written(), 2);
+        assert!(renderer.retract_lines(5).unwrap());
+        assert_eq!(renderer.lines_written(), 0);
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "first\nsecond\n\x1b[2F\x1b[J");
+    }
+
+    #[test]
+    fn test_retracted_list_numbers_afresh() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = Theme::monochrome().with_backend(backend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        let item = |content: &str| ParseEvent::ListItem {
+            indent: 0,
+            bullet: ListBullet::Ordered(1),
+            content: content.to_string(),
+        };
+        renderer.render_event(&item("draft")).unwrap();
+        renderer.render_event(&item("draft")).unwrap();
+        assert!(renderer.retract_lines(2).unwrap());
+        renderer.render_event(&item("final")).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let (_, revised) = output.rsplit_once("\x1b[J").unwrap();
+        assert_eq!(crate::strip_ansi(revised), "1. final\n");
+    }
+
+    #[test]
+    fn test_reserve_bottom_lines() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = Theme::monochrome().with_backend(backend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        assert!(renderer.reserve_bottom_lines_in(2, 30).unwrap());
+        assert_eq!(renderer.reserved_bottom_lines(), 2);
+        assert!(renderer.reserve_bottom_lines_in(0, 30).unwrap());
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\n\n\x1b[2F\x1b[J\x1b7\x1b[1;28r\x1b8\x1b7\x1b[r\x1b8"
+        );
+
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(Vec::new(), 40, theme);
+        assert!(!renderer.reserve_bottom_lines_in(2, 30).unwrap());
+        assert_eq!(renderer.reserved_bottom_lines(), 0);
+    }
+
+    #[test]
+    fn test_reserved_lines_are_released() {
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = || Theme::monochrome().with_backend(backend);
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme());
+        renderer.reserve_bottom_lines_in(2, 30).unwrap();
+        drop(renderer);
+        assert!(String::from_utf8(out).unwrap().ends_with(crate::RESTORE_SEQUENCE));
+
+        let mut out = Vec::new();
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme());
+        renderer.reserve_bottom_lines_in(2, 30).unwrap();
+        renderer.finish().unwrap();
+        assert_eq!(renderer.reserved_bottom_lines(), 0);
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.ends_with("\x1b7\x1b[r\x1b8"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_breadcrumb_in_reserved_row() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::None);
+        let theme = Theme::monochrome().with_backend(backend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            breadcrumbs: true,
+            ..Default::default()
+        });
+        assert!(renderer.reserve_bottom_lines_in(1, 24).unwrap());
+        let headings = [(1, "Install"), (2, "Linux"), (3, "*Debian*"), (2, "macOS")];
+        for (level, content) in headings {
+            let event = ParseEvent::Heading {
+                level,
+                content: content.to_string(),
+            };
+            renderer.render_event(&event).unwrap();
+        }
+        assert_eq!(renderer.breadcrumb(), ["Install", "macOS"]);
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let (_, last) = output.rsplit_once("\x1b[24;1H\x1b[2K").unwrap();
+        assert_eq!(crate::strip_ansi(last), "Install › macOS");
+        assert!(output.contains("Install › Linux › Debian"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_code_stats_footer_and_summary() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            code_stats: true,
+            ..Default::default()
+        });
+        renderer.render_event(&code_block_start("python")).unwrap();
+        for line in ["x = 1", "y = 2"] {
+            renderer.render_event(&ParseEvent::CodeBlockLine(line.to_string())).unwrap();
+        }
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+        assert_eq!(renderer.summary().code_lines(), 2);
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.ends_with("2 lines · 11 chars · python\n"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_selection_safe_ends_attributes_at_line_end() {
+        let mut out = Vec::new();
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::TrueColor);
+        let mut renderer = Renderer::with_theme(&mut out, 40, Theme::dark().with_backend(backend));
+        renderer.set_options(RendererOptions {
+            selection_safe: true,
+            ..Default::default()
+        });
+        let heading = ParseEvent::Heading {
+            level: 3,
+            content: "Title".to_string(),
+        };
+        renderer.render_event(&heading).unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.ends_with("\x1b[0m\x1b[K\n"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_commit_without_partial_draw_renders_table_once() {
+        let mut out = Vec::new();
+        let mut renderer =
+            Renderer::with_theme(&mut out, 40, Theme::dark().with_backend(crate::PlainBackend));
+        let header = vec!["A".to_string()];
+        renderer.render_partial(&ParseEvent::TableHeader(header)).unwrap();
+        renderer.commit().unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_plain_backend_emits_no_escapes() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        let events = [
+            ParseEvent::Heading {
+                level: 2,
+                content: "Title".to_string(),
+            },
+            ParseEvent::ThinkBlockStart,
+            ParseEvent::ThinkBlockLine("pondering".to_string()),
+            ParseEvent::ThinkBlockEnd,
+            ParseEvent::BlockquoteStart { depth: 1 },
+            ParseEvent::BlockquoteLine("__quoted__ [link](https://example.com)".to_string()),
+            ParseEvent::BlockquoteEnd,
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "~~item~~".to_string(),
+            },
+            ParseEvent::ListEnd,
+            ParseEvent::TableHeader(vec!["A".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["`1`".to_string()]),
+            ParseEvent::TableEnd,
+            ParseEvent::HorizontalRule,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(!output.contains('\x1b'), "unexpected escape in {:?}", output);
+    }
+
+    #[test]
+    fn test_numbered_links_reference_list() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            numbered_links: true,
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::Text("See ".to_string()),
+            ParseEvent::Link {
+                text: "docs".to_string(),
+                url: "https://docs.rs".to_string(),
+            },
+            ParseEvent::Newline,
+            ParseEvent::BlockquoteStart { depth: 1 },
+            ParseEvent::BlockquoteLine("[crate](https://crates.io)".to_string()),
+            ParseEvent::BlockquoteEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        renderer.write_link_references().unwrap();
+        drop(renderer);
+        insta::assert_snapshot!(String::from_utf8(out).unwrap(), @r"
+        See docs[1]
+        │ crate[2]
+
+        [1] https://docs.rs
+        [2] https://crates.io
+        ");
+    }
+
+    #[test]
+    fn test_ascii_profile_output() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 20, theme);
+        renderer.set_options(RendererOptions {
+            profile: crate::TerminalProfile::ascii(),
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::ThinkBlockStart,
+            ParseEvent::ThinkBlockLine("hmm".to_string()),
+            ParseEvent::ThinkBlockEnd,
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "item".to_string(),
+            },
+            ParseEvent::ListEnd,
+            ParseEvent::HorizontalRule,
+            ParseEvent::TableHeader(vec!["a".to_string(), "b".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["1".to_string(), "2".to_string()]),
+            ParseEvent::TableEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.is_ascii(), "{:?}", output);
+        assert!(output.contains("+- thinking -\n| hmm\n+\n"), "{:?}", output);
+        assert!(output.contains("* item\n"), "{:?}", output);
+        assert!(output.contains(&"-".repeat(20)), "{:?}", output);
+        assert!(output.contains("+---+---+\n| a | b |\n"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_degrade_skips_optional_output_while_backed_up() {
+        let writer = Blocking::new(0);
+        let backend = crate::AnsiBackend::new(crate::ColorDepth::TrueColor);
+        let mut renderer = Renderer::with_theme(writer, 40, Theme::dark().with_backend(backend));
+        renderer.set_options(RendererOptions {
+            output_queue: Some(1000),
+            queue_policy: QueuePolicy::Degrade,
+            typing_cursor: true,
+            ..Default::default()
+        });
+        for _ in 0..55 {
+            renderer
+                .render_event(&ParseEvent::Text("some words ".to_string()))
+                .unwrap();
+        }
+        renderer.render_event(&ParseEvent::EmptyLine).unwrap();
+        assert!(renderer.queued_bytes() * 2 >= 1000);
+        renderer.show_cursor().unwrap();
+        let header = vec!["a".to_string(), "b".to_string()];
+        renderer.render_partial(&ParseEvent::TableHeader(header)).unwrap();
+        let degradation = renderer.degradation();
+        assert_eq!(
+            (degradation.skipped_redraws, degradation.skipped_cursors),
+            (1, 1)
+        );
+        assert_eq!(degradation.unhighlighted_lines, 0);
+        assert!(degradation.coalesced_texts > 0);
+
+        // Once the writer catches up, the next redraw draws the whole table
+        renderer.writer_mut().budget = usize::MAX;
+        renderer.drain().unwrap();
+        let row = vec!["1".to_string(), "2".to_string()];
+        renderer.render_partial(&ParseEvent::TableRow(row)).unwrap();
+        assert_eq!(renderer.degradation().skipped_redraws, 1);
+        let written = String::from_utf8_lossy(&renderer.writer_mut().written).into_owned();
+        assert!(crate::strip_ansi(&written).contains("│ a │ b │"), "{:?}", written);
+    }
+
+    #[test]
+    fn test_ambiguous_wide_table_borders_align() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            ambiguous_wide: true,
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::TableHeader(vec!["○○".to_string(), "b".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["abcd".to_string(), "±".to_string()]),
+            ParseEvent::TableEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let widths: Vec<usize> = output
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| visible_length_with(line, true))
+            .collect();
+        assert!(widths.windows(2).all(|w| w[0] == w[1]), "{:?}", output);
+        // Box drawing characters would be two columns wide too
+        assert!(output.starts_with("+------+----+"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_ambiguous_wide_headings_and_prose_wrap() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 12, theme);
+        renderer.set_options(RendererOptions {
+            ambiguous_wide: true,
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::Heading {
+                level: 3,
+                content: "○○○ ○○○".to_string(),
+            },
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "±±± ±±±".to_string(),
+            },
+            ParseEvent::ListEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(
+            output.lines().all(|line| visible_length_with(line, true) <= 12),
+            "{:?}",
+            output
+        );
+        assert!(output.contains("### ○○○\n### ○○○"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_ambiguous_wide_list_items_wrap_with_ascii_bullets() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 12, theme);
+        renderer.set_options(RendererOptions {
+            ambiguous_wide: true,
+            ..Default::default()
+        });
+        renderer
+            .render_event(&ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "±±± ±±±".to_string(),
+            })
+            .unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "* ±±±\n  ±±±\n");
+    }
+
+    #[test]
+    fn test_calibration_redraws_last_table() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::AnsiBackend::default());
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        let events = [
+            ParseEvent::TableHeader(vec!["who".to_string(), "emoji".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["coder".to_string(), "\u{1f469}\u{200d}\u{1f4bb}".to_string()]),
+            ParseEvent::TableEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        let drawn = renderer.lines_written();
+        let split = Calibration::measure(|probe| Ok(if probe.is_ascii() { 1 } else { 6 })).unwrap();
+        assert!(renderer.apply_calibration(&split).unwrap());
+        assert_eq!(renderer.lines_written(), drawn);
+        // Nothing changes when the same calibration is applied again
+        assert!(!renderer.apply_calibration(&split).unwrap());
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let (_, redrawn) = output.rsplit_once("F\x1b[J").unwrap();
+        assert!(redrawn.contains("\u{1f469}\u{1f4bb}"), "{:?}", redrawn);
+    }
+
+    #[test]
+    fn test_status_markers_are_normalized() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            status_markers: Some(crate::StatusMarkers::default()),
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "[x] docs ✅".to_string(),
+            },
+            ParseEvent::ListEnd,
+            ParseEvent::TableHeader(vec!["step".to_string(), "state".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["`[x]`".to_string(), "❌".to_string()]),
+            ParseEvent::TableEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("• ✔ docs ✔\n"), "{:?}", output);
+        assert!(output.contains("[x]"), "{:?}", output);
+        assert!(output.contains("✘"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_status_markers_keep_the_surrounding_style() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::AnsiBackend::default());
+        let mut renderer = Renderer::with_theme(&mut out, 80, theme.clone());
+        renderer.set_options(RendererOptions {
+            status_markers: Some(crate::StatusMarkers::default()),
+            profile: crate::TerminalProfile {
+                hyperlinks: false,
+                ..crate::TerminalProfile::default()
+            },
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::Text("build ✅ done".to_string()),
+            ParseEvent::Bold("tests [x] pass".to_string()),
+            ParseEvent::Link {
+                text: "(pending) review".to_string(),
+                url: "https://example.com".to_string(),
+            },
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        let done = theme.paint(&theme.status_done, "✔");
+        // Text after a glyph is styled again rather than left unstyled
+        assert!(output.contains(&format!("{}{}", done, theme.text(" done"))), "{:?}", output);
+        assert!(output.contains(&format!("{}{}", done, theme.bold(" pass"))), "{:?}", output);
+        assert!(crate::strip_ansi(&output).contains("◌ review"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_backed_up_text_is_coalesced_and_inline_events_dropped() {
+        let mut renderer = Renderer::with_theme(Blocking::new(0), 80, Theme::plain());
+        renderer.set_options(RendererOptions {
+            output_queue: Some(200),
+            queue_policy: QueuePolicy::Degrade,
+            ..Default::default()
+        });
+        let text = ParseEvent::Text("0123456789".to_string());
+        for _ in 0..15 {
+            renderer.render_event(&text).unwrap();
+        }
+        assert_eq!(renderer.queued_bytes(), 100);
+        assert_eq!(renderer.degradation().coalesced_texts, 5);
+        // The held text is written before the next event
+        renderer.render_event(&ParseEvent::Bold("!".to_string())).unwrap();
+        assert_eq!(renderer.queued_bytes(), 151);
+        renderer.writer_mut().budget = usize::MAX;
+        renderer.drain().unwrap();
+        let written = String::from_utf8(renderer.writer_mut().written.clone()).unwrap();
+        assert_eq!(written, format!("{}!", "0123456789".repeat(15)));
+
+        let mut renderer = Renderer::with_theme(Blocking::new(0), 80, Theme::plain());
+        renderer.set_options(RendererOptions {
+            output_queue: Some(100),
+            queue_policy: QueuePolicy::Drop,
+            ..Default::default()
+        });
+        let bold = ParseEvent::Bold("0123456789".to_string());
+        for _ in 0..10 {
+            renderer.render_event(&bold).unwrap();
+        }
+        renderer.render_event(&ParseEvent::Prompt("$ ".to_string())).unwrap();
+        assert_eq!(renderer.degradation().dropped_events, 2);
+        assert_eq!(renderer.queued_bytes(), 82);
+    }
+
+    #[test]
+    fn test_theme_glyphs() {
+        let mut out = Vec::new();
+        let mut theme = Theme::dark().with_backend(crate::PlainBackend);
+        theme.glyphs.bullets_dash = vec![">".to_string(), "-".to_string()];
+        theme.glyphs.horizontal = "=".to_string();
+        theme.glyphs.blockquote_border = "#".to_string();
+        let mut renderer = Renderer::with_theme(&mut out, 10, theme);
+        let events = [
+            ParseEvent::ListItem {
+                indent: 0,
+                bullet: ListBullet::Dash,
+                content: "a".to_string(),
+            },
+            ParseEvent::ListItem {
+                indent: 2,
+                bullet: ListBullet::Dash,
+                content: "b".to_string(),
+            },
+            ParseEvent::ListEnd,
+            ParseEvent::HorizontalRule,
+            ParseEvent::BlockquoteStart { depth: 1 },
+            ParseEvent::BlockquoteLine("q".to_string()),
+            ParseEvent::BlockquoteEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("> a\n"), "{:?}", output);
+        assert!(output.contains("- b\n"), "{:?}", output);
+        assert!(output.contains(&"=".repeat(10)), "{:?}", output);
+        assert!(output.contains("# q"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_compact_output_elides_low_priority_content() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 30, theme);
+        renderer.set_options(RendererOptions {
+            compact_below: Some(40),
+            ..Default::default()
+        });
+        let events = [
+            ParseEvent::ThinkBlockStart,
+            ParseEvent::ThinkBlockLine("hmm".to_string()),
+            ParseEvent::ThinkBlockEnd,
+            ParseEvent::Text("see ".to_string()),
+            ParseEvent::Link {
+                text: "docs".to_string(),
+                url: "https://docs.rs".to_string(),
+            },
+            ParseEvent::Newline,
+            ParseEvent::Image {
+                alt: "logo".to_string(),
+                url: "logo.png".to_string(),
+            },
+            ParseEvent::TableHeader(vec!["a".to_string(), "b".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["1".to_string(), "2".to_string()]),
+            ParseEvent::TableEnd,
+        ];
+        for event in &events {
+            renderer.render_event(event).unwrap();
+        }
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("see docs\n"), "{:?}", output);
+        assert!(output.contains("a  b\n1  2\n"), "{:?}", output);
+        for elided in ["hmm", "https", "logo", "│"] {
+            assert!(!output.contains(elided), "{:?} in {:?}", elided, output);
+        }
+    }
+
+    #[test]
+    fn test_compact_output_has_no_reference_list() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 30, theme);
+        renderer.set_options(RendererOptions {
+            compact_below: Some(40),
+            numbered_links: true,
+            ..Default::default()
+        });
+        let link = ParseEvent::Link {
+            text: "docs".to_string(),
+            url: "https://docs.rs".to_string(),
+        };
+        renderer.render_event(&link).unwrap();
+        renderer.finish().unwrap();
+        drop(renderer);
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.trim_end(), "docs", "{:?}", output);
+    }
+
+    #[test]
+    fn test_think_block_summary() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            think_mode: ThinkMode::Summary,
+            ..Default::default()
+        });
+        renderer.render_event(&ParseEvent::ThinkBlockStart).unwrap();
+        for _ in 0..12 {
+            renderer
+                .render_event(&ParseEvent::ThinkBlockLine("x".repeat(99)))
+                .unwrap();
+        }
+        renderer.render_event(&ParseEvent::ThinkBlockEnd).unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "┌─ thinking (1.1 KB hidden) ─\n");
+        let labels = Labels::default();
+        assert_eq!(format_size(512, &NumberLocale::en(), &labels), "512 B");
+        let labels = Labels {
+            kilobytes: "Ko".to_string(),
+            megabytes: "Mo".to_string(),
+            ..Default::default()
+        };
+        let locale = NumberLocale {
+            grouping_separator: Some(' '),
+            group_size: 3,
+            decimal_separator: ',',
+        };
+        assert_eq!(format_size(1536, &locale, &labels), "1,5 Ko");
+        assert_eq!(format_size(5 * 1024 * 1024, &locale, &labels), "5,0 Mo");
+    }
+
+    #[test]
+    fn test_think_duration_in_closing_border() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_options(RendererOptions {
+            think_duration: true,
+            ..Default::default()
+        });
+        let millis = Arc::new(AtomicU64::new(1_000));
+        let clock = Arc::clone(&millis);
+        renderer.set_clock(Box::new(move || {
+            Duration::from_millis(clock.load(Ordering::SeqCst))
+        }));
+        renderer.render_event(&ParseEvent::ThinkBlockStart).unwrap();
+        millis.store(4_450, Ordering::SeqCst);
+        renderer.render_event(&ParseEvent::ThinkBlockEnd).unwrap();
+        drop(renderer);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "┌─ thinking ─\n└─ thought for 3.4s\n"
+        );
+    }
+
+    #[test]
+    fn test_backends_behind_event_renderer() {
+        let mut ansi = Vec::new();
+        let mut html = Vec::new();
+        {
+            let theme = Theme::dark().with_backend(crate::PlainBackend);
+            let mut backends: Vec<Box<dyn EventRenderer + '_>> = vec![
+                Box::new(Renderer::with_theme(&mut ansi, 40, theme)),
+                Box::new(crate::HtmlRenderer::new(&mut html)),
+            ];
+            for backend in &mut backends {
+                backend.render_event(&ParseEvent::Text("hi".to_string())).unwrap();
+                backend.render_event(&ParseEvent::Newline).unwrap();
+                backend.finish().unwrap();
+            }
+        }
+        assert_eq!(String::from_utf8(ansi).unwrap(), "hi\n");
+        assert_eq!(String::from_utf8(html).unwrap(), "<p>hi\n</p>\n");
+    }
+
+    #[test]
+    fn test_fence_handler_replaces_block() {
+        let mut out = Vec::new();
+        let theme = Theme::dark().with_backend(crate::PlainBackend);
+        let mut renderer = Renderer::with_theme(&mut out, 40, theme);
+        renderer.set_fence_handler(
+            "mermaid",
+            Box::new(|source| format!("[diagram: {} lines]", source.lines().count())),
+        );
+        renderer.render_event(&code_block_start("mermaid")).unwrap();
+        for line in ["graph TD", "A --> B"] {
+            renderer
+                .render_event(&ParseEvent::CodeBlockLine(line.to_string()))
+                .unwrap();
+        }
+        renderer.render_event(&ParseEvent::CodeBlockEnd).unwrap();
+        drop(renderer);
+        assert_eq!(String::from_utf8(out).unwrap(), "[diagram: 2 lines]\n");
     }
 }
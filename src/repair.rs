@@ -42,6 +42,31 @@ fn split_embedded_fence(line: &str) -> Option<Vec<String>> {
     None
 }
 
+/// Fence of a fenced code block: its character and how many times it is
+/// repeated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fence {
+    marker: char,
+    len: usize,
+}
+
+impl Fence {
+    /// The fence a line starts with, if it is a fence line.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+        let len = trimmed.chars().take_while(|&c| c == marker).count();
+        (len >= 3).then_some(Self { marker, len })
+    }
+
+    /// Whether `line` closes a block opened with this fence: the same
+    /// character at least as many times, and nothing else.
+    pub(crate) fn is_closed_by(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        trimmed.len() >= self.len && trimmed.chars().all(|c| c == self.marker)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +122,15 @@ mod tests {
         assert_eq!(repair_line("   ```", &state_inside_code()), vec!["   ```"]);
         assert_eq!(repair_line("\t```", &state_inside_code()), vec!["\t```"]);
     }
+
+    #[test]
+    fn test_only_matching_fences_close() {
+        let fence = Fence::parse("````markdown").unwrap();
+        assert!(fence.is_closed_by("````"));
+        assert!(fence.is_closed_by("  `````  "));
+        assert!(!fence.is_closed_by("```"));
+        assert!(!fence.is_closed_by("~~~~"));
+        assert!(!fence.is_closed_by("````rust"));
+        assert_eq!(Fence::parse("``inline``"), None);
+    }
 }
@@ -0,0 +1,217 @@
+//! Canonical SGR encoder shared by every ANSI code path.
+
+use colored::Color;
+
+use crate::color::{bg_param, fg_param, palette_rgb, underline_color_param, ColorDepth};
+use crate::theme::{Style, UnderlineStyle};
+
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// The 16 basic colors in SGR order: `30`–`37`, then the bright `90`–`97`.
+pub(crate) const BASIC_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// SGR parameters for a style, in canonical order.
+///
+/// Attributes come first in numeric order (bold, dim, italic, underline,
+/// strikethrough), followed by the foreground, background, and underline
+/// color, so the same style always encodes to the same bytes. Colors are
+/// downsampled to `depth`; underline shapes and colors are only emitted at
+/// 256-color depth and above, where terminals support them.
+pub fn sgr_params(style: &Style, depth: ColorDepth) -> Vec<String> {
+    let mut params = Vec::new();
+    if style.bold {
+        params.push("1".to_string());
+    }
+    if style.dimmed {
+        params.push("2".to_string());
+    }
+    if style.italic {
+        params.push("3".to_string());
+    }
+    let extended = matches!(depth, ColorDepth::Ansi256 | ColorDepth::TrueColor);
+    if style.underline {
+        let shape = match style.underline_style {
+            _ if !extended => "4",
+            UnderlineStyle::Straight => "4",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        };
+        params.push(shape.to_string());
+    }
+    if style.strikethrough {
+        params.push("9".to_string());
+    }
+    if let Some(fg) = style.fg.and_then(|fg| fg_param(fg, depth)) {
+        params.push(fg);
+    }
+    if let Some(bg) = style.bg.and_then(|bg| bg_param(bg, depth)) {
+        params.push(bg);
+    }
+    if style.underline {
+        if let Some(color) = style.underline_color.and_then(|c| underline_color_param(c, depth)) {
+            params.push(color);
+        }
+    }
+    params
+}
+
+/// Wrap `text` in the SGR sequence for `style` followed by a reset.
+///
+/// A style that only underlines is ended with `24` (and `59` for an
+/// underline color) instead, so bold or colors active around the span
+/// continue after it. Resets already inside `text` (from nested styled
+/// spans) are followed by the outer style again, so the outer style resumes
+/// after the inner span.
+pub fn encode(style: &Style, text: &str, depth: ColorDepth) -> String {
+    let params = sgr_params(style, depth);
+    if params.is_empty() {
+        return text.to_string();
+    }
+    let open = format!("\x1b[{}m", params.join(";"));
+    let body = text.replace(RESET, &format!("{}{}", RESET, open));
+    let underline_only = params
+        .iter()
+        .all(|p| p == "4" || p.starts_with("4:") || p.starts_with("58;"));
+    let close = match underline_only {
+        true if params.iter().any(|p| p.starts_with("58;")) => "\x1b[24;59m",
+        true => "\x1b[24m",
+        false => RESET,
+    };
+    format!("{}{}{}", open, body, close)
+}
+
+/// Apply the parameters of an SGR sequence to `style`, the inverse of
+/// [`sgr_params`]. Palette colors become their RGB values, and parameters
+/// the encoder never emits are ignored.
+pub(crate) fn apply_params(style: &mut Style, params: &str) {
+    let mut parts = params.split(';');
+    while let Some(part) = parts.next() {
+        match part {
+            "" | "0" => *style = Style::new(),
+            "1" => style.bold = true,
+            "2" => style.dimmed = true,
+            "3" => style.italic = true,
+            "9" => style.strikethrough = true,
+            "22" => (style.bold, style.dimmed) = (false, false),
+            "23" => style.italic = false,
+            "24" => style.underline = false,
+            "29" => style.strikethrough = false,
+            "39" => style.fg = None,
+            "49" => style.bg = None,
+            "38" => style.fg = decode_color(&mut parts),
+            "48" => style.bg = decode_color(&mut parts),
+            "58" => style.underline_color = decode_color(&mut parts),
+            "4" | "4:1" | "4:2" | "4:3" | "4:4" | "4:5" => {
+                style.underline = true;
+                style.underline_style = match part {
+                    "4:2" => UnderlineStyle::Double,
+                    "4:3" => UnderlineStyle::Curly,
+                    "4:4" => UnderlineStyle::Dotted,
+                    "4:5" => UnderlineStyle::Dashed,
+                    _ => UnderlineStyle::Straight,
+                };
+            }
+            part => match part.parse::<usize>() {
+                Ok(n @ 30..=37) => style.fg = Some(BASIC_COLORS[n - 30]),
+                Ok(n @ 40..=47) => style.bg = Some(BASIC_COLORS[n - 40]),
+                Ok(n @ 90..=97) => style.fg = Some(BASIC_COLORS[n - 90 + 8]),
+                Ok(n @ 100..=107) => style.bg = Some(BASIC_COLORS[n - 100 + 8]),
+                _ => {}
+            },
+        }
+    }
+}
+
+/// Color of a `38`, `48` or `58` parameter, taking its arguments from `parts`.
+fn decode_color<'a>(parts: &mut impl Iterator<Item = &'a str>) -> Option<Color> {
+    let (r, g, b) = match parts.next()? {
+        "5" => palette_rgb(parts.next()?.parse().ok()?),
+        "2" => {
+            let mut channel = || parts.next()?.parse::<u8>().ok();
+            (channel()?, channel()?, channel()?)
+        }
+        _ => return None,
+    };
+    Some(Color::TrueColor { r, g, b })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use colored::Color;
+
+    #[test]
+    fn test_unstyled_is_passthrough() {
+        assert_eq!(encode(&Style::new(), "text", ColorDepth::TrueColor), "text");
+    }
+
+    #[test]
+    fn test_canonical_order_independent_of_builder_order() {
+        let a = Style::new().fg(Color::Red).underline().bold();
+        let b = Style::new().bold().underline().fg(Color::Red);
+        assert_eq!(encode(&a, "x", ColorDepth::TrueColor), "\x1b[1;4;31mx\x1b[0m");
+        assert_eq!(encode(&a, "x", ColorDepth::TrueColor), encode(&b, "x", ColorDepth::TrueColor));
+    }
+
+    #[test]
+    fn test_nested_reset_resumes_outer_style() {
+        let inner = encode(&Style::new().italic(), "in", ColorDepth::TrueColor);
+        let outer = encode(&Style::new().bold(), &format!("a {} b", inner), ColorDepth::TrueColor);
+        assert_eq!(outer, "\x1b[1ma \x1b[3min\x1b[0m\x1b[1m b\x1b[0m");
+    }
+
+    #[test]
+    fn test_truecolor_background() {
+        let style = Style::new().bg(Color::TrueColor { r: 1, g: 2, b: 3 });
+        assert_eq!(encode(&style, "x", ColorDepth::TrueColor), "\x1b[48;2;1;2;3mx\x1b[0m");
+    }
+
+    #[test]
+    fn test_curly_colored_underline() {
+        let style = Style::new()
+            .underline_style(UnderlineStyle::Curly)
+            .underline_color(Color::TrueColor { r: 255, g: 0, b: 0 });
+        assert_eq!(
+            encode(&style, "x", ColorDepth::TrueColor),
+            "\x1b[4:3;58;2;255;0;0mx\x1b[24;59m"
+        );
+        assert_eq!(encode(&style, "x", ColorDepth::Ansi256), "\x1b[4:3;58;5;196mx\x1b[24;59m");
+        assert_eq!(encode(&style, "x", ColorDepth::Ansi16), "\x1b[4mx\x1b[24m");
+        let named = Style::new().underline().underline_color(Color::Red);
+        assert_eq!(encode(&named, "x", ColorDepth::TrueColor), "\x1b[4;58;5;1mx\x1b[24;59m");
+    }
+
+    #[test]
+    fn test_underline_ends_without_reset() {
+        let inner = encode(&Style::new().underline(), "u", ColorDepth::TrueColor);
+        assert_eq!(inner, "\x1b[4mu\x1b[24m");
+        let outer = encode(&Style::new().bold(), &format!("a {} b", inner), ColorDepth::TrueColor);
+        assert_eq!(outer, "\x1b[1ma \x1b[4mu\x1b[24m b\x1b[0m");
+    }
+
+    #[test]
+    fn test_no_color_keeps_attributes() {
+        let style = Style::new().bold().fg(Color::Red);
+        assert_eq!(encode(&style, "x", ColorDepth::None), "\x1b[1mx\x1b[0m");
+        assert_eq!(encode(&Style::new().fg(Color::Red), "x", ColorDepth::None), "x");
+    }
+}
@@ -0,0 +1,141 @@
+//! Replaying token streams at LLM-like pacing for latency measurements.
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::StreamdownRenderer;
+
+/// Pacing of a simulated token stream.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamSimulation {
+    /// Average number of tokens arriving per second.
+    pub tokens_per_second: f64,
+    /// Random variation of each inter-token delay, as a fraction of the
+    /// average delay (0.0 for a steady stream).
+    pub jitter: f64,
+    /// Seed for the jitter, so runs are reproducible.
+    pub seed: u64,
+}
+
+impl Default for StreamSimulation {
+    fn default() -> Self {
+        Self {
+            tokens_per_second: 50.0,
+            jitter: 0.5,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// End-to-end latencies measured by [`simulate_stream`].
+///
+/// Each sample is the time from a token's scheduled arrival until the
+/// renderer finished processing it, so it includes any backlog built up
+/// when rendering falls behind the stream.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyReport {
+    samples: Vec<Duration>,
+    total: Duration,
+}
+
+impl LatencyReport {
+    /// Latency of every token, in arrival order.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    /// Wall-clock time of the whole replay.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Latency at percentile `p` (0–100), using the nearest-rank method.
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+    }
+}
+
+/// Push `tokens` into `renderer` at the pacing described by `config`,
+/// then finish it, recording per-token latency.
+pub fn simulate_stream<W: Write>(
+    mut renderer: StreamdownRenderer<W>,
+    tokens: &[&str],
+    config: &StreamSimulation,
+) -> io::Result<LatencyReport> {
+    let mut rng = XorShift(config.seed.max(1));
+    let interval = if config.tokens_per_second > 0.0 {
+        1.0 / config.tokens_per_second
+    } else {
+        0.0
+    };
+    let jitter = config.jitter.clamp(0.0, 1.0);
+
+    let start = Instant::now();
+    let mut arrival = Duration::ZERO;
+    let mut samples = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let scale = 1.0 + jitter * (rng.next_f64() * 2.0 - 1.0);
+        arrival += Duration::from_secs_f64(interval * scale);
+        if let Some(wait) = arrival.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+        renderer.push(token)?;
+        samples.push(start.elapsed().saturating_sub(arrival));
+    }
+    renderer.finish()?;
+
+    Ok(LatencyReport {
+        samples,
+        total: start.elapsed(),
+    })
+}
+
+/// Small deterministic generator for jitter; quality is not a concern.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let report = LatencyReport {
+            samples: (1..=10).rev().map(Duration::from_millis).collect(),
+            total: Duration::ZERO,
+        };
+        assert_eq!(report.percentile(50.0), Duration::from_millis(5));
+        assert_eq!(report.percentile(90.0), Duration::from_millis(9));
+        assert_eq!(report.percentile(100.0), Duration::from_millis(10));
+        assert_eq!(report.percentile(0.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_simulate_stream_records_every_token() {
+        let theme = crate::Theme::dark().with_backend(crate::PlainBackend);
+        let renderer = StreamdownRenderer::with_theme(Vec::new(), 80, theme);
+        let tokens = ["# Ti", "tle\n", "Some **bo", "ld** text\n"];
+        let config = StreamSimulation {
+            tokens_per_second: 10_000.0,
+            ..Default::default()
+        };
+        let report = simulate_stream(renderer, &tokens, &config).unwrap();
+        assert_eq!(report.samples().len(), tokens.len());
+        assert!(report.percentile(50.0) <= report.percentile(99.0));
+    }
+}
@@ -0,0 +1,316 @@
+//! Writer pacing rendered output for a smooth typing effect.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::strip::strip_ansi;
+
+/// Callback told when output goes idle (`true`) and resumes (`false`),
+/// e.g. to show a spinner while waiting for the next token.
+pub type IdleHook = Box<dyn FnMut(bool) + Send>;
+
+/// Unit of text written at a time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chunking {
+    /// One character at a time.
+    #[default]
+    Char,
+    /// One word with its trailing whitespace at a time.
+    Word,
+    /// One line at a time.
+    Line,
+}
+
+/// Delay after each chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DelayCurve {
+    /// The same delay after every chunk.
+    Constant(Duration),
+    /// A delay shrinking linearly from `max` with no chunks waiting to `min`
+    /// once `backlog` chunks are waiting, so output catches up with a fast
+    /// stream.
+    CatchUp {
+        max: Duration,
+        min: Duration,
+        backlog: usize,
+    },
+}
+
+impl Default for DelayCurve {
+    fn default() -> Self {
+        Self::Constant(Duration::from_millis(1))
+    }
+}
+
+impl DelayCurve {
+    /// Delay after a chunk while `pending` more chunks are waiting.
+    pub fn delay(&self, pending: usize) -> Duration {
+        match *self {
+            Self::Constant(delay) => delay,
+            Self::CatchUp { max, min, backlog } => {
+                if backlog == 0 || pending >= backlog {
+                    return min;
+                }
+                let progress = pending as f64 / backlog as f64;
+                max.saturating_sub(max.saturating_sub(min).mul_f64(progress))
+            }
+        }
+    }
+}
+
+/// Pacing of a [`SmoothWriter`].
+pub struct SmoothOptions {
+    pub chunking: Chunking,
+    pub delay: DelayCurve,
+    /// Time without output after which the idle hook is called.
+    pub idle_timeout: Duration,
+    pub on_idle: Option<IdleHook>,
+}
+
+impl Default for SmoothOptions {
+    fn default() -> Self {
+        Self {
+            chunking: Chunking::default(),
+            delay: DelayCurve::default(),
+            idle_timeout: Duration::from_millis(50),
+            on_idle: None,
+        }
+    }
+}
+
+/// Writer passing output on in small chunks with delays from a background
+/// thread, for a typing effect independent of how tokens arrive.
+///
+/// Escape sequences are never split, and chunks holding only escape
+/// sequences are written without delay.
+///
+/// ```no_run
+/// use forge_markdown_stream::{Chunking, SmoothOptions, SmoothWriter, StreamdownRenderer};
+///
+/// let options = SmoothOptions {
+///     chunking: Chunking::Word,
+///     ..Default::default()
+/// };
+/// let writer = SmoothWriter::new(std::io::stdout(), options);
+/// let mut renderer = StreamdownRenderer::new(writer, 80);
+/// renderer.push("Hello **world**\n").unwrap();
+/// renderer.finish().unwrap();
+/// ```
+pub struct SmoothWriter<W: Write + Send + 'static> {
+    sender: Sender<Message>,
+    handle: Option<JoinHandle<io::Result<W>>>,
+}
+
+enum Message {
+    Text(String),
+    Finish,
+}
+
+impl<W: Write + Send + 'static> SmoothWriter<W> {
+    /// Start the background thread writing to `writer`.
+    pub fn new(writer: W, options: SmoothOptions) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || pace(writer, receiver, options));
+        Self {
+            sender,
+            handle: Some(handle),
+        }
+    }
+
+    /// Write all pending output, stop the background thread and return the
+    /// underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let _ = self.sender.send(Message::Finish);
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            _ => Err(io::Error::other("writer thread panicked")),
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> Write for SmoothWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf).into_owned();
+        self.sender
+            .send(Message::Text(text))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "writer thread gone"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write + Send + 'static> Drop for SmoothWriter<W> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = self.sender.send(Message::Finish);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Body of the background thread.
+fn pace<W: Write>(
+    mut writer: W,
+    receiver: Receiver<Message>,
+    mut options: SmoothOptions,
+) -> io::Result<W> {
+    let mut pending: VecDeque<String> = VecDeque::new();
+    let mut idle = false;
+    let mut finishing = false;
+    let mut set_idle = |idle_now: bool| {
+        if idle != idle_now {
+            idle = idle_now;
+            if let Some(hook) = options.on_idle.as_mut() {
+                hook(idle_now);
+            }
+        }
+    };
+    loop {
+        // Take whatever arrived without waiting, so the backlog is known
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                Message::Text(text) => pending.extend(split_chunks(&text, options.chunking)),
+                Message::Finish => finishing = true,
+            }
+        }
+        if let Some(chunk) = pending.pop_front() {
+            set_idle(false);
+            writer.write_all(chunk.as_bytes())?;
+            writer.flush()?;
+            let delay = options.delay.delay(pending.len());
+            if !delay.is_zero() && !strip_ansi(&chunk).is_empty() {
+                thread::sleep(delay);
+            }
+            continue;
+        }
+        if finishing {
+            set_idle(false);
+            return Ok(writer);
+        }
+        match receiver.recv_timeout(options.idle_timeout) {
+            Ok(Message::Text(text)) => pending.extend(split_chunks(&text, options.chunking)),
+            Ok(Message::Finish) | Err(RecvTimeoutError::Disconnected) => finishing = true,
+            Err(RecvTimeoutError::Timeout) => set_idle(true),
+        }
+    }
+}
+
+/// Split text into chunks, keeping escape sequences with the character
+/// after them.
+fn split_chunks(text: &str, chunking: Chunking) -> Vec<String> {
+    let units = char_units(text);
+    if chunking == Chunking::Char {
+        return units;
+    }
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for unit in units {
+        let ends_chunk = match chunking {
+            // A word ends where whitespace gives way to text again
+            Chunking::Word => {
+                current.ends_with(char::is_whitespace) && !unit.ends_with(char::is_whitespace)
+            }
+            _ => current.ends_with('\n'),
+        };
+        if ends_chunk {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(&unit);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Split text into characters, each preceded by any escape sequences
+/// before it.
+fn char_units(text: &str) -> Vec<String> {
+    let mut units = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        if c != '\x1b' {
+            units.push(std::mem::take(&mut current));
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                current.push('[');
+                for c in chars.by_ref() {
+                    current.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                current.push(']');
+                while let Some(c) = chars.next() {
+                    current.push(c);
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        current.extend(chars.next());
+                        break;
+                    }
+                }
+            }
+            Some(other) => current.push(other),
+            None => {}
+        }
+    }
+    if !current.is_empty() {
+        units.push(current);
+    }
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_keep_escape_sequences_whole() {
+        let text = "\x1b[1mab\x1b[0m c";
+        assert_eq!(
+            split_chunks(text, Chunking::Char),
+            vec!["\x1b[1ma", "b", "\x1b[0m ", "c"]
+        );
+        assert_eq!(split_chunks("one two  three", Chunking::Word), vec!["one ", "two  ", "three"]);
+        assert_eq!(split_chunks("a\nb\n", Chunking::Line), vec!["a\n", "b\n"]);
+    }
+
+    #[test]
+    fn test_catch_up_delay_shrinks_with_backlog() {
+        let curve = DelayCurve::CatchUp {
+            max: Duration::from_millis(10),
+            min: Duration::from_millis(2),
+            backlog: 4,
+        };
+        assert_eq!(curve.delay(0), Duration::from_millis(10));
+        assert_eq!(curve.delay(2), Duration::from_millis(6));
+        assert_eq!(curve.delay(9), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_writes_everything_before_finishing() {
+        let options = SmoothOptions {
+            chunking: Chunking::Word,
+            delay: DelayCurve::Constant(Duration::ZERO),
+            ..Default::default()
+        };
+        let mut writer = SmoothWriter::new(Vec::new(), options);
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world\n").unwrap();
+        assert_eq!(writer.finish().unwrap(), b"hello world\n");
+    }
+}
@@ -0,0 +1,265 @@
+//! Removing escape sequences from already-rendered output.
+
+use std::io::{self, Write};
+
+/// Remove escape sequences from rendered output.
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = Vec::with_capacity(text.len());
+    EscapeStripper::default().feed(text.as_bytes(), &mut out);
+    // Only whole escape sequences are removed, which keeps UTF-8 intact
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Writer that removes escape sequences before passing output on.
+///
+/// Sequences split across writes are recognized, so rendered output can be
+/// streamed through it in arbitrary chunks.
+pub struct StrippingWriter<W: Write> {
+    inner: W,
+    stripper: EscapeStripper,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> StrippingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            stripper: EscapeStripper::default(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consume the wrapper and return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for StrippingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.clear();
+        self.stripper.feed(buf, &mut self.buffer);
+        self.inner.write_all(&self.buffer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Kind of an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EscapeKind {
+    /// `ESC [`, ended by a byte in `@..=~`.
+    Csi,
+    /// `ESC ]`, ended by BEL or ST.
+    Osc,
+    /// DCS, SOS, PM or APC (`ESC P`, `ESC X`, `ESC ^`, `ESC _`), ended by ST
+    /// only, such as Kitty graphics.
+    String,
+    /// Any other escape, two bytes long.
+    Other,
+}
+
+/// Kind and length in bytes of the escape sequence at the start of `data`,
+/// which begins with ESC, or `None` if it is incomplete.
+pub(crate) fn scan_escape(data: &[u8]) -> Option<(EscapeKind, usize)> {
+    let introducer = *data.get(1)?;
+    let kind = escape_kind(introducer);
+    let len = match kind {
+        EscapeKind::Csi => {
+            data[2..]
+                .iter()
+                .position(|byte| (0x40..=0x7e).contains(byte))?
+                + 3
+        }
+        EscapeKind::Osc => terminated_len(&data[2..], true)? + 2,
+        EscapeKind::String => terminated_len(&data[2..], false)? + 2,
+        // A lone ESC before another escape or a multi-byte character
+        EscapeKind::Other if introducer == 0x1b || introducer >= 0x80 => 1,
+        EscapeKind::Other => 2,
+    };
+    Some((kind, len))
+}
+
+fn escape_kind(introducer: u8) -> EscapeKind {
+    match introducer {
+        b'[' => EscapeKind::Csi,
+        b']' => EscapeKind::Osc,
+        b'P' | b'X' | b'^' | b'_' => EscapeKind::String,
+        _ => EscapeKind::Other,
+    }
+}
+
+/// Length of an OSC or string sequence body including its terminator.
+///
+/// An ESC that does not start ST cancels the sequence, which then ends
+/// before that ESC.
+fn terminated_len(body: &[u8], bel_ends: bool) -> Option<usize> {
+    for (i, &byte) in body.iter().enumerate() {
+        match byte {
+            0x07 if bel_ends => return Some(i + 1),
+            0x1b => {
+                return match body.get(i + 1)? {
+                    b'\\' => Some(i + 2),
+                    _ => Some(i),
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A piece of rendered output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    /// Text between escape sequences.
+    Text(&'a str),
+    /// One whole escape sequence.
+    Escape(EscapeKind, &'a str),
+}
+
+impl<'a> Token<'a> {
+    /// The source text of the token.
+    pub(crate) fn as_str(&self) -> &'a str {
+        match self {
+            Self::Text(text) | Self::Escape(_, text) => text,
+        }
+    }
+
+    /// Parameters of an SGR sequence, such as `1;31` of `ESC [ 1;31 m`.
+    pub(crate) fn sgr_params(&self) -> Option<&'a str> {
+        match self {
+            Self::Escape(EscapeKind::Csi, sequence) => sequence[2..].strip_suffix('m'),
+            _ => None,
+        }
+    }
+
+    /// Body of an OSC sequence, without its terminator.
+    pub(crate) fn osc_body(&self) -> Option<&'a str> {
+        match self {
+            Self::Escape(EscapeKind::Osc, sequence) => {
+                let body = &sequence[2..];
+                Some(
+                    body.strip_suffix("\x1b\\")
+                        .or_else(|| body.strip_suffix('\x07'))
+                        .unwrap_or(body),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Split complete rendered output into text and escape sequences. An
+/// unterminated sequence at the end runs to the end of `text`.
+pub(crate) fn tokens(text: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (token, len) = match rest.find('\x1b') {
+            Some(0) => match scan_escape(rest.as_bytes()) {
+                Some((kind, len)) => (Token::Escape(kind, &rest[..len]), len),
+                None => {
+                    let kind = rest
+                        .as_bytes()
+                        .get(1)
+                        .map_or(EscapeKind::Other, |&b| escape_kind(b));
+                    (Token::Escape(kind, rest), rest.len())
+                }
+            },
+            Some(start) => (Token::Text(&rest[..start]), start),
+            None => (Token::Text(rest), rest.len()),
+        };
+        rest = &rest[len..];
+        Some(token)
+    })
+}
+
+/// Incremental remover of escape sequences, keeping an incomplete
+/// sequence until the rest of it arrives.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct EscapeStripper {
+    pending: Vec<u8>,
+}
+
+impl EscapeStripper {
+    /// Append the bytes of `bytes` outside escape sequences to `out`.
+    pub(crate) fn feed(&mut self, bytes: &[u8], out: &mut Vec<u8>) {
+        self.pending.extend_from_slice(bytes);
+        let data = std::mem::take(&mut self.pending);
+        let mut i = 0;
+        while let Some(start) = data[i..].iter().position(|&byte| byte == 0x1b) {
+            out.extend_from_slice(&data[i..i + start]);
+            i += start;
+            match scan_escape(&data[i..]) {
+                Some((_, len)) => i += len,
+                None => {
+                    self.pending = data[i..].to_vec();
+                    return;
+                }
+            }
+        }
+        out.extend_from_slice(&data[i..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        let styled = "\x1b[1;38;2;1;2;3mbold\x1b[0m \x1b]8;;https://x.dev\x1b\\link\x1b]8;;\x1b\\";
+        assert_eq!(strip_ansi(styled), "bold link");
+        assert_eq!(strip_ansi("caf\u{e9} \x1b[4:3mok\x1b[0m"), "caf\u{e9} ok");
+    }
+
+    #[test]
+    fn test_stripping_writer_handles_split_sequences() {
+        let mut writer = StrippingWriter::new(Vec::new());
+        for chunk in ["a\x1b[", "1mb\x1b]8;;htt", "ps://x\x1b", "\\c"] {
+            writer.write_all(chunk.as_bytes()).unwrap();
+        }
+        assert_eq!(writer.into_inner(), b"abc");
+    }
+
+    #[test]
+    fn test_string_sequences_end_at_st() {
+        // A Kitty graphics APC may contain BEL and `m` bytes
+        let image = "a\x1b_Gf=100,m=1;AAm\x07B\x1b\\b";
+        assert_eq!(strip_ansi(image), "ab");
+        assert_eq!(strip_ansi("a\x1bPq#0m\x1b\\b"), "ab");
+        assert_eq!(scan_escape(b"\x1b_Gm=1;AA"), None);
+        // An ESC that is not ST cancels the sequence
+        assert_eq!(strip_ansi("a\x1b]8;;x\x1b[1mb"), "ab");
+    }
+
+    #[test]
+    fn test_tokens() {
+        let text = "a\x1b[1mb\x1b]8;;https://x\x1b\\c\x1b[";
+        let tokens: Vec<Token> = tokens(text).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Text("a"),
+                Token::Escape(EscapeKind::Csi, "\x1b[1m"),
+                Token::Text("b"),
+                Token::Escape(EscapeKind::Osc, "\x1b]8;;https://x\x1b\\"),
+                Token::Text("c"),
+                Token::Escape(EscapeKind::Csi, "\x1b["),
+            ]
+        );
+        assert_eq!(tokens[1].sgr_params(), Some("1"));
+        assert_eq!(tokens[3].osc_body(), Some("8;;https://x"));
+    }
+}
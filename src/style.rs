@@ -1,3 +1,6 @@
+use crate::heading::HeadingDecoration;
+use crate::theme::Glyphs;
+
 /// Trait for styling inline elements.
 pub trait InlineStyler {
     fn text(&self, text: &str) -> String;
@@ -11,6 +14,12 @@ pub trait InlineStyler {
     fn image(&self, alt: &str, url: &str) -> String;
     fn footnote(&self, text: &str) -> String;
     fn dimmed(&self, text: &str) -> String;
+
+    /// Whether East Asian ambiguous-width characters take two columns in
+    /// the terminal, so wrapping and alignment measure them that way.
+    fn ambiguous_wide(&self) -> bool {
+        false
+    }
 }
 
 
@@ -23,6 +32,11 @@ pub trait HeadingStyler {
     fn h4(&self, text: &str) -> String;
     fn h5(&self, text: &str) -> String;
     fn h6(&self, text: &str) -> String;
+
+    /// Decoration of headings at `level`.
+    fn decoration(&self, _level: u8) -> HeadingDecoration {
+        HeadingDecoration::Prefix
+    }
 }
 
 
@@ -34,6 +48,11 @@ pub trait ListStyler {
     fn bullet_plus(&self, text: &str) -> String;
     fn bullet_plus_expand(&self, text: &str) -> String;
     fn number(&self, text: &str) -> String;
+
+    /// Characters of bullets.
+    fn glyphs(&self) -> &Glyphs {
+        Glyphs::shared(true)
+    }
 }
 
 
@@ -42,4 +61,9 @@ pub trait ListStyler {
 pub trait TableStyler {
     fn border(&self, text: &str) -> String;
     fn header(&self, text: &str) -> String;
+
+    /// Characters of borders.
+    fn glyphs(&self) -> &Glyphs {
+        Glyphs::shared(true)
+    }
 }
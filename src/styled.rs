@@ -0,0 +1,274 @@
+//! Rendered lines as styled spans, for span-based UIs such as ratatui.
+
+use std::io;
+
+use streamdown_parser::ParseEvent;
+
+use crate::backend::{AnsiBackend, OutputBackend};
+use crate::color::ColorDepth;
+use crate::options::RendererOptions;
+use crate::renderer::{EventRenderer, Renderer};
+use crate::sgr::apply_params;
+use crate::strip::{tokens, Token};
+use crate::theme::{Style, Theme};
+use crate::width::text_width;
+
+/// Run of text sharing a style and hyperlink.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+    /// Target of the OSC 8 hyperlink the text is part of.
+    pub link: Option<String>,
+}
+
+/// A line of output as runs of text sharing a style.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyledLine {
+    pub spans: Vec<Span>,
+}
+
+impl StyledLine {
+    /// Split a line of ANSI output into spans, keeping SGR styles and OSC 8
+    /// hyperlinks. Other escape sequences are dropped.
+    pub fn from_ansi(line: &str) -> Self {
+        SpanDecoder::default().line(line)
+    }
+
+    /// Serialize the spans with `backend`, e.g. back to ANSI.
+    pub fn to_ansi(&self, backend: &dyn OutputBackend) -> String {
+        self.spans
+            .iter()
+            .map(|span| {
+                let painted = backend.paint(&span.style, &span.text);
+                match &span.link {
+                    Some(url) => backend.hyperlink(&painted, url),
+                    None => painted,
+                }
+            })
+            .collect()
+    }
+
+    /// Text of the line without styles.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Display width of the line in terminal columns.
+    pub fn width(&self) -> usize {
+        text_width(&self.text())
+    }
+}
+
+/// Decoder of rendered output into spans, keeping the style and hyperlink
+/// in effect from one call to the next.
+#[derive(Debug, Default)]
+pub(crate) struct SpanDecoder {
+    style: Style,
+    link: Option<String>,
+}
+
+impl SpanDecoder {
+    /// Decode one line of output. Line breaks and carriage returns are
+    /// dropped along with escape sequences other than SGR and OSC 8.
+    pub(crate) fn line(&mut self, ansi: &str) -> StyledLine {
+        let mut line = StyledLine::default();
+        let mut text = String::new();
+        for token in tokens(ansi) {
+            if let Token::Text(run) = token {
+                text.extend(run.chars().filter(|c| !matches!(c, '\n' | '\r')));
+                continue;
+            }
+            self.push(&mut line, &mut text);
+            if let Some(params) = token.sgr_params() {
+                apply_params(&mut self.style, params);
+            }
+            if let Some(link) = token.osc_body().and_then(|body| body.strip_prefix("8;")) {
+                let url = link.split_once(';').map_or("", |(_, url)| url);
+                self.link = (!url.is_empty()).then(|| url.to_string());
+            }
+        }
+        self.push(&mut line, &mut text);
+        line
+    }
+
+    fn push(&self, line: &mut StyledLine, text: &mut String) {
+        if !text.is_empty() {
+            line.spans.push(Span {
+                style: self.style.clone(),
+                text: std::mem::take(text),
+                link: self.link.clone(),
+            });
+        }
+    }
+}
+
+/// Output of an inner renderer, split into complete lines as it arrives.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    // Output of the line still being written
+    partial: String,
+}
+
+impl LineBuffer {
+    /// Take the output of `renderer` and return the lines it completed,
+    /// without their newlines.
+    pub(crate) fn take_lines(&mut self, renderer: &mut Renderer<Vec<u8>>) -> io::Result<Vec<String>> {
+        let buffer = renderer.take_output()?;
+        self.partial.push_str(&String::from_utf8_lossy(&buffer));
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.find('\n') {
+            let mut line: String = self.partial.drain(..=end).collect();
+            line.pop();
+            lines.push(line);
+        }
+        Ok(lines)
+    }
+
+    /// The line still being written.
+    pub(crate) fn partial(&self) -> &str {
+        &self.partial
+    }
+
+    pub(crate) fn take_partial(&mut self) -> String {
+        std::mem::take(&mut self.partial)
+    }
+}
+
+/// Renderer collecting its output as [`StyledLine`]s instead of writing
+/// escape sequences, with the same layout as the terminal renderer.
+///
+/// ```
+/// use forge_markdown_stream::{EventRenderer, SpanRenderer};
+/// use streamdown_parser::ParseEvent;
+///
+/// let mut renderer = SpanRenderer::new(40);
+/// renderer.render_event(&ParseEvent::Bold("hi".to_string())).unwrap();
+/// renderer.render_event(&ParseEvent::Newline).unwrap();
+/// let lines = renderer.take_lines();
+/// assert_eq!(lines[0].text(), "hi");
+/// assert!(lines[0].spans[0].style.bold);
+/// ```
+pub struct SpanRenderer {
+    renderer: Renderer<Vec<u8>>,
+    lines: Vec<StyledLine>,
+    output: LineBuffer,
+    decoder: SpanDecoder,
+}
+
+impl SpanRenderer {
+    pub fn new(width: usize) -> Self {
+        Self::with_theme(width, Theme::default())
+    }
+
+    /// Create a renderer with a custom theme. Its styles reach the spans
+    /// unchanged, whatever the theme's backend.
+    pub fn with_theme(width: usize, theme: Theme) -> Self {
+        let theme = theme.with_backend(AnsiBackend::new(ColorDepth::TrueColor));
+        Self {
+            renderer: Renderer::with_theme(Vec::new(), width, theme),
+            lines: Vec::new(),
+            output: LineBuffer::default(),
+            decoder: SpanDecoder::default(),
+        }
+    }
+
+    /// Set the optional renderer behaviors.
+    pub fn set_options(&mut self, options: RendererOptions) {
+        self.renderer.set_options(options);
+    }
+
+    /// Take the lines completed since the last call.
+    pub fn take_lines(&mut self) -> Vec<StyledLine> {
+        std::mem::take(&mut self.lines)
+    }
+
+    /// The line still being written, e.g. a paragraph waiting for more
+    /// inline content.
+    pub fn partial_line(&self) -> StyledLine {
+        SpanDecoder {
+            style: self.decoder.style.clone(),
+            link: self.decoder.link.clone(),
+        }
+        .line(self.output.partial())
+    }
+
+    fn collect(&mut self) -> io::Result<()> {
+        for line in self.output.take_lines(&mut self.renderer)? {
+            let line = self.decoder.line(&line);
+            self.lines.push(line);
+        }
+        Ok(())
+    }
+}
+
+impl EventRenderer for SpanRenderer {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        self.renderer.render_event(event)?;
+        self.collect()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.renderer.finish()?;
+        self.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use colored::Color;
+
+    #[test]
+    fn test_ansi_round_trip() {
+        let backend = AnsiBackend::new(ColorDepth::TrueColor);
+        let red = Style::new().bold().fg(Color::TrueColor { r: 255, g: 0, b: 0 });
+        let italic = Style::new().italic();
+        let ansi = format!(
+            "{} and {}",
+            backend.paint(&red, "red"),
+            backend.paint(&italic, "slanted")
+        );
+        let line = StyledLine::from_ansi(&ansi);
+        assert_eq!(line.spans.len(), 3);
+        assert_eq!((&line.spans[0].style, line.spans[0].text.as_str()), (&red, "red"));
+        assert_eq!(line.text(), "red and slanted");
+        assert_eq!(line.to_ansi(&backend), ansi);
+    }
+
+    #[test]
+    fn test_collects_complete_lines() {
+        let mut renderer = SpanRenderer::with_theme(40, Theme::dark());
+        renderer
+            .render_event(&ParseEvent::Heading {
+                level: 2,
+                content: "Title".to_string(),
+            })
+            .unwrap();
+        renderer
+            .render_event(&ParseEvent::Text("body".to_string()))
+            .unwrap();
+        let lines = renderer.take_lines();
+        assert!(lines.iter().any(|line| line.text().contains("Title")));
+        assert_eq!(renderer.partial_line().text(), "body");
+    }
+
+    #[test]
+    fn test_links_survive_the_round_trip() {
+        let mut renderer = SpanRenderer::with_theme(40, Theme::dark());
+        renderer
+            .render_event(&ParseEvent::Link {
+                text: "docs".to_string(),
+                url: "https://docs.rs".to_string(),
+            })
+            .unwrap();
+        renderer.render_event(&ParseEvent::Newline).unwrap();
+        let lines = renderer.take_lines();
+        let span = &lines[0].spans[0];
+        assert_eq!(span.text, "docs");
+        assert_eq!(span.link.as_deref(), Some("https://docs.rs"));
+        let backend = AnsiBackend::new(ColorDepth::TrueColor);
+        let ansi = lines[0].to_ansi(&backend);
+        assert!(ansi.contains("\x1b]8;;https://docs.rs\x1b\\"), "{:?}", ansi);
+    }
+}
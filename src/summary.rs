@@ -0,0 +1,104 @@
+//! Statistics about rendered content.
+
+/// Statistics of everything rendered so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderSummary {
+    /// Code blocks in document order.
+    pub code_blocks: Vec<CodeBlockStats>,
+}
+
+impl RenderSummary {
+    /// Total lines of code across all code blocks.
+    pub fn code_lines(&self) -> usize {
+        self.code_blocks.iter().map(|block| block.lines).sum()
+    }
+}
+
+/// Size and language of one code block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlockStats {
+    /// Language from the fence, or detected from the code if the fence has
+    /// none.
+    pub language: Option<String>,
+    /// How sure the language is, from 0 to 1. A language given by the fence
+    /// is certain.
+    pub confidence: f32,
+    pub lines: usize,
+    pub chars: usize,
+}
+
+impl CodeBlockStats {
+    pub(crate) fn new(code: &str, fence_language: Option<&str>) -> Self {
+        let (language, confidence) = match fence_language {
+            Some(language) => (Some(language.to_string()), 1.0),
+            None => match detect_language(code) {
+                Some((language, confidence)) => (Some(language.to_string()), confidence),
+                None => (None, 0.0),
+            },
+        };
+        Self {
+            language,
+            confidence,
+            lines: code.lines().count(),
+            chars: code.chars().count(),
+        }
+    }
+}
+
+/// Tokens typical of each detectable language.
+const LANGUAGE_HINTS: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub ", "::", "-> ", "&self"]),
+    ("python", &["def ", "import ", "self.", "elif ", "print(", "None", "):"]),
+    ("javascript", &["function ", "const ", "=> ", "console.", "===", "require("]),
+    ("go", &["func ", "package ", ":= ", "fmt.", "err != nil"]),
+    ("bash", &["echo ", "fi\n", "then", "export ", "$(", "sudo "]),
+];
+
+/// Guess the language of unlabeled code from typical tokens, returning the
+/// best match and its share of all matched tokens. Of languages matching
+/// equally many tokens, the one listed first in `LANGUAGE_HINTS` wins.
+pub(crate) fn detect_language(code: &str) -> Option<(&'static str, f32)> {
+    let scores: Vec<(&str, usize)> = LANGUAGE_HINTS
+        .iter()
+        .map(|(language, hints)| {
+            let score = hints.iter().map(|hint| code.matches(hint).count()).sum();
+            (*language, score)
+        })
+        .collect();
+    let total: usize = scores.iter().map(|(_, score)| score).sum();
+    // `max_by_key` keeps the last of equal elements, so search backwards
+    let (language, best) = scores.into_iter().rev().max_by_key(|(_, score)| *score)?;
+    if best == 0 {
+        return None;
+    }
+    Some((language, best as f32 / total as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_language_is_certain() {
+        let stats = CodeBlockStats::new("x = 1\ny = 2", Some("python"));
+        assert_eq!(stats.language.as_deref(), Some("python"));
+        assert_eq!(stats.confidence, 1.0);
+        assert_eq!((stats.lines, stats.chars), (2, 11));
+    }
+
+    #[test]
+    fn test_detects_unlabeled_language() {
+        let code = "def main():\n    import os\n    print(os.getcwd())";
+        let (language, confidence) = detect_language(code).unwrap();
+        assert_eq!(language, "python");
+        assert!(confidence > 0.5);
+        assert_eq!(detect_language("hello world"), None);
+    }
+
+    #[test]
+    fn test_ties_go_to_the_first_language() {
+        // One Rust token and one Go token
+        let (language, confidence) = detect_language("impl fmt.").unwrap();
+        assert_eq!((language, confidence), ("rust", 0.5));
+    }
+}
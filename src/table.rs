@@ -1,9 +1,103 @@
 //! Table rendering with box-drawing characters.
 
 use crate::inline::render_inline_content;
+use crate::sgr::RESET;
+use crate::strip::{strip_ansi, tokens, Token};
 use crate::style::{InlineStyler, TableStyler};
-use streamdown_ansi::utils::visible_length;
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use crate::width::{grapheme_width_with, text_width, visible_length_with};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Horizontal alignment of a table column, from the separator row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Numbers aligned on their decimal point, other cells right-aligned.
+    Decimal,
+}
+
+/// Parse the alignment markers of a separator row such as `| :--- | :-: | --: |`.
+///
+/// Returns an empty list if the line is not a separator row.
+pub fn parse_alignments(line: &str) -> Vec<ColumnAlignment> {
+    let trimmed = line.trim().trim_start_matches('|').trim_end_matches('|');
+    let mut alignments = Vec::new();
+    for cell in trimmed.split('|') {
+        let cell = cell.trim();
+        let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+        if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+            return Vec::new();
+        }
+        alignments.push(match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => ColumnAlignment::Center,
+            (false, true) => ColumnAlignment::Right,
+            _ => ColumnAlignment::Left,
+        });
+    }
+    alignments
+}
+
+/// Find columns whose body cells are all numbers, optionally with a
+/// currency prefix or a percent or unit suffix, such as `$1,200.50`,
+/// `12.5%`, or `340 ms`. The first row is the header and is not checked.
+pub fn detect_numeric_columns(rows: &[Vec<String>]) -> Vec<bool> {
+    let n = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    (0..n)
+        .map(|i| {
+            let mut cells = rows
+                .iter()
+                .skip(1)
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.trim())
+                .filter(|cell| !cell.is_empty())
+                .peekable();
+            cells.peek().is_some() && cells.all(|cell| decimal_split(cell).is_some())
+        })
+        .collect()
+}
+
+/// Split a numeric cell into the widths before and from its decimal point.
+fn decimal_split(cell: &str) -> Option<(usize, usize)> {
+    let body = cell.trim_start_matches(|c: char| "+-−$€£¥₹(".contains(c));
+    let digits_end = body
+        .find(|c: char| !(c.is_ascii_digit() || c == ',' || c == '_'))
+        .unwrap_or(body.len());
+    if !body[..digits_end].starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let rest = &body[digits_end..];
+    let suffix = match rest.strip_prefix('.') {
+        Some(fraction) => fraction.trim_start_matches(|c: char| c.is_ascii_digit()),
+        None => rest,
+    };
+    let unit = suffix.trim();
+    let unit_ok = unit.chars().count() <= 5
+        && unit.chars().all(|c| c.is_alphabetic() || "%/)µ".contains(c));
+    if !unit_ok {
+        return None;
+    }
+    let point = cell.len() - rest.len();
+    Some((text_width(&cell[..point]), text_width(&cell[point..])))
+}
+
+/// Pad the body cells of a decimal-aligned column so their decimal points
+/// line up once the cells are right-aligned. Cells are measured without
+/// their styling.
+fn pad_decimals(rendered_rows: &mut [Vec<String>], column: usize) {
+    let splits: Vec<Option<(usize, usize)>> = rendered_rows
+        .iter()
+        .skip(1)
+        .map(|row| row.get(column).and_then(|cell| decimal_split(&strip_ansi(cell))))
+        .collect();
+    let frac_width = splits.iter().flatten().map(|(_, f)| *f).max().unwrap_or(0);
+    for (row, split) in rendered_rows.iter_mut().skip(1).zip(splits) {
+        if let (Some(cell), Some((_, frac))) = (row.get_mut(column), split) {
+            cell.push_str(&" ".repeat(frac_width - frac));
+        }
+    }
+}
 
 /// Render a table with proper column widths, shrinking and wrapping if needed.
 pub fn render_table<S: TableStyler + InlineStyler>(
@@ -11,9 +105,45 @@ pub fn render_table<S: TableStyler + InlineStyler>(
     margin: &str,
     styler: &S,
     max_width: usize,
+) -> Vec<String> {
+    render_table_aligned(rows, &[], margin, styler, max_width)
+}
+
+/// Render a table, aligning each column's cells as given.
+///
+/// Columns without an entry in `alignments` are left-aligned.
+pub fn render_table_aligned<S: TableStyler + InlineStyler>(
+    rows: &[Vec<String>],
+    alignments: &[ColumnAlignment],
+    margin: &str,
+    styler: &S,
+    max_width: usize,
+) -> Vec<String> {
+    render_rows(rows, alignments, margin, styler, max_width, false)
+}
+
+/// Render a table without borders, separating columns by two spaces, for
+/// panes too narrow to spend columns on box drawing.
+pub(crate) fn render_table_borderless<S: TableStyler + InlineStyler>(
+    rows: &[Vec<String>],
+    alignments: &[ColumnAlignment],
+    margin: &str,
+    styler: &S,
+    max_width: usize,
+) -> Vec<String> {
+    render_rows(rows, alignments, margin, styler, max_width, true)
+}
+
+fn render_rows<S: TableStyler + InlineStyler>(
+    rows: &[Vec<String>],
+    alignments: &[ColumnAlignment],
+    margin: &str,
+    styler: &S,
+    max_width: usize,
+    borderless: bool,
 ) -> Vec<String> {
     // First, render all cells with inline markdown
-    let rendered_rows: Vec<Vec<String>> = rows
+    let mut rendered_rows: Vec<Vec<String>> = rows
         .iter()
         .map(|row| {
             row.iter()
@@ -25,326 +155,432 @@ pub fn render_table<S: TableStyler + InlineStyler>(
     if rendered_rows.is_empty() || n == 0 {
         return vec![];
     }
+    for (column, alignment) in alignments.iter().enumerate() {
+        if *alignment == ColumnAlignment::Decimal {
+            pad_decimals(&mut rendered_rows, column);
+        }
+    }
+
+    let wide = styler.ambiguous_wide();
+    let margin_width = visible_length_with(margin, wide);
+    let layout = TableLayout::fit(&rendered_rows, margin_width, max_width, borderless, wide);
+    if borderless {
+        return rendered_rows
+            .iter()
+            .enumerate()
+            .flat_map(|(ri, row)| layout.row(row, alignments, ri == 0, margin, styler))
+            .collect();
+    }
+    let mut out = vec![layout.top(margin, styler)];
+    for (ri, row) in rendered_rows.iter().enumerate() {
+        out.extend(layout.row(row, alignments, ri == 0, margin, styler));
 
-    // Calculate column widths based on rendered content
-    let mut w: Vec<usize> = vec![0; n];
-    for row in &rendered_rows {
-        for (i, cell) in row.iter().enumerate() {
-            w[i] = w[i].max(visible_length(cell));
+        // Add row separator (except after last row)
+        if ri < rendered_rows.len() - 1 {
+            out.push(layout.separator(margin, styler));
         }
     }
+    out.push(layout.bottom(margin, styler));
+    out
+}
 
-    // Shrink columns if table exceeds max width
-    let overhead = margin.width() + 1 + 3 * n;
-    let total: usize = w.iter().sum();
-    if overhead + total > max_width && max_width > overhead {
-        let avail = max_width - overhead;
-        w.iter_mut().for_each(|x| *x = (*x * avail / total).max(5));
+/// Column widths of a table, used to draw its borders and rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TableLayout {
+    widths: Vec<usize>,
+    borderless: bool,
+    // Whether ambiguous-width characters in cells take two columns
+    ambiguous_wide: bool,
+}
+
+impl TableLayout {
+    /// Fit the columns to the widest rendered cells, shrinking them if the
+    /// table exceeds `max_width`.
+    fn fit(
+        rendered_rows: &[Vec<String>],
+        margin_width: usize,
+        max_width: usize,
+        borderless: bool,
+        ambiguous_wide: bool,
+    ) -> Self {
+        let n = rendered_rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut w: Vec<usize> = vec![0; n];
+        for row in rendered_rows {
+            for (i, cell) in row.iter().enumerate() {
+                w[i] = w[i].max(visible_length_with(cell, ambiguous_wide));
+            }
+        }
+
+        // Shrink columns if table exceeds max width
+        let overhead = if borderless {
+            margin_width + 2 * n.saturating_sub(1)
+        } else {
+            margin_width + 1 + 3 * n
+        };
+        let total: usize = w.iter().sum();
+        if overhead + total > max_width && max_width > overhead {
+            let avail = max_width - overhead;
+            w.iter_mut().for_each(|x| *x = (*x * avail / total).max(5));
+        }
+        Self {
+            widths: w,
+            borderless,
+            ambiguous_wide,
+        }
     }
 
-    // Helper to create horizontal lines
-    let hline = |l: &str, m: &str, r: &str| {
+    /// Estimate a layout from the rendered header alone, for tables drawn
+    /// before their remaining rows are known.
+    ///
+    /// Each column gets an equal share of the available width, or its
+    /// header's width if that is larger.
+    pub(crate) fn estimate(
+        header: &[String],
+        margin_width: usize,
+        max_width: usize,
+        ambiguous_wide: bool,
+    ) -> Self {
+        let n = header.len().max(1);
+        let overhead = margin_width + 1 + 3 * n;
+        let share = max_width.saturating_sub(overhead) / n;
+        let widths: Vec<usize> = (0..n)
+            .map(|i| {
+                let cell = header
+                    .get(i)
+                    .map(|c| visible_length_with(c, ambiguous_wide))
+                    .unwrap_or(0);
+                cell.max(share)
+            })
+            .collect();
+        let estimate: Vec<String> = widths.iter().map(|&x| " ".repeat(x)).collect();
+        Self::fit(&[estimate], margin_width, max_width, false, ambiguous_wide)
+    }
+
+    fn hline<S: TableStyler>(
+        &self,
+        l: &str,
+        m: &str,
+        r: &str,
+        margin: &str,
+        styler: &S,
+    ) -> String {
         format!(
             "{}{}{}{}",
             margin,
             styler.border(l),
-            w.iter()
-                .map(|&x| styler.border(&"─".repeat(x + 2)))
+            self.widths
+                .iter()
+                .map(|&x| styler.border(&styler.glyphs().horizontal.repeat(x + 2)))
                 .collect::<Vec<_>>()
                 .join(&styler.border(m)),
             styler.border(r)
         )
-    };
+    }
 
-    let mut out = vec![hline("┌", "┬", "┐")];
+    pub(crate) fn top<S: TableStyler>(&self, margin: &str, styler: &S) -> String {
+        let [l, m, r] = &styler.glyphs().table_top;
+        self.hline(l, m, r, margin, styler)
+    }
+
+    pub(crate) fn separator<S: TableStyler>(&self, margin: &str, styler: &S) -> String {
+        let [l, m, r] = &styler.glyphs().table_separator;
+        self.hline(l, m, r, margin, styler)
+    }
+
+    pub(crate) fn bottom<S: TableStyler>(&self, margin: &str, styler: &S) -> String {
+        let [l, m, r] = &styler.glyphs().table_bottom;
+        self.hline(l, m, r, margin, styler)
+    }
+
+    /// Lay out one row of rendered cells, wrapping them to the column widths.
+    ///
+    /// Cells beyond the layout's columns, as in body rows wider than the
+    /// header a progressive layout was estimated from, share the last
+    /// column with the cell before them.
+    pub(crate) fn row<S: TableStyler>(
+        &self,
+        row: &[String],
+        alignments: &[ColumnAlignment],
+        header: bool,
+        margin: &str,
+        styler: &S,
+    ) -> Vec<String> {
+        let w = &self.widths;
+        let n = w.len();
+        let overflow = match row.get(n.saturating_sub(1)..) {
+            Some(extra) if extra.len() > 1 => {
+                let divider = format!(" {} ", styler.border(&styler.glyphs().table_vertical));
+                Some(extra.join(&divider))
+            }
+            _ => None,
+        };
 
-    for (ri, row) in rendered_rows.iter().enumerate() {
         // Wrap each cell's content
         let wrapped: Vec<Vec<String>> = (0..n)
-            .map(|i| wrap(row.get(i).map(|s| s.as_str()).unwrap_or(""), w[i]))
+            .map(|i| {
+                let cell = match &overflow {
+                    Some(last) if i + 1 == n => last.as_str(),
+                    _ => row.get(i).map(|s| s.as_str()).unwrap_or(""),
+                };
+                wrap(cell, w[i], self.ambiguous_wide)
+            })
             .collect();
 
+        let (edge, divider, padding) = if self.borderless {
+            (String::new(), "  ".to_string(), "")
+        } else {
+            let vertical = styler.border(&styler.glyphs().table_vertical);
+            (vertical.clone(), vertical, " ")
+        };
+
         // Render each line of the wrapped cells
+        let mut out = Vec::new();
         for li in 0..wrapped.iter().map(|c| c.len()).max().unwrap_or(1) {
             let cells: String = (0..n)
                 .map(|i| {
                     let c = wrapped[i].get(li).map(|s| s.as_str()).unwrap_or("");
-                    let p = " ".repeat(w[i].saturating_sub(visible_length(c)));
-                    if ri == 0 && li == 0 && !c.is_empty() {
-                        format!(" {}{} ", styler.header(c), p)
+                    let p = w[i].saturating_sub(visible_length_with(c, self.ambiguous_wide));
+                    let (lp, rp) = match alignments.get(i).copied().unwrap_or_default() {
+                        ColumnAlignment::Left => (0, p),
+                        ColumnAlignment::Center => (p / 2, p - p / 2),
+                        ColumnAlignment::Right | ColumnAlignment::Decimal => (p, 0),
+                    };
+                    let (lp, rp) = (" ".repeat(lp), " ".repeat(rp));
+                    let c = if header && li == 0 && !c.is_empty() {
+                        styler.header(c)
                     } else {
-                        format!(" {}{} ", c, p)
-                    }
+                        c.to_string()
+                    };
+                    format!("{}{}{}{}{}", padding, lp, c, rp, padding)
                 })
                 .collect::<Vec<_>>()
-                .join(&styler.border("│"));
-            out.push(format!(
-                "{}{}{}{}",
-                margin,
-                styler.border("│"),
-                cells,
-                styler.border("│")
-            ));
+                .join(&divider);
+            let line = format!("{}{}{}{}", margin, edge, cells, edge);
+            out.push(if self.borderless {
+                line.trim_end().to_string()
+            } else {
+                line
+            });
         }
+        out
+    }
+}
 
-        // Add row separator (except after last row)
-        if ri < rendered_rows.len() - 1 {
-            out.push(hline("├", "┼", "┤"));
+/// Invisible break opportunity that shows a hyphen when a break is taken.
+const SOFT_HYPHEN: char = '\u{ad}';
+/// Invisible break opportunity without a hyphen.
+pub(crate) const ZERO_WIDTH_SPACE: char = '\u{200b}';
+
+/// Wrap text at word boundaries, preserving ANSI codes across lines.
+///
+/// Soft hyphens and zero-width spaces are preferred break points inside
+/// words; a soft hyphen becomes a visible `-` only where a line breaks.
+/// Words longer than the width are broken by character. Styles and OSC 8
+/// hyperlinks open at a break are closed at the end of the line and
+/// reopened at the start of the next. With the `linebreak` feature, UAX #14
+/// break opportunities are break points too. With `ambiguous_wide`,
+/// ambiguous-width characters are measured as two columns.
+pub(crate) fn wrap(text: &str, width: usize, ambiguous_wide: bool) -> Vec<String> {
+    #[cfg(feature = "linebreak")]
+    let text = &crate::linebreak::mark_breaks(text);
+    if width == 0 || visible_length_with(text, ambiguous_wide) <= width {
+        return vec![text.replace([SOFT_HYPHEN, ZERO_WIDTH_SPACE], "")];
+    }
+
+    let mut wrapper = Wrapper::new(width, ambiguous_wide);
+    for token in tokens(text) {
+        match token {
+            Token::Text(run) => wrapper.push_text(run),
+            Token::Escape(_, esc) => wrapper.push_escape(esc),
         }
     }
+    wrapper.finish()
+}
 
-    out.push(hline("└", "┴", "┘"));
-    out
+/// Escape state that must be reopened when a styled span wraps.
+#[derive(Debug, Clone, Default)]
+struct EscapeState {
+    style: String,
+    link: Option<String>,
 }
 
-/// Wrap text by words, preserving ANSI codes across lines.
-/// Breaks at spaces, and tries to keep content together when possible.
-/// Handles both CSI sequences (\x1b[...m) and OSC sequences (\x1b]...\x1b\\).
-fn wrap(text: &str, width: usize) -> Vec<String> {
-    if width == 0 || visible_length(text) <= width {
-        return vec![text.to_string()];
-    }
-
-    let mut lines = Vec::new();
-    let mut line = String::new();
-    let mut line_width = 0;
-    let mut word = String::new();
-    let mut word_width = 0;
-    let mut esc = String::new();
-    let mut in_osc = false;
-    let mut active_style: Option<String> = None;
-
-    let chars: Vec<char> = text.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let c = chars[i];
-
-        // Handle escape sequences
-        if c == '\x1b' {
-            esc.push(c);
-            i += 1;
-
-            // Check what type of sequence
-            if i < chars.len() {
-                let next = chars[i];
-                esc.push(next);
-                i += 1;
-
-                if next == '[' {
-                    // CSI sequence - read until 'm' or other terminator
-                    while i < chars.len() {
-                        let sc = chars[i];
-                        esc.push(sc);
-                        i += 1;
-                        if sc == 'm' || sc == 'K' || sc == 'H' || sc == 'J' {
-                            break;
-                        }
-                    }
-                    // Track active style for CSI color/style sequences
-                    if esc.ends_with('m') {
-                        active_style = if esc == "\x1b[0m" {
-                            None
-                        } else {
-                            Some(esc.clone())
-                        };
-                    }
-                    word.push_str(&esc);
-                    esc.clear();
-                } else if next == ']' {
-                    // OSC sequence - read until \x1b\\
-                    in_osc = true;
-                    while i < chars.len() {
-                        let sc = chars[i];
-                        esc.push(sc);
-                        i += 1;
-                        if sc == '\\' && esc.len() >= 2 {
-                            let prev = esc.chars().rev().nth(1);
-                            if prev == Some('\x1b') {
-                                in_osc = false;
-                                break;
-                            }
-                        }
-                        // Also check for BEL terminator
-                        if sc == '\x07' {
-                            in_osc = false;
-                            break;
-                        }
-                    }
-                    word.push_str(&esc);
-                    esc.clear();
-                } else if next == '\\' && in_osc {
-                    // End of OSC sequence
-                    word.push_str(&esc);
-                    esc.clear();
-                    in_osc = false;
-                } else {
-                    // Unknown sequence, just add it
-                    word.push_str(&esc);
-                    esc.clear();
-                }
+impl EscapeState {
+    fn apply(&mut self, esc: &str) {
+        if let Some(params) = esc.strip_prefix("\x1b]8;") {
+            let url = params
+                .split_once(';')
+                .map(|(_, rest)| rest.trim_end_matches("\x1b\\").trim_end_matches('\x07'))
+                .unwrap_or("");
+            self.link = (!url.is_empty()).then(|| esc.to_string());
+        } else if esc.starts_with("\x1b[") && esc.ends_with('m') {
+            if esc == "\x1b[0m" || esc == "\x1b[m" {
+                self.style.clear();
+            } else {
+                self.style.push_str(esc);
             }
-            continue;
         }
+    }
 
-        let cw = c.width().unwrap_or(0);
-
-        // Check if this is a word boundary (space)
-        if c.is_whitespace() {
-            // Try to add current word + space to line
-            if line_width + word_width + cw > width && line_width > 0 {
-                // Doesn't fit, start new line
-                if !line.is_empty() {
-                    line.push_str("\x1b[0m");
-                    lines.push(line);
-                }
-                line = active_style.clone().unwrap_or_default();
-                line.push_str(&word);
-                line.push(c);
-                line_width = word_width + cw;
-            } else {
-                // Fits on current line
-                line.push_str(&word);
-                line.push(c);
-                line_width += word_width + cw;
-            }
-            word.clear();
-            word_width = 0;
-        } else {
-            // Add character to current word
-            word.push(c);
-            word_width += cw;
-
-            // If word itself exceeds width, break it by character
-            if word_width > width {
-                if !line.is_empty() {
-                    line.push_str("\x1b[0m");
-                    lines.push(line);
-                    line = active_style.clone().unwrap_or_default();
-                    line_width = 0;
-                }
-                // Push the long word, breaking at width while preserving ANSI codes
-                while visible_length(&word) > width {
-                    let (chunk, rem) = split_word_at_width(&word, width);
-                    if !chunk.is_empty() {
-                        line.push_str(&chunk);
-                        line.push_str("\x1b[0m");
-                        lines.push(line);
-                        line = active_style.clone().unwrap_or_default();
-                        line_width = 0;
-                    }
-                    word = rem;
-                }
-                word_width = visible_length(&word);
-            }
+    fn reopen(&self) -> String {
+        format!("{}{}", self.style, self.link.as_deref().unwrap_or(""))
+    }
+
+    /// Terminate a line that ends while this state is active.
+    fn close(&self, line: &mut String) {
+        if self.link.is_some() {
+            line.push_str("\x1b]8;;\x1b\\");
         }
-        i += 1;
+        close_line(line);
     }
+}
 
-    // Add remaining word to line
-    if !word.is_empty() {
-        if line_width + word_width > width && line_width > 0 {
-            if !line.is_empty() {
-                line.push_str("\x1b[0m");
-                lines.push(line);
-            }
-            line = active_style.clone().unwrap_or_default();
-            line.push_str(&word);
-        } else {
-            line.push_str(&word);
+/// Incremental word wrapper used by [`wrap`].
+struct Wrapper {
+    width: usize,
+    ambiguous_wide: bool,
+    lines: Vec<String>,
+    line: String,
+    line_width: usize,
+    // Whitespace between the line and the pending word
+    spaces: String,
+    spaces_width: usize,
+    word: String,
+    word_width: usize,
+    // Escape state before the first token of `word`
+    word_start: EscapeState,
+    state: EscapeState,
+    // The pending word follows a soft hyphen rather than whitespace
+    hyphenate: bool,
+}
+
+impl Wrapper {
+    fn new(width: usize, ambiguous_wide: bool) -> Self {
+        Self {
+            width,
+            ambiguous_wide,
+            lines: Vec::new(),
+            line: String::new(),
+            line_width: 0,
+            spaces: String::new(),
+            spaces_width: 0,
+            word: String::new(),
+            word_width: 0,
+            word_start: EscapeState::default(),
+            state: EscapeState::default(),
+            hyphenate: false,
         }
     }
 
-    if !line.is_empty() {
-        lines.push(line);
+    fn push_escape(&mut self, esc: &str) {
+        if self.word.is_empty() {
+            self.word_start = self.state.clone();
+        }
+        self.word.push_str(esc);
+        self.state.apply(esc);
     }
 
-    if lines.is_empty() {
-        vec![String::new()]
-    } else {
-        lines
+    fn push_text(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            self.push_grapheme(grapheme);
+        }
     }
-}
 
-/// Split a word at a given visible width, preserving ANSI escape sequences.
-fn split_word_at_width(word: &str, width: usize) -> (String, String) {
-    let mut chunk = String::new();
-    let mut chunk_w = 0;
-    let mut remaining = String::new();
-    let mut in_chunk = true;
-    let mut esc = String::new();
-
-    let chars: Vec<char> = word.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let c = chars[i];
-
-        // Handle escape sequences
-        if c == '\x1b' {
-            esc.push(c);
-            i += 1;
-
-            if i < chars.len() {
-                let next = chars[i];
-                esc.push(next);
-                i += 1;
-
-                if next == '[' {
-                    // CSI sequence
-                    while i < chars.len() {
-                        let sc = chars[i];
-                        esc.push(sc);
-                        i += 1;
-                        if sc == 'm' || sc == 'K' || sc == 'H' || sc == 'J' {
-                            break;
-                        }
-                    }
-                } else if next == ']' {
-                    // OSC sequence - read until \x1b\\ or BEL
-                    while i < chars.len() {
-                        let sc = chars[i];
-                        esc.push(sc);
-                        i += 1;
-                        if sc == '\\' && esc.len() >= 2 {
-                            let prev_idx = esc.len() - 2;
-                            if esc.chars().nth(prev_idx) == Some('\x1b') {
-                                break;
-                            }
-                        }
-                        if sc == '\x07' {
-                            break;
-                        }
-                    }
-                }
-            }
+    fn push_grapheme(&mut self, grapheme: &str) {
+        let mut chars = grapheme.chars();
+        if let (Some(c @ (SOFT_HYPHEN | ZERO_WIDTH_SPACE)), None) = (chars.next(), chars.next()) {
+            self.place_word();
+            self.hyphenate = c == SOFT_HYPHEN;
+            return;
+        }
+        let cw = grapheme_width_with(grapheme, self.ambiguous_wide);
+        if grapheme.chars().all(char::is_whitespace) {
+            self.place_word();
+            self.hyphenate = false;
+            self.spaces.push_str(grapheme);
+            self.spaces_width += cw;
+            return;
+        }
 
-            // Add escape sequence to appropriate part
-            if in_chunk {
-                chunk.push_str(&esc);
-            } else {
-                remaining.push_str(&esc);
-            }
-            esc.clear();
-            continue;
+        // A word wider than a whole line is broken by character
+        if self.word_width > 0 && self.word_width + cw > self.width {
+            self.break_word();
+        }
+        if self.word.is_empty() {
+            self.word_start = self.state.clone();
         }
+        self.word.push_str(grapheme);
+        self.word_width += cw;
+    }
 
-        if in_chunk {
-            let cw = c.width().unwrap_or(0);
-            if chunk_w + cw <= width {
-                chunk.push(c);
-                chunk_w += cw;
-            } else {
-                remaining.push(c);
-                in_chunk = false;
+    /// Move the pending word onto the current line, or start a new line.
+    fn place_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+        if self.word_width == 0 {
+            // Escapes only; keep the whitespace pending for the next word
+            self.line.push_str(&self.word);
+            self.word.clear();
+            return;
+        }
+        if self.line_width > 0 && self.line_width + self.spaces_width + self.word_width > self.width
+        {
+            let mut line = std::mem::replace(&mut self.line, self.word_start.reopen());
+            if self.hyphenate && self.line_width < self.width {
+                line.push('-');
             }
+            self.word_start.close(&mut line);
+            self.lines.push(line);
+            self.line_width = 0;
         } else {
-            remaining.push(c);
+            self.line.push_str(&self.spaces);
+            self.line_width += self.spaces_width;
         }
-        i += 1;
+        self.line.push_str(&self.word);
+        self.line_width += self.word_width;
+        self.spaces.clear();
+        self.spaces_width = 0;
+        self.word.clear();
+        self.word_width = 0;
+        self.hyphenate = false;
     }
 
-    (chunk, remaining)
+    /// Emit the pending word as a full line of its own.
+    fn break_word(&mut self) {
+        if self.line_width > 0 {
+            let mut line = std::mem::replace(&mut self.line, self.word_start.reopen());
+            self.word_start.close(&mut line);
+            self.lines.push(line);
+        }
+        let mut line = std::mem::replace(&mut self.line, self.state.reopen());
+        line.push_str(&self.word);
+        self.state.close(&mut line);
+        self.lines.push(line);
+        self.line_width = 0;
+        self.spaces.clear();
+        self.spaces_width = 0;
+        self.word.clear();
+        self.word_width = 0;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.place_word();
+        if !self.line.is_empty() {
+            self.lines.push(self.line);
+        }
+        if self.lines.is_empty() {
+            vec![String::new()]
+        } else {
+            self.lines
+        }
+    }
+}
+
+/// Terminate a wrapped line, resetting styles only if it carries escapes.
+fn close_line(line: &mut String) {
+    if line.contains('\x1b') {
+        line.push_str(RESET);
+    }
 }
 
 #[cfg(test)]
@@ -384,6 +620,88 @@ mod tests {
         strip_ansi(&output)
     }
 
+    #[test]
+    fn test_parse_alignments() {
+        assert_eq!(
+            parse_alignments("| :--- | :-: | --: | --- |"),
+            vec![
+                ColumnAlignment::Left,
+                ColumnAlignment::Center,
+                ColumnAlignment::Right,
+                ColumnAlignment::Left,
+            ]
+        );
+        assert!(parse_alignments("| a | b |").is_empty());
+    }
+
+    #[test]
+    fn test_aligned_columns() {
+        let rows: Vec<Vec<String>> = vec![
+            vec!["Left".into(), "Mid".into(), "Right".into()],
+            vec!["a".into(), "b".into(), "c".into()],
+        ];
+        let alignments = [
+            ColumnAlignment::Left,
+            ColumnAlignment::Center,
+            ColumnAlignment::Right,
+        ];
+        let output = render_table_aligned(&rows, &alignments, "", &TagStyler, 80).join("\n");
+        insta::assert_snapshot!(strip_ansi(&output), @r"
+        ┌──────┬─────┬───────┐
+        │ Left │ Mid │ Right │
+        ├──────┼─────┼───────┤
+        │ a    │  b  │     c │
+        └──────┴─────┴───────┘
+        ");
+    }
+
+    #[test]
+    fn test_detect_numeric_columns() {
+        let rows: Vec<Vec<String>> = vec![
+            vec!["Item".into(), "Price".into(), "Change".into(), "Time".into()],
+            vec!["A".into(), "$1,200.5".into(), "-3%".into(), "340 ms".into()],
+            vec!["B".into(), "$15".into(), "+12.25%".into(), "n/a".into()],
+        ];
+        assert_eq!(detect_numeric_columns(&rows), vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn test_decimal_alignment() {
+        let rows: Vec<Vec<String>> = vec![
+            vec!["Price".into()],
+            vec!["$1,200.5".into()],
+            vec!["$15".into()],
+            vec!["0.125".into()],
+        ];
+        let alignments = [ColumnAlignment::Decimal];
+        let output = render_table_aligned(&rows, &alignments, "", &TagStyler, 80).join("\n");
+        insta::assert_snapshot!(strip_ansi(&output), @r"
+        ┌────────────┐
+        │      Price │
+        ├────────────┤
+        │ $1,200.5   │
+        ├────────────┤
+        │    $15     │
+        ├────────────┤
+        │      0.125 │
+        └────────────┘
+        ");
+    }
+
+    #[test]
+    fn test_decimal_alignment_of_styled_cells() {
+        let rows: Vec<Vec<String>> = vec![
+            vec!["Price".into()],
+            vec!["**1.5**".into()],
+            vec!["10.25".into()],
+        ];
+        let theme = Theme::dark().with_backend(crate::backend::AnsiBackend::default());
+        let output = render_table_aligned(&rows, &[ColumnAlignment::Decimal], "", &theme, 80);
+        let lines: Vec<String> = output.iter().map(|line| strip_ansi(line)).collect();
+        assert_eq!(lines[3], "│  1.5  │");
+        assert_eq!(lines[5], "│ 10.25 │");
+    }
+
     #[test]
     fn test_simple_table() {
         insta::assert_snapshot!(render(vec![vec!["Name", "Age"], vec!["Alice", "30"],]));
@@ -498,45 +816,67 @@ mod tests {
 
     #[test]
     fn test_wrap_no_wrap_needed() {
-        let result = wrap("hello", 10);
+        let result = wrap("hello", 10, false);
         assert_eq!(result, vec!["hello"]);
     }
 
     #[test]
     fn test_wrap_exact_width() {
-        let result = wrap("hello", 5);
+        let result = wrap("hello", 5, false);
         assert_eq!(result, vec!["hello"]);
     }
 
     #[test]
     fn test_wrap_splits_text() {
-        let result = wrap("hello world", 5);
-        // Word-based wrapping: "hello " and "world" = 2 lines
+        let result = wrap("hello world", 5, false);
+        // Word-based wrapping: "hello" and "world" = 2 lines
         assert_eq!(result.len(), 2);
         let strip = |s: &str| String::from_utf8(strip_ansi_escapes::strip(s)).unwrap();
-        assert_eq!(strip(&result[0]), "hello ");
+        assert_eq!(strip(&result[0]), "hello");
         assert_eq!(strip(&result[1]), "world");
     }
 
+    #[test]
+    fn test_wrap_breaks_long_words_and_reopens_style() {
+        let result = wrap("\x1b[1mab cdefgh\x1b[0m", 3, false);
+        assert_eq!(
+            result,
+            vec![
+                "\x1b[1mab\x1b[0m",
+                "\x1b[1mcde\x1b[0m",
+                "\x1b[1mfgh\x1b[0m",
+            ]
+        );
+    }
+
     #[test]
     fn test_wrap_empty() {
-        let result = wrap("", 10);
+        let result = wrap("", 10, false);
         assert_eq!(result, vec![""]);
     }
 
     #[test]
     fn test_wrap_zero_width() {
-        let result = wrap("hello", 0);
+        let result = wrap("hello", 0, false);
         assert_eq!(result, vec!["hello"]);
     }
 
     #[test]
     fn test_wrap_unicode() {
         // Chinese chars are 2 wide each
-        let result = wrap("你好世界", 4);
+        let result = wrap("你好世界", 4, false);
         assert_eq!(result.len(), 2); // "你好" and "世界"
     }
 
+    #[test]
+    fn test_wrap_at_soft_hyphens() {
+        let result = wrap("Donau\u{ad}dampf\u{ad}schiff", 12, false);
+        assert_eq!(result, vec!["Donaudampf-", "schiff"]);
+        let result = wrap("path/\u{200b}to/\u{200b}file", 9, false);
+        assert_eq!(result, vec!["path/to/", "file"]);
+        assert_eq!(wrap("a\u{ad}b", 10, false), vec!["ab"]);
+    }
+
     // ==================== Edge cases ====================
 
     #[test]
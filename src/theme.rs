@@ -2,19 +2,182 @@
 //!
 //! Provides customizable styling for all markdown elements using the `colored` crate.
 
+use std::sync::{Arc, OnceLock};
+
 use colored::{Color, ColoredString, Colorize};
 use streamdown_parser::decode_html_entities;
 
+use crate::backend::{AnsiBackend, OutputBackend, PlainBackend};
+use crate::heading::HeadingDecoration;
+use crate::links::strip_controls;
+use crate::profile::TerminalProfile;
 use crate::style::{HeadingStyler, InlineStyler, ListStyler, TableStyler};
 
+/// Default glyph shown in image placeholders (U+1F5BC FRAME WITH PICTURE).
+pub const DEFAULT_IMAGE_ICON: &str = "\u{1f5bc}";
+
+/// Default image placeholder format.
+pub const DEFAULT_IMAGE_FORMAT: &str = "[{icon} {alt}]";
+
+/// Characters drawing bullets, borders and rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glyphs {
+    /// Bullets of `-`, `*` and `+` lists, cycled by nesting level.
+    pub bullets_dash: Vec<String>,
+    pub bullets_asterisk: Vec<String>,
+    pub bullets_plus: Vec<String>,
+    /// Marker of expandable list items.
+    pub bullet_plus_expand: String,
+    pub blockquote_border: String,
+    /// Think-block corners before the title and after the content, and the
+    /// border beside its lines.
+    pub think_top: String,
+    pub think_bottom: String,
+    pub think_border: String,
+    /// Line of rules, think-block titles and table borders.
+    pub horizontal: String,
+    /// Glyph done status markers such as `[x]` are rewritten to.
+    pub status_done: String,
+    /// Glyph failed status markers such as `❌` are rewritten to.
+    pub status_failed: String,
+    /// Glyph pending status markers such as `[ ]` are rewritten to.
+    pub status_pending: String,
+    /// Left, inner and right corners of the table top, the header
+    /// separator and the table bottom.
+    pub table_top: [String; 3],
+    pub table_separator: [String; 3],
+    pub table_bottom: [String; 3],
+    pub table_vertical: String,
+    /// Top-left, top-right, bottom-left and bottom-right corners of code
+    /// frames, whose sides are `horizontal` and `table_vertical`.
+    pub frame_corners: [String; 4],
+    /// Typing cursor drawn after the output while the stream is active.
+    pub cursor: String,
+    /// Separator between the headings of a breadcrumb.
+    pub breadcrumb_separator: String,
+    /// Borders beside the stdout and stderr lines of command output.
+    pub output_border: String,
+    pub stderr_border: String,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl Glyphs {
+    /// Box drawing characters and symbol bullets.
+    pub fn unicode() -> Self {
+        let strings = |glyphs: &[&str]| glyphs.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+        let corners = |l: &str, m: &str, r: &str| [l.to_string(), m.to_string(), r.to_string()];
+        Self {
+            bullets_dash: strings(&["•", "◦", "▪", "‣"]),
+            bullets_asterisk: strings(&["∗", "⁎", "✱", "✳"]),
+            bullets_plus: strings(&["⊕", "⊙", "⊛", "⊜"]),
+            bullet_plus_expand: "⊞".to_string(),
+            blockquote_border: "│".to_string(),
+            think_top: "┌".to_string(),
+            think_bottom: "└".to_string(),
+            think_border: "│".to_string(),
+            horizontal: "─".to_string(),
+            status_done: "✔".to_string(),
+            status_failed: "✘".to_string(),
+            status_pending: "◌".to_string(),
+            table_top: corners("┌", "┬", "┐"),
+            table_separator: corners("├", "┼", "┤"),
+            table_bottom: corners("└", "┴", "┘"),
+            table_vertical: "│".to_string(),
+            frame_corners: ["╭", "╮", "╰", "╯"].map(String::from),
+            cursor: "▌".to_string(),
+            breadcrumb_separator: " › ".to_string(),
+            output_border: "│".to_string(),
+            stderr_border: "┃".to_string(),
+        }
+    }
+
+    /// ASCII only: `+--+` borders and `*` bullets.
+    pub fn ascii() -> Self {
+        let plus = || ["+".to_string(), "+".to_string(), "+".to_string()];
+        Self {
+            bullets_dash: vec!["*".to_string()],
+            bullets_asterisk: vec!["*".to_string()],
+            bullets_plus: vec!["*".to_string()],
+            bullet_plus_expand: "+".to_string(),
+            blockquote_border: "|".to_string(),
+            think_top: "+".to_string(),
+            think_bottom: "+".to_string(),
+            think_border: "|".to_string(),
+            horizontal: "-".to_string(),
+            status_done: "[x]".to_string(),
+            status_failed: "[!]".to_string(),
+            status_pending: "[ ]".to_string(),
+            table_top: plus(),
+            table_separator: plus(),
+            table_bottom: plus(),
+            table_vertical: "|".to_string(),
+            frame_corners: ["+", "+", "+", "+"].map(String::from),
+            cursor: "_".to_string(),
+            breadcrumb_separator: " > ".to_string(),
+            output_border: "|".to_string(),
+            stderr_border: "!".to_string(),
+        }
+    }
+
+    /// Shared [`unicode`](Self::unicode) or [`ascii`](Self::ascii) glyphs.
+    pub(crate) fn shared(unicode: bool) -> &'static Self {
+        static UNICODE: OnceLock<Glyphs> = OnceLock::new();
+        static ASCII: OnceLock<Glyphs> = OnceLock::new();
+        if unicode {
+            UNICODE.get_or_init(Self::unicode)
+        } else {
+            ASCII.get_or_init(Self::ascii)
+        }
+    }
+
+    /// Bullet of a list at nesting `level`.
+    pub(crate) fn bullet(bullets: &[String], level: usize) -> &str {
+        match bullets.len() {
+            0 => "",
+            len => &bullets[level % len],
+        }
+    }
+}
+
+/// Kind of a badge, selecting its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadgeKind {
+    Success,
+    Failure,
+    Warning,
+    Info,
+    Neutral,
+}
+
+/// Shape of an underline.
+///
+/// Terminals without extended underline support draw every shape as a
+/// straight line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    #[default]
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
 /// Style configuration for a single element.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Style {
     pub fg: Option<Color>,
     pub bg: Option<Color>,
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    pub underline_style: UnderlineStyle,
+    pub underline_color: Option<Color>,
     pub strikethrough: bool,
     pub dimmed: bool,
 }
@@ -27,6 +190,8 @@ impl Default for Style {
             bold: false,
             italic: false,
             underline: false,
+            underline_style: UnderlineStyle::Straight,
+            underline_color: None,
             strikethrough: false,
             dimmed: false,
         }
@@ -63,6 +228,19 @@ impl Style {
         self
     }
 
+    /// Underline with the given shape.
+    pub fn underline_style(mut self, style: UnderlineStyle) -> Self {
+        self.underline = true;
+        self.underline_style = style;
+        self
+    }
+
+    /// Color the underline independently of the text.
+    pub fn underline_color(mut self, color: Color) -> Self {
+        self.underline_color = Some(color);
+        self
+    }
+
     pub fn strikethrough(mut self) -> Self {
         self.strikethrough = true;
         self
@@ -73,7 +251,30 @@ impl Style {
         self
     }
 
+    /// Combine with `other`, whose colors take precedence when set.
+    pub fn merge(&self, other: &Style) -> Style {
+        Style {
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg),
+            bold: self.bold || other.bold,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            underline_style: if other.underline {
+                other.underline_style
+            } else {
+                self.underline_style
+            },
+            underline_color: other.underline_color.or(self.underline_color),
+            strikethrough: self.strikethrough || other.strikethrough,
+            dimmed: self.dimmed || other.dimmed,
+        }
+    }
+
     /// Apply this style to a string.
+    ///
+    /// Styling is done by the `colored` crate, which decides on its own
+    /// whether to emit escapes and does not know underline styles or
+    /// colors; use [`Theme::paint`] to honor the theme's backend instead.
     pub fn apply(&self, text: &str) -> ColoredString {
         let mut result = text.normal();
 
@@ -111,6 +312,7 @@ pub struct Theme {
     pub italic: Style,
     pub code: Style,
     pub strikethrough: Style,
+    pub underline: Style,
     pub link: Style,
     pub link_url: Style,
 
@@ -121,6 +323,8 @@ pub struct Theme {
     pub heading4: Style,
     pub heading5: Style,
     pub heading6: Style,
+    /// Decoration of each heading level, H1 first.
+    pub heading_decorations: [HeadingDecoration; 6],
 
     // List styles
     pub bullet_dash: Style,
@@ -130,6 +334,9 @@ pub struct Theme {
     pub list_number: Style,
     pub checkbox_checked: Style,
     pub checkbox_unchecked: Style,
+    pub status_done: Style,
+    pub status_failed: Style,
+    pub status_pending: Style,
 
     // Table styles
     pub table_header: Style,
@@ -144,9 +351,40 @@ pub struct Theme {
 
     // Code block
     pub code_block_lang: Style,
+    pub code_gutter: Style,
+    pub code_frame: Style,
+    pub doc_comment: Style,
+    pub doc_heading: Style,
+    pub diff_added: Style,
+    pub diff_removed: Style,
+    pub diff_hunk: Style,
 
     // Horizontal rule
     pub hr: Style,
+
+    // Timestamp gutter
+    pub timestamp: Style,
+
+    // Command output
+    pub output_border: Style,
+    pub stderr_border: Style,
+
+    // Badges
+    pub badge_success: Style,
+    pub badge_failure: Style,
+    pub badge_warning: Style,
+    pub badge_info: Style,
+    pub badge_neutral: Style,
+
+    // Image placeholder: `image_format` may reference `{icon}`, `{alt}` and `{url}`
+    pub image_icon: String,
+    pub image_format: String,
+
+    // Bullet, border and rule characters
+    pub glyphs: Glyphs,
+
+    // Escape-sequence emission
+    pub backend: Arc<dyn OutputBackend>,
 }
 
 impl Default for Theme {
@@ -162,47 +400,44 @@ impl InlineStyler for Theme {
     }
 
     fn bold(&self, text: &str) -> String {
-        self.bold.apply(&decode_html_entities(text)).to_string()
+        self.paint(&self.bold, &decode_html_entities(text))
     }
 
     fn italic(&self, text: &str) -> String {
-        self.italic.apply(&decode_html_entities(text)).to_string()
+        self.paint(&self.italic, &decode_html_entities(text))
     }
 
     fn bold_italic(&self, text: &str) -> String {
-        let decoded = decode_html_entities(text);
-        let styled = self.bold.apply(&decoded);
-        self.italic.apply(&styled.to_string()).to_string()
+        let style = self.bold.merge(&self.italic);
+        self.paint(&style, &decode_html_entities(text))
     }
 
     fn strikethrough(&self, text: &str) -> String {
-        self.strikethrough
-            .apply(&decode_html_entities(text))
-            .to_string()
+        self.paint(&self.strikethrough, &decode_html_entities(text))
     }
 
     fn underline(&self, text: &str) -> String {
-        format!("\x1b[4m{}\x1b[24m", decode_html_entities(text))
+        self.paint(&self.underline, &decode_html_entities(text))
     }
 
     fn code(&self, text: &str) -> String {
-        self.code.apply(text).to_string()
+        self.paint(&self.code, text)
     }
 
     fn link(&self, text: &str, url: &str) -> String {
-        let mut result = String::new();
-        result.push_str("\x1b]8;;");
-        result.push_str(url);
-        result.push_str("\x1b\\");
-        result.push_str(&self.link.apply(&decode_html_entities(text)).to_string());
-        result.push_str("\x1b]8;;\x1b\\");
+        let label = self.paint(&self.link, &decode_html_entities(text));
+        let mut result = self.backend.hyperlink(&label, url);
         result.push(' ');
-        result.push_str(&self.link_url.apply(&format!("({})", url)).to_string());
+        let url = format!("({})", strip_controls(url));
+        result.push_str(&self.paint(&self.link_url, &url));
         result
     }
 
-    fn image(&self, alt: &str, _url: &str) -> String {
-        format!("[🖼 {}]", alt)
+    fn image(&self, alt: &str, url: &str) -> String {
+        match shield_badge(alt, url) {
+            Some((text, kind)) => self.badge(&text, kind),
+            None => self.image_placeholder(alt, url),
+        }
     }
 
     fn footnote(&self, text: &str) -> String {
@@ -210,80 +445,168 @@ impl InlineStyler for Theme {
     }
 
     fn dimmed(&self, text: &str) -> String {
-        Style::new().dimmed().apply(text).to_string()
+        self.paint(&Style::new().dimmed(), text)
     }
 }
 
 impl HeadingStyler for Theme {
     fn h1(&self, text: &str) -> String {
-        self.heading1.apply(text).to_string()
+        self.paint(&self.heading1, text)
     }
 
     fn h2(&self, text: &str) -> String {
-        self.heading2.apply(text).to_string()
+        self.paint(&self.heading2, text)
     }
 
     fn h3(&self, text: &str) -> String {
-        self.heading3.apply(text).to_string()
+        self.paint(&self.heading3, text)
     }
 
     fn h4(&self, text: &str) -> String {
-        self.heading4.apply(text).to_string()
+        self.paint(&self.heading4, text)
     }
 
     fn h5(&self, text: &str) -> String {
-        self.heading5.apply(text).to_string()
+        self.paint(&self.heading5, text)
     }
 
     fn h6(&self, text: &str) -> String {
-        self.heading6.apply(text).to_string()
+        self.paint(&self.heading6, text)
+    }
+
+    fn decoration(&self, level: u8) -> HeadingDecoration {
+        self.heading_decorations[usize::from(level.clamp(1, 6)) - 1]
     }
 }
 
 impl ListStyler for Theme {
     fn bullet_dash(&self, text: &str) -> String {
-        self.bullet_dash.apply(text).to_string()
+        self.paint(&self.bullet_dash, text)
     }
 
     fn bullet_asterisk(&self, text: &str) -> String {
-        self.bullet_asterisk.apply(text).to_string()
+        self.paint(&self.bullet_asterisk, text)
     }
 
     fn bullet_plus(&self, text: &str) -> String {
-        self.bullet_plus.apply(text).to_string()
+        self.paint(&self.bullet_plus, text)
     }
 
     fn bullet_plus_expand(&self, text: &str) -> String {
-        self.bullet_plus_expand.apply(text).to_string()
+        self.paint(&self.bullet_plus_expand, text)
     }
 
     fn number(&self, text: &str) -> String {
-        self.list_number.apply(text).to_string()
+        self.paint(&self.list_number, text)
+    }
+
+    fn glyphs(&self) -> &Glyphs {
+        &self.glyphs
     }
 }
 
 impl TableStyler for Theme {
     fn border(&self, text: &str) -> String {
-        self.table_border.apply(text).to_string()
+        self.paint(&self.table_border, text)
     }
 
     fn header(&self, text: &str) -> String {
-        self.table_header.apply(text).to_string()
+        self.paint(&self.table_header, text)
+    }
+
+    fn glyphs(&self) -> &Glyphs {
+        &self.glyphs
     }
 }
 
 impl Theme {
+    /// Replace the backend used to emit escape sequences.
+    pub fn with_backend<B: OutputBackend + 'static>(mut self, backend: B) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Paint `text` with `style` through the configured backend.
+    pub fn paint(&self, style: &Style, text: &str) -> String {
+        self.backend.paint(style, text)
+    }
+
+    /// Sequence that clears every active attribute.
+    pub fn reset(&self) -> &'static str {
+        self.backend.reset()
+    }
+
+    /// Format the placeholder shown in place of an image.
+    ///
+    /// The format is read in one pass, so placeholders inside the alt text
+    /// or URL are not expanded.
+    pub fn image_placeholder(&self, alt: &str, url: &str) -> String {
+        let mut result = String::new();
+        let mut rest = self.image_format.as_str();
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let value = if rest.starts_with("{icon}") {
+                Some(self.image_icon.clone())
+            } else if rest.starts_with("{alt}") {
+                Some(decode_html_entities(alt))
+            } else if rest.starts_with("{url}") {
+                Some(url.to_string())
+            } else {
+                None
+            };
+            match value {
+                Some(value) => {
+                    result.push_str(&value);
+                    rest = &rest[rest.find('}').unwrap_or(0) + 1..];
+                }
+                None => {
+                    result.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Format a pill-style label such as `[ PASS ]` with the colors for `kind`.
+    pub fn badge(&self, text: &str, kind: BadgeKind) -> String {
+        let style = match kind {
+            BadgeKind::Success => &self.badge_success,
+            BadgeKind::Failure => &self.badge_failure,
+            BadgeKind::Warning => &self.badge_warning,
+            BadgeKind::Info => &self.badge_info,
+            BadgeKind::Neutral => &self.badge_neutral,
+        };
+        self.paint(style, &format!("[ {} ]", text))
+    }
+
     /// Detects the terminal theme (dark or light) and returns the appropriate theme.
     pub fn detect() -> Self {
+        Self::auto()
+    }
+
+    /// Picks the dark or light preset for the terminal background, with the
+    /// backend of the [detected](TerminalProfile::detect) terminal profile.
+    ///
+    /// Uses `COLORFGBG` when set, otherwise queries the background color
+    /// with OSC 11, falling back to the dark preset.
+    pub fn auto() -> Self {
         use crate::utils::{detect_theme_mode, ThemeMode};
 
-        match detect_theme_mode() {
+        let theme = match detect_theme_mode() {
             ThemeMode::Light => Self::light(),
             ThemeMode::Dark => Self::dark(),
+        };
+        Self {
+            backend: TerminalProfile::detect().backend(),
+            ..theme
         }
     }
 
-    /// Dark theme (default).
+    /// Dark theme (default), emitting 24-bit ANSI escapes whatever the
+    /// environment; [`auto`](Self::auto) picks the backend for the terminal.
     pub fn dark() -> Self {
         Self {
             // Inline
@@ -291,6 +614,7 @@ impl Theme {
             italic: Style::new().italic(),
             code: Style::new().fg(Color::Yellow),
             strikethrough: Style::new().strikethrough().dimmed(),
+            underline: Style::new().underline(),
             link: Style::new().fg(Color::Cyan).underline(),
             link_url: Style::new().fg(Color::Blue).dimmed(),
 
@@ -301,6 +625,7 @@ impl Theme {
             heading4: Style::new().fg(Color::Green).bold(),
             heading5: Style::new().fg(Color::Yellow).bold(),
             heading6: Style::new().fg(Color::White).bold(),
+            heading_decorations: [HeadingDecoration::Prefix; 6],
 
             // Lists
             bullet_dash: Style::new().fg(Color::Cyan),
@@ -310,6 +635,9 @@ impl Theme {
             list_number: Style::new().fg(Color::Cyan),
             checkbox_checked: Style::new().fg(Color::Green),
             checkbox_unchecked: Style::new().fg(Color::Red),
+            status_done: Style::new().fg(Color::Green),
+            status_failed: Style::new().fg(Color::Red),
+            status_pending: Style::new().fg(Color::Yellow),
 
             // Tables
             table_header: Style::new().bold(),
@@ -324,9 +652,38 @@ impl Theme {
 
             // Code block
             code_block_lang: Style::new().fg(Color::BrightBlack).italic(),
+            code_gutter: Style::new().fg(Color::BrightBlack).dimmed(),
+            code_frame: Style::new().fg(Color::BrightBlack),
+            doc_comment: Style::new().italic().fg(Color::Green),
+            doc_heading: Style::new().bold().fg(Color::Green),
+            diff_added: Style::new().fg(Color::Green),
+            diff_removed: Style::new().fg(Color::Red),
+            diff_hunk: Style::new().fg(Color::Cyan),
 
             // HR
             hr: Style::new().fg(Color::BrightBlack),
+
+            // Timestamp gutter
+            timestamp: Style::new().fg(Color::BrightBlack).dimmed(),
+
+            // Command output
+            output_border: Style::new().fg(Color::BrightBlack),
+            stderr_border: Style::new().fg(Color::Red),
+
+            // Badges
+            badge_success: Style::new().fg(Color::Black).bg(Color::Green).bold(),
+            badge_failure: Style::new().fg(Color::White).bg(Color::Red).bold(),
+            badge_warning: Style::new().fg(Color::Black).bg(Color::Yellow).bold(),
+            badge_info: Style::new().fg(Color::Black).bg(Color::Cyan).bold(),
+            badge_neutral: Style::new().fg(Color::White).bg(Color::BrightBlack),
+
+            // Image placeholder
+            image_icon: DEFAULT_IMAGE_ICON.to_string(),
+            image_format: DEFAULT_IMAGE_FORMAT.to_string(),
+
+            glyphs: Glyphs::default(),
+
+            backend: Arc::new(AnsiBackend::default()),
         }
     }
 
@@ -338,6 +695,7 @@ impl Theme {
             italic: Style::new().italic(),
             code: Style::new().fg(Color::Red),
             strikethrough: Style::new().strikethrough().dimmed(),
+            underline: Style::new().underline(),
             link: Style::new().fg(Color::Blue).underline(),
             link_url: Style::new().fg(Color::Cyan).dimmed(),
 
@@ -348,6 +706,7 @@ impl Theme {
             heading4: Style::new().fg(Color::Green).bold(),
             heading5: Style::new().fg(Color::Yellow).bold(),
             heading6: Style::new().fg(Color::Black).bold(),
+            heading_decorations: [HeadingDecoration::Prefix; 6],
 
             // Lists
             bullet_dash: Style::new().fg(Color::Blue),
@@ -357,6 +716,9 @@ impl Theme {
             list_number: Style::new().fg(Color::Blue),
             checkbox_checked: Style::new().fg(Color::Green),
             checkbox_unchecked: Style::new().fg(Color::Red),
+            status_done: Style::new().fg(Color::Green),
+            status_failed: Style::new().fg(Color::Red),
+            status_pending: Style::new().fg(Color::Yellow),
 
             // Tables
             table_header: Style::new().bold(),
@@ -371,13 +733,169 @@ impl Theme {
 
             // Code block
             code_block_lang: Style::new().fg(Color::Black).italic(),
+            code_gutter: Style::new().fg(Color::Black).dimmed(),
+            code_frame: Style::new().fg(Color::Black),
+            doc_comment: Style::new().italic().fg(Color::Green),
+            doc_heading: Style::new().bold().fg(Color::Green),
+            diff_added: Style::new().fg(Color::Green),
+            diff_removed: Style::new().fg(Color::Red),
+            diff_hunk: Style::new().fg(Color::Cyan),
 
             // HR
             hr: Style::new().fg(Color::Black),
+
+            // Timestamp gutter
+            timestamp: Style::new().fg(Color::Black).dimmed(),
+
+            // Command output
+            output_border: Style::new().fg(Color::Black),
+            stderr_border: Style::new().fg(Color::Red),
+
+            // Badges
+            badge_success: Style::new().fg(Color::White).bg(Color::Green).bold(),
+            badge_failure: Style::new().fg(Color::White).bg(Color::Red).bold(),
+            badge_warning: Style::new().fg(Color::Black).bg(Color::Yellow).bold(),
+            badge_info: Style::new().fg(Color::White).bg(Color::Blue).bold(),
+            badge_neutral: Style::new().fg(Color::Black).bg(Color::White),
+
+            // Image placeholder
+            image_icon: DEFAULT_IMAGE_ICON.to_string(),
+            image_format: DEFAULT_IMAGE_FORMAT.to_string(),
+
+            glyphs: Glyphs::default(),
+
+            backend: Arc::new(AnsiBackend::default()),
         }
     }
+
+    /// Monochrome theme using only text attributes, for any background.
+    pub fn monochrome() -> Self {
+        Self {
+            // Inline
+            bold: Style::new().bold(),
+            italic: Style::new().italic(),
+            code: Style::new().bold(),
+            strikethrough: Style::new().strikethrough(),
+            underline: Style::new().underline(),
+            link: Style::new().underline(),
+            link_url: Style::new().dimmed(),
+
+            // Headings
+            heading1: Style::new().bold().underline(),
+            heading2: Style::new().bold(),
+            heading3: Style::new().bold(),
+            heading4: Style::new().bold().italic(),
+            heading5: Style::new().italic(),
+            heading6: Style::new().italic().dimmed(),
+            heading_decorations: [HeadingDecoration::Prefix; 6],
+
+            // Lists
+            bullet_dash: Style::new(),
+            bullet_asterisk: Style::new(),
+            bullet_plus: Style::new(),
+            bullet_plus_expand: Style::new().bold(),
+            list_number: Style::new().bold(),
+            checkbox_checked: Style::new().bold(),
+            checkbox_unchecked: Style::new().dimmed(),
+            status_done: Style::new().bold(),
+            status_failed: Style::new().bold().italic(),
+            status_pending: Style::new().dimmed(),
+
+            // Tables
+            table_header: Style::new().bold(),
+            table_border: Style::new().dimmed(),
+            table_cell: Style::new(),
+
+            // Quotes
+            blockquote: Style::new().italic().dimmed(),
+            blockquote_border: Style::new().dimmed(),
+            think: Style::new().italic().dimmed(),
+            think_border: Style::new().dimmed(),
+
+            // Code block
+            code_block_lang: Style::new().italic().dimmed(),
+            code_gutter: Style::new().dimmed(),
+            code_frame: Style::new().dimmed(),
+            doc_comment: Style::new().italic(),
+            doc_heading: Style::new().bold(),
+            diff_added: Style::new().bold(),
+            diff_removed: Style::new().strikethrough(),
+            diff_hunk: Style::new().dimmed(),
+
+            // HR
+            hr: Style::new().dimmed(),
+
+            // Timestamp gutter
+            timestamp: Style::new().dimmed(),
+
+            // Command output
+            output_border: Style::new().dimmed(),
+            stderr_border: Style::new().bold(),
+
+            // Badges
+            badge_success: Style::new().bold(),
+            badge_failure: Style::new().bold().underline(),
+            badge_warning: Style::new().bold().italic(),
+            badge_info: Style::new().bold(),
+            badge_neutral: Style::new().dimmed(),
+
+            // Image placeholder
+            image_icon: DEFAULT_IMAGE_ICON.to_string(),
+            image_format: DEFAULT_IMAGE_FORMAT.to_string(),
+
+            glyphs: Glyphs::default(),
+
+            backend: Arc::new(AnsiBackend::default()),
+        }
+    }
+
+    /// Unstyled theme for log files and snapshot tests.
+    ///
+    /// Output keeps the layout of the styled themes (wrapping, borders,
+    /// bullets) but contains no escape sequences at all.
+    pub fn plain() -> Self {
+        Self::monochrome().with_backend(PlainBackend)
+    }
 }
 
+/// Recognize a shields.io badge image and return its text and kind.
+///
+/// Badge URLs have the form `/badge/<label>-<message>-<color>`, where `--`
+/// stands for a literal dash and `_` for a space. The alt text is used when
+/// the path cannot be parsed.
+fn shield_badge(alt: &str, url: &str) -> Option<(String, BadgeKind)> {
+    let path = url
+        .strip_prefix("https://img.shields.io/badge/")
+        .or_else(|| url.strip_prefix("http://img.shields.io/badge/"))?;
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    let path = path.strip_suffix(".svg").unwrap_or(path);
+    let parts: Vec<String> = path
+        .replace("--", "\0")
+        .split('-')
+        .map(|part| {
+            part.replace('\0', "-")
+                .replace("__", "\0")
+                .replace('_', " ")
+                .replace('\0', "_")
+        })
+        .collect();
+
+    let (text, color) = match parts.as_slice() {
+        [label, message, color] if !label.is_empty() => {
+            (format!("{} {}", label, message), color.as_str())
+        }
+        [_, message, color] | [message, color] => (message.clone(), color.as_str()),
+        _ => (decode_html_entities(alt), ""),
+    };
+    let kind = match color {
+        "brightgreen" | "green" | "success" => BadgeKind::Success,
+        "red" | "critical" => BadgeKind::Failure,
+        "yellow" | "orange" | "important" => BadgeKind::Warning,
+        "blue" | "informational" => BadgeKind::Info,
+        _ => BadgeKind::Neutral,
+    };
+    Some((text, kind))
+}
 
 /// Test styler that outputs readable HTML-like tags.
 #[cfg(test)]
@@ -486,10 +1004,61 @@ impl ListStyler for TagStyler {
 #[cfg(test)]
 impl TableStyler for TagStyler {
     fn border(&self, text: &str) -> String {
-        Theme::default().border(text)
+        Theme::dark().border(text)
     }
 
     fn header(&self, text: &str) -> String {
-        Theme::default().header(text)
+        Theme::dark().header(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamdownRenderer;
+
+    #[test]
+    fn test_badge() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        assert_eq!(theme.badge("PASS", BadgeKind::Success), "[ PASS ]");
+    }
+
+    #[test]
+    fn test_image_placeholder_does_not_expand_alt_text() {
+        let mut theme = Theme::dark();
+        theme.image_format = "[{alt}] {url} {x}".to_string();
+        assert_eq!(
+            theme.image_placeholder("see {url}", "a.png"),
+            "[see {url}] a.png {x}"
+        );
+    }
+
+    #[test]
+    fn test_shield_image_renders_as_badge() {
+        let theme = Theme::dark().with_backend(PlainBackend);
+        assert_eq!(
+            theme.image("badge", "https://img.shields.io/badge/build-passing-brightgreen"),
+            "[ build passing ]"
+        );
+        assert_eq!(
+            shield_badge("", "https://img.shields.io/badge/version-v1.2--beta-blue.svg"),
+            Some(("version v1.2-beta".to_string(), BadgeKind::Info))
+        );
+        assert_eq!(shield_badge("logo", "https://example.com/logo.png"), None);
+    }
+
+    #[test]
+    fn test_plain_theme_renders_unstyled_text() {
+        let mut out = Vec::new();
+        let mut renderer = StreamdownRenderer::with_theme(&mut out, 30, Theme::plain());
+        renderer
+            .push("## Notes\n\nSome **bold** and `code` with a [link](https://a.dev).\n")
+            .unwrap();
+        renderer.push("- one\n- two\n").unwrap();
+        renderer.finish().unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(!output.contains('\x1b'), "unexpected escape in {:?}", output);
+        assert!(output.contains("Some bold and code with a"));
+        assert!(output.contains("• one"));
     }
 }
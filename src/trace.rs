@@ -0,0 +1,128 @@
+//! `tracing` integration: a span per block and a debug event per parse event.
+
+use std::time::Duration;
+
+use streamdown_parser::ParseEvent;
+use tracing::{debug, debug_span, Span};
+
+use crate::renderer::ends_block;
+
+/// Tracks the span of the block currently being rendered.
+#[derive(Default)]
+pub(crate) struct EventTracer {
+    block: Option<Span>,
+}
+
+impl EventTracer {
+    /// Return the span to enter while rendering `event`, opening a new block
+    /// span if no block is in progress.
+    pub(crate) fn span_for(&mut self, event: &ParseEvent) -> Span {
+        if self.block.is_none() {
+            if let Some(kind) = block_kind(event) {
+                self.block = Some(debug_span!("block", kind = kind));
+            }
+        }
+        self.block.clone().unwrap_or_else(Span::none)
+    }
+
+    /// Emit a debug event for a rendered event, closing the block span if
+    /// the event ends it.
+    pub(crate) fn record(&mut self, event: &ParseEvent, elapsed: Duration) {
+        debug!(
+            event = event_kind(event),
+            bytes = event_size(event),
+            elapsed_us = elapsed.as_micros() as u64,
+            "render_event"
+        );
+        if ends_block(event) {
+            self.block = None;
+        }
+    }
+}
+
+/// The kind of block an event opens, if it can open one.
+fn block_kind(event: &ParseEvent) -> Option<&'static str> {
+    match event {
+        ParseEvent::EmptyLine | ParseEvent::Newline => None,
+        ParseEvent::Heading { .. } => Some("heading"),
+        ParseEvent::CodeBlockStart { .. } | ParseEvent::CodeBlockLine(_) => Some("code"),
+        ParseEvent::ListItem { .. } | ParseEvent::ListEnd => Some("list"),
+        ParseEvent::TableHeader(_)
+        | ParseEvent::TableRow(_)
+        | ParseEvent::TableSeparator
+        | ParseEvent::TableEnd => Some("table"),
+        ParseEvent::BlockquoteStart { .. }
+        | ParseEvent::BlockquoteLine(_)
+        | ParseEvent::BlockquoteEnd => Some("blockquote"),
+        ParseEvent::ThinkBlockStart | ParseEvent::ThinkBlockLine(_) | ParseEvent::ThinkBlockEnd => {
+            Some("think")
+        }
+        ParseEvent::HorizontalRule => Some("rule"),
+        _ => Some("paragraph"),
+    }
+}
+
+/// Stable name of an event variant.
+fn event_kind(event: &ParseEvent) -> &'static str {
+    match event {
+        ParseEvent::Text(_) => "Text",
+        ParseEvent::InlineCode(_) => "InlineCode",
+        ParseEvent::Bold(_) => "Bold",
+        ParseEvent::Italic(_) => "Italic",
+        ParseEvent::BoldItalic(_) => "BoldItalic",
+        ParseEvent::Underline(_) => "Underline",
+        ParseEvent::Strikeout(_) => "Strikeout",
+        ParseEvent::Link { .. } => "Link",
+        ParseEvent::Image { .. } => "Image",
+        ParseEvent::Footnote(_) => "Footnote",
+        ParseEvent::Prompt(_) => "Prompt",
+        ParseEvent::Heading { .. } => "Heading",
+        ParseEvent::CodeBlockStart { .. } => "CodeBlockStart",
+        ParseEvent::CodeBlockLine(_) => "CodeBlockLine",
+        ParseEvent::CodeBlockEnd => "CodeBlockEnd",
+        ParseEvent::ListItem { .. } => "ListItem",
+        ParseEvent::ListEnd => "ListEnd",
+        ParseEvent::TableHeader(_) => "TableHeader",
+        ParseEvent::TableRow(_) => "TableRow",
+        ParseEvent::TableSeparator => "TableSeparator",
+        ParseEvent::TableEnd => "TableEnd",
+        ParseEvent::BlockquoteStart { .. } => "BlockquoteStart",
+        ParseEvent::BlockquoteLine(_) => "BlockquoteLine",
+        ParseEvent::BlockquoteEnd => "BlockquoteEnd",
+        ParseEvent::ThinkBlockStart => "ThinkBlockStart",
+        ParseEvent::ThinkBlockLine(_) => "ThinkBlockLine",
+        ParseEvent::ThinkBlockEnd => "ThinkBlockEnd",
+        ParseEvent::HorizontalRule => "HorizontalRule",
+        ParseEvent::EmptyLine => "EmptyLine",
+        ParseEvent::Newline => "Newline",
+        ParseEvent::InlineElements(_) => "InlineElements",
+    }
+}
+
+/// Size in bytes of the source text carried by an event.
+fn event_size(event: &ParseEvent) -> usize {
+    match event {
+        ParseEvent::Text(text)
+        | ParseEvent::InlineCode(text)
+        | ParseEvent::Bold(text)
+        | ParseEvent::Italic(text)
+        | ParseEvent::BoldItalic(text)
+        | ParseEvent::Underline(text)
+        | ParseEvent::Strikeout(text)
+        | ParseEvent::Footnote(text)
+        | ParseEvent::Prompt(text)
+        | ParseEvent::CodeBlockLine(text)
+        | ParseEvent::BlockquoteLine(text)
+        | ParseEvent::ThinkBlockLine(text) => text.len(),
+        ParseEvent::Link { text, url } => text.len() + url.len(),
+        ParseEvent::Image { alt, url } => alt.len() + url.len(),
+        ParseEvent::Heading { content, .. } | ParseEvent::ListItem { content, .. } => {
+            content.len()
+        }
+        ParseEvent::TableHeader(cols) | ParseEvent::TableRow(cols) => {
+            cols.iter().map(|c| c.len()).sum()
+        }
+        ParseEvent::InlineElements(elements) => elements.len(),
+        _ => 0,
+    }
+}
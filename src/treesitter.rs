@@ -0,0 +1,278 @@
+//! Code highlighting with tree-sitter grammars.
+
+use std::collections::HashMap;
+
+use colored::Color;
+use tree_sitter::{
+    InputEdit, Language, Parser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
+
+use crate::backend::OutputBackend;
+use crate::theme::Style;
+
+/// Capture names styled by the highlighter. Each capture of a grammar takes
+/// the longest name here that is a prefix of it.
+const HIGHLIGHT_NAMES: &[&str] = &[
+    "attribute",
+    "comment",
+    "constant",
+    "constant.builtin",
+    "constructor",
+    "escape",
+    "function",
+    "function.builtin",
+    "function.macro",
+    "function.method",
+    "keyword",
+    "label",
+    "number",
+    "operator",
+    "property",
+    "punctuation",
+    "string",
+    "type",
+    "type.builtin",
+    "variable.builtin",
+    "variable.parameter",
+];
+
+/// Grammar of one language with its highlight query.
+struct Grammar {
+    language: Language,
+    query: Query,
+    // Index into `HIGHLIGHT_NAMES` of each capture of the query
+    styles: Vec<Option<usize>>,
+}
+
+/// Highlighter parsing the code block seen so far with tree-sitter.
+///
+/// Every line is highlighted in the context of the whole block, so
+/// constructs spanning lines such as multi-line strings keep their style.
+/// The syntax tree is updated incrementally as lines arrive, and only the
+/// nodes overlapping the new line are queried, so highlighting a block
+/// stays linear in its length.
+pub(crate) struct TreeSitterHighlighter {
+    parser: Parser,
+    grammars: HashMap<&'static str, Grammar>,
+    // Grammar, source and syntax tree of the current block
+    block: Option<&'static str>,
+    source: String,
+    tree: Option<Tree>,
+}
+
+impl TreeSitterHighlighter {
+    pub(crate) fn new() -> Self {
+        let mut grammars = HashMap::new();
+        let sources: [(&str, Language, &str); 2] = [
+            (
+                "rust",
+                tree_sitter_rust::LANGUAGE.into(),
+                tree_sitter_rust::HIGHLIGHTS_QUERY,
+            ),
+            (
+                "python",
+                tree_sitter_python::LANGUAGE.into(),
+                tree_sitter_python::HIGHLIGHTS_QUERY,
+            ),
+        ];
+        for (name, language, highlights) in sources {
+            // A grammar whose query fails to load falls back to syntect
+            if let Ok(query) = Query::new(&language, highlights) {
+                let styles = query
+                    .capture_names()
+                    .iter()
+                    .map(|name| style_index(name))
+                    .collect();
+                grammars.insert(
+                    name,
+                    Grammar {
+                        language,
+                        query,
+                        styles,
+                    },
+                );
+            }
+        }
+        Self {
+            parser: Parser::new(),
+            grammars,
+            block: None,
+            source: String::new(),
+            tree: None,
+        }
+    }
+
+    /// Forget the source of the previous code block.
+    pub(crate) fn start_block(&mut self) {
+        self.block = None;
+        self.source.clear();
+        self.tree = None;
+    }
+
+    /// Highlight the next piece of the current block, or `None` if the
+    /// language has no grammar.
+    pub(crate) fn highlight_line(
+        &mut self,
+        line: &str,
+        language: Option<&str>,
+        end_of_line: bool,
+        backend: &dyn OutputBackend,
+    ) -> Option<String> {
+        let name = match language? {
+            "rust" | "rs" => "rust",
+            "python" | "py" => "python",
+            _ => return None,
+        };
+        let grammar = self.grammars.get(name)?;
+        if self.block != Some(name) {
+            self.parser.set_language(&grammar.language).ok()?;
+            self.block = Some(name);
+            self.source.clear();
+            self.tree = None;
+        }
+
+        let start = self.source.len();
+        let start_position = end_point(&self.source);
+        self.source.push_str(line);
+        let end = self.source.len();
+        if end_of_line {
+            self.source.push('\n');
+        }
+        if let Some(tree) = self.tree.as_mut() {
+            tree.edit(&InputEdit {
+                start_byte: start,
+                old_end_byte: start,
+                new_end_byte: self.source.len(),
+                start_position,
+                old_end_position: start_position,
+                new_end_position: end_point(&self.source),
+            });
+        }
+        let tree = self.parser.parse(&self.source, self.tree.as_ref())?;
+
+        // Capture of each byte of the piece; inner nodes win over the nodes
+        // containing them, and earlier patterns over later ones
+        let mut styles: Vec<Option<(usize, usize)>> = vec![None; end - start];
+        {
+            let mut cursor = QueryCursor::new();
+            cursor.set_byte_range(start..end);
+            let mut captures =
+                cursor.captures(&grammar.query, tree.root_node(), self.source.as_bytes());
+            while let Some((found, index)) = captures.next() {
+                let capture = found.captures[*index];
+                let Some(style) = grammar.styles[capture.index as usize] else {
+                    continue;
+                };
+                let range = capture.node.byte_range();
+                let size = range.len();
+                for byte in range.start.max(start)..range.end.min(end) {
+                    let slot = &mut styles[byte - start];
+                    if slot.is_none_or(|(_, current)| size < current) {
+                        *slot = Some((style, size));
+                    }
+                }
+            }
+        }
+        self.tree = Some(tree);
+
+        let mut result = String::new();
+        let mut from = 0;
+        while from < styles.len() {
+            let style = styles[from].map(|(style, _)| style);
+            let to = (from..styles.len())
+                .find(|&byte| styles[byte].map(|(style, _)| style) != style)
+                .unwrap_or(styles.len());
+            let text = &self.source[start + from..start + to];
+            match style {
+                Some(index) => result.push_str(&backend.paint(&capture_style(index), text)),
+                None => result.push_str(text),
+            }
+            from = to;
+        }
+        Some(result)
+    }
+}
+
+/// Position just after the end of `source`.
+fn end_point(source: &str) -> Point {
+    let row = source.matches('\n').count();
+    let line_start = source.rfind('\n').map_or(0, |newline| newline + 1);
+    Point::new(row, source.len() - line_start)
+}
+
+/// Index into [`HIGHLIGHT_NAMES`] of the longest name that is a prefix of
+/// the capture `name`.
+fn style_index(name: &str) -> Option<usize> {
+    HIGHLIGHT_NAMES
+        .iter()
+        .enumerate()
+        .filter(|(_, known)| {
+            name == **known
+                || name
+                    .strip_prefix(**known)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+        .max_by_key(|(_, known)| known.len())
+        .map(|(index, _)| index)
+}
+
+/// Style of the capture at `index` in [`HIGHLIGHT_NAMES`].
+///
+/// Basic ANSI colors are used so the terminal palette decides how they look
+/// on light and dark backgrounds.
+fn capture_style(index: usize) -> Style {
+    let name = HIGHLIGHT_NAMES.get(index).copied().unwrap_or_default();
+    let family = name.split('.').next().unwrap_or_default();
+    match family {
+        "comment" => Style::new().fg(Color::BrightBlack).italic(),
+        "keyword" | "operator" => Style::new().fg(Color::Magenta),
+        "string" | "escape" => Style::new().fg(Color::Green),
+        "number" | "constant" => Style::new().fg(Color::Yellow),
+        "function" | "constructor" => Style::new().fg(Color::Blue),
+        "type" | "attribute" | "label" => Style::new().fg(Color::Cyan),
+        "variable" | "property" => Style::new().fg(Color::Red),
+        _ => Style::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::AnsiBackend;
+    use crate::color::ColorDepth;
+    use crate::strip::strip_ansi;
+
+    #[test]
+    fn test_highlights_lines_in_block_context() {
+        let backend = AnsiBackend::new(ColorDepth::Ansi16);
+        let mut highlighter = TreeSitterHighlighter::new();
+        highlighter.start_block();
+        let first = highlighter
+            .highlight_line("let s = \"a", Some("rust"), true, &backend)
+            .unwrap();
+        let second = highlighter
+            .highlight_line("b\";", Some("rust"), true, &backend)
+            .unwrap();
+        assert_eq!(strip_ansi(&first), "let s = \"a");
+        assert_eq!(strip_ansi(&second), "b\";");
+        // The continued string keeps its color on the second line
+        let string = HIGHLIGHT_NAMES.iter().position(|name| *name == "string");
+        let style = capture_style(string.unwrap());
+        assert!(second.starts_with(&backend.paint(&style, "b\"")));
+    }
+
+    #[test]
+    fn test_captures_take_longest_known_name() {
+        let index = |name| style_index(name).map(|index| HIGHLIGHT_NAMES[index]);
+        assert_eq!(index("function.method.call"), Some("function.method"));
+        assert_eq!(index("functional"), None);
+        assert_eq!(index("string"), Some("string"));
+    }
+
+    #[test]
+    fn test_unknown_language_is_not_handled() {
+        let mut highlighter = TreeSitterHighlighter::new();
+        let backend = AnsiBackend::new(ColorDepth::Ansi16);
+        assert!(highlighter.highlight_line("x", Some("cobol"), true, &backend).is_none());
+    }
+}
@@ -1,5 +1,12 @@
 //! Utility functions for the markdown renderer.
 
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use streamdown_parser::ParseEvent;
+
+use crate::options::EmojiNormalization;
+
 /// Terminal theme mode (dark or light).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThemeMode {
@@ -10,7 +17,23 @@ pub enum ThemeMode {
 }
 
 /// Detects the terminal theme mode (dark or light).
+///
+/// Checks the `COLORFGBG` environment variable first, then queries the
+/// terminal background color with OSC 11 (with the `detect-theme` feature),
+/// defaulting to dark.
 pub fn detect_theme_mode() -> ThemeMode {
+    if let Some(mode) = std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| theme_mode_from_colorfgbg(&value))
+    {
+        return mode;
+    }
+    query_theme_mode()
+}
+
+/// Ask the terminal for its background color.
+#[cfg(feature = "detect-theme")]
+fn query_theme_mode() -> ThemeMode {
     use terminal_colorsaurus::{theme_mode, QueryOptions};
     use terminal_colorsaurus::ThemeMode as ColorsaurusThemeMode;
 
@@ -19,3 +42,429 @@ pub fn detect_theme_mode() -> ThemeMode {
         Ok(ColorsaurusThemeMode::Dark) | Err(_) => ThemeMode::Dark,
     }
 }
+
+/// Without terminal queries, assume a dark background.
+#[cfg(not(feature = "detect-theme"))]
+fn query_theme_mode() -> ThemeMode {
+    ThemeMode::Dark
+}
+
+/// Parse a `COLORFGBG` value such as `15;0` or `0;default;15`.
+///
+/// The last field is the background palette index: 0-6 and 8 are dark
+/// colors, anything else in the 16-color palette is light.
+pub fn theme_mode_from_colorfgbg(value: &str) -> Option<ThemeMode> {
+    let bg: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    match bg {
+        0..=6 | 8 => Some(ThemeMode::Dark),
+        7 | 9..=15 => Some(ThemeMode::Light),
+        _ => None,
+    }
+}
+
+/// Width used when the terminal size cannot be determined.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Height used when the terminal size cannot be determined.
+pub const DEFAULT_HEIGHT: usize = 24;
+
+/// Writer that may be attached to a terminal whose size can be queried.
+///
+/// Standard streams and files ask the terminal they are attached to;
+/// buffers and other writers report no terminal.
+pub trait TerminalHandle {
+    /// Columns and rows of the attached terminal, or `None` if there is none.
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+macro_rules! terminal_handle {
+    ($($ty:ty),*) => {$(
+        impl TerminalHandle for $ty {
+            fn terminal_size(&self) -> Option<(usize, usize)> {
+                query_terminal_size(self)
+            }
+        }
+    )*};
+}
+
+terminal_handle!(io::Stdout, io::StdoutLock<'_>, io::Stderr, io::StderrLock<'_>, std::fs::File);
+
+impl TerminalHandle for Vec<u8> {}
+impl TerminalHandle for io::Sink {}
+
+impl<T: TerminalHandle + ?Sized> TerminalHandle for &mut T {
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        (**self).terminal_size()
+    }
+}
+
+impl<T: TerminalHandle + ?Sized> TerminalHandle for Box<T> {
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        (**self).terminal_size()
+    }
+}
+
+impl<T: TerminalHandle + Write> TerminalHandle for io::BufWriter<T> {
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        self.get_ref().terminal_size()
+    }
+}
+
+impl<T: TerminalHandle + Write> TerminalHandle for io::LineWriter<T> {
+    fn terminal_size(&self) -> Option<(usize, usize)> {
+        self.get_ref().terminal_size()
+    }
+}
+
+#[cfg(unix)]
+fn query_terminal_size(handle: &impl std::os::fd::AsFd) -> Option<(usize, usize)> {
+    let (width, height) = terminal_size::terminal_size_of(handle)?;
+    Some((width.0 as usize, height.0 as usize))
+}
+
+#[cfg(windows)]
+fn query_terminal_size(handle: &impl std::os::windows::io::AsHandle) -> Option<(usize, usize)> {
+    let (width, height) = terminal_size::terminal_size_of(handle)?;
+    Some((width.0 as usize, height.0 as usize))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn query_terminal_size<T>(_handle: &T) -> Option<(usize, usize)> {
+    None
+}
+
+/// Detects the width in columns of the terminal attached to stdout.
+pub fn detect_terminal_width() -> usize {
+    terminal_width_of(&io::stdout())
+}
+
+/// Detects the width in columns of the terminal `handle` writes to.
+///
+/// Falls back to the `COLUMNS` environment variable, and finally to
+/// [`DEFAULT_WIDTH`].
+pub fn terminal_width_of<T: TerminalHandle + ?Sized>(handle: &T) -> usize {
+    handle
+        .terminal_size()
+        .map(|(width, _)| width)
+        .filter(|&width| width > 0)
+        .or_else(|| env_size("COLUMNS"))
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Detects the height in rows of the terminal `handle` writes to.
+///
+/// Falls back to the `LINES` environment variable, and finally to
+/// [`DEFAULT_HEIGHT`].
+pub fn terminal_height_of<T: TerminalHandle + ?Sized>(handle: &T) -> usize {
+    handle
+        .terminal_size()
+        .map(|(_, height)| height)
+        .filter(|&height| height > 0)
+        .or_else(|| env_size("LINES"))
+        .unwrap_or(DEFAULT_HEIGHT)
+}
+
+fn env_size(name: &str) -> Option<usize> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&size: &usize| size > 0)
+}
+
+/// Rewrite emoji sequences whose width terminals disagree on.
+///
+/// Variation selectors (U+FE0E, U+FE0F) are removed, and with
+/// [`EmojiNormalization::SplitJoined`] zero-width joiners (U+200D) between
+/// two emoji too, so a ZWJ sequence shows as its component emoji, each
+/// measured two cells wide. Joiners in other scripts are kept, since they
+/// select letter forms there.
+pub fn normalize_emoji(text: &str, mode: EmojiNormalization) -> String {
+    let stripped = match mode {
+        EmojiNormalization::Keep => return text.to_string(),
+        _ => text.replace(['\u{fe0e}', '\u{fe0f}'], ""),
+    };
+    if mode != EmojiNormalization::SplitJoined {
+        return stripped;
+    }
+    let chars: Vec<char> = stripped.chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            let joins_emoji = i > 0
+                && chars.get(i + 1).is_some_and(|&next| is_pictographic(next))
+                && is_pictographic(chars[i - 1]);
+            c != '\u{200d}' || !joins_emoji
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+/// Whether `c` lies in a block of pictographic symbols and emoji.
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2190}'..='\u{21ff}'
+            | '\u{2300}'..='\u{23ff}'
+            | '\u{2600}'..='\u{27bf}'
+            | '\u{2b00}'..='\u{2bff}'
+            | '\u{1f000}'..='\u{1faff}'
+    )
+}
+
+/// Apply [`normalize_emoji`] to the prose carried by `event`. Code is left
+/// as written.
+pub(crate) fn normalize_event(event: &ParseEvent, mode: EmojiNormalization) -> ParseEvent {
+    let mut event = event.clone();
+    match &mut event {
+        ParseEvent::Text(text)
+        | ParseEvent::Bold(text)
+        | ParseEvent::Italic(text)
+        | ParseEvent::BoldItalic(text)
+        | ParseEvent::Underline(text)
+        | ParseEvent::Strikeout(text)
+        | ParseEvent::BlockquoteLine(text)
+        | ParseEvent::ThinkBlockLine(text)
+        | ParseEvent::Link { text, .. }
+        | ParseEvent::Image { alt: text, .. }
+        | ParseEvent::Heading { content: text, .. }
+        | ParseEvent::ListItem { content: text, .. } => *text = normalize_emoji(text, mode),
+        ParseEvent::TableHeader(cells) | ParseEvent::TableRow(cells) => {
+            for cell in cells {
+                *cell = normalize_emoji(cell, mode);
+            }
+        }
+        _ => {}
+    }
+    event
+}
+
+/// 64-bit FNV-1a hash of `bytes`, the same on every platform and release.
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Vulgar fraction characters by numerator and denominator.
+const FRACTIONS: &[(char, char, char)] = &[
+    ('1', '2', '½'),
+    ('1', '3', '⅓'),
+    ('2', '3', '⅔'),
+    ('1', '4', '¼'),
+    ('3', '4', '¾'),
+    ('1', '5', '⅕'),
+    ('2', '5', '⅖'),
+    ('3', '5', '⅗'),
+    ('4', '5', '⅘'),
+    ('1', '6', '⅙'),
+    ('5', '6', '⅚'),
+    ('1', '8', '⅛'),
+    ('3', '8', '⅜'),
+    ('5', '8', '⅝'),
+    ('7', '8', '⅞'),
+];
+
+pub(crate) const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+/// Replace ASCII spellings of common fractions and units with their Unicode
+/// forms: `1/2` becomes `½`, `m^2` becomes `m²` and `20 degC` becomes
+/// `20 °C`. Code spans, link destinations, autolinks and bare URLs are
+/// left untouched.
+pub fn typeset_units(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let before = i.checked_sub(1).map(|j| chars[j]);
+        if let Some(len) = verbatim_len(&chars[i..], before) {
+            result.extend(&chars[i..i + len]);
+            i += len;
+        } else if let Some((len, replacement)) = unit_at(&chars[i..], before) {
+            result.push_str(&replacement);
+            i += len;
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    result
+}
+
+/// Length in chars of the span at the start of `rest` that must not be
+/// typeset: a code span, a link destination, an autolink or a bare URL.
+fn verbatim_len(rest: &[char], before: Option<char>) -> Option<usize> {
+    match rest {
+        ['`', ..] => {
+            // A code span closes at the next run of as many backticks; an
+            // unmatched run is literal
+            let ticks = rest.iter().take_while(|&&c| c == '`').count();
+            let mut i = ticks;
+            while i < rest.len() {
+                let run = rest[i..].iter().take_while(|&&c| c == '`').count();
+                if run == ticks {
+                    return Some(i + run);
+                }
+                i += run.max(1);
+            }
+            Some(ticks)
+        }
+        [']', '(', ..] => {
+            // Up to the matching parenthesis, or the rest of the text while
+            // the destination is still streaming in
+            let mut depth = 0;
+            for (i, &c) in rest.iter().enumerate().skip(1) {
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 1 => return Some(i + 1),
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+            }
+            Some(rest.len())
+        }
+        ['<', ..] => {
+            let end = rest.iter().position(|&c| c == '>' || c.is_whitespace())?;
+            let inner = &rest[1..end];
+            (rest[end] == '>' && inner.iter().any(|&c| c == ':' || c == '@')).then_some(end + 1)
+        }
+        _ if !before.is_some_and(char::is_alphanumeric) => {
+            let scheme = rest
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || "+.-".contains(**c))
+                .count();
+            let url = (scheme > 0 && rest[scheme..].starts_with(&[':', '/', '/']))
+                || rest.starts_with(&['w', 'w', 'w', '.']);
+            url.then(|| rest.iter().take_while(|c| !c.is_whitespace()).count())
+        }
+        _ => None,
+    }
+}
+
+/// Match a fraction, exponent or degree unit at the start of `rest`,
+/// returning its length in chars and its replacement.
+fn unit_at(rest: &[char], before: Option<char>) -> Option<(usize, String)> {
+    // Parts of longer numbers, dates and paths such as 11/2, 1/2/2024 and
+    // /v1/2 are not fractions
+    let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || "/.".contains(*c));
+    let standalone = !is_word(before.as_ref()) && !is_word(rest.get(3));
+    match rest {
+        [numerator, '/', denominator, ..] if standalone => FRACTIONS
+            .iter()
+            .find(|(n, d, _)| n == numerator && d == denominator)
+            .map(|(_, _, fraction)| (3, fraction.to_string())),
+        ['^', exponent @ ..] if before.is_some_and(char::is_alphabetic) => {
+            let digits = exponent.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 || exponent.get(digits).is_some_and(|c| c.is_alphanumeric()) {
+                return None;
+            }
+            let superscript = exponent[..digits]
+                .iter()
+                .filter_map(|c| c.to_digit(10))
+                .map(|digit| SUPERSCRIPT_DIGITS[digit as usize])
+                .collect();
+            Some((digits + 1, superscript))
+        }
+        ['d', 'e', 'g', scale @ ('C' | 'F'), ..]
+            if !before.is_some_and(char::is_alphabetic)
+                && !rest.get(4).is_some_and(|c| c.is_alphanumeric()) =>
+        {
+            Some((4, format!("°{}", scale)))
+        }
+        _ => None,
+    }
+}
+
+/// Writer whose output its clones can take while a renderer owns it.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Take the output written since the last call.
+    pub(crate) fn take(&self) -> String {
+        let buffer = std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()));
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorfgbg_dark_background() {
+        assert_eq!(theme_mode_from_colorfgbg("15;0"), Some(ThemeMode::Dark));
+        assert_eq!(theme_mode_from_colorfgbg("7;default;8"), Some(ThemeMode::Dark));
+    }
+
+    #[test]
+    fn test_colorfgbg_light_background() {
+        assert_eq!(theme_mode_from_colorfgbg("0;15"), Some(ThemeMode::Light));
+        assert_eq!(theme_mode_from_colorfgbg("0;7"), Some(ThemeMode::Light));
+    }
+
+    #[test]
+    fn test_colorfgbg_invalid() {
+        assert_eq!(theme_mode_from_colorfgbg(""), None);
+        assert_eq!(theme_mode_from_colorfgbg("0;default"), None);
+        assert_eq!(theme_mode_from_colorfgbg("0;200"), None);
+    }
+
+    #[test]
+    fn test_normalize_emoji() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let heart = "\u{2764}\u{fe0f}";
+        let text = format!("{} {}", family, heart);
+        assert_eq!(normalize_emoji(&text, EmojiNormalization::Keep), text);
+        assert_eq!(
+            normalize_emoji(&text, EmojiNormalization::StripSelectors),
+            format!("{} \u{2764}", family)
+        );
+        assert_eq!(
+            normalize_emoji(&text, EmojiNormalization::SplitJoined),
+            "\u{1f468}\u{1f469}\u{1f467} \u{2764}"
+        );
+        // A joiner between Devanagari letters selects a half form
+        let conjunct = "\u{915}\u{94d}\u{200d}\u{937}";
+        assert_eq!(normalize_emoji(conjunct, EmojiNormalization::SplitJoined), conjunct);
+    }
+
+    #[test]
+    fn test_typeset_units() {
+        assert_eq!(typeset_units("add 1/2 cup and 3/4 tsp"), "add ½ cup and ¾ tsp");
+        assert_eq!(typeset_units("an area of 4 m^2, or 8 cm^3"), "an area of 4 m², or 8 cm³");
+        assert_eq!(typeset_units("heat to 180 degC (356degF)"), "heat to 180 °C (356°F)");
+        assert_eq!(typeset_units("on 1/2/2024 see /v1/2 or 11/2"), "on 1/2/2024 see /v1/2 or 11/2");
+        assert_eq!(typeset_units("`x = 1/2` and 2^8 degrees"), "`x = 1/2` and 2^8 degrees");
+    }
+
+    #[test]
+    fn test_typeset_units_skips_code_and_links() {
+        let unchanged = [
+            "``a ` 1/2`` and `` ` ``",
+            "[half](https://example.com/1/2 degC) or <https://x.io/m^2>",
+            "see https://example.com/?q=1/2 and www.example.com/x^2",
+            "[streaming](http://example.com/a/1/2",
+        ];
+        for text in unchanged {
+            assert_eq!(typeset_units(text), text);
+        }
+        assert_eq!(typeset_units("[1/2 cup](x) or <a 1/2 b>"), "[½ cup](x) or <a ½ b>");
+    }
+}
@@ -0,0 +1,242 @@
+//! Middleware checking that parse events arrive in a valid order.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+
+use streamdown_parser::ParseEvent;
+
+use crate::renderer::EventRenderer;
+
+/// Events kept as context for a violation.
+const CONTEXT_EVENTS: usize = 3;
+
+/// An event that broke an ordering invariant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Position of the event in the stream, counting from 0.
+    pub index: usize,
+    /// The offending event, or `finish` for blocks left open at the end.
+    pub event: String,
+    pub message: String,
+    /// The events just before the offending one, oldest first.
+    pub context: Vec<String>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event {} {}: {}", self.index, self.event, self.message)?;
+        for event in &self.context {
+            write!(f, "\n  after {}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renderer wrapper that checks event-ordering invariants before forwarding
+/// each event, so integrators can tell parser bugs from renderer bugs.
+///
+/// Checked invariants: code block lines only between `CodeBlockStart` and
+/// `CodeBlockEnd`, nothing else inside a code block, table rows only between
+/// `TableHeader` and `TableEnd`, think block lines only inside a think
+/// block, and balanced blockquote starts and ends.
+///
+/// Violations are collected and the event is still forwarded, unless
+/// [`fail_fast`](Self::fail_fast) is set.
+pub struct StrictValidator<R: EventRenderer> {
+    inner: R,
+    fail_fast: bool,
+    index: usize,
+    recent: VecDeque<String>,
+    in_code: bool,
+    in_table: bool,
+    in_think: bool,
+    blockquote_depth: usize,
+    violations: Vec<Violation>,
+}
+
+impl<R: EventRenderer> StrictValidator<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            fail_fast: false,
+            index: 0,
+            recent: VecDeque::new(),
+            in_code: false,
+            in_table: false,
+            in_think: false,
+            blockquote_depth: 0,
+            violations: Vec::new(),
+        }
+    }
+
+    /// Return an `InvalidData` error for the first violation instead of
+    /// forwarding the offending event.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Violations found so far.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Update the open blocks for `event`, describing the broken invariant
+    /// if it is out of order.
+    fn check(&mut self, event: &ParseEvent) -> Option<&'static str> {
+        if self.in_code
+            && !matches!(event, ParseEvent::CodeBlockLine(_) | ParseEvent::CodeBlockEnd)
+        {
+            return Some("inside an open code block");
+        }
+        match event {
+            ParseEvent::CodeBlockStart { .. } => self.in_code = true,
+            ParseEvent::CodeBlockLine(_) if !self.in_code => {
+                return Some("code block line outside a code block")
+            }
+            ParseEvent::CodeBlockEnd if !self.in_code => {
+                return Some("code block end without a start")
+            }
+            ParseEvent::CodeBlockEnd => self.in_code = false,
+            ParseEvent::TableHeader(_) if self.in_table => {
+                return Some("table header inside an open table")
+            }
+            ParseEvent::TableHeader(_) => self.in_table = true,
+            ParseEvent::TableRow(_) | ParseEvent::TableSeparator if !self.in_table => {
+                return Some("table row without a table header")
+            }
+            ParseEvent::TableEnd if !self.in_table => return Some("table end without a header"),
+            ParseEvent::TableEnd => self.in_table = false,
+            ParseEvent::ThinkBlockStart => self.in_think = true,
+            ParseEvent::ThinkBlockLine(_) if !self.in_think => {
+                return Some("think block line outside a think block")
+            }
+            ParseEvent::ThinkBlockEnd if !self.in_think => {
+                return Some("think block end without a start")
+            }
+            ParseEvent::ThinkBlockEnd => self.in_think = false,
+            ParseEvent::BlockquoteStart { .. } => self.blockquote_depth += 1,
+            ParseEvent::BlockquoteLine(_) if self.blockquote_depth == 0 => {
+                return Some("blockquote line outside a blockquote")
+            }
+            ParseEvent::BlockquoteEnd if self.blockquote_depth == 0 => {
+                return Some("blockquote end without a start")
+            }
+            ParseEvent::BlockquoteEnd => self.blockquote_depth -= 1,
+            _ => {}
+        }
+        None
+    }
+
+    fn record(&mut self, event: String, message: &str) -> io::Result<()> {
+        let violation = Violation {
+            index: self.index,
+            event,
+            message: message.to_string(),
+            context: self.recent.iter().cloned().collect(),
+        };
+        let error = self
+            .fail_fast
+            .then(|| io::Error::new(io::ErrorKind::InvalidData, violation.to_string()));
+        self.violations.push(violation);
+        error.map_or(Ok(()), Err)
+    }
+}
+
+impl<R: EventRenderer> EventRenderer for StrictValidator<R> {
+    fn render_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        let described = format!("{:?}", event);
+        if let Some(message) = self.check(event) {
+            self.record(described.clone(), message)?;
+        }
+        if self.recent.len() == CONTEXT_EVENTS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(described);
+        self.index += 1;
+        self.inner.render_event(event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let unclosed = [
+            (self.in_code, "code block left open"),
+            (self.in_table, "table left open"),
+            (self.in_think, "think block left open"),
+            (self.blockquote_depth > 0, "blockquote left open"),
+        ];
+        for (open, message) in unclosed {
+            if open {
+                self.record("finish".to_string(), message)?;
+            }
+        }
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Renderer, Theme};
+
+    fn validator() -> StrictValidator<Renderer<Vec<u8>>> {
+        StrictValidator::new(Renderer::with_theme(Vec::new(), 40, Theme::plain()))
+    }
+
+    fn validate(events: &[ParseEvent]) -> Vec<Violation> {
+        let mut validator = validator();
+        for event in events {
+            validator.render_event(event).unwrap();
+        }
+        validator.finish().unwrap();
+        validator.violations().to_vec()
+    }
+
+    #[test]
+    fn test_valid_stream_has_no_violations() {
+        let events = [
+            ParseEvent::TableHeader(vec!["a".to_string()]),
+            ParseEvent::TableSeparator,
+            ParseEvent::TableRow(vec!["1".to_string()]),
+            ParseEvent::TableEnd,
+            ParseEvent::BlockquoteStart { depth: 1 },
+            ParseEvent::BlockquoteLine("quote".to_string()),
+            ParseEvent::BlockquoteEnd,
+        ];
+        assert_eq!(validate(&events), vec![]);
+    }
+
+    #[test]
+    fn test_reports_violations_with_context() {
+        let events = [
+            ParseEvent::Text("intro".to_string()),
+            ParseEvent::CodeBlockLine("stray".to_string()),
+            ParseEvent::TableRow(vec!["1".to_string()]),
+            ParseEvent::BlockquoteStart { depth: 1 },
+        ];
+        let violations = validate(&events);
+        let messages: Vec<_> = violations.iter().map(|v| v.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "code block line outside a code block",
+                "table row without a table header",
+                "blockquote left open"
+            ]
+        );
+        assert_eq!(violations[0].index, 1);
+        assert_eq!(violations[0].context, [r#"Text("intro")"#]);
+    }
+
+    #[test]
+    fn test_fail_fast_returns_error() {
+        let mut validator = validator().fail_fast(true);
+        let error = validator.render_event(&ParseEvent::CodeBlockEnd).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("code block end without a start"));
+    }
+}
@@ -0,0 +1,98 @@
+//! Display width of rendered text, measured by grapheme cluster so that
+//! combining marks, emoji sequences and Hangul jamo take the cells the
+//! terminal gives them.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::strip::strip_ansi;
+
+/// Columns `text` occupies in the terminal, ignoring escape sequences.
+pub fn visible_length(text: &str) -> usize {
+    visible_length_with(text, false)
+}
+
+/// Columns `text` occupies in the terminal, ignoring escape sequences, with
+/// East Asian ambiguous-width characters such as `○` or `±` taking two
+/// columns if `ambiguous_wide` is set, as in CJK terminals.
+pub(crate) fn visible_length_with(text: &str, ambiguous_wide: bool) -> usize {
+    if !text.contains('\x1b') {
+        return text_width_with(text, ambiguous_wide);
+    }
+    text_width_with(&strip_ansi(text), ambiguous_wide)
+}
+
+/// Columns of text without escape sequences.
+pub(crate) fn text_width(text: &str) -> usize {
+    text_width_with(text, false)
+}
+
+/// Columns of text without escape sequences, measuring ambiguous-width
+/// characters as two columns if `ambiguous_wide` is set.
+pub(crate) fn text_width_with(text: &str, ambiguous_wide: bool) -> usize {
+    if text.is_ascii() {
+        return text.len();
+    }
+    text.graphemes(true)
+        .map(|grapheme| grapheme_width_with(grapheme, ambiguous_wide))
+        .sum()
+}
+
+/// Columns of a single grapheme cluster.
+///
+/// A cluster is drawn as one glyph: emoji sequences joined with ZWJ or
+/// modifiers are as wide as a single emoji, and a variation selector 16
+/// requests the two-cell emoji presentation.
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    grapheme_width_with(grapheme, false)
+}
+
+/// Columns of a single grapheme cluster, measuring an ambiguous-width one
+/// as two columns if `ambiguous_wide` is set.
+pub(crate) fn grapheme_width_with(grapheme: &str, ambiguous_wide: bool) -> usize {
+    if grapheme.contains('\u{fe0f}') {
+        return 2;
+    }
+    let width = if ambiguous_wide {
+        grapheme.width_cjk()
+    } else {
+        grapheme.width()
+    };
+    width.min(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme_widths() {
+        assert_eq!(visible_length("abc"), 3);
+        // Combining acute accent
+        assert_eq!(visible_length("e\u{301}te\u{301}"), 4);
+        // Family emoji joined with ZWJ
+        assert_eq!(visible_length("👨\u{200d}👩\u{200d}👧"), 2);
+        assert_eq!(visible_length("👍🏽"), 2);
+        assert_eq!(visible_length("❤\u{fe0f}"), 2);
+        // Conjoining Hangul jamo forming one syllable
+        assert_eq!(visible_length("\u{1100}\u{1161}\u{11a8}"), 2);
+        assert_eq!(visible_length("日本語"), 6);
+        assert_eq!(visible_length("🇯🇵"), 2);
+    }
+
+    #[test]
+    fn test_ambiguous_width() {
+        assert_eq!(visible_length("±○"), 2);
+        assert_eq!(visible_length_with("±○", true), 4);
+        assert_eq!(visible_length_with("\x1b[1ma日\x1b[0m", true), 3);
+    }
+
+    #[test]
+    fn test_escapes_have_no_width() {
+        assert_eq!(visible_length("\x1b[1mbold\x1b[0m"), 4);
+        assert_eq!(
+            visible_length("\x1b]8;;https://x\x1b\\link\x1b]8;;\x1b\\"),
+            4
+        );
+    }
+}